@@ -0,0 +1,132 @@
+use clap::ValueHint;
+use prop_amm_shared::config::{HyperparameterVariance, BASELINE_STEPS};
+use prop_amm_shared::normalizer::{
+    after_swap as normalizer_after_swap_fn, compute_swap as normalizer_swap,
+};
+use prop_amm_sim::calibrate::{self, CalibrationSpec};
+use prop_amm_sim::runner;
+
+use super::compile;
+use super::native_lib::LoadedSubmission;
+
+#[derive(clap::Args)]
+pub struct CalibrateArgs {
+    /// Average edge per simulation the reference strategy should earn
+    #[arg(long, allow_negative_numbers = true)]
+    pub target: f64,
+    /// Reference strategy source
+    #[arg(
+        long,
+        default_value = "programs/starter/src/lib.rs",
+        value_hint = ValueHint::FilePath,
+    )]
+    pub reference: String,
+    /// Simulations per calibration batch (the same seeds every batch)
+    #[arg(long, default_value = "100")]
+    pub simulations: u32,
+    /// Number of steps per simulation
+    #[arg(long, default_value_t = BASELINE_STEPS)]
+    pub steps: u32,
+    /// Starting seed of the calibration batch
+    #[arg(long, default_value = "0")]
+    pub seed_start: u64,
+    /// Easiest difficulty searched (scales volatility and normalizer liquidity up, fees down)
+    #[arg(long, default_value = "0.25")]
+    pub min_difficulty: f64,
+    /// Hardest difficulty searched
+    #[arg(long, default_value = "4.0")]
+    pub max_difficulty: f64,
+    /// Accept once the average edge is within this distance of the target
+    #[arg(long, default_value = "1.0")]
+    pub tol: f64,
+    /// Maximum bisection steps after evaluating the bracket ends
+    #[arg(long, default_value = "12")]
+    pub max_iterations: u32,
+    /// Number of parallel workers (0 = auto)
+    #[arg(long, default_value = "0")]
+    pub workers: usize,
+}
+
+pub fn run(args: CalibrateArgs) -> anyhow::Result<()> {
+    if args.simulations == 0 {
+        anyhow::bail!("--simulations must be >= 1");
+    }
+    let n_workers = if args.workers == 0 {
+        None
+    } else {
+        Some(args.workers)
+    };
+
+    tracing::info!("Compiling {} (native)...", args.reference);
+    let native_path = compile::compile_native(&args.reference)?;
+    let reference = LoadedSubmission::load(&native_path)?;
+
+    let spec = CalibrationSpec {
+        base: HyperparameterVariance::default(),
+        target_avg_edge: args.target,
+        seeds: (0..args.simulations as u64)
+            .map(|i| args.seed_start.wrapping_add(i))
+            .collect(),
+        n_steps: args.steps,
+        min_difficulty: args.min_difficulty,
+        max_difficulty: args.max_difficulty,
+        edge_tolerance: args.tol,
+        max_iterations: args.max_iterations,
+    };
+    tracing::info!(
+        "Calibrating {} to avg edge {:.2} over {} simulations ({} steps each)...",
+        args.reference,
+        args.target,
+        args.simulations,
+        args.steps
+    );
+    let calibration = calibrate::calibrate(&spec, |configs| {
+        let result = runner::run_batch_native(
+            reference.swap_fn(),
+            reference.after_swap_fn(),
+            normalizer_swap,
+            Some(normalizer_after_swap_fn),
+            configs,
+            n_workers,
+        )?;
+        Ok(result.avg_edge())
+    })?;
+
+    let best = calibration.best;
+    println!(
+        "\n{} after {} batches: difficulty {:.4}, avg edge {:.2} (target {:.2})",
+        if calibration.converged {
+            "Converged"
+        } else {
+            "Did not converge"
+        },
+        calibration.steps.len(),
+        best.difficulty,
+        best.avg_edge,
+        args.target
+    );
+    let v = &calibration.variance;
+    println!("\nCalibrated variance box:");
+    println!(
+        "  gbm_sigma:           [{:.6}, {:.6}]",
+        v.gbm_sigma_min, v.gbm_sigma_max
+    );
+    println!(
+        "  norm_fee_bps:        [{}, {}]",
+        v.norm_fee_bps_min, v.norm_fee_bps_max
+    );
+    println!(
+        "  norm_liquidity_mult: [{:.4}, {:.4}]",
+        v.norm_liquidity_mult_min, v.norm_liquidity_mult_max
+    );
+
+    if !calibration.converged {
+        anyhow::bail!(
+            "Closest avg edge {:.2} is more than {} from the target; raise --max-iterations or \
+             --simulations",
+            best.avg_edge,
+            args.tol
+        );
+    }
+    Ok(())
+}
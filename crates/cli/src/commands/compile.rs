@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{
@@ -40,6 +41,7 @@ pub fn ensure_build_dir(safe_source: &str) -> anyhow::Result<PathBuf> {
     let build_key = format!("{:016x}", hasher.finish());
 
     let build_dir = PathBuf::from(BUILD_RUNS_DIR).join(build_key);
+    tracing::debug!(build_dir = %build_dir.display(), "submission build directory");
     std::fs::create_dir_all(build_dir.join("src"))?;
 
     let cargo_toml = cargo_toml_with_sdk_path();
@@ -74,16 +76,16 @@ pub fn compile_native(rs_file: &str) -> anyhow::Result<PathBuf> {
     let safe_source = make_safe_submission_source(rs_path)?;
     let build_dir = ensure_build_dir(&safe_source)?;
 
-    let status = Command::new("cargo")
+    let mut cargo = Command::new("cargo");
+    cargo
         .arg("build")
         .arg("--release")
         .arg("--manifest-path")
         .arg(build_dir.join("Cargo.toml"))
         .arg("--features")
-        .arg("no-entrypoint")
-        .status()?;
+        .arg("no-entrypoint");
 
-    if !status.success() {
+    if !run_cargo(&mut cargo)? {
         anyhow::bail!("Native build failed");
     }
 
@@ -99,19 +101,32 @@ pub fn compile_bpf(rs_file: &str) -> anyhow::Result<PathBuf> {
     let safe_source = make_safe_submission_source(rs_path)?;
     let build_dir = ensure_build_dir(&safe_source)?;
 
-    let status = Command::new("cargo")
+    let mut cargo = Command::new("cargo");
+    cargo
         .arg("build-sbf")
         .arg("--manifest-path")
-        .arg(build_dir.join("Cargo.toml"))
-        .status()?;
+        .arg(build_dir.join("Cargo.toml"));
 
-    if !status.success() {
+    if !run_cargo(&mut cargo)? {
         anyhow::bail!("BPF build failed");
     }
 
     find_bpf_so(&build_dir)
 }
 
+/// Run a cargo build, returning whether it succeeded. Cargo's own output counts as `info`
+/// logging: below that level it is captured and only replayed if the build fails.
+fn run_cargo(cargo: &mut Command) -> anyhow::Result<bool> {
+    if tracing::enabled!(tracing::Level::INFO) {
+        return Ok(cargo.status()?.success());
+    }
+    let output = cargo.arg("--quiet").output()?;
+    if !output.status.success() {
+        std::io::stderr().write_all(&output.stderr)?;
+    }
+    Ok(output.status.success())
+}
+
 fn find_native_lib(build_dir: &Path) -> anyhow::Result<PathBuf> {
     let release_dir = build_dir.join("target").join("release");
     let ext = if cfg!(target_os = "macos") {
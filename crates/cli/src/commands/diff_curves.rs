@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueHint;
+use prop_amm_executor::{BpfExecutor, BpfProgram, NativeExecutor};
+use prop_amm_shared::config::{INITIAL_PRICE, INITIAL_X};
+use prop_amm_shared::instruction::STORAGE_SIZE;
+use prop_amm_shared::nano::{f64_to_nano, nano_to_f64};
+
+use super::compile;
+use super::native_lib::LoadedSubmission;
+
+/// Reserve X levels, as multiples of the initial reserve.
+const RESERVE_SCALES: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+/// Pool prices (Y per X), as multiples of the initial price.
+const PRICE_SCALES: [f64; 3] = [0.5, 1.0, 2.0];
+/// Smallest and largest trade, as fractions of the input-side reserve.
+const MIN_INPUT_FRAC: f64 = 1e-5;
+const MAX_INPUT_FRAC: f64 = 0.5;
+const MAX_REPORTED_REGIONS: usize = 10;
+
+#[derive(clap::Args)]
+pub struct DiffCurvesArgs {
+    /// Baseline program: a .rs source, or a prebuilt library for the selected backend
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub a: String,
+    /// Program to compare against the baseline, in the same form as A
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub b: String,
+    /// Evaluate BPF programs instead of native libraries
+    #[arg(long)]
+    pub bpf: bool,
+    /// Input sizes per (side, reserves) region, log-spaced
+    #[arg(long, default_value = "24")]
+    pub sizes: usize,
+    /// Relative output divergence tolerated before the curves count as different
+    #[arg(long, default_value = "1e-9")]
+    pub tol: f64,
+}
+
+/// One quote on the grid. Quotes use empty storage, i.e. the program's initial state.
+#[derive(Clone, Copy)]
+struct GridPoint {
+    side: u8,
+    reserve_x: f64,
+    reserve_y: f64,
+    input: f64,
+}
+
+/// Largest divergence within one (side, reserves) region of the grid.
+struct Region {
+    side: u8,
+    reserve_x: f64,
+    reserve_y: f64,
+    max_rel: f64,
+    max_abs: f64,
+    /// Input size at which `max_rel` occurs.
+    worst_input: f64,
+    differing: usize,
+    points: usize,
+}
+
+pub fn run(args: DiffCurvesArgs) -> anyhow::Result<()> {
+    if args.sizes < 2 {
+        anyhow::bail!("--sizes must be >= 2");
+    }
+    if args.tol.is_nan() || args.tol < 0.0 {
+        anyhow::bail!("--tol must be >= 0");
+    }
+
+    let grid = build_grid(args.sizes);
+    let outputs_a = evaluate(&args.a, args.bpf, &grid)?;
+    let outputs_b = evaluate(&args.b, args.bpf, &grid)?;
+
+    let mut regions: Vec<Region> = Vec::new();
+    let mut total_abs = 0.0;
+    let mut max_abs = 0.0_f64;
+    let mut max_rel = 0.0_f64;
+    let mut differing = 0usize;
+    for ((point, &out_a), &out_b) in grid.iter().zip(&outputs_a).zip(&outputs_b) {
+        let (a, b) = (nano_to_f64(out_a), nano_to_f64(out_b));
+        let abs = (a - b).abs();
+        let rel = if abs == 0.0 { 0.0 } else { abs / a.abs().max(b.abs()) };
+        total_abs += abs;
+        max_abs = max_abs.max(abs);
+        max_rel = max_rel.max(rel);
+        if rel > args.tol {
+            differing += 1;
+        }
+
+        let region = match regions.last_mut() {
+            Some(r)
+                if r.side == point.side
+                    && r.reserve_x == point.reserve_x
+                    && r.reserve_y == point.reserve_y =>
+            {
+                r
+            }
+            _ => {
+                regions.push(Region {
+                    side: point.side,
+                    reserve_x: point.reserve_x,
+                    reserve_y: point.reserve_y,
+                    max_rel: 0.0,
+                    max_abs: 0.0,
+                    worst_input: point.input,
+                    differing: 0,
+                    points: 0,
+                });
+                regions.last_mut().expect("region just pushed")
+            }
+        };
+        region.points += 1;
+        region.max_abs = region.max_abs.max(abs);
+        if rel > region.max_rel {
+            region.max_rel = rel;
+            region.worst_input = point.input;
+        }
+        if rel > args.tol {
+            region.differing += 1;
+        }
+    }
+
+    println!("Compared {} quotes ({} vs {})", grid.len(), args.a, args.b);
+    println!("  Max abs divergence: {:.9}", max_abs);
+    println!("  Avg abs divergence: {:.9}", total_abs / grid.len() as f64);
+    println!("  Max rel divergence: {:.3e}", max_rel);
+    println!(
+        "  Differing quotes:   {} ({:.1}%, tol {:.0e})",
+        differing,
+        100.0 * differing as f64 / grid.len() as f64,
+        args.tol
+    );
+
+    if differing == 0 {
+        println!("\nCurves match.");
+        return Ok(());
+    }
+
+    regions.retain(|r| r.differing > 0);
+    regions.sort_by(|a, b| b.max_rel.total_cmp(&a.max_rel));
+    println!("\nRegions that differ (worst first):");
+    println!(
+        "  {:<7} {:>10} {:>12} {:>12} {:>10} {:>12} {:>9}",
+        "side", "reserve_x", "reserve_y", "worst input", "max rel", "max abs", "differ"
+    );
+    for r in regions.iter().take(MAX_REPORTED_REGIONS) {
+        println!(
+            "  {:<7} {:>10.3} {:>12.3} {:>12.6} {:>10.3e} {:>12.6} {:>5}/{:<3}",
+            if r.side == 0 { "buy X" } else { "sell X" },
+            r.reserve_x,
+            r.reserve_y,
+            r.worst_input,
+            r.max_rel,
+            r.max_abs,
+            r.differing,
+            r.points
+        );
+    }
+    if regions.len() > MAX_REPORTED_REGIONS {
+        println!("  ... and {} more", regions.len() - MAX_REPORTED_REGIONS);
+    }
+    anyhow::bail!("Curves differ in {} of {} quotes", differing, grid.len())
+}
+
+/// Grid points ordered so that each (side, reserves) region is contiguous.
+fn build_grid(sizes: usize) -> Vec<GridPoint> {
+    let mut grid = Vec::with_capacity(2 * RESERVE_SCALES.len() * PRICE_SCALES.len() * sizes);
+    for side in [0u8, 1] {
+        for reserve_scale in RESERVE_SCALES {
+            for price_scale in PRICE_SCALES {
+                let reserve_x = INITIAL_X * reserve_scale;
+                let reserve_y = reserve_x * INITIAL_PRICE * price_scale;
+                let input_reserve = if side == 0 { reserve_y } else { reserve_x };
+                for i in 0..sizes {
+                    let t = i as f64 / (sizes - 1) as f64;
+                    let frac = MIN_INPUT_FRAC * (MAX_INPUT_FRAC / MIN_INPUT_FRAC).powf(t);
+                    grid.push(GridPoint {
+                        side,
+                        reserve_x,
+                        reserve_y,
+                        input: input_reserve * frac,
+                    });
+                }
+            }
+        }
+    }
+    grid
+}
+
+/// Quote every grid point with one program. Native libraries are loaded one at a time, so
+/// each program is evaluated in full before the next is loaded.
+fn evaluate(program: &str, bpf: bool, grid: &[GridPoint]) -> anyhow::Result<Vec<u64>> {
+    let storage = [0u8; STORAGE_SIZE];
+    let is_source = Path::new(program).extension().is_some_and(|e| e == "rs");
+
+    if bpf {
+        let so_path = if is_source {
+            tracing::info!("Compiling {} (BPF)...", program);
+            compile::compile_bpf(program)?
+        } else {
+            PathBuf::from(program)
+        };
+        let bytes = std::fs::read(&so_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", so_path.display(), e))?;
+        let loaded = BpfProgram::load(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to load BPF program {}: {}", program, e))?;
+        let mut executor = BpfExecutor::new(loaded);
+        return grid
+            .iter()
+            .map(|p| {
+                executor
+                    .execute(
+                        p.side,
+                        f64_to_nano(p.input),
+                        f64_to_nano(p.reserve_x),
+                        f64_to_nano(p.reserve_y),
+                        &storage,
+                    )
+                    .map(|metered| metered.value)
+                    .map_err(|e| anyhow::anyhow!("{} quote failed: {}", program, e))
+            })
+            .collect();
+    }
+
+    let native_path = if is_source {
+        tracing::info!("Compiling {} (native)...", program);
+        compile::compile_native(program)?
+    } else {
+        PathBuf::from(program)
+    };
+    let submission = LoadedSubmission::load(&native_path)?;
+    let executor = NativeExecutor::new(submission.swap_fn(), None);
+    Ok(grid
+        .iter()
+        .map(|p| {
+            executor.execute(
+                p.side,
+                f64_to_nano(p.input),
+                f64_to_nano(p.reserve_x),
+                f64_to_nano(p.reserve_y),
+                &storage,
+            )
+        })
+        .collect())
+}
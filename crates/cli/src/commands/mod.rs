@@ -1,4 +1,11 @@
 pub mod build;
+pub mod calibrate;
 pub mod compile;
+pub mod diff_curves;
+pub mod native_lib;
+pub mod plot;
+pub mod repro;
 pub mod run;
+pub mod submit;
 pub mod validate;
+pub mod verify_manifest;
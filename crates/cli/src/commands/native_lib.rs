@@ -0,0 +1,159 @@
+//! Loading compiled submission dylibs for native simulation.
+//!
+//! The simulator takes plain `fn` pointers, so calls go through process-wide trampolines that
+//! forward to whichever library is currently loaded. Only one library can be loaded at a
+//! time; a `LoadedSubmission` handle owns it, and dropping the handle unloads it. Watch mode
+//! drops each generation before loading the next, so it can run indefinitely without
+//! accumulating libraries.
+//!
+//! A trampoline holds a read lock on the loaded symbols for the whole call, and unloading
+//! takes the write lock, so a library is never closed under a running call. A trampoline
+//! called while no library is loaded panics, which the batch runner reports as a failed
+//! simulation, instead of calling into a closed library.
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+use prop_amm_executor::{AfterSwapFn, SwapFn};
+
+use super::compile;
+
+type FfiSwapFn = unsafe extern "C" fn(*const u8, usize) -> u64;
+type FfiAfterSwapFn = unsafe extern "C" fn(*const u8, usize, *mut u8, usize);
+
+/// Entry points of the loaded library, valid while it stays open.
+struct LoadedSymbols {
+    generation: u64,
+    swap: FfiSwapFn,
+    after_swap: Option<FfiAfterSwapFn>,
+}
+
+/// The library the trampolines currently forward to; `None` while none is loaded.
+static LOADED: RwLock<Option<LoadedSymbols>> = RwLock::new(None);
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// The loaded symbols, locked against unloading until the guard drops.
+fn loaded_symbols() -> RwLockReadGuard<'static, Option<LoadedSymbols>> {
+    // The guarded data is plain pointers, so a panic under the lock cannot corrupt it.
+    LOADED.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn dynamic_swap(data: &[u8]) -> u64 {
+    let loaded = loaded_symbols();
+    let Some(symbols) = loaded.as_ref() else {
+        panic!("submission compute_swap called after its library was unloaded");
+    };
+    unsafe { (symbols.swap)(data.as_ptr(), data.len()) }
+}
+
+fn dynamic_after_swap(data: &[u8], storage: &mut [u8]) {
+    let loaded = loaded_symbols();
+    let Some(after_swap) = loaded.as_ref().and_then(|symbols| symbols.after_swap) else {
+        panic!("submission after_swap called after its library was unloaded");
+    };
+    unsafe {
+        after_swap(
+            data.as_ptr(),
+            data.len(),
+            storage.as_mut_ptr(),
+            storage.len(),
+        )
+    }
+}
+
+/// Handle to the currently loaded submission library.
+///
+/// The functions from `swap_fn`/`after_swap_fn` call whichever library is loaded when they
+/// run: this one until the handle drops, after which they panic (or, once another library
+/// is loaded, call that one). Drop the handle only after the batch using them has returned.
+pub struct LoadedSubmission {
+    generation: u64,
+    has_after_swap: bool,
+    _library: libloading::Library,
+}
+
+impl LoadedSubmission {
+    pub fn load(native_path: &Path) -> anyhow::Result<Self> {
+        // Held until the symbols are published, so two loads cannot race.
+        let mut loaded = LOADED.write().unwrap_or_else(PoisonError::into_inner);
+        if loaded.is_some() {
+            anyhow::bail!("Another submission library is still loaded; drop it first");
+        }
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        let (submission, symbols) = Self::open(native_path, generation)?;
+        *loaded = Some(symbols);
+        Ok(submission)
+    }
+
+    fn open(native_path: &Path, generation: u64) -> anyhow::Result<(Self, LoadedSymbols)> {
+        let library = unsafe { libloading::Library::new(native_path) }.map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load native library {}: {}",
+                native_path.display(),
+                e
+            )
+        })?;
+
+        let swap: FfiSwapFn = *unsafe {
+            library
+                .get::<FfiSwapFn>(compile::NATIVE_SWAP_SYMBOL)
+                .or_else(|_| library.get::<FfiSwapFn>(b"compute_swap_ffi"))
+        }
+        .map_err(|e| anyhow::anyhow!("Missing native swap symbol: {}", e))?;
+
+        let after_swap: Option<FfiAfterSwapFn> = unsafe {
+            library
+                .get::<FfiAfterSwapFn>(compile::NATIVE_AFTER_SWAP_SYMBOL)
+                .or_else(|_| library.get::<FfiAfterSwapFn>(b"after_swap_ffi"))
+        }
+        .ok()
+        .map(|symbol| *symbol);
+
+        let has_after_swap = after_swap.is_some();
+        tracing::debug!(
+            path = %native_path.display(),
+            generation,
+            has_after_swap,
+            "loaded submission library"
+        );
+        let symbols = LoadedSymbols {
+            generation,
+            swap,
+            after_swap,
+        };
+        let submission = Self {
+            generation,
+            has_after_swap,
+            _library: library,
+        };
+        Ok((submission, symbols))
+    }
+
+    /// Increases by at least one every time a library is loaded.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn swap_fn(&self) -> SwapFn {
+        dynamic_swap
+    }
+
+    pub fn after_swap_fn(&self) -> Option<AfterSwapFn> {
+        if self.has_after_swap {
+            Some(dynamic_after_swap)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for LoadedSubmission {
+    fn drop(&mut self) {
+        // Waits out calls in flight; later ones find no library and panic.
+        let mut loaded = LOADED.write().unwrap_or_else(PoisonError::into_inner);
+        debug_assert_eq!(loaded.as_ref().map(|s| s.generation), Some(self.generation));
+        *loaded = None;
+        tracing::debug!(generation = self.generation, "unloading submission library");
+        // `_library` is dropped after this, closing the handle.
+    }
+}
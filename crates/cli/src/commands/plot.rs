@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use clap::ValueHint;
+use plotters::prelude::*;
+use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig, BASELINE_STEPS};
+use prop_amm_shared::scoring::{RiskPolicy, ShapePenaltyPolicy};
+use prop_amm_sim::runner;
+
+use super::run::{self, BatchPlan};
+use crate::export::{self, SeedRow};
+
+const PLOT_DEFAULT_SIMS: u32 = 200;
+const PLOT_SIZE: (u32, u32) = (1200, 500);
+
+#[derive(clap::Args)]
+pub struct PlotArgs {
+    /// Results export from `run --export` (.csv/.json), or a submission .rs to run first
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub input: String,
+    /// Output SVG path
+    #[arg(short, long, default_value = "edge.svg", value_hint = ValueHint::FilePath)]
+    pub output: String,
+    /// Number of histogram bins
+    #[arg(long, default_value = "30")]
+    pub bins: usize,
+    /// Number of simulations when INPUT is a .rs source
+    #[arg(long, default_value_t = PLOT_DEFAULT_SIMS)]
+    pub simulations: u32,
+    /// Number of steps per simulation when INPUT is a .rs source
+    #[arg(long, default_value_t = BASELINE_STEPS)]
+    pub steps: u32,
+    /// Number of parallel workers (0 = auto)
+    #[arg(long, default_value = "0")]
+    pub workers: usize,
+}
+
+pub fn run(args: PlotArgs) -> anyhow::Result<()> {
+    let output = Path::new(&args.output);
+    if !output
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+    {
+        anyhow::bail!("Only SVG output is supported (got {})", args.output);
+    }
+    if args.bins == 0 {
+        anyhow::bail!("--bins must be >= 1");
+    }
+
+    let input = Path::new(&args.input);
+    let rows = if input.extension().is_some_and(|e| e == "rs") {
+        let plan = BatchPlan {
+            configs: runner::default_configs(args.simulations, args.steps, 0, 1),
+            base: SimulationConfig {
+                n_steps: args.steps,
+                ..SimulationConfig::default()
+            },
+            seeds: "seeds 0 + i*1".to_string(),
+            risk: RiskPolicy::default(),
+            shape_penalty: ShapePenaltyPolicy::default(),
+            variance: HyperparameterVariance::default(),
+            early_stop: None,
+        };
+        let n_workers = if args.workers == 0 {
+            None
+        } else {
+            Some(args.workers)
+        };
+        export::rows(&run::run_native(&args.input, None, &plan, n_workers)?.result)
+    } else {
+        export::read_results(input)?
+    };
+    if rows.is_empty() {
+        anyhow::bail!("No results to plot");
+    }
+
+    draw(output, &rows, args.bins)?;
+    tracing::info!("Wrote edge plot for {} seeds to {}", rows.len(), args.output);
+    Ok(())
+}
+
+/// Histogram of per-seed edge (left) and edge against seed (right).
+fn draw(output: &Path, rows: &[SeedRow], bins: usize) -> anyhow::Result<()> {
+    let edges: Vec<f64> = rows.iter().map(|r| r.edge).collect();
+    let mean = edges.iter().sum::<f64>() / edges.len() as f64;
+    let mut lo = edges.iter().copied().fold(f64::INFINITY, f64::min);
+    let mut hi = edges.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if hi - lo < 1e-9 {
+        lo -= 1.0;
+        hi += 1.0;
+    }
+    let bin_width = (hi - lo) / bins as f64;
+    let mut counts = vec![0u32; bins];
+    for edge in &edges {
+        let bin = (((edge - lo) / bin_width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0) + 1;
+
+    let root = SVGBackend::new(output, PLOT_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+    let (left, right) = root.split_horizontally(PLOT_SIZE.0 / 2);
+
+    let mut histogram = ChartBuilder::on(&left)
+        .caption("Edge distribution", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(35)
+        .y_label_area_size(45)
+        .build_cartesian_2d(lo..hi, 0u32..max_count)?;
+    histogram
+        .configure_mesh()
+        .x_desc("edge")
+        .y_desc("simulations")
+        .draw()?;
+    histogram.draw_series(counts.iter().enumerate().map(|(i, &count)| {
+        let x0 = lo + i as f64 * bin_width;
+        Rectangle::new([(x0, 0), (x0 + bin_width, count)], BLUE.mix(0.6).filled())
+    }))?;
+    histogram
+        .draw_series(LineSeries::new([(mean, 0), (mean, max_count)], RED))?
+        .label(format!("mean {mean:.2}"))
+        .legend(|(x, y)| PathElement::new([(x, y), (x + 15, y)], RED));
+    histogram
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    let seed_lo = rows.iter().map(|r| r.seed).min().unwrap_or(0) as f64;
+    let seed_hi = rows.iter().map(|r| r.seed).max().unwrap_or(0) as f64 + 1.0;
+    let mut scatter = ChartBuilder::on(&right)
+        .caption("Edge by seed", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(35)
+        .y_label_area_size(45)
+        .build_cartesian_2d(seed_lo..seed_hi, lo.min(0.0)..hi.max(0.0))?;
+    scatter
+        .configure_mesh()
+        .x_desc("seed")
+        .y_desc("edge")
+        .draw()?;
+    scatter.draw_series(LineSeries::new([(seed_lo, 0.0), (seed_hi, 0.0)], BLACK))?;
+    scatter.draw_series(
+        rows.iter()
+            .map(|r| Circle::new((r.seed as f64, r.edge), 2, BLUE.filled())),
+    )?;
+
+    root.present()?;
+    Ok(())
+}
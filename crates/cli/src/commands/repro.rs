@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use clap::ValueHint;
+use prop_amm_shared::config::HyperparameterVariance;
+use prop_amm_shared::result::SimResult;
+use prop_amm_shared::scoring::{RiskPolicy, ShapePenaltyPolicy};
+use prop_amm_sim::engine::RngStreams;
+use prop_amm_sim::runner;
+
+use super::run::{self, BatchPlan};
+use super::validate::PARITY_ABS_TOL;
+use crate::manifest::{self, Backend};
+use crate::repro::{ProblemKind, ReproBundle, SOURCE_FILE};
+
+#[derive(clap::Args)]
+pub struct ReproArgs {
+    /// Reproducer directory written by `prop-amm run` or `prop-amm validate`
+    #[arg(value_hint = ValueHint::DirPath)]
+    pub dir: String,
+    /// Prebuilt BPF .so to use instead of compiling the bundled source
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub bpf_so: Option<String>,
+    /// Prebuilt native dylib to use instead of compiling the bundled source
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub native_so: Option<String>,
+}
+
+pub fn run(args: ReproArgs) -> anyhow::Result<()> {
+    let dir = Path::new(&args.dir);
+    let bundle = ReproBundle::read(dir)?;
+    let source = dir.join(SOURCE_FILE);
+    let file = source.to_string_lossy().into_owned();
+    println!(
+        "Reproducing {} for seed {} ({} steps): {}",
+        bundle.problem, bundle.config.seed, bundle.config.n_steps, bundle.detail
+    );
+
+    let source_hash = manifest::hash_file(&source)?;
+    if source_hash != bundle.source_hash {
+        anyhow::bail!(
+            "FAIL: Source hash mismatch for {}: {} (reproducer: {})",
+            file,
+            source_hash,
+            bundle.source_hash
+        );
+    }
+    println!("  [PASS] Source hash: {}", source_hash);
+
+    // The price path and order flow are regenerated from these seeds, not stored.
+    let derived = RngStreams::for_config(&bundle.config);
+    if derived != bundle.rng_streams {
+        anyhow::bail!(
+            "FAIL: This build derives the simulation's RNG streams differently\n  \
+             reproducer: {:?}\n  derived:    {:?}",
+            bundle.rng_streams,
+            derived
+        );
+    }
+    println!("  [PASS] RNG streams");
+
+    let plan = BatchPlan {
+        configs: vec![bundle.config.clone()],
+        base: bundle.config.clone(),
+        seeds: format!("seed {} from {}", bundle.config.seed, args.dir),
+        risk: RiskPolicy::default(),
+        shape_penalty: ShapePenaltyPolicy::default(),
+        variance: HyperparameterVariance::default(),
+        early_stop: None,
+    };
+    let reproduced = match bundle.problem {
+        ProblemKind::Parity => reproduce_parity(&args, &file, &plan)?,
+        ProblemKind::SimulationFailed | ProblemKind::ShapeIssues => {
+            reproduce_single(&args, &bundle, &file, &plan)?
+        }
+    };
+
+    println!();
+    if !reproduced {
+        anyhow::bail!("Did not reproduce: {}", bundle.problem);
+    }
+    println!("Reproduced: {}", bundle.problem);
+    Ok(())
+}
+
+fn reproduce_single(
+    args: &ReproArgs,
+    bundle: &ReproBundle,
+    file: &str,
+    plan: &BatchPlan,
+) -> anyhow::Result<bool> {
+    let outcome = match bundle.backend {
+        Backend::Native => run::run_native(file, args.native_so.as_deref(), plan, Some(1)),
+        Backend::Bpf => run::run_bpf(file, args.bpf_so.as_deref(), plan, Some(1)),
+    };
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            let Some(failed) = err.downcast_ref::<runner::SimulationFailed>() else {
+                return Err(err);
+            };
+            println!("  Simulation failed: {}", failed.message);
+            return Ok(bundle.problem == ProblemKind::SimulationFailed);
+        }
+    };
+
+    // Compiled bytes depend on the toolchain, so a differing artifact is reported, not fatal.
+    if let Some(recorded) = &bundle.artifact_hash {
+        let artifact_hash = manifest::hash_file(&outcome.artifact)?;
+        if artifact_hash == *recorded {
+            println!("  [PASS] Artifact hash: {}", artifact_hash);
+        } else {
+            println!(
+                "  [WARN] Artifact hash differs: {} (reproducer: {}); different toolchain?",
+                artifact_hash, recorded
+            );
+        }
+    }
+
+    let result = &outcome.result.results[0];
+    if let Some(expected) = &bundle.expected {
+        let trade_hash = format!("{:016x}", result.trade_hash);
+        if trade_hash == expected.trade_hash
+            && result.submission_edge.to_bits() == expected.submission_edge.to_bits()
+        {
+            println!("  [PASS] Edge {} and trade hash {}", expected.submission_edge, trade_hash);
+        } else {
+            println!(
+                "  [WARN] Edge {} / trade hash {} differ from the reproducer ({} / {})",
+                result.submission_edge, trade_hash, expected.submission_edge, expected.trade_hash
+            );
+        }
+    }
+    Ok(bundle.problem == ProblemKind::ShapeIssues && !result.shape.is_clean())
+}
+
+fn reproduce_parity(args: &ReproArgs, file: &str, plan: &BatchPlan) -> anyhow::Result<bool> {
+    let native = single_result(run::run_native(file, args.native_so.as_deref(), plan, Some(1)))?;
+    let bpf = single_result(run::run_bpf(file, args.bpf_so.as_deref(), plan, Some(1)))?;
+    let delta = (native.submission_edge - bpf.submission_edge).abs();
+    println!(
+        "  native_edge={:.9} bpf_edge={:.9} delta={:.9} tol={:.9}",
+        native.submission_edge, bpf.submission_edge, delta, PARITY_ABS_TOL
+    );
+    Ok(delta > PARITY_ABS_TOL)
+}
+
+fn single_result(outcome: anyhow::Result<run::RunOutcome>) -> anyhow::Result<SimResult> {
+    let mut outcome = outcome?;
+    Ok(outcome.result.results.swap_remove(0))
+}
@@ -1,161 +1,1176 @@
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use prop_amm_executor::{AfterSwapFn, BpfProgram};
+use clap::ValueHint;
+use prop_amm_executor::BpfProgram;
+use prop_amm_shared::config::{
+    AfterSwapFailurePolicy, ArbInventory, ArrivalCycle, ArrivalWindow, BaselineVenue, Clock,
+    EdgeMode, ExecutionMode, HyperparameterVariance, LiquidityEvent, MomentumCohort, PriceGap,
+    RetailProfile, RouterKind, Scenario, ShapeViolationPolicy, SimulationConfig, StepSequencing,
+    VolatilityRegimes, BASELINE_SIMS, GBM_DT, INITIAL_LIQUIDITY, MIN_TRADE_SIZE,
+};
+use prop_amm_shared::nano::{TokenDecimals, MAX_TOKEN_DECIMALS};
 use prop_amm_shared::normalizer::{
     after_swap as normalizer_after_swap_fn, compute_swap as normalizer_swap,
 };
-use prop_amm_sim::runner;
+use prop_amm_shared::result::{BatchResult, SimResult};
+use prop_amm_shared::scoring::{RiskPolicy, ShapePenaltyPolicy};
+use prop_amm_sim::engine::RngStreams;
+use prop_amm_sim::runner::{self, EarlyStop};
 
 use super::compile;
+use super::native_lib::LoadedSubmission;
+use crate::export;
+use crate::manifest::{self, Backend, RunManifest};
 use crate::output;
+use crate::repro::{self, ProblemKind, ReproBundle};
+
+const WATCH_DEFAULT_SIMS: u32 = 50;
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
-type FfiSwapFn = unsafe extern "C" fn(*const u8, usize) -> u64;
-type FfiAfterSwapFn = unsafe extern "C" fn(*const u8, usize, *mut u8, usize);
+#[derive(clap::Args)]
+pub struct RunArgs {
+    /// Path to the .rs source file
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: String,
+    /// Number of simulations [default: 1000, or 50 with --watch]; the maximum with
+    /// --target-ci
+    #[arg(long)]
+    pub simulations: Option<u32>,
+    /// Stop early once the 95% confidence interval on average edge is within +/- this
+    /// much edge (checked every 32 simulations)
+    #[arg(long, value_name = "EDGE", conflicts_with = "watch")]
+    pub target_ci: Option<f64>,
+    /// Minimum simulations before --target-ci may stop the run
+    #[arg(long, default_value = "32", requires = "target_ci")]
+    pub min_simulations: usize,
+    /// Number of steps per simulation
+    #[arg(long, default_value = "10000")]
+    pub steps: u32,
+    /// Draw each simulation's step count uniformly from MIN:MAX instead of using --steps
+    #[arg(long, value_name = "MIN:MAX", conflicts_with = "steps", value_parser = parse_steps_range)]
+    pub steps_range: Option<(u32, u32)>,
+    /// Chance that a simulation contains one sampled order-flow imbalance regime, in [0, 1]
+    #[arg(long, value_name = "P", default_value = "0", value_parser = parse_probability)]
+    pub flow_regime_prob: f64,
+    /// Unscored steps run before each simulation so stateful strategies can warm up
+    #[arg(long, default_value = "0")]
+    pub warmup: u32,
+    /// Chain this many sessions of --steps per simulation: pools and the fair price reopen
+    /// at their starting values each session, but the submission's storage persists
+    #[arg(long, default_value = "1")]
+    pub sessions: u32,
+    /// Length of a step in the time unit volatility, retail arrival rates and the
+    /// arbitrageur's arrival probability are quoted per: 1 keeps them per-step, 1/60 runs
+    /// the same market on steps sixty times finer (pair it with sixty times the --steps)
+    #[arg(long, value_name = "DT", default_value_t = GBM_DT)]
+    pub dt: f64,
+    /// Number of parallel workers (0 = auto)
+    #[arg(long, default_value = "0")]
+    pub workers: usize,
+    /// Starting seed for simulation config generation
+    #[arg(long, default_value = "0")]
+    pub seed_start: u64,
+    /// Seed step between simulations
+    #[arg(long, default_value = "1")]
+    pub seed_stride: u64,
+    /// File of explicit seeds to run instead of seed_start + i*seed_stride: one per line,
+    /// or CSV whose first column is the seed (header row and `#` comments are skipped)
+    #[arg(
+        long,
+        conflicts_with_all = ["simulations", "seed_start", "seed_stride"],
+        value_hint = ValueHint::FilePath,
+    )]
+    pub seeds_file: Option<String>,
+    /// Use BPF runtime instead of native (slower, for validation)
+    #[arg(long)]
+    pub bpf: bool,
+    /// Path to a prebuilt BPF .so to use when running with --bpf (skips compilation).
+    /// Useful on machines without the Solana SBF toolchain installed.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub bpf_so: Option<String>,
+    /// Quote the normalizer as its embedded BPF program instead of its native build, for a
+    /// BPF-vs-BPF reference run (needs a build with the `prebuilt-programs` feature)
+    #[arg(long, requires = "bpf")]
+    pub bpf_normalizer: bool,
+    /// Path to a prebuilt native dylib to use instead of compiling FILE (e.g. the
+    /// library printed by `prop-amm build`)
+    #[arg(long, conflicts_with = "bpf", value_hint = ValueHint::FilePath)]
+    pub native_so: Option<String>,
+    /// Quote latency handicap: after a step whose mean CU per quote exceeds this budget,
+    /// the submission is quoted after the normalizer and loses routing ties (BPF only)
+    #[arg(long, requires = "bpf")]
+    pub quote_cu_budget: Option<u64>,
+    /// Add a constant-product baseline venue competing for flow alongside the normalizer,
+    /// with this fee and liquidity multiple of the submission's reserves (default 1.0).
+    /// Repeatable.
+    #[arg(long, value_name = "FEE_BPS[:LIQ_MULT]", value_parser = parse_extra_venue)]
+    pub extra_venue: Vec<BaselineVenue>,
+    /// Split orders across the normalizer and extra venues by their quotes alone, chunk by
+    /// chunk, instead of in closed form as constant-product pools
+    #[arg(long)]
+    pub black_box_baselines: bool,
+    /// Scale every pool's reserves by SCALE at the start of scored step STEP, as an LP
+    /// deposit (SCALE > 1) or withdrawal (SCALE < 1). Repeatable.
+    #[arg(long, value_name = "STEP:SCALE", value_parser = parse_liquidity_event)]
+    pub liquidity_event: Vec<LiquidityEvent>,
+    /// Send each pool's program a tag 5 call for every liquidity event (see the README)
+    #[arg(long, requires = "liquidity_event")]
+    pub liquidity_event_callbacks: bool,
+    /// Gap the fair price by SIZE (e.g. -0.05 for a 5% drop) at scored step STEP, on top of
+    /// the diffusion. Repeatable.
+    #[arg(long, value_name = "STEP:SIZE", value_parser = parse_price_gap)]
+    pub price_gap: Vec<PriceGap>,
+    /// Replay a scripted market from a JSON FILE: retail `orders` ({"step", "is_buy",
+    /// "size"}) replace the generated flow unless `background_retail` is true, and any
+    /// `prices` ({"step", "price"}) replace the price path
+    #[arg(long, value_name = "FILE")]
+    pub scenario: Option<PathBuf>,
+    /// Add COUNT gaps of +/- SIZE at random scored steps of every simulation, e.g. 3:0.05
+    #[arg(long, value_name = "COUNT:SIZE", value_parser = parse_random_gaps)]
+    pub random_gaps: Option<(u32, f64)>,
+    /// Switch the fair price's volatility between a calm and a volatile regime: each step a
+    /// calm market turns volatile with probability P_UP and a volatile one calms with
+    /// probability P_DOWN, e.g. 0.001:0.004:0.002:0.01. Replaces the sampled sigma
+    #[arg(
+        long,
+        value_name = "CALM_SIGMA:VOL_SIGMA:P_UP:P_DOWN",
+        value_parser = parse_volatility_regimes
+    )]
+    pub volatility_regimes: Option<VolatilityRegimes>,
+    /// Draw price shocks from a Student-t distribution with DF degrees of freedom (above 2),
+    /// scaled to the same volatility, instead of a Gaussian: lower DF gives fatter tails
+    #[arg(long, value_name = "DF")]
+    pub shock_df: Option<f64>,
+    /// Starting value of the submission pool in Y, split evenly between its reserves;
+    /// baselines scale it by their liquidity multiples
+    #[arg(long, value_name = "Y", default_value_t = INITIAL_LIQUIDITY)]
+    pub initial_liquidity: f64,
+    /// Open every pool at this fraction above the fair price (negative: below), so the
+    /// simulation launches into a mispriced market, e.g. 0.05 for 5% rich
+    #[arg(long, value_name = "FRAC", default_value = "0", allow_negative_numbers = true)]
+    pub initial_mispricing: f64,
+    /// Chance that a retail order is informed, trading in the direction of the next price
+    /// move; the summary reports edge against informed flow separately
+    #[arg(long, default_value = "0")]
+    pub informed_prob: f64,
+    /// Bid/ask spread, in bps of the fair price, of the external market the arbitrageur
+    /// trades against; it buys at the ask and sells at the bid instead of at mid
+    #[arg(long, value_name = "BPS", default_value = "0")]
+    pub external_spread_bps: f64,
+    /// Fee, in bps of the notional, the arbitrageur pays to hedge on the external market;
+    /// like a spread, it widens the mispricing a pool must show before it is arbed
+    #[arg(long, value_name = "BPS", default_value = "0")]
+    pub arb_taker_fee_bps: f64,
+    /// Chance the arbitrageur checks the pools in a given step; below 1, mispricings
+    /// persist between its visits and retail trades against them
+    #[arg(long, value_name = "P", default_value = "1")]
+    pub arb_arrival_prob: f64,
+    /// Size arbitrage on a submission that quotes like constant product with a fee in
+    /// closed form, fitted from a few quotes each step, instead of searching; submissions
+    /// that do not fit are searched as usual
+    #[arg(long)]
+    pub arb_cp_fast_path: bool,
+    /// After arbitraging each venue against the fair price, also arbitrage the submission
+    /// against the normalizer directly, buying X on the cheaper pool and selling it on the
+    /// richer without an external hedge
+    #[arg(long)]
+    pub arb_cross_venue: bool,
+    /// Perturb every arbitrage size by a log-normal factor exp(NOISE * z), so the
+    /// arbitrageur over- and undershoots instead of optimizing perfectly, e.g. 0.2
+    #[arg(long, value_name = "NOISE", default_value = "0")]
+    pub arb_size_noise: f64,
+    /// Give the arbitrageur a finite inventory of X and Y shared across every venue: no
+    /// trade spends more than it holds, and it is restocked every REBALANCE steps (never if
+    /// omitted), e.g. 50:5000:100
+    #[arg(long, value_name = "X:Y[:REBALANCE]", value_parser = parse_arb_inventory)]
+    pub arb_inventory: Option<ArbInventory>,
+    /// Correlate retail direction with each step's price move: this share of orders buy
+    /// after an up move and sell after a down move (negative: against the move), in [-1, 1]
+    #[arg(long, value_name = "C", default_value = "0", allow_negative_numbers = true)]
+    pub flow_price_correlation: f64,
+    /// Make retail order direction persistent: each trader's next order repeats its last
+    /// direction with this extra probability, in [0, 1), so flow arrives in runs
+    #[arg(long, value_name = "RHO", default_value = "0")]
+    pub flow_persistence: f64,
+    /// Largest slippage, in bps of the fair price, a retail trader accepts: an order whose
+    /// best route would average worse than this is abandoned and counted, not traded
+    #[arg(long, value_name = "BPS")]
+    pub retail_max_slippage_bps: Option<f64>,
+    /// Fill the largest part of an order that meets its slippage limit or minimum output
+    /// instead of abandoning it, and count it as partially filled
+    #[arg(long)]
+    pub partial_fills: bool,
+    /// Size sell orders by the Y their execution pays out rather than converting their Y
+    /// notional to X at the fair price, which large sells would otherwise miss
+    #[arg(long)]
+    pub exact_sell_notional: bool,
+    /// Have every retail order ask for an exact output, buys for their size in X at the fair
+    /// price and sells for their size in Y, and route the input that pays it out
+    #[arg(long)]
+    pub exact_output: bool,
+    /// Smallest input, in the input token, the router sends to any one venue; retail orders
+    /// at or below it are dropped as dust and reported in the summary
+    #[arg(long, value_name = "SIZE", default_value_t = MIN_TRADE_SIZE)]
+    pub min_trade_size: f64,
+    /// Cap every trade the router sends a venue at this fraction of the venue's input-side
+    /// reserve, like venues that revert oversized trades; orders larger than every cap
+    /// combined are cut to it and reported as partial fills
+    #[arg(long, value_name = "FRACTION")]
+    pub max_trade_fraction: Option<f64>,
+    /// Leave unfilled, and count, any retail order whose best route averages more than this
+    /// many bps from the fair price against the trader or pays out nothing, however badly
+    /// every venue quotes
+    #[arg(long, value_name = "BPS")]
+    pub price_guard_bps: Option<f64>,
+    /// Let the router observe the fair price with log-normal error exp(NOISE * z), redrawn
+    /// every step: sell orders are sized and slippage limits checked at the observed price,
+    /// while edge is still measured at the true one, e.g. 0.002
+    #[arg(long, value_name = "NOISE", default_value = "0")]
+    pub oracle_noise: f64,
+    /// Multiply every retail trader's arrival rate by MULT for scored steps START..END, e.g.
+    /// 2000:2500:4 for a burst. Repeatable; where windows overlap, the last one wins
+    #[arg(long, value_name = "START:END:MULT", value_parser = parse_arrival_window)]
+    pub arrival_window: Vec<ArrivalWindow>,
+    /// Cycle retail arrival rates by 1 + AMPLITUDE * sin(2 pi step / PERIOD), e.g.
+    /// 2500:0.8 for four busy and quiet phases in a 10,000-step simulation
+    #[arg(long, value_name = "PERIOD:AMPLITUDE", value_parser = parse_arrival_cycle)]
+    pub arrival_cycle: Option<ArrivalCycle>,
+    /// Replace the single retail trader with a mixture of classes: WEIGHT is the class's
+    /// share of retail arrivals (weights are normalized), SIZE and SIGMA its mean order size
+    /// as a multiple of the retail size and its size dispersion, BUY_PROB an optional buy
+    /// probability of its own, e.g. 0.9:0.4:0.8 and 0.1:5:1:0.7. Repeatable
+    #[arg(
+        long,
+        value_name = "WEIGHT:SIZE:SIGMA[:BUY_PROB]",
+        value_parser = parse_retail_class
+    )]
+    pub retail_class: Vec<RetailProfile>,
+    /// Mix in trend-following retail traders arriving at SCALE times the retail rate, who
+    /// buy with probability 0.5 + WEIGHT/2 after the price rose over the last LOOKBACK steps
+    /// and sell likewise after it fell, e.g. 0.5:50:0.8
+    #[arg(long, value_name = "SCALE:LOOKBACK:WEIGHT", value_parser = parse_momentum_traders)]
+    pub momentum_traders: Option<MomentumCohort>,
+    /// Score inventory-adjusted edge: flow edge plus the revaluation of each pool's
+    /// inventory at the final fair price (equal to inventory PnL), for the submission and
+    /// the normalizer alike
+    #[arg(long)]
+    pub inventory_adjusted: bool,
+    /// Net each step's retail orders into one aggregate order and route it once, instead of
+    /// routing every order in arrival order
+    #[arg(long)]
+    pub batch_auction: bool,
+    /// Find the split once per step for each direction's total input, then route every
+    /// order on its own at that split, instead of searching again for each order
+    #[arg(long, conflicts_with = "batch_auction")]
+    pub batch_routing: bool,
+    /// Drive the simulation by events instead of fixed steps: retail orders and arbitrage
+    /// checks (RATE per unit of time) arrive in continuous time and the price moves exactly
+    /// to each one. Needs one session, no warm-up, momentum traders, scenario or
+    /// --arb-arrival-prob
+    #[arg(long, value_name = "RATE")]
+    pub event_clock: Option<f64>,
+    /// How retail orders are split between the submission and the baselines: search for
+    /// the best split (golden, integer-golden over the program's integer amounts,
+    /// adaptive-golden with less effort on small orders, or warm-golden seeded from the
+    /// previous order's split), send each whole order to the side quoting more for it
+    /// (best-price), split by input-side reserves (proportional), equalize marginal prices
+    /// across the venues (marginal), or send each whole order to the single venue quoting the
+    /// most for it (winner-take-all)
+    #[arg(long, value_name = "ROUTER", default_value = "golden", value_parser = parse_router)]
+    pub router: RouterKind,
+    /// Time arbitrage search, routing and program calls, and report the split after the
+    /// summary (adds a clock read around every program call)
+    #[arg(long)]
+    pub profile: bool,
+    /// Serve repeated identical quotes within a step from a cache instead of re-running the
+    /// program; results are unchanged, BPF runs get faster
+    #[arg(long)]
+    pub quote_cache: bool,
+    /// Decimals of the integer amounts programs see for X and Y, e.g. 9:6 for a
+    /// USDC-like quote token (default 9:9, the nano scale)
+    #[arg(long, value_name = "X:Y", value_parser = parse_token_decimals)]
+    pub decimals: Option<TokenDecimals>,
+    /// What to do when the submission's BPF after_swap returns an error: keep going
+    /// (ignore), keep going but report a count per simulation (count), or fail the
+    /// simulation (fail)
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "ignore",
+        value_parser = parse_after_swap_policy,
+    )]
+    pub after_swap_failures: AfterSwapFailurePolicy,
+    /// When the submission's curve fails a shape check: fail the batch (abort), or record
+    /// the violation, keep going and score that simulation's edge as zero (zero-edge)
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "abort",
+        value_parser = parse_shape_violation_policy,
+    )]
+    pub shape_violations: ShapeViolationPolicy,
+    /// Where the arbitrageur trades within each step: before the retail orders (arb-first),
+    /// after them (arb-last), or at a random position among them (interleaved)
+    #[arg(
+        long,
+        value_name = "ORDER",
+        default_value = "arb-first",
+        value_parser = parse_step_sequencing,
+    )]
+    pub sequencing: StepSequencing,
+    /// Watch the source file; recompile and re-run the same seed batch on every change
+    /// (native only)
+    #[arg(long, conflicts_with_all = ["bpf", "bpf_so", "native_so"])]
+    pub watch: bool,
+    /// Weight of the tail loss (CVaR) in the risk-adjusted score
+    #[arg(long, default_value_t = RiskPolicy::default().lambda)]
+    pub risk_lambda: f64,
+    /// Fraction of worst seeds averaged for CVaR in the risk-adjusted score
+    #[arg(long, default_value_t = RiskPolicy::default().tail_fraction)]
+    pub risk_tail: f64,
+    /// Edge deducted from a simulation's score per shape-check warning
+    #[arg(
+        long,
+        value_name = "EDGE",
+        default_value_t = ShapePenaltyPolicy::default().warning_penalty,
+    )]
+    pub shape_warning_penalty: f64,
+    /// Edge deducted from a simulation's score per shape-check violation (with
+    /// --shape-violations zero-edge)
+    #[arg(
+        long,
+        value_name = "EDGE",
+        default_value_t = ShapePenaltyPolicy::default().violation_penalty,
+    )]
+    pub shape_violation_penalty: f64,
+    /// Disqualify (score zero) any simulation with more than this many shape-check warnings
+    #[arg(long, value_name = "N")]
+    pub max_shape_warnings: Option<u32>,
+    /// Write per-seed results to this .csv or .json file (readable by `plot` and
+    /// `--seeds-file`)
+    #[arg(long, conflicts_with = "watch", value_hint = ValueHint::FilePath)]
+    pub export: Option<String>,
+    /// Write each simulation's submission reserve trajectory (seed, step, reserves, fair
+    /// price) to this .csv or .json file
+    #[arg(long, conflicts_with = "watch", value_hint = ValueHint::FilePath)]
+    pub trajectory: Option<String>,
+    /// Steps between trajectory snapshots
+    #[arg(long, default_value = "100", requires = "trajectory")]
+    pub trajectory_stride: u32,
+    /// Write every executed trade on every venue (seed, step, venue, counterparty, side,
+    /// amounts in and out, fair price) to this .csv or .json file
+    #[arg(long, conflicts_with = "watch", value_hint = ValueHint::FilePath)]
+    pub trade_tape: Option<String>,
+    /// Where to write the reproducibility manifest for this run
+    #[arg(
+        long,
+        default_value = manifest::DEFAULT_MANIFEST_PATH,
+        conflicts_with = "watch",
+        value_hint = ValueHint::FilePath,
+    )]
+    pub manifest: String,
+    /// Record every simulation's RNG stream seeds in the manifest, so `verify-manifest`
+    /// can check they are still derived the same way
+    #[arg(long, conflicts_with = "watch")]
+    pub record_rng_streams: bool,
+    /// Where to write a single-seed reproducer when a simulation fails or reports shape
+    /// issues (replay with `prop-amm repro <DIR>/seed-<SEED>`)
+    #[arg(
+        long,
+        default_value = repro::DEFAULT_REPRO_DIR,
+        conflicts_with = "watch",
+        value_hint = ValueHint::DirPath,
+    )]
+    pub repro_dir: String,
+}
 
-static LOADED_SWAP: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
-static LOADED_AFTER_SWAP: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+impl RunArgs {
+    fn risk_policy(&self) -> RiskPolicy {
+        RiskPolicy {
+            lambda: self.risk_lambda,
+            tail_fraction: self.risk_tail,
+        }
+    }
 
-fn dynamic_swap(data: &[u8]) -> u64 {
-    let ptr = LOADED_SWAP.load(Ordering::Relaxed);
-    let f: FfiSwapFn = unsafe { std::mem::transmute(ptr) };
-    unsafe { f(data.as_ptr(), data.len()) }
+    fn shape_penalty_policy(&self) -> ShapePenaltyPolicy {
+        ShapePenaltyPolicy {
+            warning_penalty: self.shape_warning_penalty,
+            violation_penalty: self.shape_violation_penalty,
+            max_warnings: self.max_shape_warnings,
+            ..ShapePenaltyPolicy::default()
+        }
+    }
 }
 
-fn dynamic_after_swap(data: &[u8], storage: &mut [u8]) {
-    let ptr = LOADED_AFTER_SWAP.load(Ordering::Relaxed);
-    let f: FfiAfterSwapFn = unsafe { std::mem::transmute(ptr) };
-    unsafe {
-        f(
-            data.as_ptr(),
-            data.len(),
-            storage.as_mut_ptr(),
-            storage.len(),
-        )
+fn parse_steps_range(value: &str) -> Result<(u32, u32), String> {
+    let (min, max) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected MIN:MAX, got {value:?}"))?;
+    let min: u32 = min.trim().parse().map_err(|e| format!("invalid MIN: {e}"))?;
+    let max: u32 = max.trim().parse().map_err(|e| format!("invalid MAX: {e}"))?;
+    if min == 0 || min > max {
+        return Err(format!("need 1 <= MIN <= MAX, got {min}:{max}"));
     }
+    Ok((min, max))
 }
 
-pub fn run(
-    file: &str,
-    simulations: u32,
-    steps: u32,
-    workers: usize,
-    seed_start: u64,
-    seed_stride: u64,
-    bpf: bool,
-    bpf_so: Option<&str>,
-) -> anyhow::Result<()> {
-    if seed_stride == 0 {
+fn parse_probability(value: &str) -> Result<f64, String> {
+    let p: f64 = value.parse().map_err(|e| format!("invalid probability: {e}"))?;
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("probability must be in [0, 1], got {p}"));
+    }
+    Ok(p)
+}
+
+fn parse_extra_venue(value: &str) -> Result<BaselineVenue, String> {
+    let (fee, mult) = value.split_once(':').unwrap_or((value, "1"));
+    let fee_bps: u16 = fee.trim().parse().map_err(|e| format!("invalid FEE_BPS: {e}"))?;
+    let liquidity_mult: f64 = mult.trim().parse().map_err(|e| format!("invalid LIQ_MULT: {e}"))?;
+    Ok(BaselineVenue {
+        fee_bps,
+        liquidity_mult,
+    })
+}
+
+fn parse_liquidity_event(value: &str) -> Result<LiquidityEvent, String> {
+    let (step, scale) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected STEP:SCALE, got {value:?}"))?;
+    let step: u32 = step.trim().parse().map_err(|e| format!("invalid STEP: {e}"))?;
+    let scale: f64 = scale.trim().parse().map_err(|e| format!("invalid SCALE: {e}"))?;
+    Ok(LiquidityEvent { step, scale })
+}
+
+fn parse_price_gap(value: &str) -> Result<PriceGap, String> {
+    let (step, size) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected STEP:SIZE, got {value:?}"))?;
+    let step: u32 = step.trim().parse().map_err(|e| format!("invalid STEP: {e}"))?;
+    let size: f64 = size.trim().parse().map_err(|e| format!("invalid SIZE: {e}"))?;
+    Ok(PriceGap { step, size })
+}
+
+fn parse_arrival_window(value: &str) -> Result<ArrivalWindow, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    let [start_step, end_step, multiplier] = fields[..] else {
+        return Err(format!("expected START:END:MULT, got {value:?}"));
+    };
+    Ok(ArrivalWindow {
+        start_step: start_step.trim().parse().map_err(|e| format!("invalid START: {e}"))?,
+        end_step: end_step.trim().parse().map_err(|e| format!("invalid END: {e}"))?,
+        multiplier: multiplier.trim().parse().map_err(|e| format!("invalid MULT: {e}"))?,
+    })
+}
+
+fn parse_arrival_cycle(value: &str) -> Result<ArrivalCycle, String> {
+    let (period, amplitude) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected PERIOD:AMPLITUDE, got {value:?}"))?;
+    Ok(ArrivalCycle {
+        period_steps: period.trim().parse().map_err(|e| format!("invalid PERIOD: {e}"))?,
+        amplitude: amplitude.trim().parse().map_err(|e| format!("invalid AMPLITUDE: {e}"))?,
+    })
+}
+
+fn parse_momentum_traders(value: &str) -> Result<MomentumCohort, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    let [arrival_scale, lookback_steps, weight] = fields[..] else {
+        return Err(format!("expected SCALE:LOOKBACK:WEIGHT, got {value:?}"));
+    };
+    Ok(MomentumCohort {
+        arrival_scale: arrival_scale.trim().parse().map_err(|e| format!("invalid SCALE: {e}"))?,
+        lookback_steps: lookback_steps
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid LOOKBACK: {e}"))?,
+        weight: weight.trim().parse().map_err(|e| format!("invalid WEIGHT: {e}"))?,
+    })
+}
+
+fn parse_arb_inventory(value: &str) -> Result<ArbInventory, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    let (x, y, rebalance) = match fields[..] {
+        [x, y] => (x, y, None),
+        [x, y, rebalance] => (x, y, Some(rebalance)),
+        _ => return Err(format!("expected X:Y[:REBALANCE], got {value:?}")),
+    };
+    Ok(ArbInventory {
+        x: x.trim().parse().map_err(|e| format!("invalid X: {e}"))?,
+        y: y.trim().parse().map_err(|e| format!("invalid Y: {e}"))?,
+        rebalance_steps: rebalance
+            .map(|r| r.trim().parse().map_err(|e| format!("invalid REBALANCE: {e}")))
+            .transpose()?
+            .unwrap_or(0),
+    })
+}
+
+fn parse_retail_class(value: &str) -> Result<RetailProfile, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    let (weight, size_scale, size_sigma, buy_prob) = match fields[..] {
+        [weight, size, sigma] => (weight, size, sigma, None),
+        [weight, size, sigma, buy_prob] => (weight, size, sigma, Some(buy_prob)),
+        _ => return Err(format!("expected WEIGHT:SIZE:SIGMA[:BUY_PROB], got {value:?}")),
+    };
+    let parse = |name: &str, s: &str| -> Result<f64, String> {
+        s.trim().parse().map_err(|e| format!("invalid {name}: {e}"))
+    };
+    Ok(RetailProfile {
+        arrival_scale: parse("WEIGHT", weight)?,
+        size_scale: parse("SIZE", size_scale)?,
+        size_sigma: parse("SIGMA", size_sigma)?,
+        buy_prob: buy_prob.map(|p| parse("BUY_PROB", p)).transpose()?,
+    })
+}
+
+/// `classes` with their arrival scales normalized to mixture weights summing to 1.
+fn retail_mixture(classes: &[RetailProfile]) -> anyhow::Result<Vec<RetailProfile>> {
+    if classes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let total: f64 = classes.iter().map(|c| c.arrival_scale).sum();
+    if !(total > 0.0 && total.is_finite()) {
+        anyhow::bail!("--retail-class weights must sum to a positive number (got {total})");
+    }
+    Ok(classes
+        .iter()
+        .map(|c| RetailProfile {
+            arrival_scale: c.arrival_scale / total,
+            ..*c
+        })
+        .collect())
+}
+
+fn parse_volatility_regimes(value: &str) -> Result<VolatilityRegimes, String> {
+    let fields: Vec<&str> = value.split(':').collect();
+    let [calm_sigma, volatile_sigma, calm_to_volatile, volatile_to_calm] = fields[..] else {
+        return Err(format!("expected CALM_SIGMA:VOL_SIGMA:P_UP:P_DOWN, got {value:?}"));
+    };
+    let parse = |name: &str, s: &str| -> Result<f64, String> {
+        s.trim().parse().map_err(|e| format!("invalid {name}: {e}"))
+    };
+    Ok(VolatilityRegimes {
+        calm_sigma: parse("CALM_SIGMA", calm_sigma)?,
+        volatile_sigma: parse("VOL_SIGMA", volatile_sigma)?,
+        calm_to_volatile: parse("P_UP", calm_to_volatile)?,
+        volatile_to_calm: parse("P_DOWN", volatile_to_calm)?,
+    })
+}
+
+fn parse_token_decimals(value: &str) -> Result<TokenDecimals, String> {
+    let (x, y) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected X:Y, got {value:?}"))?;
+    let parse = |s: &str| -> Result<u8, String> {
+        let decimals: u8 = s.trim().parse().map_err(|e| format!("invalid decimals: {e}"))?;
+        if decimals > MAX_TOKEN_DECIMALS {
+            return Err(format!("decimals must be <= {MAX_TOKEN_DECIMALS}, got {decimals}"));
+        }
+        Ok(decimals)
+    };
+    Ok(TokenDecimals {
+        x: parse(x)?,
+        y: parse(y)?,
+    })
+}
+
+fn parse_after_swap_policy(value: &str) -> Result<AfterSwapFailurePolicy, String> {
+    match value {
+        "ignore" => Ok(AfterSwapFailurePolicy::Ignore),
+        "count" => Ok(AfterSwapFailurePolicy::CountAndReport),
+        "fail" => Ok(AfterSwapFailurePolicy::FailSim),
+        _ => Err(format!("expected ignore, count or fail, got {value:?}")),
+    }
+}
+
+fn parse_shape_violation_policy(value: &str) -> Result<ShapeViolationPolicy, String> {
+    match value {
+        "abort" => Ok(ShapeViolationPolicy::Abort),
+        "zero-edge" => Ok(ShapeViolationPolicy::ZeroEdge),
+        _ => Err(format!("expected abort or zero-edge, got {value:?}")),
+    }
+}
+
+fn parse_step_sequencing(value: &str) -> Result<StepSequencing, String> {
+    match value {
+        "arb-first" => Ok(StepSequencing::ArbFirst),
+        "arb-last" => Ok(StepSequencing::ArbLast),
+        "interleaved" => Ok(StepSequencing::Interleaved),
+        _ => Err(format!("expected arb-first, arb-last or interleaved, got {value:?}")),
+    }
+}
+
+fn parse_router(value: &str) -> Result<RouterKind, String> {
+    match value {
+        "golden" => Ok(RouterKind::GoldenSection),
+        "best-price" => Ok(RouterKind::BestPrice),
+        "proportional" => Ok(RouterKind::Proportional),
+        "marginal" => Ok(RouterKind::MarginalPrice),
+        "winner-take-all" => Ok(RouterKind::WinnerTakeAll),
+        "integer-golden" => Ok(RouterKind::IntegerGolden),
+        "adaptive-golden" => Ok(RouterKind::AdaptiveGolden),
+        "warm-golden" => Ok(RouterKind::WarmStartGolden),
+        _ => Err(format!(
+            "expected golden, integer-golden, adaptive-golden, warm-golden, best-price, \
+             proportional, marginal or winner-take-all, got {value:?}"
+        )),
+    }
+}
+
+fn parse_random_gaps(value: &str) -> Result<(u32, f64), String> {
+    let (count, size) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected COUNT:SIZE, got {value:?}"))?;
+    let count: u32 = count.trim().parse().map_err(|e| format!("invalid COUNT: {e}"))?;
+    let size: f64 = size.trim().parse().map_err(|e| format!("invalid SIZE: {e}"))?;
+    Ok((count, size))
+}
+
+/// The configs a run will execute, plus a human-readable description of their seeds.
+pub(crate) struct BatchPlan {
+    pub configs: Vec<SimulationConfig>,
+    /// The config `configs` were sampled from; what the manifest records.
+    pub base: SimulationConfig,
+    pub seeds: String,
+    pub risk: RiskPolicy,
+    pub shape_penalty: ShapePenaltyPolicy,
+    /// The box `configs` were sampled from.
+    pub variance: HyperparameterVariance,
+    /// Run `configs` as a prefix-ordered sequence and stop once edge has converged.
+    pub early_stop: Option<EarlyStop>,
+}
+
+impl BatchPlan {
+    /// `base` at each of `seeds`, with the hyperparameters `variance` samples for the seed;
+    /// `description` labels the seeds in logs.
+    pub fn sample(
+        base: &SimulationConfig,
+        variance: HyperparameterVariance,
+        seeds: &[u64],
+        description: String,
+    ) -> Self {
+        Self {
+            configs: seeds.iter().map(|&seed| variance.apply(base, seed)).collect(),
+            base: base.clone(),
+            seeds: description,
+            risk: RiskPolicy::default(),
+            shape_penalty: ShapePenaltyPolicy::default(),
+            variance,
+            early_stop: None,
+        }
+    }
+
+    /// e.g. "10000 steps each" or "5000-20000 steps".
+    pub fn steps_label(&self) -> String {
+        let min = self.configs.iter().map(|c| c.n_steps).min().unwrap_or(self.base.n_steps);
+        let max = self.configs.iter().map(|c| c.n_steps).max().unwrap_or(self.base.n_steps);
+        if min == max {
+            format!("{} steps each", min)
+        } else {
+            format!("{}-{} steps", min, max)
+        }
+    }
+
+    pub fn seed_list(&self) -> Vec<u64> {
+        self.configs.iter().map(|c| c.seed).collect()
+    }
+
+    /// Run the plan through `run_batch`, stopping early if the plan asks to.
+    fn execute(
+        &self,
+        mut run_batch: impl FnMut(Vec<SimulationConfig>) -> anyhow::Result<BatchResult>,
+    ) -> anyhow::Result<BatchResult> {
+        let Some(stop) = &self.early_stop else {
+            return run_batch(self.configs.clone());
+        };
+        let result = runner::run_until_converged(self.configs.clone(), stop, run_batch)?;
+        let half_width = result.edge_ci_half_width(stop.z);
+        if half_width <= stop.half_width {
+            tracing::info!(
+                "Edge converged after {} of {} simulations (95% CI +/- {:.3})",
+                result.n_sims(),
+                self.configs.len(),
+                half_width
+            );
+        } else {
+            tracing::warn!(
+                "Edge did not converge within {} simulations (95% CI +/- {:.3}, target {:.3})",
+                result.n_sims(),
+                half_width,
+                stop.half_width
+            );
+        }
+        Ok(result)
+    }
+}
+
+/// A finished batch and the compiled submission artifact it ran.
+pub(crate) struct RunOutcome {
+    pub result: BatchResult,
+    pub artifact: PathBuf,
+}
+
+pub fn run(args: RunArgs) -> anyhow::Result<()> {
+    if args.seed_stride == 0 {
         anyhow::bail!("--seed-stride must be >= 1");
     }
-    let n_workers = if workers == 0 { None } else { Some(workers) };
+    if !(args.risk_tail > 0.0 && args.risk_tail <= 1.0) {
+        anyhow::bail!("--risk-tail must be in (0, 1]");
+    }
+    for (flag, penalty) in [
+        ("--shape-warning-penalty", args.shape_warning_penalty),
+        ("--shape-violation-penalty", args.shape_violation_penalty),
+    ] {
+        if !(penalty >= 0.0 && penalty.is_finite()) {
+            anyhow::bail!("{flag} must be non-negative");
+        }
+    }
+    if args.target_ci.is_some_and(|ci| !(ci > 0.0 && ci.is_finite())) {
+        anyhow::bail!("--target-ci must be positive");
+    }
+    let n_workers = if args.workers == 0 {
+        None
+    } else {
+        Some(args.workers)
+    };
+    let default_sims = if args.watch {
+        WATCH_DEFAULT_SIMS
+    } else {
+        BASELINE_SIMS
+    };
+    let mut plan = plan_batch(&args, default_sims)?;
+    if args.inventory_adjusted {
+        tracing::info!("Scoring inventory-adjusted edge (flow edge plus inventory revaluation)");
+    }
+    if args.batch_auction {
+        tracing::info!("Batch-auction execution: each step's retail orders are routed as one");
+    }
+    // Fail before compiling rather than after.
+    runner::validate_configs(&plan.configs)?;
 
-    if bpf {
-        run_bpf(
-            file,
-            simulations,
-            steps,
-            n_workers,
-            bpf_so,
-            seed_start,
-            seed_stride,
-        )
+    if args.watch {
+        return run_watch(&args.file, &plan, n_workers);
+    }
+
+    let (backend, outcome) = if args.bpf {
+        let normalizer = args.bpf_normalizer.then(embedded_normalizer).transpose()?;
+        let outcome =
+            run_bpf_against(&args.file, args.bpf_so.as_deref(), normalizer, &plan, n_workers);
+        (Backend::Bpf, outcome)
     } else {
-        run_native(file, simulations, steps, n_workers, seed_start, seed_stride)
+        let outcome = run_native(&args.file, args.native_so.as_deref(), &plan, n_workers);
+        (Backend::Native, outcome)
+    };
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            if let Some(failed) = err.downcast_ref::<runner::SimulationFailed>() {
+                emit_failure_repro(&args, backend, &plan, failed);
+            }
+            return Err(err);
+        }
+    };
+    // Record only the seeds that ran, so the manifest replays exactly this batch.
+    plan.configs.truncate(outcome.result.n_sims());
+    if let Some(flagged) = outcome.result.results.iter().find(|r| !r.shape.is_clean()) {
+        emit_shape_repro(&args, backend, &plan, &outcome, flagged);
     }
-}
 
-fn run_native(
-    file: &str,
-    simulations: u32,
-    steps: u32,
-    n_workers: Option<usize>,
-    seed_start: u64,
-    seed_stride: u64,
-) -> anyhow::Result<()> {
-    let total_start = std::time::Instant::now();
-    println!("Compiling {} (native)...", file);
-    let build_start = std::time::Instant::now();
-    let native_path = compile::compile_native(file)?;
-    let build_elapsed = build_start.elapsed();
+    if let Some(path) = &args.export {
+        export::write_results(Path::new(path), &outcome.result)?;
+        tracing::info!("Results exported to {}", path);
+    }
+    if let Some(path) = &args.trajectory {
+        export::write_trajectories(Path::new(path), &outcome.result)?;
+        tracing::info!("Trajectories written to {}", path);
+    }
+    if let Some(path) = &args.trade_tape {
+        export::write_trade_tape(Path::new(path), &outcome.result)?;
+        tracing::info!("Trade tape written to {}", path);
+    }
 
-    // Load the native library — leak it so symbols remain valid for the process lifetime.
-    let load_start = std::time::Instant::now();
-    let lib = Box::new(
-        unsafe { libloading::Library::new(&native_path) }
-            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", native_path.display(), e))?,
+    let mut manifest = RunManifest::record(&args.file, backend, &outcome, &plan)?;
+    manifest.bpf_normalizer = args.bpf_normalizer;
+    if args.record_rng_streams {
+        manifest.rng_streams = Some(plan.configs.iter().map(RngStreams::for_config).collect());
+    }
+    manifest.write(Path::new(&args.manifest))?;
+    tracing::info!("Manifest written to {}", args.manifest);
+    Ok(())
+}
+
+fn emit_failure_repro(
+    args: &RunArgs,
+    backend: Backend,
+    plan: &BatchPlan,
+    failed: &runner::SimulationFailed,
+) {
+    let Some(config) = plan.configs.iter().find(|c| c.seed == failed.seed) else {
+        return;
+    };
+    // The artifact is only known up front when it was prebuilt.
+    let artifact = match backend {
+        Backend::Native => args.native_so.as_deref(),
+        Backend::Bpf => args.bpf_so.as_deref(),
+    };
+    let bundle = ReproBundle::new(
+        ProblemKind::SimulationFailed,
+        failed.message.clone(),
+        backend,
+        Path::new(&args.file),
+        artifact.map(Path::new),
+        config,
     );
-    let lib = Box::leak(lib);
+    match bundle {
+        Ok(bundle) => repro::emit(&bundle, Path::new(&args.repro_dir), Path::new(&args.file)),
+        Err(err) => tracing::warn!("Failed to write reproducer: {err:#}"),
+    }
+}
 
-    let swap_fn: libloading::Symbol<FfiSwapFn> = unsafe {
-        lib.get(compile::NATIVE_SWAP_SYMBOL)
-            .or_else(|_| lib.get(b"compute_swap_ffi"))
+/// Bundle the first simulation with shape findings; the rest are usually the same issue.
+fn emit_shape_repro(
+    args: &RunArgs,
+    backend: Backend,
+    plan: &BatchPlan,
+    outcome: &RunOutcome,
+    flagged: &SimResult,
+) {
+    let Some(config) = plan.configs.iter().find(|c| c.seed == flagged.seed) else {
+        return;
+    };
+    let detail = flagged
+        .shape
+        .examples
+        .first()
+        .map_or_else(String::new, |issue| format!("{}: {}", issue.context, issue));
+    let bundle = ReproBundle::new(
+        ProblemKind::ShapeIssues,
+        detail,
+        backend,
+        Path::new(&args.file),
+        Some(&outcome.artifact),
+        config,
+    );
+    match bundle {
+        Ok(mut bundle) => {
+            bundle.expected = Some(flagged.into());
+            repro::emit(&bundle, Path::new(&args.repro_dir), Path::new(&args.file));
+        }
+        Err(err) => tracing::warn!("Failed to write reproducer: {err:#}"),
     }
-    .map_err(|e| anyhow::anyhow!("Missing native swap symbol: {}", e))?;
-    LOADED_SWAP.store(*swap_fn as *mut (), Ordering::Relaxed);
+}
 
-    let has_after_swap = if let Ok(after_fn) = unsafe {
-        lib.get::<FfiAfterSwapFn>(compile::NATIVE_AFTER_SWAP_SYMBOL)
-            .or_else(|_| lib.get::<FfiAfterSwapFn>(b"after_swap_ffi"))
-    } {
-        LOADED_AFTER_SWAP.store(*after_fn as *mut (), Ordering::Relaxed);
-        true
+fn plan_batch(args: &RunArgs, default_sims: u32) -> anyhow::Result<BatchPlan> {
+    let variance = HyperparameterVariance {
+        n_steps_range: args.steps_range,
+        flow_regime_prob: args.flow_regime_prob,
+        ..HyperparameterVariance::default()
+    };
+    let (seeds, description) = if let Some(path) = &args.seeds_file {
+        let seeds = read_seeds_file(Path::new(path))?;
+        let description = format!("{} seeds from {}", seeds.len(), path);
+        (seeds, description)
     } else {
-        false
+        let simulations = args.simulations.unwrap_or(default_sims);
+        (
+            runner::seed_sequence(simulations, args.seed_start, args.seed_stride),
+            format!("seeds {} + i*{}", args.seed_start, args.seed_stride),
+        )
+    };
+
+    Ok(BatchPlan {
+        risk: args.risk_policy(),
+        shape_penalty: args.shape_penalty_policy(),
+        early_stop: args.target_ci.map(|half_width| EarlyStop {
+            min_sims: args.min_simulations,
+            ..EarlyStop::new(half_width)
+        }),
+        ..BatchPlan::sample(&base_config(args)?, variance, &seeds, description)
+    })
+}
+
+/// The config every simulation of a run starts from, before [`BatchPlan::sample`] draws
+/// each seed's hyperparameters; the manifest records it as `RunManifest::config`.
+fn base_config(args: &RunArgs) -> anyhow::Result<SimulationConfig> {
+    let mut config = SimulationConfig {
+        n_steps: args.steps,
+        quote_cu_budget: args.quote_cu_budget,
+        warmup_steps: args.warmup,
+        sessions: args.sessions,
+        gbm_dt: args.dt,
+        extra_venues: args.extra_venue.clone(),
+        black_box_baselines: args.black_box_baselines,
+        liquidity_events: args.liquidity_event.clone(),
+        liquidity_event_callbacks: args.liquidity_event_callbacks,
+        price_gaps: args.price_gap.clone(),
+        scenario: args.scenario.as_deref().map(read_scenario_file).transpose()?,
+        volatility_regimes: args.volatility_regimes,
+        gbm_shock_df: args.shock_df,
+        informed_prob: args.informed_prob,
+        external_spread_bps: args.external_spread_bps,
+        arb_taker_fee_bps: args.arb_taker_fee_bps,
+        arb_arrival_prob: args.arb_arrival_prob,
+        arb_cp_fast_path: args.arb_cp_fast_path,
+        arb_cross_venue: args.arb_cross_venue,
+        arb_size_noise: args.arb_size_noise,
+        arb_inventory: args.arb_inventory,
+        flow_price_correlation: args.flow_price_correlation,
+        flow_persistence: args.flow_persistence,
+        retail_max_slippage_bps: args.retail_max_slippage_bps,
+        retail_partial_fills: args.partial_fills,
+        retail_exact_sell_notional: args.exact_sell_notional,
+        retail_exact_output: args.exact_output,
+        min_trade_size: args.min_trade_size,
+        max_trade_fraction: args.max_trade_fraction,
+        price_guard_bps: args.price_guard_bps,
+        oracle_noise: args.oracle_noise,
+        arrival_windows: args.arrival_window.clone(),
+        arrival_cycle: args.arrival_cycle,
+        retail_mix: retail_mixture(&args.retail_class)?,
+        momentum_traders: args.momentum_traders,
+        after_swap_failure_policy: args.after_swap_failures,
+        shape_violation_policy: args.shape_violations,
+        step_sequencing: args.sequencing,
+        profile_components: args.profile,
+        quote_cache: args.quote_cache,
+        router: args.router,
+        record_tape: args.trade_tape.is_some(),
+        ..SimulationConfig::default()
     };
+    config.set_initial_pool(args.initial_liquidity, args.initial_mispricing);
+    if let Some((count, size)) = args.random_gaps {
+        config.random_gaps = count;
+        config.random_gap_size = size;
+    }
+    if args.inventory_adjusted {
+        config.edge_mode = EdgeMode::InventoryAdjusted;
+    }
+    if let Some(decimals) = args.decimals {
+        config.token_decimals = decimals;
+    }
+    if args.batch_auction {
+        config.execution_mode = ExecutionMode::BatchAuction;
+    }
+    if args.batch_routing {
+        config.execution_mode = ExecutionMode::BatchRouted;
+    }
+    if let Some(arb_check_rate) = args.event_clock {
+        config.clock = Clock::EventDriven { arb_check_rate };
+    }
+    if args.trajectory.is_some() {
+        config.trajectory_stride = Some(args.trajectory_stride);
+    }
+    Ok(config)
+}
+
+fn read_scenario_file(path: &Path) -> anyhow::Result<Scenario> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read scenario file {}: {}", path.display(), e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("Invalid scenario file {}: {}", path.display(), e))
+}
+
+fn read_seeds_file(path: &Path) -> anyhow::Result<Vec<u64>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read seeds file {}: {}", path.display(), e))?;
+    parse_seeds(&text, &path.display().to_string())
+}
+
+/// Seeds listed one per line, or in the first column of a CSV; `origin` names the source in
+/// errors. Blank lines and `#` comments are skipped, and so is a header row.
+fn parse_seeds(text: &str, origin: &str) -> anyhow::Result<Vec<u64>> {
+    let mut seeds = Vec::new();
+    let mut seen = HashMap::new();
+    let mut first_record = true;
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let field = line.split(',').next().unwrap_or("").trim();
+        match field.parse::<u64>() {
+            Ok(seed) => {
+                // A repeated seed would run the same simulation twice and weight it double.
+                if let Some(first) = seen.insert(seed, line_no + 1) {
+                    anyhow::bail!(
+                        "{}:{}: duplicate seed {} (first on line {})",
+                        origin,
+                        line_no + 1,
+                        seed,
+                        first
+                    );
+                }
+                seeds.push(seed);
+            }
+            // Tolerate a CSV header row, e.g. from a previous results export.
+            Err(_) if first_record => {}
+            Err(e) => anyhow::bail!("{}:{}: invalid seed {:?}: {}", origin, line_no + 1, field, e),
+        }
+        first_record = false;
+    }
+
+    if seeds.is_empty() {
+        anyhow::bail!("Seeds file {} contains no seeds", origin);
+    }
+    Ok(seeds)
+}
 
-    let submission_after_swap: Option<AfterSwapFn> = if has_after_swap {
-        Some(dynamic_after_swap)
+pub(crate) fn run_native(
+    file: &str,
+    native_so: Option<&str>,
+    plan: &BatchPlan,
+    n_workers: Option<usize>,
+) -> anyhow::Result<RunOutcome> {
+    let total_start = std::time::Instant::now();
+    let build_start = std::time::Instant::now();
+    let native_path = if let Some(path) = native_so {
+        tracing::info!("Using prebuilt native library: {}", path);
+        PathBuf::from(path)
     } else {
-        None
+        tracing::info!("Compiling {} (native)...", file);
+        compile::compile_native(file)?
     };
+    let build_elapsed = build_start.elapsed();
+
+    let load_start = std::time::Instant::now();
+    let submission = LoadedSubmission::load(&native_path)?;
     let compile_or_load_elapsed = build_elapsed + load_start.elapsed();
 
-    println!(
-        "Running {} simulations ({} steps each) natively with seeds {} + i*{}...",
-        simulations, steps, seed_start, seed_stride,
+    tracing::info!(
+        "Running {} simulations ({}) natively with {}...",
+        plan.configs.len(),
+        plan.steps_label(),
+        plan.seeds,
+    );
+
+    let sim_start = std::time::Instant::now();
+    let result = plan.execute(|configs| {
+        runner::run_batch_native(
+            submission.swap_fn(),
+            submission.after_swap_fn(),
+            normalizer_swap,
+            Some(normalizer_after_swap_fn),
+            configs,
+            n_workers,
+        )
+    })?;
+    let sim_elapsed = sim_start.elapsed();
+
+    output::print_results(
+        &result,
+        &plan.risk,
+        &plan.shape_penalty,
+        output::RunTimings {
+            compile_or_load: compile_or_load_elapsed,
+            simulation: sim_elapsed,
+            total: total_start.elapsed(),
+        },
     );
+    Ok(RunOutcome {
+        result,
+        artifact: native_path,
+    })
+}
 
+fn run_watch(file: &str, plan: &BatchPlan, n_workers: Option<usize>) -> anyhow::Result<()> {
+    let path = Path::new(file);
+    let mut last_modified: Option<SystemTime> = None;
+    let mut previous_avg: Option<f64> = None;
+
+    tracing::info!("Watching {} (Ctrl-C to stop)...", file);
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            match run_watch_iteration(file, plan, n_workers) {
+                Ok(result) => {
+                    output::print_edge_delta(previous_avg, result.avg_edge());
+                    previous_avg = Some(result.avg_edge());
+                }
+                // Keep watching: the next save usually fixes a compile error.
+                Err(err) => tracing::error!("{err:#}"),
+            }
+            tracing::info!("Watching {} for changes...", file);
+        }
+        std::thread::sleep(WATCH_POLL_INTERVAL);
+    }
+}
+
+fn run_watch_iteration(
+    file: &str,
+    plan: &BatchPlan,
+    n_workers: Option<usize>,
+) -> anyhow::Result<BatchResult> {
+    let total_start = std::time::Instant::now();
+    tracing::info!("Compiling {} (native)...", file);
+    let native_path = compile::compile_native(file)?;
+    // Dropped at the end of the iteration, unloading this generation before the next build.
+    let submission = LoadedSubmission::load(&native_path)?;
+    tracing::debug!(generation = submission.generation(), "watch iteration");
+    let compile_or_load_elapsed = total_start.elapsed();
+
+    tracing::info!(
+        "Running {} simulations ({}) natively with {}...",
+        plan.configs.len(),
+        plan.steps_label(),
+        plan.seeds,
+    );
     let sim_start = std::time::Instant::now();
-    let result = runner::run_default_batch_native_seeded(
-        dynamic_swap,
-        submission_after_swap,
+    let result = runner::run_batch_native(
+        submission.swap_fn(),
+        submission.after_swap_fn(),
         normalizer_swap,
         Some(normalizer_after_swap_fn),
-        simulations,
-        steps,
+        plan.configs.clone(),
         n_workers,
-        seed_start,
-        seed_stride,
     )?;
-    let sim_elapsed = sim_start.elapsed();
 
     output::print_results(
         &result,
+        &plan.risk,
+        &plan.shape_penalty,
         output::RunTimings {
             compile_or_load: compile_or_load_elapsed,
-            simulation: sim_elapsed,
+            simulation: sim_start.elapsed(),
             total: total_start.elapsed(),
         },
     );
-    Ok(())
+    Ok(result)
+}
+
+/// The normalizer program embedded by the `prebuilt-programs` feature.
+pub(crate) fn embedded_normalizer() -> anyhow::Result<BpfProgram> {
+    #[cfg(feature = "prebuilt-programs")]
+    return prop_amm_sim::prebuilt::normalizer_program();
+    #[cfg(not(feature = "prebuilt-programs"))]
+    anyhow::bail!("--bpf-normalizer needs prop-amm built with the prebuilt-programs feature")
 }
 
-fn run_bpf(
+pub(crate) fn run_bpf(
     file: &str,
-    simulations: u32,
-    steps: u32,
+    bpf_so: Option<&str>,
+    plan: &BatchPlan,
     n_workers: Option<usize>,
+) -> anyhow::Result<RunOutcome> {
+    run_bpf_against(file, bpf_so, None, plan, n_workers)
+}
+
+/// [`run_bpf`] against `normalizer_program`, or the native normalizer if `None`.
+pub(crate) fn run_bpf_against(
+    file: &str,
     bpf_so: Option<&str>,
-    seed_start: u64,
-    seed_stride: u64,
-) -> anyhow::Result<()> {
+    normalizer_program: Option<BpfProgram>,
+    plan: &BatchPlan,
+    n_workers: Option<usize>,
+) -> anyhow::Result<RunOutcome> {
     let total_start = std::time::Instant::now();
     let build_or_load_start = std::time::Instant::now();
     let bpf_path = if let Some(path) = bpf_so {
-        println!("Using prebuilt BPF .so: {}", path);
-        std::path::PathBuf::from(path)
+        tracing::info!("Using prebuilt BPF .so: {}", path);
+        PathBuf::from(path)
     } else {
-        println!("Compiling {} (BPF)...", file);
+        tracing::info!("Compiling {} (BPF)...", file);
         compile::compile_bpf(file)?
     };
 
@@ -167,40 +1182,80 @@ fn run_bpf(
 
     let meter_disabled = std::env::var_os("PROP_AMM_BPF_DISABLE_METER").is_some();
 
-    println!(
-        "Running {} simulations ({} steps each) via BPF{}{} with seeds {} + i*{}...",
-        simulations,
-        steps,
+    tracing::info!(
+        "Running {} simulations ({}) via BPF{}{} with {}...",
+        plan.configs.len(),
+        plan.steps_label(),
         if submission_program.jit_available() {
             " (JIT)"
         } else {
             " (interpreter)"
         },
         if meter_disabled { " (no meter)" } else { "" },
-        seed_start,
-        seed_stride,
+        plan.seeds,
     );
 
     let sim_start = std::time::Instant::now();
-    let result = runner::run_default_batch_mixed_seeded(
-        submission_program,
-        normalizer_swap,
-        Some(normalizer_after_swap_fn),
-        simulations,
-        steps,
-        n_workers,
-        seed_start,
-        seed_stride,
-    )?;
+    let result = plan.execute(|configs| match &normalizer_program {
+        Some(normalizer) => {
+            runner::run_batch(submission_program.clone(), normalizer.clone(), configs, n_workers)
+        }
+        None => runner::run_batch_mixed(
+            submission_program.clone(),
+            normalizer_swap,
+            Some(normalizer_after_swap_fn),
+            configs,
+            n_workers,
+        ),
+    })?;
     let sim_elapsed = sim_start.elapsed();
 
     output::print_results(
         &result,
+        &plan.risk,
+        &plan.shape_penalty,
         output::RunTimings {
             compile_or_load: compile_or_load_elapsed,
             simulation: sim_elapsed,
             total: total_start.elapsed(),
         },
     );
-    Ok(())
+    Ok(RunOutcome {
+        result,
+        artifact: bpf_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_seeds;
+
+    #[test]
+    fn seeds_skip_blank_lines_comments_and_a_header() {
+        let text = "seed,edge\n# warm-up seeds\n\n  7, 301.5\n3\n\n# tail\n12,-4\n";
+        assert_eq!(parse_seeds(text, "seeds.csv").unwrap(), vec![7, 3, 12]);
+        assert_eq!(parse_seeds("5\n", "seeds.txt").unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn bad_seeds_are_rejected_with_their_line() {
+        let err = parse_seeds("1\n2\nx3\n", "seeds.txt").unwrap_err().to_string();
+        assert!(err.starts_with("seeds.txt:3: invalid seed \"x3\""), "{err}");
+        let err = parse_seeds("1\n-2\n", "seeds.txt").unwrap_err().to_string();
+        assert!(err.starts_with("seeds.txt:2:"), "{err}");
+        // Only the first record may be a header.
+        assert!(parse_seeds("seed\nedge\n1\n", "seeds.txt").is_err());
+    }
+
+    #[test]
+    fn duplicate_seeds_are_rejected() {
+        let err = parse_seeds("4\n9\n\n4\n", "seeds.txt").unwrap_err().to_string();
+        assert_eq!(err, "seeds.txt:4: duplicate seed 4 (first on line 1)");
+    }
+
+    #[test]
+    fn a_file_without_seeds_is_rejected() {
+        assert!(parse_seeds("", "seeds.txt").is_err());
+        assert!(parse_seeds("# nothing\n\nseed\n", "seeds.txt").is_err());
+    }
 }
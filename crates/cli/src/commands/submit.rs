@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+use base64::Engine as _;
+use clap::ValueHint;
+use serde::{Deserialize, Serialize};
+
+use super::{compile, validate};
+use crate::manifest::{self, RunManifest};
+
+#[derive(clap::Args)]
+pub struct SubmitArgs {
+    /// Path to the .rs source file
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: String,
+    /// Challenge API submission endpoint
+    #[arg(long, env = "PROP_AMM_API_URL", value_hint = ValueHint::Url)]
+    pub endpoint: Option<String>,
+    /// API auth token, sent as a bearer token
+    #[arg(long, env = "PROP_AMM_API_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+    /// Run manifest whose hash is attached to the submission
+    #[arg(
+        long,
+        default_value = manifest::DEFAULT_MANIFEST_PATH,
+        value_hint = ValueHint::FilePath,
+    )]
+    pub manifest: String,
+    /// Prebuilt BPF .so to upload instead of compiling FILE
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub bpf_so: Option<String>,
+    /// Skip local validation (NAME/MODEL_USED are still checked); the server validates anyway
+    #[arg(long)]
+    pub no_validate: bool,
+    /// Package the submission and print what would be sent, without uploading
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct SubmissionPackage {
+    name: String,
+    model_used: String,
+    cli_version: String,
+    /// Hex FNV-1a 64 of the submission source.
+    source_hash: String,
+    /// Hex FNV-1a 64 of the uploaded program.
+    program_hash: String,
+    /// Hex FNV-1a 64 of the run manifest, when one matching the source exists.
+    manifest_hash: Option<String>,
+    /// Base64 BPF program.
+    program: String,
+}
+
+#[derive(Deserialize)]
+struct SubmitResponse {
+    #[serde(default)]
+    accepted: bool,
+    #[serde(default)]
+    submission_id: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    checks: Vec<ServerCheck>,
+}
+
+#[derive(Deserialize)]
+struct ServerCheck {
+    name: String,
+    passed: bool,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+pub fn run(args: SubmitArgs) -> anyhow::Result<()> {
+    // Check credentials before validating, which compiles and can take a while.
+    let upload_to = if args.dry_run {
+        None
+    } else {
+        let endpoint = args.endpoint.clone().ok_or_else(|| {
+            anyhow::anyhow!("No endpoint: pass --endpoint or set PROP_AMM_API_URL")
+        })?;
+        let token = args.token.clone().ok_or_else(|| {
+            anyhow::anyhow!("No auth token: pass --token or set PROP_AMM_API_TOKEN")
+        })?;
+        Some((endpoint, token))
+    };
+
+    let (metadata, so_path) = if args.no_validate {
+        let metadata = validate::validate_submission_metadata(&args.file)?;
+        let so_path = match &args.bpf_so {
+            Some(path) => PathBuf::from(path),
+            None => {
+                tracing::info!("Compiling {} (BPF)...", args.file);
+                compile::compile_bpf(&args.file)?
+            }
+        };
+        (metadata, so_path)
+    } else {
+        if args.bpf_so.is_some() {
+            anyhow::bail!("--bpf-so requires --no-validate (validation compiles FILE itself)");
+        }
+        let validated = validate::validate(&args.file)?;
+        (validated.metadata, validated.so_path)
+    };
+
+    let package = package(&args, metadata, &so_path)?;
+    println!("\nSubmission package:");
+    println!("  Name:          {}", package.name);
+    println!("  Model used:    {}", package.model_used);
+    println!("  Program:       {} ({})", so_path.display(), package.program_hash);
+    println!("  Source hash:   {}", package.source_hash);
+    println!(
+        "  Manifest hash: {}",
+        package.manifest_hash.as_deref().unwrap_or("none")
+    );
+
+    let Some((endpoint, token)) = upload_to else {
+        println!("\nDry run: nothing uploaded.");
+        return Ok(());
+    };
+
+    tracing::info!("Uploading to {}...", endpoint);
+    let response = match ureq::post(&endpoint)
+        .set("Authorization", &format!("Bearer {}", token))
+        .send_json(&package)
+    {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            anyhow::bail!("Upload rejected (HTTP {}): {}", code, body.trim());
+        }
+        Err(err) => anyhow::bail!("Upload to {} failed: {}", endpoint, err),
+    };
+    let body = response.into_string()?;
+    print_response(&body)
+}
+
+fn package(
+    args: &SubmitArgs,
+    metadata: validate::SubmissionMetadata,
+    so_path: &Path,
+) -> anyhow::Result<SubmissionPackage> {
+    let program = std::fs::read(so_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", so_path.display(), e))?;
+    let source_hash = manifest::hash_file(Path::new(&args.file))?;
+
+    // Only attach a manifest that was recorded for this exact source.
+    let manifest_path = Path::new(&args.manifest);
+    let manifest_hash = match RunManifest::read(manifest_path) {
+        Ok(recorded) if recorded.source_hash == source_hash => {
+            Some(manifest::hash_file(manifest_path)?)
+        }
+        Ok(_) => {
+            tracing::warn!(
+                "Manifest {} was recorded for a different source; not attaching it \
+                 (re-run `prop-amm run` first)",
+                args.manifest
+            );
+            None
+        }
+        Err(err) => {
+            tracing::warn!("No manifest attached: {err:#}");
+            None
+        }
+    };
+
+    Ok(SubmissionPackage {
+        name: metadata.name,
+        model_used: metadata.model_used,
+        cli_version: env!("CARGO_PKG_VERSION").to_string(),
+        source_hash,
+        program_hash: format!("{:016x}", prop_amm_shared::hash::fnv64(&program)),
+        manifest_hash,
+        program: base64::engine::general_purpose::STANDARD.encode(&program),
+    })
+}
+
+fn print_response(body: &str) -> anyhow::Result<()> {
+    let Ok(response) = serde_json::from_str::<SubmitResponse>(body) else {
+        println!("\nServer response:\n{}", body.trim());
+        return Ok(());
+    };
+
+    println!("\nServer validation:");
+    for check in &response.checks {
+        println!(
+            "  [{}] {}{}",
+            if check.passed { "PASS" } else { "FAIL" },
+            check.name,
+            check
+                .message
+                .as_deref()
+                .map(|m| format!(": {m}"))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(message) = &response.message {
+        println!("  {}", message);
+    }
+    if !response.accepted {
+        anyhow::bail!("Submission rejected by the server");
+    }
+    match &response.submission_id {
+        Some(id) => println!("\nSubmission accepted (id {}).", id),
+        None => println!("\nSubmission accepted."),
+    }
+    Ok(())
+}
@@ -1,52 +1,41 @@
-use std::path::Path;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
-use prop_amm_executor::{AfterSwapFn, BpfExecutor, BpfProgram};
+use prop_amm_executor::{BpfExecutor, BpfProgram};
+use prop_amm_shared::config::AfterSwapFailurePolicy;
 use prop_amm_shared::instruction::STORAGE_SIZE;
 use prop_amm_shared::nano::{f64_to_nano, nano_to_f64};
 use prop_amm_shared::normalizer::{
     after_swap as normalizer_after_swap, compute_swap as normalizer_swap,
 };
+use prop_amm_shared::result::BatchResult;
 use prop_amm_sim::runner;
 use syn::{Expr, Item, Lit, Type};
 
 use super::compile;
-
-type FfiSwapFn = unsafe extern "C" fn(*const u8, usize) -> u64;
-type FfiAfterSwapFn = unsafe extern "C" fn(*const u8, usize, *mut u8, usize);
-
-static LOADED_SWAP: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
-static LOADED_AFTER_SWAP: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+use super::native_lib::LoadedSubmission;
+use crate::manifest::Backend;
+use crate::repro::{self, ProblemKind, ReproBundle};
 
 const PARITY_SIMS: u32 = 12;
 const PARITY_STEPS: u32 = 2_000;
 const PARITY_SEED_START: u64 = 9_001;
 const PARITY_SEED_STRIDE: u64 = 7;
-const PARITY_ABS_TOL: f64 = 1e-6;
+pub(crate) const PARITY_ABS_TOL: f64 = 1e-6;
 const CONCAVITY_DELTA_NANO: u64 = 1_000_000;
 const CONCAVITY_STEP_TOL_NANO: i128 = 1;
 
-fn dynamic_swap(data: &[u8]) -> u64 {
-    let ptr = LOADED_SWAP.load(Ordering::Relaxed);
-    let f: FfiSwapFn = unsafe { std::mem::transmute(ptr) };
-    unsafe { f(data.as_ptr(), data.len()) }
+pub fn run(file: &str) -> anyhow::Result<()> {
+    validate(file).map(|_| ())
 }
 
-fn dynamic_after_swap(data: &[u8], storage: &mut [u8]) {
-    let ptr = LOADED_AFTER_SWAP.load(Ordering::Relaxed);
-    let f: FfiAfterSwapFn = unsafe { std::mem::transmute(ptr) };
-    unsafe {
-        f(
-            data.as_ptr(),
-            data.len(),
-            storage.as_mut_ptr(),
-            storage.len(),
-        )
-    }
+/// A submission that passed every check, with its compiled BPF program.
+pub(crate) struct ValidatedSubmission {
+    pub metadata: SubmissionMetadata,
+    pub so_path: PathBuf,
 }
 
-pub fn run(file: &str) -> anyhow::Result<()> {
+pub(crate) fn validate(file: &str) -> anyhow::Result<ValidatedSubmission> {
     let metadata = validate_submission_metadata(file)?;
     println!("  [PASS] Name: {}", metadata.name);
     if metadata.model_used == "None" {
@@ -55,9 +44,9 @@ pub fn run(file: &str) -> anyhow::Result<()> {
         println!("  [PASS] Model used: {}", metadata.model_used);
     }
 
-    println!("Compiling {} (BPF)...", file);
+    tracing::info!("Compiling {} (BPF)...", file);
     let so_path = compile::compile_bpf(file)?;
-    println!("Compiling {} (native)...", file);
+    tracing::info!("Compiling {} (native)...", file);
     let native_path = compile::compile_native(file)?;
 
     println!("Validating program: {}", so_path.display());
@@ -76,26 +65,28 @@ pub fn run(file: &str) -> anyhow::Result<()> {
     let rx = f64_to_nano(100.0);
     let ry = f64_to_nano(10000.0);
 
-    let buy_output = executor
+    let buy = executor
         .execute(0, f64_to_nano(10.0), rx, ry, &storage)
         .map_err(|e| anyhow::anyhow!("Buy execution failed: {}", e))?;
-    if buy_output == 0 {
+    if buy.value == 0 {
         anyhow::bail!("FAIL: Buy X returned zero output");
     }
     println!(
-        "  [PASS] Buy X: input_y=10.0 -> output_x={:.6}",
-        nano_to_f64(buy_output)
+        "  [PASS] Buy X: input_y=10.0 -> output_x={:.6} ({} CU)",
+        nano_to_f64(buy.value),
+        buy.compute_units
     );
 
-    let sell_output = executor
+    let sell = executor
         .execute(1, f64_to_nano(1.0), rx, ry, &storage)
         .map_err(|e| anyhow::anyhow!("Sell execution failed: {}", e))?;
-    if sell_output == 0 {
+    if sell.value == 0 {
         anyhow::bail!("FAIL: Sell X returned zero output");
     }
     println!(
-        "  [PASS] Sell X: input_x=1.0 -> output_y={:.6}",
-        nano_to_f64(sell_output)
+        "  [PASS] Sell X: input_x=1.0 -> output_y={:.6} ({} CU)",
+        nano_to_f64(sell.value),
+        sell.compute_units
     );
 
     // Monotonicity check: larger input -> larger output
@@ -107,7 +98,8 @@ pub fn run(file: &str) -> anyhow::Result<()> {
     for &size in &trade_sizes {
         let output = executor
             .execute(0, f64_to_nano(size), rx, ry, &storage)
-            .map_err(|e| anyhow::anyhow!("Execution failed at size {}: {}", size, e))?;
+            .map_err(|e| anyhow::anyhow!("Execution failed at size {}: {}", size, e))?
+            .value;
         if output <= prev_output && prev_output > 0 {
             anyhow::bail!(
                 "FAIL: Monotonicity violation (buy side). size={} output={} <= prev_output={}",
@@ -125,7 +117,8 @@ pub fn run(file: &str) -> anyhow::Result<()> {
     for &size in &trade_sizes {
         let output = executor
             .execute(1, f64_to_nano(size), rx, ry, &storage)
-            .map_err(|e| anyhow::anyhow!("Execution failed at size {}: {}", size, e))?;
+            .map_err(|e| anyhow::anyhow!("Execution failed at size {}: {}", size, e))?
+            .value;
         if output <= prev_output && prev_output > 0 {
             anyhow::bail!(
                 "FAIL: Monotonicity violation (sell side). size={} output={} <= prev_output={}",
@@ -150,9 +143,9 @@ pub fn run(file: &str) -> anyhow::Result<()> {
             continue;
         }
 
-        let out_0 = executor.execute(0, in_0, rx, ry, &storage)? as i128;
-        let out_1 = executor.execute(0, in_1, rx, ry, &storage)? as i128;
-        let out_2 = executor.execute(0, in_2, rx, ry, &storage)? as i128;
+        let out_0 = executor.execute(0, in_0, rx, ry, &storage)?.value as i128;
+        let out_1 = executor.execute(0, in_1, rx, ry, &storage)?.value as i128;
+        let out_2 = executor.execute(0, in_2, rx, ry, &storage)?.value as i128;
         let step_1 = out_1 - out_0;
         let step_2 = out_2 - out_1;
 
@@ -178,9 +171,9 @@ pub fn run(file: &str) -> anyhow::Result<()> {
             continue;
         }
 
-        let out_0 = executor.execute(1, in_0, rx, ry, &storage)? as i128;
-        let out_1 = executor.execute(1, in_1, rx, ry, &storage)? as i128;
-        let out_2 = executor.execute(1, in_2, rx, ry, &storage)? as i128;
+        let out_0 = executor.execute(1, in_0, rx, ry, &storage)?.value as i128;
+        let out_1 = executor.execute(1, in_1, rx, ry, &storage)?.value as i128;
+        let out_2 = executor.execute(1, in_2, rx, ry, &storage)?.value as i128;
         let step_1 = out_1 - out_0;
         let step_2 = out_2 - out_1;
 
@@ -200,8 +193,8 @@ pub fn run(file: &str) -> anyhow::Result<()> {
     println!("  Checking randomized reserve/storage states...");
     for seed in 0..32u64 {
         let mut storage = [0u8; STORAGE_SIZE];
-        for i in 0..32usize {
-            storage[i] = (mix(seed.wrapping_add(i as u64)) & 0xFF) as u8;
+        for (i, byte) in storage.iter_mut().take(32).enumerate() {
+            *byte = (mix(seed.wrapping_add(i as u64)) & 0xFF) as u8;
         }
 
         let rx = 1_000_000_000u64 + (mix(seed ^ 0x0123_4567_89AB_CDEF) % 2_000_000_000_000u64);
@@ -210,7 +203,7 @@ pub fn run(file: &str) -> anyhow::Result<()> {
         // Exercise after_swap and then re-check quote behavior with updated storage.
         let side = (seed & 1) as u8;
         let amount = 1_000_000 + (mix(seed ^ 0xDEAD_BEEF) % 10_000_000_000);
-        let out = executor.execute(side, amount, rx, ry, &storage)?;
+        let out = executor.execute(side, amount, rx, ry, &storage)?.value;
         let (post_rx, post_ry) = if side == 0 {
             (rx.saturating_sub(out), ry.saturating_add(amount))
         } else {
@@ -220,23 +213,28 @@ pub fn run(file: &str) -> anyhow::Result<()> {
     }
     println!("  [PASS] Randomized reserve/storage checks");
 
-    run_native_bpf_parity_check(parity_program, &native_path)?;
+    run_native_bpf_parity_check(file, parity_program, &so_path, &native_path)?;
 
     println!("\nAll validation checks passed!");
-    Ok(())
+    Ok(ValidatedSubmission { metadata, so_path })
 }
 
-fn run_native_bpf_parity_check(program: BpfProgram, native_path: &Path) -> anyhow::Result<()> {
+fn run_native_bpf_parity_check(
+    file: &str,
+    program: BpfProgram,
+    so_path: &Path,
+    native_path: &Path,
+) -> anyhow::Result<()> {
     println!(
         "  Checking native/BPF parity ({} sims, {} steps, seeds {} + i*{})...",
         PARITY_SIMS, PARITY_STEPS, PARITY_SEED_START, PARITY_SEED_STRIDE
     );
 
-    let submission_after_swap = load_native_submission(native_path)?;
+    let submission = LoadedSubmission::load(native_path)?;
 
     let native = runner::run_default_batch_native_seeded(
-        dynamic_swap,
-        submission_after_swap,
+        submission.swap_fn(),
+        submission.after_swap_fn(),
         normalizer_swap,
         Some(normalizer_after_swap),
         PARITY_SIMS,
@@ -245,15 +243,18 @@ fn run_native_bpf_parity_check(program: BpfProgram, native_path: &Path) -> anyho
         PARITY_SEED_START,
         PARITY_SEED_STRIDE,
     )?;
-    let bpf = runner::run_default_batch_mixed_seeded(
+    // Counting after_swap failures leaves results unchanged, so parity still holds.
+    let mut configs =
+        runner::default_configs(PARITY_SIMS, PARITY_STEPS, PARITY_SEED_START, PARITY_SEED_STRIDE);
+    for config in &mut configs {
+        config.after_swap_failure_policy = AfterSwapFailurePolicy::CountAndReport;
+    }
+    let bpf = runner::run_batch_mixed(
         program,
         normalizer_swap,
         Some(normalizer_after_swap),
-        PARITY_SIMS,
-        PARITY_STEPS,
+        configs,
         Some(4),
-        PARITY_SEED_START,
-        PARITY_SEED_STRIDE,
     )?;
 
     let total_delta = (native.total_edge - bpf.total_edge).abs();
@@ -272,6 +273,7 @@ fn run_native_bpf_parity_check(program: BpfProgram, native_path: &Path) -> anyho
     );
 
     if total_delta > PARITY_ABS_TOL || avg_delta > PARITY_ABS_TOL {
+        emit_parity_repro(file, so_path, &native, &bpf);
         anyhow::bail!(
             "FAIL: Native/BPF parity check failed. avg_delta={:.9}, total_delta={:.9}, tol={:.9}",
             avg_delta,
@@ -281,43 +283,51 @@ fn run_native_bpf_parity_check(program: BpfProgram, native_path: &Path) -> anyho
     }
 
     println!("  [PASS] Native/BPF parity");
+
+    // Edges can agree within tolerance while decisions differ in the last bit.
+    match native.results.iter().zip(&bpf.results).find(|(n, b)| n.checksum != b.checksum) {
+        None => println!("  [PASS] Native/BPF checksums match ({:016x})", native.checksum()),
+        Some((n, b)) => println!(
+            "  [WARN] Native/BPF checksums differ from seed {}: native={:016x} bpf={:016x}",
+            n.seed, n.checksum, b.checksum
+        ),
+    }
+    match bpf.results.iter().find(|r| r.after_swap_failures > 0) {
+        None => println!("  [PASS] BPF after_swap never failed"),
+        Some(first) => println!(
+            "  [WARN] BPF after_swap failed {} time(s), first in seed {}",
+            bpf.results.iter().map(|r| r.after_swap_failures).sum::<u32>(),
+            first.seed
+        ),
+    }
     Ok(())
 }
 
-fn load_native_submission(native_path: &Path) -> anyhow::Result<Option<AfterSwapFn>> {
-    let lib = Box::new(
-        unsafe { libloading::Library::new(native_path) }.map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to load native library {}: {}",
-                native_path.display(),
-                e
-            )
-        })?,
+/// Bundle the first seed whose native and BPF edges disagree.
+fn emit_parity_repro(file: &str, so_path: &Path, native: &BatchResult, bpf: &BatchResult) {
+    let Some((n, b)) = native
+        .results
+        .iter()
+        .zip(&bpf.results)
+        .find(|(n, b)| (n.submission_edge - b.submission_edge).abs() > PARITY_ABS_TOL)
+    else {
+        return;
+    };
+    let configs =
+        runner::default_configs(PARITY_SIMS, PARITY_STEPS, PARITY_SEED_START, PARITY_SEED_STRIDE);
+    let Some(config) = configs.iter().find(|c| c.seed == n.seed) else {
+        return;
+    };
+    let detail = format!(
+        "native_edge={:.9} bpf_edge={:.9}",
+        n.submission_edge, b.submission_edge
     );
-    let lib = Box::leak(lib);
-
-    let swap_fn: libloading::Symbol<FfiSwapFn> = unsafe {
-        lib.get(compile::NATIVE_SWAP_SYMBOL)
-            .or_else(|_| lib.get(b"compute_swap_ffi"))
+    let source = Path::new(file);
+    match ReproBundle::new(ProblemKind::Parity, detail, Backend::Bpf, source, Some(so_path), config)
+    {
+        Ok(bundle) => repro::emit(&bundle, Path::new(repro::DEFAULT_REPRO_DIR), source),
+        Err(err) => tracing::warn!("Failed to write reproducer: {err:#}"),
     }
-    .map_err(|e| anyhow::anyhow!("Missing native swap symbol: {}", e))?;
-    LOADED_SWAP.store(*swap_fn as *mut (), Ordering::Relaxed);
-
-    let has_after_swap = if let Ok(after_fn) = unsafe {
-        lib.get::<FfiAfterSwapFn>(compile::NATIVE_AFTER_SWAP_SYMBOL)
-            .or_else(|_| lib.get::<FfiAfterSwapFn>(b"after_swap_ffi"))
-    } {
-        LOADED_AFTER_SWAP.store(*after_fn as *mut (), Ordering::Relaxed);
-        true
-    } else {
-        false
-    };
-
-    Ok(if has_after_swap {
-        Some(dynamic_after_swap)
-    } else {
-        None
-    })
 }
 
 #[inline]
@@ -329,12 +339,12 @@ fn mix(mut z: u64) -> u64 {
     z ^ (z >> 31)
 }
 
-struct SubmissionMetadata {
-    name: String,
-    model_used: String,
+pub(crate) struct SubmissionMetadata {
+    pub name: String,
+    pub model_used: String,
 }
 
-fn validate_submission_metadata(file: &str) -> anyhow::Result<SubmissionMetadata> {
+pub(crate) fn validate_submission_metadata(file: &str) -> anyhow::Result<SubmissionMetadata> {
     let source = std::fs::read_to_string(file)
         .map_err(|e| anyhow::anyhow!("Failed to read {} for metadata checks: {}", file, e))?;
     let parsed = syn::parse_file(&source)
@@ -356,10 +366,8 @@ fn validate_submission_metadata(file: &str) -> anyhow::Result<SubmissionMetadata
                         .context("`MODEL_USED` must be a string literal constant")?;
                 }
             }
-            Item::Fn(item_fn) => {
-                if item_fn.sig.ident == "get_model_used" {
-                    has_get_model_used = true;
-                }
+            Item::Fn(item_fn) if item_fn.sig.ident == "get_model_used" => {
+                has_get_model_used = true;
             }
             _ => {}
         }
@@ -0,0 +1,432 @@
+use std::path::Path;
+
+use clap::ValueHint;
+use prop_amm_executor::BpfProgram;
+use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
+use prop_amm_shared::result::BatchResult;
+use prop_amm_sim::engine::RngStreams;
+
+use super::run::{self, BatchPlan};
+use crate::manifest::{self, Backend, ConfigRanges, ManifestTotals, RunManifest};
+
+#[derive(clap::Args)]
+pub struct VerifyManifestArgs {
+    /// Path to the manifest written by `prop-amm run`
+    #[arg(default_value = manifest::DEFAULT_MANIFEST_PATH, value_hint = ValueHint::FilePath)]
+    pub manifest: String,
+    /// Submission source to re-run instead of the path recorded in the manifest
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub file: Option<String>,
+    /// Prebuilt BPF .so to use for a BPF manifest (skips compilation)
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub bpf_so: Option<String>,
+    /// Prebuilt native dylib to use for a native manifest (skips compilation)
+    #[arg(long, conflicts_with = "bpf_so", value_hint = ValueHint::FilePath)]
+    pub native_so: Option<String>,
+    /// Number of parallel workers (0 = auto)
+    #[arg(long, default_value = "0")]
+    pub workers: usize,
+    /// Only re-derive the recorded RNG streams; skip compiling and re-running the batch
+    #[arg(long, conflicts_with_all = ["file", "bpf_so", "native_so"])]
+    pub rng_only: bool,
+}
+
+pub fn run(args: VerifyManifestArgs) -> anyhow::Result<()> {
+    let recorded = RunManifest::read(Path::new(&args.manifest))?;
+    let file = args.file.as_deref().unwrap_or(&recorded.source_path);
+    println!("Verifying manifest {}", args.manifest);
+
+    if args.rng_only {
+        if recorded.rng_streams.is_none() {
+            anyhow::bail!(
+                "Manifest has no RNG streams; re-run with `prop-amm run --record-rng-streams`"
+            );
+        }
+        recorded_plan(&recorded)?;
+        println!("\nRNG streams verified.");
+        return Ok(());
+    }
+
+    let source_hash = manifest::hash_file(Path::new(file))?;
+    if source_hash != recorded.source_hash {
+        anyhow::bail!(
+            "FAIL: Source hash mismatch for {}: {} (manifest: {})",
+            file,
+            source_hash,
+            recorded.source_hash
+        );
+    }
+    println!("  [PASS] Source hash: {}", source_hash);
+
+    let plan = recorded_plan(&recorded)?;
+    let n_workers = if args.workers == 0 {
+        None
+    } else {
+        Some(args.workers)
+    };
+    let outcome = match recorded.backend {
+        Backend::Native => run::run_native(file, args.native_so.as_deref(), &plan, n_workers)?,
+        Backend::Bpf => {
+            let normalizer = recorded_normalizer(&recorded)?;
+            run::run_bpf_against(file, args.bpf_so.as_deref(), normalizer, &plan, n_workers)?
+        }
+    };
+
+    println!();
+    // Compiled bytes depend on the toolchain, so a differing artifact is reported, not fatal.
+    let artifact_hash = manifest::hash_file(&outcome.artifact)?;
+    if artifact_hash == recorded.artifact_hash {
+        println!("  [PASS] Artifact hash: {}", artifact_hash);
+    } else {
+        println!(
+            "  [WARN] Artifact hash differs: {} (manifest: {}); different toolchain?",
+            artifact_hash, recorded.artifact_hash
+        );
+    }
+
+    check_totals(&recorded.totals, &outcome.result)?;
+
+    println!("\nManifest verified.");
+    Ok(())
+}
+
+/// The batch a manifest records, rebuilt with this build's config sampling; fails if this
+/// build samples different config ranges or derives different RNG streams.
+fn recorded_plan(recorded: &RunManifest) -> anyhow::Result<BatchPlan> {
+    let variance = recorded_variance(recorded);
+    if ConfigRanges::from(&variance) != recorded.config_ranges {
+        anyhow::bail!("FAIL: This build samples different config ranges than the manifest");
+    }
+    println!("  [PASS] Config ranges");
+
+    let plan = BatchPlan {
+        shape_penalty: recorded.shape_penalty.clone(),
+        ..BatchPlan::sample(
+            &recorded.config,
+            variance,
+            &recorded.seeds,
+            format!("{} seeds from manifest", recorded.seeds.len()),
+        )
+    };
+    if let Some(streams) = &recorded.rng_streams {
+        check_rng_streams(streams, &plan.configs)?;
+    }
+    Ok(plan)
+}
+
+/// The normalizer program a BPF manifest ran against, or `None` for the native normalizer.
+fn recorded_normalizer(recorded: &RunManifest) -> anyhow::Result<Option<BpfProgram>> {
+    recorded.bpf_normalizer.then(run::embedded_normalizer).transpose()
+}
+
+/// Compare a re-run's totals and engine checksum with the manifest's.
+fn check_totals(totals: &ManifestTotals, result: &BatchResult) -> anyhow::Result<()> {
+    let trade_hash = format!("{:016x}", result.trade_hash());
+    if result.n_sims() != totals.n_sims
+        || result.total_edge.to_bits() != totals.total_edge.to_bits()
+        || trade_hash != totals.trade_hash
+    {
+        anyhow::bail!(
+            "FAIL: Totals differ from manifest. sims={} total_edge={} trade_hash={} \
+             (manifest: sims={} total_edge={} trade_hash={})",
+            result.n_sims(),
+            result.total_edge,
+            trade_hash,
+            totals.n_sims,
+            totals.total_edge,
+            totals.trade_hash
+        );
+    }
+    println!(
+        "  [PASS] Totals: {} sims, total edge {}, trade hash {}",
+        totals.n_sims, totals.total_edge, totals.trade_hash
+    );
+
+    if let Some(recorded) = &totals.checksum {
+        let checksum = format!("{:016x}", result.checksum());
+        if checksum != *recorded {
+            anyhow::bail!(
+                "FAIL: Engine checksum differs: {} (manifest: {}); final reserves or storage \
+                 diverged",
+                checksum,
+                recorded
+            );
+        }
+        println!("  [PASS] Engine checksum: {}", checksum);
+    }
+    Ok(())
+}
+
+/// This build's variance box, with the manifest's opt-in step count range and flow regime
+/// chance.
+fn recorded_variance(recorded: &RunManifest) -> HyperparameterVariance {
+    HyperparameterVariance {
+        n_steps_range: recorded.config_ranges.n_steps.map(|[min, max]| (min, max)),
+        flow_regime_prob: recorded.config_ranges.flow_regime_prob,
+        ..HyperparameterVariance::default()
+    }
+}
+
+/// Re-derive each simulation's RNG stream seeds and compare them with the manifest.
+fn check_rng_streams(
+    streams: &[RngStreams],
+    configs: &[SimulationConfig],
+) -> anyhow::Result<()> {
+    if streams.len() != configs.len() {
+        anyhow::bail!(
+            "FAIL: Manifest records RNG streams for {} simulations, but lists {} seeds",
+            streams.len(),
+            configs.len()
+        );
+    }
+    for (expected, config) in streams.iter().zip(configs) {
+        let derived = RngStreams::for_config(config);
+        if derived != *expected {
+            anyhow::bail!(
+                "FAIL: RNG streams for seed {} are derived differently by this build\n  \
+                 manifest: {:?}\n  derived:  {:?}",
+                config.seed,
+                expected,
+                derived
+            );
+        }
+    }
+    println!("  [PASS] RNG streams: {} simulations", streams.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
+    use prop_amm_shared::normalizer::{after_swap, compute_swap};
+    use prop_amm_shared::result::BatchResult;
+    use prop_amm_shared::scoring::ShapePenaltyPolicy;
+    use prop_amm_sim::engine::RngStreams;
+    use prop_amm_sim::runner;
+
+    use super::{check_totals, recorded_normalizer, recorded_plan, VerifyManifestArgs};
+    use crate::commands::run::{BatchPlan, RunOutcome};
+    use crate::manifest::{Backend, RunManifest};
+
+    /// A scratch directory holding a stand-in source and artifact, removed on drop.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("prop-amm-manifest-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("lib.rs"), "// submission").unwrap();
+            std::fs::write(dir.join("lib.so"), [0x7f, b'E', b'L', b'F']).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn run_plan(plan: &BatchPlan) -> BatchResult {
+        let configs = plan.configs.clone();
+        runner::run_batch_native(
+            compute_swap,
+            Some(after_swap),
+            compute_swap,
+            Some(after_swap),
+            configs,
+            None,
+        )
+        .unwrap()
+    }
+
+    /// A manifest for a small native batch, read back from disk, and the batch's result.
+    fn recorded_batch(scratch: &Scratch) -> (RunManifest, BatchResult) {
+        let variance = HyperparameterVariance::default();
+        let seeds = [3, 11];
+        let base = SimulationConfig {
+            n_steps: 400,
+            informed_prob: 0.2,
+            ..SimulationConfig::default()
+        };
+        let plan = BatchPlan::sample(&base, variance, &seeds, "test seeds".to_string());
+        let outcome = RunOutcome {
+            result: run_plan(&plan),
+            artifact: scratch.0.join("lib.so"),
+        };
+        let source = scratch.0.join("lib.rs");
+        let mut manifest =
+            RunManifest::record(source.to_str().unwrap(), Backend::Native, &outcome, &plan)
+                .unwrap();
+        manifest.rng_streams = Some(plan.configs.iter().map(RngStreams::for_config).collect());
+        let path = scratch.0.join("manifest.json");
+        manifest.write(&path).unwrap();
+        let read = RunManifest::read(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            serde_json::to_value(&manifest).unwrap()
+        );
+        (read, outcome.result)
+    }
+
+    #[test]
+    fn manifest_round_trips_and_reproduces_its_batch() {
+        let scratch = Scratch::new("round-trip");
+        let (manifest, result) = recorded_batch(&scratch);
+        assert_eq!(manifest.seeds, vec![3, 11]);
+        assert_eq!(manifest.config.informed_prob, 0.2);
+
+        let plan = recorded_plan(&manifest).unwrap();
+        let rerun = run_plan(&plan);
+        assert_eq!(rerun.trade_hash(), result.trade_hash());
+        check_totals(&manifest.totals, &rerun).unwrap();
+    }
+
+    #[test]
+    fn bpf_normalizer_manifest_round_trips_and_replays_against_it() {
+        let scratch = Scratch::new("bpf-normalizer");
+        let (mut manifest, _) = recorded_batch(&scratch);
+        manifest.backend = Backend::Bpf;
+        manifest.bpf_normalizer = true;
+        let path = scratch.0.join("bpf.json");
+        manifest.write(&path).unwrap();
+        let read = RunManifest::read(&path).unwrap();
+        assert_eq!(read.backend, Backend::Bpf);
+        assert!(read.bpf_normalizer);
+
+        // Replayed against the embedded normalizer, never silently the native one.
+        let normalizer = recorded_normalizer(&read);
+        #[cfg(feature = "prebuilt-programs")]
+        assert!(normalizer.unwrap().is_some());
+        #[cfg(not(feature = "prebuilt-programs"))]
+        assert!(normalizer.err().unwrap().to_string().contains("prebuilt-programs"));
+
+        // Manifests written before the field existed ran against the native normalizer.
+        let mut value = serde_json::to_value(&read).unwrap();
+        value.as_object_mut().unwrap().remove("bpf_normalizer");
+        let old: RunManifest = serde_json::from_value(value).unwrap();
+        assert!(!old.bpf_normalizer);
+        assert!(recorded_normalizer(&old).unwrap().is_none());
+    }
+
+    #[test]
+    fn recorded_plan_rebuilds_the_sampled_configs() {
+        let scratch = Scratch::new("configs");
+        let base = SimulationConfig {
+            n_steps: 300,
+            warmup_steps: 20,
+            informed_prob: 0.1,
+            arb_cp_fast_path: false,
+            retail_partial_fills: true,
+            retail_max_slippage_bps: Some(40.0),
+            max_trade_fraction: Some(0.25),
+            price_guard_bps: Some(500.0),
+            oracle_noise: 0.002,
+            flow_persistence: 0.3,
+            min_trade_size: 0.01,
+            ..SimulationConfig::default()
+        };
+        let plan = BatchPlan {
+            shape_penalty: ShapePenaltyPolicy {
+                warning_penalty: 0.5,
+                max_warnings: Some(3),
+                ..ShapePenaltyPolicy::default()
+            },
+            ..BatchPlan::sample(
+                &base,
+                HyperparameterVariance::default(),
+                &[5, 8],
+                "test seeds".to_string(),
+            )
+        };
+        let outcome = RunOutcome {
+            result: run_plan(&plan),
+            artifact: scratch.0.join("lib.so"),
+        };
+        let source = scratch.0.join("lib.rs");
+        let manifest =
+            RunManifest::record(source.to_str().unwrap(), Backend::Native, &outcome, &plan)
+                .unwrap();
+
+        let replayed = recorded_plan(&manifest).unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed.configs).unwrap(),
+            serde_json::to_value(&plan.configs).unwrap()
+        );
+        assert_eq!(replayed.shape_penalty, plan.shape_penalty);
+    }
+
+    #[test]
+    fn rng_only_plans_the_batch_a_full_verify_would() {
+        let scratch = Scratch::new("rng-only");
+        let (manifest, _) = recorded_batch(&scratch);
+        let verify = |manifest: &RunManifest| {
+            let path = scratch.0.join("verify.json");
+            manifest.write(&path).unwrap();
+            super::run(VerifyManifestArgs {
+                manifest: path.to_str().unwrap().to_string(),
+                file: None,
+                bpf_so: None,
+                native_so: None,
+                workers: 0,
+                rng_only: true,
+            })
+        };
+        verify(&manifest).unwrap();
+
+        let mut tampered = manifest.clone();
+        tampered.config_ranges.gbm_sigma[1] *= 2.0;
+        let err = verify(&tampered).err().unwrap().to_string();
+        assert!(err.contains("config ranges"), "{err}");
+
+        let mut tampered = manifest.clone();
+        tampered.rng_streams = None;
+        let err = verify(&tampered).err().unwrap().to_string();
+        assert!(err.contains("no RNG streams"), "{err}");
+    }
+
+    #[test]
+    fn tampered_totals_are_rejected() {
+        let scratch = Scratch::new("totals");
+        let (manifest, result) = recorded_batch(&scratch);
+
+        let mut totals = manifest.totals.clone();
+        totals.checksum = Some("0123456789abcdef".to_string());
+        let err = check_totals(&totals, &result).unwrap_err().to_string();
+        assert!(err.contains("Engine checksum differs"), "{err}");
+
+        let mut totals = manifest.totals.clone();
+        totals.total_edge += 1.0;
+        let err = check_totals(&totals, &result).unwrap_err().to_string();
+        assert!(err.contains("Totals differ"), "{err}");
+    }
+
+    #[test]
+    fn tampered_config_is_rejected() {
+        let scratch = Scratch::new("config");
+        let (manifest, result) = recorded_batch(&scratch);
+
+        // Ranges this build would not sample from.
+        let mut tampered = manifest.clone();
+        tampered.config_ranges.gbm_sigma[1] *= 2.0;
+        let err = recorded_plan(&tampered).err().unwrap().to_string();
+        assert!(err.contains("config ranges"), "{err}");
+
+        // RNG streams this build would not derive.
+        let mut tampered = manifest.clone();
+        tampered.rng_streams.as_mut().unwrap()[1].price ^= 1;
+        let err = recorded_plan(&tampered).err().unwrap().to_string();
+        assert!(err.contains("RNG streams for seed 11"), "{err}");
+
+        // A setting changed in place plans a different batch, whose totals no longer match.
+        let mut tampered = manifest.clone();
+        tampered.config.informed_prob = 0.0;
+        tampered.rng_streams = None;
+        let rerun = run_plan(&recorded_plan(&tampered).unwrap());
+        assert!(check_totals(&manifest.totals, &rerun).is_err());
+        assert!(check_totals(&manifest.totals, &result).is_ok());
+    }
+}
@@ -0,0 +1,229 @@
+//! Per-seed result exports (`run --export`), readable back by `plot` and `--seeds-file`,
+//! reserve trajectory exports (`run --trajectory`), and trade tapes (`run --trade-tape`).
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use prop_amm_shared::result::{BatchResult, Counterparty};
+use serde::{Deserialize, Serialize};
+
+const CSV_HEADER: &str = "seed,edge,arbitrageur_edge,retail_edge,trade_hash,normalizer_edge";
+/// Header of exports written before `normalizer_edge` was added; still readable.
+const LEGACY_CSV_HEADER: &str = "seed,edge,arbitrageur_edge,retail_edge,trade_hash";
+const TRAJECTORY_CSV_HEADER: &str = "seed,step,reserve_x,reserve_y,fair_price";
+const TAPE_CSV_HEADER: &str =
+    "seed,step,venue,counterparty,side,amount_in,amount_out,fair_price";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedRow {
+    pub seed: u64,
+    pub edge: f64,
+    pub arbitrageur_edge: f64,
+    pub retail_edge: f64,
+    /// Hex `SimResult::trade_hash`.
+    pub trade_hash: String,
+    /// `None` when read from an export that predates the column.
+    #[serde(default)]
+    pub normalizer_edge: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TrajectoryRow {
+    seed: u64,
+    step: u32,
+    reserve_x: f64,
+    reserve_y: f64,
+    fair_price: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TapeRow {
+    seed: u64,
+    step: u32,
+    venue: u8,
+    counterparty: &'static str,
+    /// 0 = trader bought X with Y, 1 = trader sold X.
+    side: u8,
+    amount_in: f64,
+    amount_out: f64,
+    fair_price: f64,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Csv,
+    Json,
+}
+
+fn format_for(path: &Path) -> anyhow::Result<Format> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => Ok(Format::Csv),
+        Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(Format::Json),
+        _ => anyhow::bail!(
+            "Unsupported results file {} (expected .csv or .json)",
+            path.display()
+        ),
+    }
+}
+
+pub fn rows(result: &BatchResult) -> Vec<SeedRow> {
+    result
+        .results
+        .iter()
+        .map(|r| SeedRow {
+            seed: r.seed,
+            edge: r.submission_edge,
+            arbitrageur_edge: r.edge_by_counterparty.get(Counterparty::Arbitrageur),
+            retail_edge: r.edge_by_counterparty.get(Counterparty::Retail),
+            trade_hash: format!("{:016x}", r.trade_hash),
+            normalizer_edge: Some(r.normalizer_edge()),
+        })
+        .collect()
+}
+
+pub fn write_results(path: &Path, result: &BatchResult) -> anyhow::Result<()> {
+    let rows = rows(result);
+    let contents = match format_for(path)? {
+        Format::Json => serde_json::to_string_pretty(&rows)?,
+        Format::Csv => {
+            let mut out = String::from(CSV_HEADER);
+            out.push('\n');
+            for row in &rows {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    row.seed,
+                    row.edge,
+                    row.arbitrageur_edge,
+                    row.retail_edge,
+                    row.trade_hash,
+                    row.normalizer_edge.unwrap_or(f64::NAN)
+                )?;
+            }
+            out
+        }
+    };
+    write_file(path, contents)
+}
+
+/// Every simulation's recorded `SimResult::trajectory`, one row per snapshot.
+pub fn write_trajectories(path: &Path, result: &BatchResult) -> anyhow::Result<()> {
+    let rows: Vec<TrajectoryRow> = result
+        .results
+        .iter()
+        .flat_map(|r| {
+            r.trajectory.iter().map(|s| TrajectoryRow {
+                seed: r.seed,
+                step: s.step,
+                reserve_x: s.reserve_x,
+                reserve_y: s.reserve_y,
+                fair_price: s.fair_price,
+            })
+        })
+        .collect();
+    let contents = match format_for(path)? {
+        Format::Json => serde_json::to_string_pretty(&rows)?,
+        Format::Csv => {
+            let mut out = String::from(TRAJECTORY_CSV_HEADER);
+            out.push('\n');
+            for row in &rows {
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    row.seed, row.step, row.reserve_x, row.reserve_y, row.fair_price
+                )?;
+            }
+            out
+        }
+    };
+    write_file(path, contents)
+}
+
+/// Every simulation's recorded `SimResult::tape`, one row per trade in execution order.
+pub fn write_trade_tape(path: &Path, result: &BatchResult) -> anyhow::Result<()> {
+    let rows: Vec<TapeRow> = result
+        .results
+        .iter()
+        .flat_map(|r| {
+            r.tape.iter().map(|t| TapeRow {
+                seed: r.seed,
+                step: t.step,
+                venue: t.venue,
+                counterparty: t.counterparty.label(),
+                side: t.side(),
+                amount_in: t.amount_in(),
+                amount_out: t.amount_out(),
+                fair_price: t.fair_price,
+            })
+        })
+        .collect();
+    let contents = match format_for(path)? {
+        Format::Json => serde_json::to_string_pretty(&rows)?,
+        Format::Csv => {
+            let mut out = String::from(TAPE_CSV_HEADER);
+            out.push('\n');
+            for row in &rows {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{}",
+                    row.seed,
+                    row.step,
+                    row.venue,
+                    row.counterparty,
+                    row.side,
+                    row.amount_in,
+                    row.amount_out,
+                    row.fair_price
+                )?;
+            }
+            out
+        }
+    };
+    write_file(path, contents)
+}
+
+fn write_file(path: &Path, contents: String) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn read_results(path: &Path) -> anyhow::Result<Vec<SeedRow>> {
+    let format = format_for(path)?;
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    match format {
+        Format::Json => serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid results file {}: {}", path.display(), e)),
+        Format::Csv => {
+            let mut rows = Vec::new();
+            for (line_no, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line == CSV_HEADER || line == LEGACY_CSV_HEADER {
+                    continue;
+                }
+                rows.push(parse_csv_row(line).map_err(|e| {
+                    anyhow::anyhow!("{}:{}: {}", path.display(), line_no + 1, e)
+                })?);
+            }
+            Ok(rows)
+        }
+    }
+}
+
+fn parse_csv_row(line: &str) -> anyhow::Result<SeedRow> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if !(5..=6).contains(&fields.len()) {
+        anyhow::bail!("expected 5 or 6 fields, got {}", fields.len());
+    }
+    Ok(SeedRow {
+        seed: fields[0].parse()?,
+        edge: fields[1].parse()?,
+        arbitrageur_edge: fields[2].parse()?,
+        retail_edge: fields[3].parse()?,
+        trade_hash: fields[4].to_string(),
+        normalizer_edge: fields.get(5).map(|f| f.parse()).transpose()?,
+    })
+}
@@ -0,0 +1,33 @@
+use tracing_subscriber::EnvFilter;
+
+/// Crates whose log level follows `-q`/`-v`; dependencies stay at `warn`.
+const WORKSPACE_TARGETS: &[&str] = &["prop_amm", "prop_amm_sim", "prop_amm_executor"];
+
+/// Install the global subscriber. Logs go to stderr so stdout stays clean for results.
+///
+/// `-q` shows warnings only, the default is `info`, `-v` is `debug`, `-vv` is `trace`.
+/// `RUST_LOG`, when set, overrides the flags entirely.
+pub fn init(verbose: u8, quiet: bool, json: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => "warn",
+        (false, 0) => "info",
+        (false, 1) => "debug",
+        (false, _) => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        let mut directives = String::from("warn");
+        for target in WORKSPACE_TARGETS {
+            directives.push_str(&format!(",{target}={level}"));
+        }
+        EnvFilter::new(directives)
+    });
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        builder.json().init();
+    } else {
+        builder.without_time().with_target(verbose > 0).init();
+    }
+}
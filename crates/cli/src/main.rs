@@ -1,13 +1,36 @@
 mod commands;
+mod export;
+mod logging;
+mod manifest;
 mod output;
+mod repro;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
 
 #[derive(Parser)]
-#[command(name = "prop-amm", about = "Prop AMM Challenge CLI")]
+#[command(
+    name = "prop-amm",
+    about = "Prop AMM Challenge CLI",
+    after_help = "Examples:\n  \
+        prop-amm validate my_amm.rs\n  \
+        prop-amm run my_amm.rs --simulations 100\n  \
+        prop-amm run my_amm.rs --watch\n  \
+        prop-amm run my_amm.rs --bpf --simulations 10\n\n\
+        Shell completions: prop-amm completions <SHELL> (bash, zsh, fish, elvish, powershell)"
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// More log output (-v debug, -vv trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only log warnings and errors (silences compile output)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Emit logs as JSON lines on stderr
+    #[arg(long, global = true)]
+    log_json: bool,
 }
 
 #[derive(Subcommand)]
@@ -15,66 +38,58 @@ enum Commands {
     /// Build program (native for simulation, BPF for submission)
     Build {
         /// Path to the .rs source file
+        #[arg(value_hint = ValueHint::FilePath)]
         file: String,
     },
     /// Validate a program (convexity, monotonicity, CU)
     Validate {
         /// Path to the .rs source file
+        #[arg(value_hint = ValueHint::FilePath)]
         file: String,
     },
     /// Run simulation batch
-    Run {
-        /// Path to the .rs source file
-        file: String,
-        /// Number of simulations
-        #[arg(long, default_value = "1000")]
-        simulations: u32,
-        /// Number of steps per simulation
-        #[arg(long, default_value = "10000")]
-        steps: u32,
-        /// Number of parallel workers (0 = auto)
-        #[arg(long, default_value = "0")]
-        workers: usize,
-        /// Starting seed for simulation config generation
-        #[arg(long, default_value = "0")]
-        seed_start: u64,
-        /// Seed step between simulations
-        #[arg(long, default_value = "1")]
-        seed_stride: u64,
-        /// Use BPF runtime instead of native (slower, for validation)
-        #[arg(long)]
-        bpf: bool,
-        /// Path to a prebuilt BPF .so to use when running with --bpf (skips compilation).
-        /// Useful on machines without the Solana SBF toolchain installed.
-        #[arg(long)]
-        bpf_so: Option<String>,
+    Run(Box<commands::run::RunArgs>),
+    /// Plot the per-seed edge distribution of a batch as SVG
+    Plot(commands::plot::PlotArgs),
+    /// Compare two programs' quotes over a grid of sides, reserves, and input sizes
+    DiffCurves(commands::diff_curves::DiffCurvesArgs),
+    /// Validate, package, and upload a submission to the challenge API
+    Submit(commands::submit::SubmitArgs),
+    /// Scale the config variance box so a reference strategy earns a target average edge
+    Calibrate(commands::calibrate::CalibrateArgs),
+    /// Re-run the batch recorded in a run manifest and confirm identical totals
+    VerifyManifest(commands::verify_manifest::VerifyManifestArgs),
+    /// Re-run the single simulation captured in a reproducer directory
+    Repro(commands::repro::ReproArgs),
+    /// Print a shell completion script, e.g. `prop-amm completions zsh > _prop-amm`
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
     },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    logging::init(cli.verbose, cli.quiet, cli.log_json);
 
     match cli.command {
         Commands::Build { file } => commands::build::run(&file),
         Commands::Validate { file } => commands::validate::run(&file),
-        Commands::Run {
-            file,
-            simulations,
-            steps,
-            workers,
-            seed_start,
-            seed_stride,
-            bpf,
-            bpf_so,
-        } => commands::run::run(
-            &file,
-            simulations,
-            steps,
-            workers,
-            seed_start,
-            seed_stride,
-            bpf,
-            bpf_so.as_deref(),
-        ),
+        Commands::Run(args) => commands::run::run(*args),
+        Commands::Plot(args) => commands::plot::run(args),
+        Commands::DiffCurves(args) => commands::diff_curves::run(args),
+        Commands::Calibrate(args) => commands::calibrate::run(args),
+        Commands::Submit(args) => commands::submit::run(args),
+        Commands::VerifyManifest(args) => commands::verify_manifest::run(args),
+        Commands::Repro(args) => commands::repro::run(args),
+        Commands::Completions { shell } => {
+            // Generate into a buffer: `generate` panics on write errors such as a closed pipe.
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "prop-amm", &mut script);
+            match std::io::Write::write_all(&mut std::io::stdout(), &script) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+                _ => Ok(()),
+            }
+        }
     }
 }
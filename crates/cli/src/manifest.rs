@@ -0,0 +1,159 @@
+//! Reproducibility manifests: everything needed to re-run a batch and check that it
+//! produces the same totals, written after every `prop-amm run`.
+
+use std::path::Path;
+
+use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
+use prop_amm_shared::hash::fnv64;
+use prop_amm_shared::scoring::ShapePenaltyPolicy;
+use prop_amm_sim::engine::RngStreams;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::run::{BatchPlan, RunOutcome};
+
+pub const DEFAULT_MANIFEST_PATH: &str = ".build/manifest.json";
+pub const MANIFEST_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Native,
+    Bpf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigRanges {
+    pub gbm_sigma: [f64; 2],
+    pub retail_arrival_rate: [f64; 2],
+    pub retail_mean_size: [f64; 2],
+    pub norm_fee_bps: [u16; 2],
+    pub norm_liquidity_mult: [f64; 2],
+    #[serde(default)]
+    pub flow_regime_prob: f64,
+    #[serde(default)]
+    pub flow_regime_skew: [f64; 2],
+    #[serde(default)]
+    pub flow_regime_len_frac: [f64; 2],
+    #[serde(default)]
+    pub n_steps: Option<[u32; 2]>,
+}
+
+impl From<&HyperparameterVariance> for ConfigRanges {
+    fn from(v: &HyperparameterVariance) -> Self {
+        Self {
+            gbm_sigma: [v.gbm_sigma_min, v.gbm_sigma_max],
+            retail_arrival_rate: [v.retail_arrival_rate_min, v.retail_arrival_rate_max],
+            retail_mean_size: [v.retail_mean_size_min, v.retail_mean_size_max],
+            norm_fee_bps: [v.norm_fee_bps_min, v.norm_fee_bps_max],
+            norm_liquidity_mult: [v.norm_liquidity_mult_min, v.norm_liquidity_mult_max],
+            flow_regime_prob: v.flow_regime_prob,
+            flow_regime_skew: [v.flow_regime_skew_min, v.flow_regime_skew_max],
+            flow_regime_len_frac: [v.flow_regime_len_frac_min, v.flow_regime_len_frac_max],
+            n_steps: v.n_steps_range.map(|(min, max)| [min, max]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestTotals {
+    pub n_sims: usize,
+    pub total_edge: f64,
+    pub avg_edge: f64,
+    /// Hex `BatchResult::trade_hash`.
+    pub trade_hash: String,
+    /// Hex `BatchResult::checksum`; absent in manifests written before it existed.
+    #[serde(default)]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub version: u32,
+    pub cli_version: String,
+    pub args: Vec<String>,
+    pub backend: Backend,
+    /// A BPF run quoted the normalizer as its embedded BPF program (`--bpf-normalizer`).
+    #[serde(default)]
+    pub bpf_normalizer: bool,
+    pub source_path: String,
+    /// Hex FNV-1a 64 of the submission source as read from disk.
+    pub source_hash: String,
+    pub artifact_path: String,
+    /// Hex FNV-1a 64 of the compiled dylib (native) or .so (BPF).
+    pub artifact_hash: String,
+    /// The config every simulation starts from, before each seed's hyperparameters are
+    /// sampled from `config_ranges`.
+    pub config: SimulationConfig,
+    pub config_ranges: ConfigRanges,
+    /// How shape-check warnings and violations were scored.
+    #[serde(default)]
+    pub shape_penalty: ShapePenaltyPolicy,
+    pub seeds: Vec<u64>,
+    /// Per-simulation RNG stream seeds, recorded with `--record-rng-streams`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rng_streams: Option<Vec<RngStreams>>,
+    pub totals: ManifestTotals,
+}
+
+pub fn hash_file(path: &Path) -> anyhow::Result<String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    Ok(format!("{:016x}", fnv64(&bytes)))
+}
+
+impl RunManifest {
+    pub(crate) fn record(
+        file: &str,
+        backend: Backend,
+        outcome: &RunOutcome,
+        plan: &BatchPlan,
+    ) -> anyhow::Result<Self> {
+        let result = &outcome.result;
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            args: std::env::args().collect(),
+            backend,
+            bpf_normalizer: false,
+            source_path: file.to_string(),
+            source_hash: hash_file(Path::new(file))?,
+            artifact_path: outcome.artifact.display().to_string(),
+            artifact_hash: hash_file(&outcome.artifact)?,
+            config: plan.base.clone(),
+            config_ranges: ConfigRanges::from(&plan.variance),
+            shape_penalty: plan.shape_penalty.clone(),
+            seeds: plan.seed_list(),
+            rng_streams: None,
+            totals: ManifestTotals {
+                n_sims: result.n_sims(),
+                total_edge: result.total_edge,
+                avg_edge: result.avg_edge(),
+                trade_hash: format!("{:016x}", result.trade_hash()),
+                checksum: Some(format!("{:016x}", result.checksum())),
+            },
+        })
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read manifest {}: {}", path.display(), e))?;
+        let manifest: Self = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?;
+        if manifest.version != MANIFEST_VERSION {
+            anyhow::bail!(
+                "Unsupported manifest version {} (expected {})",
+                manifest.version,
+                MANIFEST_VERSION
+            );
+        }
+        Ok(manifest)
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write manifest {}: {}", path.display(), e))
+    }
+}
@@ -1,13 +1,24 @@
-use prop_amm_shared::result::BatchResult;
+use prop_amm_shared::result::{
+    BatchResult, CostDistribution, Counterparty, QuoteDivergence, SimResult, VolumeBreakdown,
+    COST_BUCKETS_BPS,
+};
+use prop_amm_shared::scoring::{RiskPolicy, ShapePenaltyPolicy};
 use std::time::Duration;
 
+const WORST_SEEDS_SHOWN: usize = 5;
+
 pub struct RunTimings {
     pub compile_or_load: Duration,
     pub simulation: Duration,
     pub total: Duration,
 }
 
-pub fn print_results(result: &BatchResult, timings: RunTimings) {
+pub fn print_results(
+    result: &BatchResult,
+    risk: &RiskPolicy,
+    shape_penalty: &ShapePenaltyPolicy,
+    timings: RunTimings,
+) {
     let seed_range = result
         .results
         .iter()
@@ -29,9 +40,130 @@ pub fn print_results(result: &BatchResult, timings: RunTimings) {
     println!("  Simulation:  {:>8.2}s", timings.simulation.as_secs_f64());
     println!("  Total:       {:>8.2}s", timings.total.as_secs_f64());
     println!("  Avg edge:    {:.2}", result.avg_edge());
+    print_edge_breakdown(result);
     println!("  Total edge:  {:.2}", result.total_edge);
+    println!("  LVR:         {:.2} avg", result.avg_lvr());
+    println!(
+        "  Fee revenue: {:.2} avg (normalizer {:.2})",
+        result.avg_fee_revenue(),
+        result.avg_normalizer_fee_revenue()
+    );
+    println!(
+        "  Inventory:   {:+.2} avg PnL (marked to terminal fair price)",
+        result.avg_inventory_pnl()
+    );
+    println!("  Normalizer:  {:.2} avg edge", result.avg_normalizer_edge());
+    if let Some((submission, normalizer)) = result.edge_shares() {
+        println!(
+            "  Edge share:  {:.1}% of all venues' edge (normalizer {:.1}%)",
+            100.0 * submission,
+            100.0 * normalizer
+        );
+    }
+    print_extra_venues(result);
+    print_execution(result);
+    let abandoned: u64 = result.results.iter().map(|r| u64::from(r.abandoned_orders)).sum();
+    if abandoned > 0 {
+        println!(
+            "  Abandoned:   {} retail order(s) over their limits ({:.1} avg)",
+            abandoned,
+            abandoned as f64 / result.n_sims() as f64
+        );
+    }
+    let partial: u64 = result.results.iter().map(|r| u64::from(r.partial_fills)).sum();
+    if partial > 0 {
+        println!(
+            "  Partial:     {} retail order(s) partly filled within their limits or trade caps \
+             ({:.1} avg)",
+            partial,
+            partial as f64 / result.n_sims() as f64
+        );
+    }
+    let dust: u64 = result.results.iter().map(|r| u64::from(r.dust_orders)).sum();
+    if dust > 0 {
+        let notional: f64 = result.results.iter().map(|r| r.dust_notional).sum();
+        println!(
+            "  Dust:        {} retail order(s) at or below the minimum trade size, {:.2} Y \
+             notional ({:.2} avg)",
+            dust,
+            notional,
+            notional / result.n_sims() as f64
+        );
+    }
+    let guarded: u64 = result.results.iter().map(|r| u64::from(r.guarded_orders)).sum();
+    if guarded > 0 {
+        println!(
+            "  Guarded:     {} retail order(s) left unfilled at quotes beyond the price guard \
+             ({:.1} avg)",
+            guarded,
+            guarded as f64 / result.n_sims() as f64
+        );
+    }
+    let down: u64 = result.results.iter().map(|r| u64::from(r.submission_down_steps)).sum();
+    if down > 0 {
+        println!(
+            "  Venue down:  submission quoted nothing in {} scored step(s) ({:.1} avg)",
+            down,
+            down as f64 / result.n_sims() as f64
+        );
+    }
+    let edges: Vec<f64> = result.results.iter().map(|r| r.submission_edge).collect();
+    let relative: Vec<f64> = result.results.iter().map(|r| r.relative_edge()).collect();
+    println!(
+        "  Relative:    {:+.2} ± {:.2} vs normalizer (avg edge ± {:.2})",
+        result.avg_relative_edge(),
+        std_error(&relative),
+        std_error(&edges)
+    );
+    println!(
+        "  Dispersion:  std {:.2}, p5 {:.2}, max drawdown {:.2} avg / {:.2} worst",
+        result.edge_std_dev(),
+        result.edge_percentile(0.05),
+        result.avg_max_drawdown(),
+        result.max_drawdown()
+    );
+    let worst: Vec<String> = result
+        .worst_seeds(WORST_SEEDS_SHOWN)
+        .iter()
+        .map(|(seed, edge)| format!("{} ({:.2})", seed, edge))
+        .collect();
+    println!("  Worst seeds: {}", worst.join(", "));
+    let risk_score = risk.score_batch(result);
+    println!(
+        "  Risk score:  {:.2} (avg - {} x CVaR{:.0}% {:.2})",
+        risk_score.score,
+        risk.lambda,
+        risk.tail_fraction * 100.0,
+        risk_score.cvar
+    );
+    println!("  Trade hash:  {:016x}", result.trade_hash());
+    println!("  Checksum:    {:016x}", result.checksum());
+    let compute = result.submission_compute();
+    if compute.calls > 0 {
+        println!(
+            "  Quote CU:    min {}, avg {:.0}, max {}",
+            compute.min_cu,
+            compute.avg_cu(),
+            compute.max_cu
+        );
+    }
+    let compute = result.submission_after_swap_compute();
+    if compute.calls > 0 {
+        println!(
+            "  Swap CU:     min {}, avg {:.0}, max {}",
+            compute.min_cu,
+            compute.avg_cu(),
+            compute.max_cu
+        );
+    }
     println!("========================================");
 
+    print_shape_summary(result, shape_penalty);
+    print_depletion_summary(result);
+    print_after_swap_summary(result);
+    print_quote_divergence_summary(result);
+    print_timing(result);
+
     if let Some(stats) = prop_amm_sim::search_stats::snapshot_if_enabled() {
         let arb_calls = stats.arb_golden_calls.max(1);
         let router_calls = stats.router_calls.max(1);
@@ -51,6 +183,12 @@ pub fn print_results(result: &BatchResult, timings: RunTimings) {
             stats.arb_bracket_evals,
             stats.arb_bracket_evals as f64 / stats.arb_bracket_calls.max(1) as f64,
         );
+        if stats.arb_cp_fit_hits + stats.arb_cp_fit_misses > 0 {
+            println!(
+                "  Arb CP fit:  hits={} misses={}",
+                stats.arb_cp_fit_hits, stats.arb_cp_fit_misses,
+            );
+        }
         println!(
             "  Router:     calls={} iters={} (avg {:.2}/call) evals={} (avg {:.2}/call) early_stop_rel_gap={}",
             stats.router_calls,
@@ -60,5 +198,294 @@ pub fn print_results(result: &BatchResult, timings: RunTimings) {
             stats.router_evals as f64 / router_calls as f64,
             stats.router_early_stop_rel_gap,
         );
+        println!(
+            "  Router split: all-submission={} all-baselines={} interior={}",
+            stats.router_split_all_submission,
+            stats.router_split_all_baselines,
+            stats.router_split_interior,
+        );
+        if stats.router_marginal_fallbacks > 0 {
+            println!(
+                "  Router marginal: fallbacks to golden section={}",
+                stats.router_marginal_fallbacks,
+            );
+        }
+        if stats.router_execution_fallbacks > 0 {
+            println!(
+                "  Router execution: parts re-routed after a short fill={}",
+                stats.router_execution_fallbacks,
+            );
+        }
+        if stats.router_warm_start_misses > 0 {
+            println!(
+                "  Router warm start: searches widened to every share={}",
+                stats.router_warm_start_misses,
+            );
+        }
+        if stats.quote_cache_hits > 0 {
+            println!("  Quote cache: hits={}", stats.quote_cache_hits);
+        }
+        if stats.arb_venue_down + stats.router_venue_down > 0 {
+            println!(
+                "  Venue down:  arb searches skipped={} router searches skipped={}",
+                stats.arb_venue_down, stats.router_venue_down,
+            );
+        }
+    }
+}
+
+pub fn print_edge_delta(previous_avg: Option<f64>, current_avg: f64) {
+    match previous_avg {
+        Some(previous) => println!(
+            "  Avg edge delta vs previous run: {:+.2} ({:.2} -> {:.2})",
+            current_avg - previous,
+            previous,
+            current_avg
+        ),
+        None => println!("  Avg edge delta vs previous run: n/a (first run)"),
+    }
+}
+
+/// Standard error of the mean.
+fn std_error(values: &[f64]) -> f64 {
+    let n = values.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    (var / n as f64).sqrt()
+}
+
+/// Average per-simulation edge and trade count from each counterparty class that traded, with the
+/// submission's share of that class's trades across all venues.
+fn print_edge_breakdown(result: &BatchResult) {
+    let n = result.n_sims().max(1) as f64;
+    let breakdown = result.edge_by_counterparty();
+    let volume = result.volume_by_counterparty();
+    let mut baselines = vec![result.normalizer_edge_by_counterparty()];
+    baselines.extend(result.extra_venue_edge_by_counterparty());
+    let mut baseline_volumes = vec![result.normalizer_volume_by_counterparty()];
+    baseline_volumes.extend(result.extra_venue_volume_by_counterparty());
+    for counterparty in Counterparty::ALL {
+        let trades = breakdown.trades(counterparty);
+        let all_trades = trades + baselines.iter().map(|b| b.trades(counterparty)).sum::<u64>();
+        let volume_y = volume.y(counterparty);
+        let all_volume_y =
+            volume_y + baseline_volumes.iter().map(|v| v.y(counterparty)).sum::<f64>();
+        if all_trades == 0 {
+            continue;
+        }
+        println!(
+            "    {:<12} {:+.2} ({:.1} trades/sim, {:.1}% share; {:.1} Y/sim, {:.1}% of volume)",
+            format!("{}:", counterparty.label()),
+            breakdown.get(counterparty) / n,
+            trades as f64 / n,
+            100.0 * trades as f64 / all_trades.max(1) as f64,
+            volume_y / n,
+            100.0 * volume_y / all_volume_y.max(1e-12)
+        );
+    }
+}
+
+/// Average edge and retail volume share of each extra baseline venue, when there are any.
+fn print_extra_venues(result: &BatchResult) {
+    let edges = result.extra_venue_edge_by_counterparty();
+    if edges.is_empty() {
+        return;
+    }
+    let n = result.n_sims().max(1) as f64;
+    let volumes = result.extra_venue_volume_by_counterparty();
+    let total_retail_y = result.volume_by_counterparty().retail_y()
+        + result.normalizer_volume_by_counterparty().retail_y()
+        + volumes.iter().map(VolumeBreakdown::retail_y).sum::<f64>();
+    let venues: Vec<String> = edges
+        .iter()
+        .zip(&volumes)
+        .enumerate()
+        .map(|(i, (edge, volume))| {
+            format!(
+                "#{} {:.2} ({:.1}% retail)",
+                i + 1,
+                edge.total() / n,
+                100.0 * volume.retail_y() / total_retail_y.max(1e-12)
+            )
+        })
+        .collect();
+    println!("  Extra venues: {}", venues.join(", "));
+}
+
+/// Retail fill costs on each venue that filled any, in bps against the fair price and against
+/// the venue's spot before the order.
+fn print_execution(result: &BatchResult) {
+    let venues = result.execution_by_venue();
+    if venues.iter().all(|v| v.trades() == 0) {
+        return;
+    }
+    println!("  Execution:   retail cost in bps vs fair / vs pre-trade spot");
+    for (venue, stats) in venues.iter().enumerate() {
+        if stats.trades() == 0 {
+            continue;
+        }
+        let name = match venue {
+            0 => "submission".to_string(),
+            1 => "normalizer".to_string(),
+            extra => format!("venue #{}", extra - 1),
+        };
+        println!(
+            "    {:<12} mean {:.1} / {:.1}, p95 {} / {}, max {:.1} / {:.1} ({} fills)",
+            format!("{}:", name),
+            stats.slippage.mean(),
+            stats.impact.mean(),
+            bucket_bound(&stats.slippage, 0.95),
+            bucket_bound(&stats.impact, 0.95),
+            stats.slippage.max,
+            stats.impact.max,
+            stats.trades()
+        );
+    }
+}
+
+/// e.g. "<=50", or ">1000" past the last histogram bucket.
+fn bucket_bound(costs: &CostDistribution, q: f64) -> String {
+    let bound = costs.percentile(q);
+    if bound.is_finite() {
+        format!("<={}", bound)
+    } else {
+        format!(">{}", COST_BUCKETS_BPS[COST_BUCKETS_BPS.len() - 1])
+    }
+}
+
+fn print_depletion_summary(result: &BatchResult) {
+    let depleted: Vec<_> = result
+        .results
+        .iter()
+        .filter_map(|r| r.depletion.map(|d| (r.seed, d)))
+        .collect();
+    if depleted.is_empty() {
+        return;
+    }
+    println!("\nDepleted venues: {} simulation(s) ended early", depleted.len());
+    for (seed, depletion) in depleted.iter().take(5) {
+        println!("  seed {}: {}", seed, depletion);
+    }
+}
+
+fn print_timing(result: &BatchResult) {
+    let timing = result.timing();
+    if timing.is_empty() {
+        return;
+    }
+    let secs = |ns: u64| ns as f64 / 1e9;
+    let program_ns = timing.submission_program_ns + timing.baseline_program_ns;
+    println!("\nComponent time (summed over simulations and workers):");
+    println!("  Arbitrage:   {:>8.2}s", secs(timing.arb_ns));
+    println!("  Routing:     {:>8.2}s", secs(timing.router_ns));
+    println!(
+        "  Programs:    {:>8.2}s (submission {:.2}s, baselines {:.2}s)",
+        secs(program_ns),
+        secs(timing.submission_program_ns),
+        secs(timing.baseline_program_ns)
+    );
+    // Searches include the program calls they make. Probes outside them (LVR, liquidity)
+    // are program calls too, so this slightly understates the loops.
+    let searches_ns = timing.arb_ns + timing.router_ns;
+    println!(
+        "  Search loops:{:>8.2}s (arbitrage and routing less all program calls)",
+        secs(searches_ns.saturating_sub(program_ns))
+    );
+}
+
+fn print_after_swap_summary(result: &BatchResult) {
+    let failed: Vec<_> = result.results.iter().filter(|r| r.after_swap_failures > 0).collect();
+    if failed.is_empty() {
+        return;
+    }
+    let failures: u32 = failed.iter().map(|r| r.after_swap_failures).sum();
+    println!(
+        "\nafter_swap failures: {} call(s) across {} simulation(s)",
+        failures,
+        failed.len()
+    );
+    for r in failed.iter().take(5) {
+        println!("  seed {}: {} failed call(s)", r.seed, r.after_swap_failures);
+    }
+}
+
+fn print_quote_divergence_summary(result: &BatchResult) {
+    print_quote_divergence(result, "Arbitrage", "the arbitrageur", |r| r.arb_quote_divergence);
+    print_quote_divergence(result, "Retail", "retail", |r| r.retail_quote_divergence);
+}
+
+fn print_quote_divergence(
+    result: &BatchResult,
+    label: &str,
+    trader: &str,
+    divergence: impl Fn(&SimResult) -> QuoteDivergence,
+) {
+    let diverged: Vec<_> = result.results.iter().filter(|r| divergence(r).diverged > 0).collect();
+    if diverged.is_empty() {
+        return;
+    }
+    let trades: u32 = diverged.iter().map(|r| divergence(r).diverged).sum();
+    let checked: u32 = result.results.iter().map(|r| divergence(r).checked).sum();
+    println!(
+        "\n{} quote divergence: {} of {} trade(s) executed off their quote, across {} \
+         simulation(s)",
+        label,
+        trades,
+        checked,
+        diverged.len()
+    );
+    for r in diverged.iter().take(5) {
+        let d = divergence(r);
+        println!(
+            "  seed {}: {} trade(s), max {:.4}% off, net {:+.4} Y to {}",
+            r.seed,
+            d.diverged,
+            d.max_rel * 100.0,
+            d.net_y,
+            trader
+        );
+    }
+}
+
+fn print_shape_summary(result: &BatchResult, penalty: &ShapePenaltyPolicy) {
+    let flagged: Vec<_> = result.results.iter().filter(|r| !r.shape.is_clean()).collect();
+    if flagged.is_empty() {
+        return;
+    }
+    let warnings: u32 = flagged.iter().map(|r| r.shape.warnings).sum();
+    let violations: u32 = flagged.iter().map(|r| r.shape.violations).sum();
+    println!(
+        "\nShape checks: {} warning(s), {} violation(s) across {} simulation(s)",
+        warnings,
+        violations,
+        flagged.len()
+    );
+    let score = penalty.score_batch(result);
+    println!(
+        "  Penalized:   {:.2} avg score ({:.2} total penalty)",
+        score.avg_score, score.total_penalty
+    );
+    if score.is_disqualified() {
+        let seeds: Vec<String> = score
+            .disqualified_seeds
+            .iter()
+            .take(WORST_SEEDS_SHOWN)
+            .map(|seed| seed.to_string())
+            .collect();
+        let more = score.disqualified_seeds.len().saturating_sub(WORST_SEEDS_SHOWN);
+        println!(
+            "  Disqualified: {} simulation(s), seeds {}{}",
+            score.disqualified_seeds.len(),
+            seeds.join(", "),
+            if more > 0 { format!(" and {} more", more) } else { String::new() }
+        );
+    }
+    for r in flagged.iter().take(5) {
+        if let Some(issue) = r.shape.examples.first() {
+            println!("  seed {}: {} ({})", r.seed, issue, issue.context);
+        }
     }
 }
@@ -0,0 +1,157 @@
+//! Single-seed reproducer bundles: a directory holding a copy of the submission source, the
+//! exact config of one flagged simulation, and the RNG stream seeds its price path and order
+//! flow are drawn from. Written when a run flags a problem; replayed by `prop-amm repro`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use prop_amm_shared::config::SimulationConfig;
+use prop_amm_shared::result::SimResult;
+use prop_amm_sim::engine::RngStreams;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::{self, Backend};
+
+pub const DEFAULT_REPRO_DIR: &str = ".build/repro";
+pub const REPRO_VERSION: u32 = 1;
+const BUNDLE_FILE: &str = "repro.json";
+pub const SOURCE_FILE: &str = "submission.rs";
+const README_FILE: &str = "README.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// The simulation returned an error or panicked (shape violations panic).
+    SimulationFailed,
+    /// The simulation finished with shape warnings or violations recorded.
+    ShapeIssues,
+    /// Native and BPF builds of the submission earned different edge.
+    Parity,
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProblemKind::SimulationFailed => "simulation failed",
+            ProblemKind::ShapeIssues => "shape issues",
+            ProblemKind::Parity => "native/BPF parity failure",
+        })
+    }
+}
+
+/// What the flagged simulation produced, when it ran to completion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpectedOutcome {
+    pub submission_edge: f64,
+    /// Hex `SimResult::trade_hash`.
+    pub trade_hash: String,
+    pub shape_warnings: u32,
+    pub shape_violations: u32,
+}
+
+impl From<&SimResult> for ExpectedOutcome {
+    fn from(result: &SimResult) -> Self {
+        Self {
+            submission_edge: result.submission_edge,
+            trade_hash: format!("{:016x}", result.trade_hash),
+            shape_warnings: result.shape.warnings,
+            shape_violations: result.shape.violations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproBundle {
+    pub version: u32,
+    pub cli_version: String,
+    pub problem: ProblemKind,
+    /// What the run reported, e.g. the panic message or the first shape issue.
+    pub detail: String,
+    pub backend: Backend,
+    /// Hex FNV-1a 64 of the submission source (bundled as `submission.rs`).
+    pub source_hash: String,
+    /// Hex FNV-1a 64 of the artifact that ran, when known.
+    pub artifact_hash: Option<String>,
+    pub config: SimulationConfig,
+    pub rng_streams: RngStreams,
+    pub expected: Option<ExpectedOutcome>,
+}
+
+impl ReproBundle {
+    pub fn new(
+        problem: ProblemKind,
+        detail: String,
+        backend: Backend,
+        source: &Path,
+        artifact: Option<&Path>,
+        config: &SimulationConfig,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            version: REPRO_VERSION,
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            problem,
+            detail,
+            backend,
+            source_hash: manifest::hash_file(source)?,
+            artifact_hash: artifact.map(manifest::hash_file).transpose()?,
+            config: config.clone(),
+            rng_streams: RngStreams::for_config(config),
+            expected: None,
+        })
+    }
+
+    /// Write the bundle to `<root>/seed-<seed>`, replacing an earlier bundle for the same
+    /// seed, and return that directory.
+    pub fn write(&self, root: &Path, source: &Path) -> anyhow::Result<PathBuf> {
+        let dir = root.join(format!("seed-{}", self.config.seed));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+        std::fs::copy(source, dir.join(SOURCE_FILE))
+            .map_err(|e| anyhow::anyhow!("Failed to copy {}: {}", source.display(), e))?;
+        std::fs::write(dir.join(BUNDLE_FILE), serde_json::to_string_pretty(self)?)?;
+        let readme = format!(
+            "{} (seed {}): {}\n\nRe-run with:\n  {}\n",
+            self.problem,
+            self.config.seed,
+            self.detail,
+            command(&dir)
+        );
+        std::fs::write(dir.join(README_FILE), readme)?;
+        Ok(dir)
+    }
+
+    pub fn read(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(BUNDLE_FILE);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let bundle: Self = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid reproducer {}: {}", path.display(), e))?;
+        if bundle.version != REPRO_VERSION {
+            anyhow::bail!(
+                "Unsupported reproducer version {} (expected {})",
+                bundle.version,
+                REPRO_VERSION
+            );
+        }
+        Ok(bundle)
+    }
+}
+
+pub fn command(dir: &Path) -> String {
+    format!("prop-amm repro {}", dir.display())
+}
+
+/// Write a bundle and log where it went. Failures are logged rather than returned so they
+/// never mask the problem being reported.
+pub fn emit(bundle: &ReproBundle, root: &Path, source: &Path) {
+    match bundle.write(root, source) {
+        Ok(dir) => tracing::warn!(
+            "{} for seed {}; reproducer written to {}. Re-run with: {}",
+            bundle.problem,
+            bundle.config.seed,
+            dir.display(),
+            command(&dir)
+        ),
+        Err(err) => tracing::warn!("Failed to write reproducer: {err:#}"),
+    }
+}
@@ -5,4 +5,4 @@ pub mod vm;
 
 pub use loader::{BpfProgram, ExecutorError};
 pub use native::{AfterSwapFn, NativeExecutor, SwapFn};
-pub use vm::BpfExecutor;
+pub use vm::{BpfExecutor, Metered, COMPUTE_BUDGET};
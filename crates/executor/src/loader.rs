@@ -88,6 +88,12 @@ impl BpfProgram {
             }
         }
 
+        tracing::debug!(
+            elf_bytes = elf_bytes.len(),
+            jit_available,
+            "loaded BPF program"
+        );
+
         Ok(Self {
             executable: Arc::new(executable),
             loader,
@@ -1,4 +1,6 @@
-use prop_amm_shared::instruction::{encode_after_swap, encode_swap_instruction, STORAGE_SIZE};
+use prop_amm_shared::instruction::{
+    encode_after_swap_tagged, encode_swap_instruction, AFTER_SWAP_TAG, STORAGE_SIZE,
+};
 
 /// A swap function signature: takes instruction data (with storage appended), returns output amount.
 pub type SwapFn = fn(&[u8]) -> u64;
@@ -28,6 +30,7 @@ impl NativeExecutor {
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn execute_after_swap(
         &self,
         side: u8,
@@ -37,9 +40,45 @@ impl NativeExecutor {
         ry: u64,
         step: u64,
         storage: &mut [u8],
+    ) {
+        self.execute_after_swap_tagged(
+            AFTER_SWAP_TAG,
+            side,
+            input_amount,
+            output_amount,
+            rx,
+            ry,
+            step,
+            storage,
+        );
+    }
+
+    /// [`Self::execute_after_swap`] with another tag in the after_swap layout, e.g.
+    /// `LIQUIDITY_EVENT_TAG`.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_after_swap_tagged(
+        &self,
+        tag: u8,
+        side: u8,
+        input_amount: u64,
+        output_amount: u64,
+        rx: u64,
+        ry: u64,
+        step: u64,
+        storage: &mut [u8],
     ) {
         if let Some(after_swap) = self.after_swap_fn {
-            let data = encode_after_swap(side, input_amount, output_amount, rx, ry, step, storage);
+            let data = encode_after_swap_tagged(
+                tag,
+                side,
+                input_amount,
+                output_amount,
+                rx,
+                ry,
+                step,
+                storage,
+            );
             let copy_len = storage.len().min(STORAGE_SIZE);
             after_swap(&data, &mut storage[..copy_len]);
         }
@@ -96,17 +96,27 @@ declare_builtin_function!(
 );
 
 declare_builtin_function!(
-    /// No-op log syscall
+    /// Log syscall: forwards the program's message at trace level, otherwise a no-op.
+    /// arg1 = vm address of message, arg2 = length
     SyscallLog,
     fn rust(
         _context_object: &mut SyscallContext,
-        _arg1: u64,
-        _arg2: u64,
+        addr: u64,
+        len: u64,
         _arg3: u64,
         _arg4: u64,
         _arg5: u64,
-        _memory_mapping: &mut MemoryMapping,
+        memory_mapping: &mut MemoryMapping,
     ) -> Result<u64, Box<dyn std::error::Error>> {
+        if tracing::enabled!(tracing::Level::TRACE) {
+            let host_addr: Result<u64, EbpfError> =
+                memory_mapping.map(AccessType::Load, addr, len).into();
+            if let Ok(host_addr) = host_addr {
+                let slice =
+                    unsafe { std::slice::from_raw_parts(host_addr as *const u8, len as usize) };
+                tracing::trace!(message = %String::from_utf8_lossy(slice), "program log");
+            }
+        }
         Ok(0)
     }
 );
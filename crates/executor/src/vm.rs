@@ -2,12 +2,14 @@ use solana_rbpf::{
     aligned_memory::AlignedMemory,
     ebpf,
     memory_region::{MemoryMapping, MemoryRegion},
-    vm::EbpfVm,
+    vm::{ContextObject, EbpfVm},
 };
 
 use crate::loader::{BpfProgram, ExecutorError};
 use crate::syscalls::SyscallContext;
-use prop_amm_shared::instruction::{AFTER_SWAP_SIZE, STORAGE_SIZE, SWAP_INSTRUCTION_SIZE};
+use prop_amm_shared::instruction::{
+    AFTER_SWAP_SIZE, AFTER_SWAP_TAG, STORAGE_SIZE, SWAP_INSTRUCTION_SIZE,
+};
 
 /// Solana input buffer layout for 0 accounts:
 /// [0..8]   u64 num_accounts = 0
@@ -16,12 +18,23 @@ use prop_amm_shared::instruction::{AFTER_SWAP_SIZE, STORAGE_SIZE, SWAP_INSTRUCTI
 /// [..]     program_id (32 bytes, zeros)
 const INPUT_BUF_SIZE: usize = 8 + 8 + AFTER_SWAP_SIZE + 32; // 1106
 
+/// Compute units one program call may consume.
+pub const COMPUTE_BUDGET: u64 = 100_000;
+
+/// A program call's result and the compute units it consumed (0 when the meter is disabled).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metered<T> {
+    pub value: T,
+    pub compute_units: u64,
+}
+
 pub struct BpfExecutor {
     program: BpfProgram,
     input_buf: Vec<u8>,
     stack: AlignedMemory<{ ebpf::HOST_ALIGN }>,
     heap: AlignedMemory<{ ebpf::HOST_ALIGN }>,
     context: SyscallContext,
+    last_compute_units: u64,
 }
 
 impl BpfExecutor {
@@ -34,7 +47,8 @@ impl BpfExecutor {
             heap: AlignedMemory::zero_filled(32 * 1024),
             program,
             input_buf,
-            context: SyscallContext::new(100_000),
+            context: SyscallContext::new(COMPUTE_BUDGET),
+            last_compute_units: 0,
         }
     }
 
@@ -43,7 +57,8 @@ impl BpfExecutor {
         self.input_buf[8..16].copy_from_slice(&(instr_data_len as u64).to_le_bytes());
 
         // Reset context flags without reallocating storage Vec.
-        self.context.reset(100_000);
+        self.context.reset(COMPUTE_BUDGET);
+        let budget = self.context.get_remaining();
 
         let executable = self.program.executable();
         let loader = self.program.loader();
@@ -70,7 +85,8 @@ impl BpfExecutor {
         );
 
         let use_interpreter = !self.program.jit_available();
-        let (_instruction_count, result) = vm.execute_program(executable, use_interpreter);
+        let (_, result) = vm.execute_program(executable, use_interpreter);
+        self.last_compute_units = budget - self.context.get_remaining();
 
         let result: Result<u64, _> = result.into();
         result.map_err(|e| ExecutorError::Execution(e.to_string()))?;
@@ -78,6 +94,13 @@ impl BpfExecutor {
         Ok(())
     }
 
+    /// Compute units consumed by the most recent call (0 when the meter is disabled), failed
+    /// or not; successful calls also return them.
+    #[inline]
+    pub fn last_compute_units(&self) -> u64 {
+        self.last_compute_units
+    }
+
     pub fn execute(
         &mut self,
         side: u8,
@@ -85,7 +108,7 @@ impl BpfExecutor {
         rx: u64,
         ry: u64,
         storage: &[u8],
-    ) -> Result<u64, ExecutorError> {
+    ) -> Result<Metered<u64>, ExecutorError> {
         self.input_buf.fill(0);
 
         // Write instruction data: [side(1)][amount(8)][rx(8)][ry(8)][storage(1024)]
@@ -105,9 +128,13 @@ impl BpfExecutor {
             return Err(ExecutorError::NoReturnData);
         }
 
-        Ok(u64::from_le_bytes(self.context.return_data))
+        Ok(Metered {
+            value: u64::from_le_bytes(self.context.return_data),
+            compute_units: self.last_compute_units,
+        })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn execute_after_swap(
         &mut self,
         side: u8,
@@ -117,12 +144,38 @@ impl BpfExecutor {
         ry: u64,
         step: u64,
         storage: &mut [u8],
-    ) -> Result<(), ExecutorError> {
+    ) -> Result<Metered<()>, ExecutorError> {
+        self.execute_after_swap_tagged(
+            AFTER_SWAP_TAG,
+            side,
+            input_amount,
+            output_amount,
+            rx,
+            ry,
+            step,
+            storage,
+        )
+    }
+
+    /// [`Self::execute_after_swap`] with another tag in the after_swap layout, e.g.
+    /// `LIQUIDITY_EVENT_TAG`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_after_swap_tagged(
+        &mut self,
+        tag: u8,
+        side: u8,
+        input_amount: u64,
+        output_amount: u64,
+        rx: u64,
+        ry: u64,
+        step: u64,
+        storage: &mut [u8],
+    ) -> Result<Metered<()>, ExecutorError> {
         self.input_buf.fill(0);
 
         // Write after_swap instruction data:
-        // [tag=2(1)][side(1)][input(8)][output(8)][rx(8)][ry(8)][step(8)][storage(1024)]
-        self.input_buf[16] = 2; // tag
+        // [tag(1)][side(1)][input(8)][output(8)][rx(8)][ry(8)][step(8)][storage(1024)]
+        self.input_buf[16] = tag;
         self.input_buf[17] = side;
         self.input_buf[18..26].copy_from_slice(&input_amount.to_le_bytes());
         self.input_buf[26..34].copy_from_slice(&output_amount.to_le_bytes());
@@ -142,6 +195,9 @@ impl BpfExecutor {
             storage[..out_len].copy_from_slice(&self.context.storage_data[..out_len]);
         }
 
-        Ok(())
+        Ok(Metered {
+            value: (),
+            compute_units: self.last_compute_units,
+        })
     }
 }
@@ -1,13 +1,20 @@
+use std::fmt;
+
 use rand::Rng;
 use rand::SeedableRng;
 use rand_pcg::Pcg64;
 
+use crate::nano::{TokenDecimals, MAX_TOKEN_DECIMALS};
+use crate::rng::{substream_seed, Component};
+
 // Baseline simulation parameters
 pub const BASELINE_STEPS: u32 = 10_000;
 pub const BASELINE_SIMS: u32 = 1_000;
 pub const INITIAL_PRICE: f64 = 100.0;
 pub const INITIAL_X: f64 = 100.0;
 pub const INITIAL_Y: f64 = 10_000.0;
+/// Starting value of the submission pool in Y, both reserves together.
+pub const INITIAL_LIQUIDITY: f64 = 2.0 * INITIAL_Y;
 pub const GBM_MU: f64 = 0.0;
 pub const GBM_SIGMA: f64 = 0.000945; // baseline point estimate
 pub const GBM_DT: f64 = 1.0;
@@ -16,33 +23,630 @@ pub const RETAIL_MEAN_SIZE: f64 = 20.0; // midpoint of [12, 28]
 pub const RETAIL_SIZE_SIGMA: f64 = 1.2;
 pub const RETAIL_BUY_PROB: f64 = 0.5;
 pub const MIN_ARB_PROFIT: f64 = 0.01; // 1 cent in quote token (Y)
+pub const TRADE_HASH_CHECKPOINT_STEPS: u32 = 1_000;
+pub const MAX_FEE_BPS: u16 = 10_000;
+pub const MAX_EXTRA_VENUES: usize = 254;
+pub const DEPLETION_RESERVE_FRAC: f64 = 1e-3;
+pub const MIN_TRADE_SIZE: f64 = 0.001; // in the input token
+
+/// One class of retail trader. Rates and sizes are multipliers on the simulation's
+/// `retail_arrival_rate` and `retail_mean_size`, so hyperparameter variance still scales
+/// the whole mix; arrival scales summing to 1 act as mixture weights that keep the total
+/// arrival rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetailProfile {
+    pub arrival_scale: f64,
+    pub size_scale: f64,
+    pub size_sigma: f64,
+    /// The class's own buy probability, in place of `retail_buy_prob` and any flow regime.
+    /// `None` follows the simulation's.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub buy_prob: Option<f64>,
+}
+
+impl RetailProfile {
+    /// Many small orders: 1.5x the arrivals at 0.4x the size.
+    pub const SMALL_FREQUENT: Self = Self {
+        arrival_scale: 1.5,
+        size_scale: 0.4,
+        size_sigma: 0.8,
+        buy_prob: None,
+    };
+    /// Occasional large orders: 0.1x the arrivals at 4x the size.
+    pub const LARGE_INFREQUENT: Self = Self {
+        arrival_scale: 0.1,
+        size_scale: 4.0,
+        size_sigma: 1.0,
+        buy_prob: None,
+    };
+
+    /// Small frequent plus large infrequent flow, with the same expected notional per step
+    /// as the single baseline trader.
+    pub fn two_tier() -> Vec<Self> {
+        vec![Self::SMALL_FREQUENT, Self::LARGE_INFREQUENT]
+    }
+}
+
+/// A retail trader class resolved against the simulation's sampled retail parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetailClass {
+    pub arrival_rate: f64,
+    pub mean_size: f64,
+    pub size_sigma: f64,
+    /// `None` follows the simulation's buy probability.
+    pub buy_prob: Option<f64>,
+}
+
+/// A window of skewed retail flow: for steps in `start_step..end_step`, orders are buys
+/// with probability `buy_prob` instead of `retail_buy_prob`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowRegime {
+    pub start_step: u32,
+    pub end_step: u32,
+    pub buy_prob: f64,
+}
+
+/// A window of different retail activity: for steps in `start_step..end_step`, every
+/// trader's arrival rate is multiplied by `multiplier` (e.g. 3 for a burst, 0.2 for a lull).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrivalWindow {
+    pub start_step: u32,
+    pub end_step: u32,
+    pub multiplier: f64,
+}
+
+/// A periodic swing in retail activity: arrival rates are multiplied by
+/// `1 + amplitude * sin(2 * pi * step / period_steps)`, a daily cycle of busy and quiet hours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrivalCycle {
+    pub period_steps: u32,
+    pub amplitude: f64,
+}
+
+/// Trend-following retail traders, mixed in with the noise traders. They arrive at
+/// `arrival_scale` times `retail_arrival_rate` with the usual order sizes, and buy with
+/// probability `0.5 + weight / 2` once the fair price has risen over the last
+/// `lookback_steps` steps (sell with that probability once it has fallen).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MomentumCohort {
+    pub arrival_scale: f64,
+    pub lookback_steps: u32,
+    pub weight: f64,
+}
+
+/// The arbitrageur's finite capital: it starts holding `x` and `y`, every trade moves them,
+/// and no trade spends more than it holds. Every `rebalance_steps` steps (0: never) it is
+/// restocked to the starting amounts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArbInventory {
+    pub x: f64,
+    pub y: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rebalance_steps: u32,
+}
+
+/// An additional constant-product pool competing for retail flow alongside the normalizer,
+/// e.g. a 10 bps pool at twice the submission's depth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BaselineVenue {
+    pub fee_bps: u16,
+    /// Reserves as a multiple of `initial_x` / `initial_y`.
+    pub liquidity_mult: f64,
+}
+
+/// An LP deposit or withdrawal: at the start of scored step `step`, before arbitrage, every
+/// pool's reserves are multiplied by `scale` (above 1 adds liquidity, below 1 removes it).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LiquidityEvent {
+    pub step: u32,
+    pub scale: f64,
+}
+
+/// A discrete jump in the fair price: at scored step `step`, after that step's diffusion
+/// move, the price is multiplied by `1 + size` (e.g. -0.05 gaps down 5%).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceGap {
+    pub step: u32,
+    pub size: f64,
+}
+
+/// A retail order a [`Scenario`] places at scored step `step`; `size` is in Y, as for
+/// generated orders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptedOrder {
+    pub step: u32,
+    pub is_buy: bool,
+    pub size: f64,
+    /// This order's own slippage limit, in place of `retail_max_slippage_bps`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_slippage_bps: Option<f64>,
+    /// Smallest total output accepted, in X for buys and Y for sells; routed for less, the
+    /// order is abandoned (or partially filled, under `retail_partial_fills`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_output: Option<f64>,
+}
+
+/// A fair price a [`Scenario`] pins at scored step `step`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptedPrice {
+    pub step: u32,
+    pub price: f64,
+}
+
+/// A hand-written market replayed exactly, e.g. a whale selling into a crash. Steps count
+/// from the start of each session, like every step-indexed option.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    /// Retail orders in place of the generated flow; orders on one step arrive in the
+    /// listed order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub orders: Vec<ScriptedOrder>,
+    /// Price points, by increasing step. When any are listed the fair price follows them in
+    /// place of the price process and its gaps: log-linear between points, flat before the
+    /// first and after the last. Warm-up steps sit at the session's opening price.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prices: Vec<ScriptedPrice>,
+    /// Keep generating retail flow alongside the scripted orders instead of replacing it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub background_retail: bool,
+}
+
+impl Scenario {
+    /// Scripted fair price at scored step `step`; `None` if no prices are scripted.
+    pub fn price_at(&self, step: u32) -> Option<f64> {
+        let next = self.prices.partition_point(|p| p.step <= step);
+        let before = next.checked_sub(1).map(|i| self.prices[i]);
+        match (before, self.prices.get(next)) {
+            (Some(a), Some(b)) => {
+                let t = (step - a.step) as f64 / (b.step - a.step) as f64;
+                Some(a.price * (b.price / a.price).powf(t))
+            }
+            (Some(p), None) | (None, Some(&p)) => Some(p.price),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The fair price process the engine simulates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PriceProcessKind {
+    /// Geometric Brownian motion with `gbm_mu`, `gbm_sigma` and `gbm_dt`, switching
+    /// volatility when `volatility_regimes` is set.
+    #[default]
+    Gbm,
+}
+
+/// Markov-switching volatility: the fair price diffuses with `calm_sigma` or
+/// `volatile_sigma`, and before each move a calm market turns volatile with probability
+/// `calm_to_volatile` and a volatile one calms with probability `volatile_to_calm`. Every
+/// session opens calm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VolatilityRegimes {
+    pub calm_sigma: f64,
+    pub volatile_sigma: f64,
+    pub calm_to_volatile: f64,
+    pub volatile_to_calm: f64,
+}
+
+/// What `SimResult::submission_edge` (and the normalizer's edge) measures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeMode {
+    /// Each trade valued at the fair price when it executes.
+    #[default]
+    Flow,
+    /// Flow edge plus the revaluation of the inventory the pool ends up holding: its change
+    /// in reserve value over the scored steps, marked at the final fair price, less LP
+    /// deposits. Strategies can no longer shed inventory risk the flow metric never sees.
+    InventoryAdjusted,
+}
+
+/// How the retail orders arriving in one step reach the venues.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExecutionMode {
+    /// Each order is routed on its own, in arrival order, against the pools the previous
+    /// orders left behind.
+    #[default]
+    Sequential,
+    /// The step's orders are netted into one aggregate order, routed once. Informed orders
+    /// are netted separately, so their fills stay attributed to informed flow. Scripted
+    /// orders with their own limits are routed on their own after the aggregates.
+    BatchAuction,
+    /// Each direction's orders share one split search per step: the submission's share is
+    /// found once for the direction's total input, and every order is then routed on its
+    /// own at that share. Nothing is netted or merged, so each order keeps its own limits
+    /// and fill accounting.
+    BatchRouted,
+}
+
+/// What drives a simulation forward.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Clock {
+    /// Fixed steps of `gbm_dt`: each one moves the price, lets the arbitrageur trade, then
+    /// routes whatever retail orders arrived in it.
+    #[default]
+    FixedSteps,
+    /// Retail orders and arbitrage checks arrive one at a time, as Poisson processes over
+    /// the same `n_steps * gbm_dt` of time, and the price moves exactly to each arrival.
+    /// Retail classes keep their rates (scaled by any arrival window or cycle); the
+    /// arbitrageur checks the pools `arb_check_rate` times per unit of time. Step-indexed
+    /// settings read the step an event falls in. Needs one session, no warm-up, no
+    /// momentum traders or scenario, and `arb_arrival_prob` of 1.
+    EventDriven { arb_check_rate: f64 },
+}
+
+/// How the router divides each retail order between the submission and the baselines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RouterKind {
+    /// Golden-section search for the split with the most total output.
+    #[default]
+    GoldenSection,
+    /// The whole order goes to whichever side, submission or baselines, quotes more for it.
+    BestPrice,
+    /// The submission gets its share of the input-side reserves across all venues.
+    Proportional,
+    /// Equalize the submission's marginal price with the baselines', falling back to
+    /// golden section when its marginals are noisy.
+    MarginalPrice,
+    /// The whole order goes to the single venue, submission or one baseline, that quotes
+    /// the most for it.
+    WinnerTakeAll,
+    /// Golden section over the integer amounts the submission's program receives, instead
+    /// of over a share converted to them.
+    IntegerGolden,
+    /// Golden section with effort scaled to the order's size against the venues' depth:
+    /// the smallest orders only compare sending everything to either side.
+    AdaptiveGolden,
+    /// Golden section seeded from the previous order's best share on the same side, in a
+    /// bracket that narrows while that share holds steady.
+    WarmStartGolden,
+}
+
+/// What the engine does when the submission's BPF `after_swap` returns an error. Native
+/// submissions cannot fail this way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AfterSwapFailurePolicy {
+    /// Keep simulating with the storage as the failed call left it.
+    #[default]
+    Ignore,
+    /// Keep simulating, but count failures into `SimResult::after_swap_failures`.
+    CountAndReport,
+    /// Fail the simulation at the end of the step in which the first failure happened.
+    FailSim,
+}
+
+/// What the engine does when the submission's curve fails a shape check (monotonicity or
+/// concavity) in the router or the arbitrageur.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeViolationPolicy {
+    /// Fail the simulation, and with it the batch.
+    #[default]
+    Abort,
+    /// Record each violation in `SimResult::shape`, keep simulating, and report zero edge
+    /// for the simulation.
+    ZeroEdge,
+}
+
+/// Where in each step the arbitrageur trades relative to that step's retail orders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepSequencing {
+    /// Arbitrage first, so retail always trades against a freshly aligned pool.
+    #[default]
+    ArbFirst,
+    /// Retail first, against whatever mispricing the price move left behind.
+    ArbLast,
+    /// At a uniformly random position among the step's retail orders.
+    Interleaved,
+}
+
+/// A [`SimulationConfig`] field holding a value the engine cannot simulate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Field path, e.g. `gbm_sigma` or `flow_regimes[1].buy_prob`.
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn check(field: impl Into<String>, ok: bool, message: String) -> Result<(), ConfigError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(ConfigError {
+            field: field.into(),
+            message,
+        })
+    }
+}
+
+fn check_positive(field: &str, value: f64) -> Result<(), ConfigError> {
+    check(field, value.is_finite() && value > 0.0, format!("must be > 0 (got {value})"))
+}
+
+fn check_non_negative(field: &str, value: f64) -> Result<(), ConfigError> {
+    check(field, value.is_finite() && value >= 0.0, format!("must be >= 0 (got {value})"))
+}
+
+fn check_probability(field: &str, value: f64) -> Result<(), ConfigError> {
+    check(field, (0.0..=1.0).contains(&value), format!("must be in [0, 1] (got {value})"))
+}
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimulationConfig {
     pub n_steps: u32,
+    /// Steps run before the `n_steps` scored ones, so `after_swap` state can settle. Trades
+    /// still execute and are hashed, but no edge accrues.
+    pub warmup_steps: u32,
+    /// Sessions of `n_steps` scored steps chained after the warm-up. Each session reopens
+    /// every pool at its starting reserves and the fair price at `initial_price`, but the
+    /// submission's storage carries over. Step-indexed schedules (flow regimes, liquidity
+    /// events, price gaps) count from the start of each session and so repeat in every one.
+    #[cfg_attr(feature = "serde", serde(default = "default_sessions"))]
+    pub sessions: u32,
     pub initial_price: f64,
     pub initial_x: f64,
     pub initial_y: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub price_process: PriceProcessKind,
     pub gbm_mu: f64,
     pub gbm_sigma: f64,
+    /// Length of a step in the unit of time `gbm_mu`, `gbm_sigma`, `retail_arrival_rate` and
+    /// `arb_arrival_prob` are quoted per. The default 1 makes them all per-step figures;
+    /// shrinking it (with more steps) runs the same market on a finer clock.
     pub gbm_dt: f64,
+    /// Mean noise-trader orders per unit of time; a step expects `retail_arrival_rate *
+    /// gbm_dt`.
     pub retail_arrival_rate: f64,
     pub retail_mean_size: f64,
     pub retail_size_sigma: f64,
     pub retail_buy_prob: f64,
+    /// Chance that a retail order is informed: it keeps its size but trades in the direction
+    /// of the next step's price move, and its fills are reported as `Counterparty::Informed`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub informed_prob: f64,
+    /// Worst all-in cost against the fair price, in bps, a retail order accepts: an order
+    /// whose best routed execution would cost more is abandoned and counted in
+    /// `SimResult::abandoned_orders`. `None` trades at any price.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retail_max_slippage_bps: Option<f64>,
+    /// Orders that miss their slippage limit or minimum output fill the largest part of
+    /// their size that meets them, counted in `SimResult::partial_fills`, instead of being
+    /// abandoned.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retail_partial_fills: bool,
+    /// Sell orders sell however much X their routed execution needs to pay out their size
+    /// in Y, instead of their size converted to X at the (observed) fair price.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retail_exact_sell_notional: bool,
+    /// Retail orders ask for an exact output rather than spending an exact input: buys for
+    /// their size converted to X at the (observed) fair price, sells for their size in Y.
+    /// The router finds the input that pays it out; implies `retail_exact_sell_notional`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retail_exact_output: bool,
+    /// Smallest input, in the input token, the router sends to any one venue. Retail orders
+    /// at or below it are dropped as dust before routing and counted in
+    /// `SimResult::dust_orders` and `SimResult::dust_notional`.
+    #[cfg_attr(feature = "serde", serde(default = "default_min_trade_size"))]
+    pub min_trade_size: f64,
+    /// Largest input the router sends to any one venue in one trade, as a fraction of that
+    /// venue's input-side reserve, like venues that revert oversized trades. Orders larger
+    /// than every venue's cap combined fill only up to it. `None` leaves trades uncapped.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_trade_fraction: Option<f64>,
+    /// Worst average price, in bps from the (observed) fair price against the trader, at
+    /// which the router fills a retail order. An order whose best split is quoted worse, or
+    /// at nothing, is left unfilled and counted in `SimResult::guarded_orders`, however
+    /// much worse every venue quotes. `None` fills at any price.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub price_guard_bps: Option<f64>,
+    /// Relative noise on the fair price the router observes: it sizes sell orders and checks
+    /// slippage limits at `fair_price * exp(oracle_noise * z)`, z standard normal, drawn each
+    /// step on its own stream. Edge is still measured at the true price. 0 observes it
+    /// exactly.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub oracle_noise: f64,
+    /// Correlation, in [-1, 1], of retail direction with the step's own price move: this
+    /// share of orders buy after an up move and sell after a down move (negative: the
+    /// reverse), the rest keep the regular buy probability.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flow_price_correlation: f64,
+    /// Persistence, in [0, 1), of each retail trader's order direction: an order repeats
+    /// the trader's previous direction with this extra probability, so the direction is an
+    /// AR(1)-like chain with this autocorrelation and flow arrives in runs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flow_persistence: f64,
+    /// Independent retail traders whose orders are merged each step. Empty means a single
+    /// trader using the `retail_*` fields directly.
+    pub retail_mix: Vec<RetailProfile>,
+    /// Order-flow imbalance windows; where they overlap, the last one listed wins.
+    pub flow_regimes: Vec<FlowRegime>,
+    /// Retail activity windows; where they overlap, the last one listed wins.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arrival_windows: Vec<ArrivalWindow>,
+    /// Periodic retail activity, on top of any window.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arrival_cycle: Option<ArrivalCycle>,
+    /// Momentum traders on their own RNG stream, so the noise traders' orders are unchanged.
+    /// Their orders are reported as retail.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub momentum_traders: Option<MomentumCohort>,
     pub min_arb_profit: f64,
+    /// Full bid/ask spread, in bps of the fair price, of the external market arbitrageurs
+    /// unwind against. 0 is a frictionless market at mid; edge is valued at mid either way.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub external_spread_bps: f64,
+    /// Proportional fee, in bps of the hedge leg's notional, the arbitrageur pays on the
+    /// external market, on top of `min_arb_profit` and any spread. Edge is valued at mid.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_taker_fee_bps: f64,
+    /// Chance the arbitrageur shows up within a unit of time; each step it is active with
+    /// [`Self::arb_arrival_prob_per_step`], drawn on its own stream. 1 checks every venue
+    /// every step; below 1, mispricings persist between its visits.
+    #[cfg_attr(feature = "serde", serde(default = "default_arb_arrival_prob"))]
+    pub arb_arrival_prob: f64,
+    /// Size arbitrage on a submission that quotes like constant product with a fee in
+    /// closed form, as on the normalizer, instead of searching. Far fewer quote calls; sizes
+    /// differ from the search's by its ~1% tolerance.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_cp_fast_path: bool,
+    /// After arbitraging each venue against the fair price, also arbitrage the submission
+    /// against the normalizer directly: buy X on the cheaper pool and sell it on the richer.
+    /// The round trip needs no external hedge, so it closes gaps that trading each pool
+    /// against the external market leaves, e.g. after noisy or inventory-capped sizing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_cross_venue: bool,
+    /// Relative noise on the arbitrageur's trade size: each chosen size is multiplied by
+    /// `exp(arb_size_noise * z)`, z standard normal, drawn on its own stream. 0 sizes
+    /// optimally.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_size_noise: f64,
+    /// Finite arbitrageur inventory shared across every venue. `None` is unlimited capital.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_inventory: Option<ArbInventory>,
     pub seed: u64,
     pub norm_fee_bps: u16,
     pub norm_liquidity_mult: f64,
+    /// Baseline pools beyond the normalizer. Retail orders are split across every venue and
+    /// each pool is arbitraged; empty reproduces the two-venue market.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_venues: Vec<BaselineVenue>,
+    /// Route retail flow across the normalizer and extra venues by their quotes alone,
+    /// handing each order's baseline part out in chunks, instead of splitting it in closed
+    /// form as constant-product pools.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub black_box_baselines: bool,
+    /// Depth changes applied to the submission and every baseline alike; events on the same
+    /// step compound in order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub liquidity_events: Vec<LiquidityEvent>,
+    /// Tell every pool's program about each liquidity event with a `LIQUIDITY_EVENT_TAG`
+    /// call. Off by default, since programs written before LP events may misread it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub liquidity_event_callbacks: bool,
+    /// Fair price gaps on top of the diffusion; gaps on the same step compound.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub price_gaps: Vec<PriceGap>,
+    /// A scripted market replacing the generated retail flow, and the price path if it
+    /// lists prices. `None` leaves everything stochastic.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scenario: Option<Scenario>,
+    /// This many further gaps of `random_gap_size` in either direction, at scored steps drawn
+    /// uniformly from their own RNG stream.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub random_gaps: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub random_gap_size: f64,
+    /// Regime-switching volatility in place of the constant `gbm_sigma`, driven by its own
+    /// RNG stream. `None` keeps a single regime.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub volatility_regimes: Option<VolatilityRegimes>,
+    /// Degrees of freedom of Student-t price shocks, rescaled to unit variance so
+    /// `gbm_sigma` is unchanged, in place of Gaussian ones: lower is fatter-tailed. Must
+    /// exceed 2. `None` keeps Gaussian shocks.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gbm_shock_df: Option<f64>,
+    /// Quote latency handicap: after any step in which the submission's mean CU per quote
+    /// exceeds this budget, it is quoted after the normalizer in the next step and loses
+    /// routing ties. `None` disables the handicap; native submissions are never metered.
+    pub quote_cu_budget: Option<u64>,
+    /// A pool whose X or Y reserve falls below this fraction of its starting value counts as
+    /// depleted and ends the simulation. 0 disables the reserve check; pools that stop
+    /// quoting are still caught.
+    #[cfg_attr(feature = "serde", serde(default = "default_depletion_reserve_frac"))]
+    pub depletion_reserve_frac: f64,
+    /// Snapshot the submission pool every this many steps (and after the last step) into
+    /// `SimResult::trajectory`. `None` records nothing.
+    pub trajectory_stride: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub edge_mode: EdgeMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub execution_mode: ExecutionMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clock: Clock,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub router: RouterKind,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub after_swap_failure_policy: AfterSwapFailurePolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shape_violation_policy: ShapeViolationPolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub step_sequencing: StepSequencing,
+    /// Decimals of the integer amounts and reserves every program (submission and baselines)
+    /// receives; the engine itself trades in f64 either way.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub token_decimals: TokenDecimals,
+    /// Time the engine's components into `SimResult::timing`. Costs a clock read around
+    /// every program call; results are otherwise unchanged.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub profile_components: bool,
+    /// Serve repeated identical quotes within a step from a per-pool cache instead of
+    /// re-running the program. Cached quotes are metered as if they ran, so results are
+    /// unchanged; only BPF runs get noticeably faster.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub quote_cache: bool,
+    /// Record every executed trade, on every venue, into `SimResult::tape`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record_tape: bool,
+}
+
+#[cfg(feature = "serde")]
+fn default_sessions() -> u32 {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn default_arb_arrival_prob() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "serde")]
+fn default_min_trade_size() -> f64 {
+    MIN_TRADE_SIZE
+}
+
+#[cfg(feature = "serde")]
+fn default_depletion_reserve_frac() -> f64 {
+    DEPLETION_RESERVE_FRAC
 }
 
 impl Default for SimulationConfig {
     fn default() -> Self {
         Self {
             n_steps: BASELINE_STEPS,
+            warmup_steps: 0,
+            sessions: 1,
             initial_price: INITIAL_PRICE,
             initial_x: INITIAL_X,
             initial_y: INITIAL_Y,
+            price_process: PriceProcessKind::Gbm,
             gbm_mu: GBM_MU,
             gbm_sigma: GBM_SIGMA,
             gbm_dt: GBM_DT,
@@ -50,14 +654,369 @@ impl Default for SimulationConfig {
             retail_mean_size: RETAIL_MEAN_SIZE,
             retail_size_sigma: RETAIL_SIZE_SIGMA,
             retail_buy_prob: RETAIL_BUY_PROB,
+            informed_prob: 0.0,
+            retail_max_slippage_bps: None,
+            retail_partial_fills: false,
+            retail_exact_sell_notional: false,
+            retail_exact_output: false,
+            min_trade_size: MIN_TRADE_SIZE,
+            max_trade_fraction: None,
+            price_guard_bps: None,
+            oracle_noise: 0.0,
+            flow_price_correlation: 0.0,
+            flow_persistence: 0.0,
+            retail_mix: Vec::new(),
+            flow_regimes: Vec::new(),
+            arrival_windows: Vec::new(),
+            arrival_cycle: None,
+            momentum_traders: None,
             min_arb_profit: MIN_ARB_PROFIT,
+            external_spread_bps: 0.0,
+            arb_taker_fee_bps: 0.0,
+            arb_arrival_prob: 1.0,
+            arb_cp_fast_path: false,
+            arb_cross_venue: false,
+            arb_size_noise: 0.0,
+            arb_inventory: None,
             seed: 0,
             norm_fee_bps: 30,
             norm_liquidity_mult: 1.0,
+            extra_venues: Vec::new(),
+            black_box_baselines: false,
+            liquidity_events: Vec::new(),
+            liquidity_event_callbacks: false,
+            price_gaps: Vec::new(),
+            scenario: None,
+            random_gaps: 0,
+            random_gap_size: 0.0,
+            volatility_regimes: None,
+            gbm_shock_df: None,
+            quote_cu_budget: None,
+            depletion_reserve_frac: DEPLETION_RESERVE_FRAC,
+            trajectory_stride: None,
+            edge_mode: EdgeMode::Flow,
+            execution_mode: ExecutionMode::Sequential,
+            clock: Clock::FixedSteps,
+            router: RouterKind::GoldenSection,
+            after_swap_failure_policy: AfterSwapFailurePolicy::Ignore,
+            shape_violation_policy: ShapeViolationPolicy::Abort,
+            step_sequencing: StepSequencing::ArbFirst,
+            token_decimals: TokenDecimals { x: 9, y: 9 },
+            profile_components: false,
+            quote_cache: false,
+            record_tape: false,
         }
     }
 }
 
+impl SimulationConfig {
+    /// The retail trader classes to simulate, resolved to absolute rates and sizes.
+    pub fn retail_traders(&self) -> Vec<RetailClass> {
+        if self.retail_mix.is_empty() {
+            return vec![RetailClass {
+                arrival_rate: self.retail_arrival_rate,
+                mean_size: self.retail_mean_size,
+                size_sigma: self.retail_size_sigma,
+                buy_prob: None,
+            }];
+        }
+        self.retail_mix
+            .iter()
+            .map(|p| RetailClass {
+                arrival_rate: self.retail_arrival_rate * p.arrival_scale,
+                mean_size: self.retail_mean_size * p.size_scale,
+                size_sigma: p.size_sigma,
+                buy_prob: p.buy_prob,
+            })
+            .collect()
+    }
+
+    /// Size the starting pools independently of the fair price. The submission pool opens
+    /// holding `liquidity` worth of Y, split evenly between its reserves, at a spot price of
+    /// `initial_price * (1 + mispricing)`; baselines scale it by their liquidity multiples.
+    /// A nonzero `mispricing` launches every pool into a market the arbitrageur must first
+    /// correct.
+    pub fn set_initial_pool(&mut self, liquidity: f64, mispricing: f64) {
+        let pool_price = self.initial_price * (1.0 + mispricing);
+        self.initial_y = liquidity / 2.0;
+        self.initial_x = self.initial_y / pool_price;
+    }
+
+    /// Value of the submission pool's starting reserves in Y, at its own spot price.
+    pub fn initial_liquidity(&self) -> f64 {
+        2.0 * self.initial_y
+    }
+
+    /// How far the pools' starting spot price sits above (or below) `initial_price`, as a
+    /// fraction.
+    pub fn initial_mispricing(&self) -> f64 {
+        self.initial_y / self.initial_x / self.initial_price - 1.0
+    }
+
+    /// Reject values that would produce NaNs or panics inside the engine, naming the first
+    /// offending field.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check("sessions", self.sessions >= 1, "must be >= 1".to_string())?;
+        check_positive("initial_price", self.initial_price)?;
+        check_positive("initial_x", self.initial_x)?;
+        check_positive("initial_y", self.initial_y)?;
+        check("gbm_mu", self.gbm_mu.is_finite(), format!("must be finite (got {})", self.gbm_mu))?;
+        check_non_negative("gbm_sigma", self.gbm_sigma)?;
+        check_positive("gbm_dt", self.gbm_dt)?;
+        check_non_negative("retail_arrival_rate", self.retail_arrival_rate)?;
+        check_positive("retail_mean_size", self.retail_mean_size)?;
+        check_non_negative("retail_size_sigma", self.retail_size_sigma)?;
+        check_probability("retail_buy_prob", self.retail_buy_prob)?;
+        check_probability("informed_prob", self.informed_prob)?;
+        if let Some(bps) = self.retail_max_slippage_bps {
+            check_non_negative("retail_max_slippage_bps", bps)?;
+        }
+        check_non_negative("min_trade_size", self.min_trade_size)?;
+        if let Some(fraction) = self.max_trade_fraction {
+            check_positive("max_trade_fraction", fraction)?;
+        }
+        if let Some(bps) = self.price_guard_bps {
+            check_non_negative("price_guard_bps", bps)?;
+        }
+        check_non_negative("oracle_noise", self.oracle_noise)?;
+        check(
+            "flow_price_correlation",
+            (-1.0..=1.0).contains(&self.flow_price_correlation),
+            format!("must be in [-1, 1] (got {})", self.flow_price_correlation),
+        )?;
+        check(
+            "flow_persistence",
+            (0.0..1.0).contains(&self.flow_persistence),
+            format!("must be in [0, 1) (got {})", self.flow_persistence),
+        )?;
+        for (i, profile) in self.retail_mix.iter().enumerate() {
+            check_non_negative(&format!("retail_mix[{i}].arrival_scale"), profile.arrival_scale)?;
+            check_positive(&format!("retail_mix[{i}].size_scale"), profile.size_scale)?;
+            check_non_negative(&format!("retail_mix[{i}].size_sigma"), profile.size_sigma)?;
+            if let Some(buy_prob) = profile.buy_prob {
+                check_probability(&format!("retail_mix[{i}].buy_prob"), buy_prob)?;
+            }
+        }
+        for (i, regime) in self.flow_regimes.iter().enumerate() {
+            check(
+                format!("flow_regimes[{i}]"),
+                regime.start_step <= regime.end_step,
+                format!(
+                    "start_step {} is after end_step {}",
+                    regime.start_step, regime.end_step
+                ),
+            )?;
+            check_probability(&format!("flow_regimes[{i}].buy_prob"), regime.buy_prob)?;
+        }
+        for (i, window) in self.arrival_windows.iter().enumerate() {
+            check(
+                format!("arrival_windows[{i}]"),
+                window.start_step <= window.end_step,
+                format!(
+                    "start_step {} is after end_step {}",
+                    window.start_step, window.end_step
+                ),
+            )?;
+            check_non_negative(&format!("arrival_windows[{i}].multiplier"), window.multiplier)?;
+        }
+        if let Some(cohort) = &self.momentum_traders {
+            check_non_negative("momentum_traders.arrival_scale", cohort.arrival_scale)?;
+            check(
+                "momentum_traders.lookback_steps",
+                cohort.lookback_steps >= 1,
+                "must be >= 1".to_string(),
+            )?;
+            check_probability("momentum_traders.weight", cohort.weight)?;
+        }
+        if let Some(cycle) = &self.arrival_cycle {
+            check(
+                "arrival_cycle.period_steps",
+                cycle.period_steps >= 1,
+                "must be >= 1".to_string(),
+            )?;
+            check(
+                "arrival_cycle.amplitude",
+                (0.0..=1.0).contains(&cycle.amplitude),
+                format!("must be in [0, 1] (got {})", cycle.amplitude),
+            )?;
+        }
+        check_non_negative("min_arb_profit", self.min_arb_profit)?;
+        check(
+            "external_spread_bps",
+            (0.0..20_000.0).contains(&self.external_spread_bps),
+            format!("must be in [0, 20000) (got {})", self.external_spread_bps),
+        )?;
+        check(
+            "arb_taker_fee_bps",
+            (0.0..10_000.0).contains(&self.arb_taker_fee_bps),
+            format!("must be in [0, 10000) (got {})", self.arb_taker_fee_bps),
+        )?;
+        check_probability("arb_arrival_prob", self.arb_arrival_prob)?;
+        check_non_negative("arb_size_noise", self.arb_size_noise)?;
+        if let Some(inventory) = &self.arb_inventory {
+            check_non_negative("arb_inventory.x", inventory.x)?;
+            check_non_negative("arb_inventory.y", inventory.y)?;
+        }
+        check(
+            "norm_fee_bps",
+            self.norm_fee_bps <= MAX_FEE_BPS,
+            format!("must be <= {MAX_FEE_BPS} (got {})", self.norm_fee_bps),
+        )?;
+        check_positive("norm_liquidity_mult", self.norm_liquidity_mult)?;
+        // Trades name their venue by a `u8`, after the submission and the normalizer.
+        check(
+            "extra_venues",
+            self.extra_venues.len() <= MAX_EXTRA_VENUES,
+            format!("at most {MAX_EXTRA_VENUES} (got {})", self.extra_venues.len()),
+        )?;
+        for (i, venue) in self.extra_venues.iter().enumerate() {
+            // 0 would fall back to the normalizer's 30 bps default.
+            check(
+                format!("extra_venues[{i}].fee_bps"),
+                (1..MAX_FEE_BPS).contains(&venue.fee_bps),
+                format!("must be in 1..{MAX_FEE_BPS} (got {})", venue.fee_bps),
+            )?;
+            check_positive(&format!("extra_venues[{i}].liquidity_mult"), venue.liquidity_mult)?;
+        }
+        for (i, event) in self.liquidity_events.iter().enumerate() {
+            check_positive(&format!("liquidity_events[{i}].scale"), event.scale)?;
+        }
+        if let Some(scenario) = &self.scenario {
+            for (i, order) in scenario.orders.iter().enumerate() {
+                check_positive(&format!("scenario.orders[{i}].size"), order.size)?;
+                check(
+                    format!("scenario.orders[{i}].step"),
+                    order.step < self.n_steps,
+                    format!("must be below n_steps {} (got {})", self.n_steps, order.step),
+                )?;
+                if let Some(bps) = order.max_slippage_bps {
+                    check_non_negative(&format!("scenario.orders[{i}].max_slippage_bps"), bps)?;
+                }
+                if let Some(min) = order.min_output {
+                    check_positive(&format!("scenario.orders[{i}].min_output"), min)?;
+                }
+            }
+            for (i, point) in scenario.prices.iter().enumerate() {
+                check_positive(&format!("scenario.prices[{i}].price"), point.price)?;
+                let after_previous = i == 0 || point.step > scenario.prices[i - 1].step;
+                check(
+                    format!("scenario.prices[{i}].step"),
+                    after_previous && point.step < self.n_steps,
+                    format!(
+                        "must increase and stay below n_steps {} (got {})",
+                        self.n_steps, point.step
+                    ),
+                )?;
+            }
+        }
+        for (i, gap) in self.price_gaps.iter().enumerate() {
+            check(
+                format!("price_gaps[{i}].size"),
+                gap.size.is_finite() && gap.size > -1.0,
+                format!("must be > -1 (got {})", gap.size),
+            )?;
+        }
+        check(
+            "random_gap_size",
+            (0.0..1.0).contains(&self.random_gap_size),
+            format!("must be in [0, 1) (got {})", self.random_gap_size),
+        )?;
+        if let Some(regimes) = &self.volatility_regimes {
+            check_non_negative("volatility_regimes.calm_sigma", regimes.calm_sigma)?;
+            check_non_negative("volatility_regimes.volatile_sigma", regimes.volatile_sigma)?;
+            check_probability("volatility_regimes.calm_to_volatile", regimes.calm_to_volatile)?;
+            check_probability("volatility_regimes.volatile_to_calm", regimes.volatile_to_calm)?;
+        }
+        if let Some(df) = self.gbm_shock_df {
+            let ok = df.is_finite() && df > 2.0;
+            check("gbm_shock_df", ok, format!("must be finite and exceed 2 (got {df})"))?;
+        }
+        if let Clock::EventDriven { arb_check_rate } = self.clock {
+            check_non_negative("clock.arb_check_rate", arb_check_rate)?;
+            let supported = self.sessions == 1
+                && self.warmup_steps == 0
+                && self.momentum_traders.is_none()
+                && self.scenario.is_none()
+                && self.arb_arrival_prob == 1.0;
+            check(
+                "clock",
+                supported,
+                "the event-driven clock needs one session, no warm-up, no momentum traders or \
+                 scenario, and arb_arrival_prob 1"
+                    .to_string(),
+            )?;
+        }
+        for (field, decimals) in [
+            ("token_decimals.x", self.token_decimals.x),
+            ("token_decimals.y", self.token_decimals.y),
+        ] {
+            check(
+                field,
+                decimals <= MAX_TOKEN_DECIMALS,
+                format!("must be <= {MAX_TOKEN_DECIMALS} (got {decimals})"),
+            )?;
+        }
+        check(
+            "depletion_reserve_frac",
+            (0.0..1.0).contains(&self.depletion_reserve_frac),
+            format!("must be in [0, 1) (got {})", self.depletion_reserve_frac),
+        )?;
+        check(
+            "trajectory_stride",
+            self.trajectory_stride != Some(0),
+            "must be >= 1".to_string(),
+        )
+    }
+
+    /// Steps a simulation runs: the warm-up, then every session's scored steps.
+    pub fn total_steps(&self) -> u32 {
+        let scored_steps = self.n_steps.saturating_mul(self.sessions.max(1));
+        self.warmup_steps.saturating_add(scored_steps)
+    }
+
+    /// Scored step within its session, for absolute step `step`; `None` while warming up.
+    pub fn session_step(&self, step: u32) -> Option<u32> {
+        step.checked_sub(self.warmup_steps).map(|s| s % self.n_steps.max(1))
+    }
+
+    /// Retail buy probability at scored step `step`, after applying any flow regime covering
+    /// it.
+    pub fn retail_buy_prob_at(&self, step: u32) -> f64 {
+        self.flow_regimes
+            .iter()
+            .rev()
+            .find(|r| (r.start_step..r.end_step).contains(&step))
+            .map_or(self.retail_buy_prob, |r| r.buy_prob)
+    }
+
+    /// Chance the arbitrageur is active in one step of `gbm_dt`, compounding
+    /// `arb_arrival_prob` over the step's length. Exactly `arb_arrival_prob` at the default
+    /// `gbm_dt` of 1.
+    pub fn arb_arrival_prob_per_step(&self) -> f64 {
+        if self.gbm_dt == 1.0 {
+            self.arb_arrival_prob
+        } else {
+            1.0 - (1.0 - self.arb_arrival_prob).powf(self.gbm_dt)
+        }
+    }
+
+    /// Multiplier on every retail trader's arrival rate at scored step `step`, from the
+    /// arrival window covering it and the arrival cycle.
+    pub fn arrival_intensity_at(&self, step: u32) -> f64 {
+        let window = self
+            .arrival_windows
+            .iter()
+            .rev()
+            .find(|w| (w.start_step..w.end_step).contains(&step))
+            .map_or(1.0, |w| w.multiplier);
+        let cycle = self.arrival_cycle.map_or(1.0, |c| {
+            let phase = (step % c.period_steps.max(1)) as f64 / c.period_steps.max(1) as f64;
+            1.0 + c.amplitude * (std::f64::consts::TAU * phase).sin()
+        });
+        window * cycle
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HyperparameterVariance {
     pub gbm_sigma_min: f64,
@@ -70,6 +1029,18 @@ pub struct HyperparameterVariance {
     pub norm_fee_bps_max: u16,
     pub norm_liquidity_mult_min: f64,
     pub norm_liquidity_mult_max: f64,
+    /// Chance that a simulation contains one order-flow imbalance regime. Zero by default;
+    /// its draws are taken either way, so raising it leaves the other draws untouched.
+    pub flow_regime_prob: f64,
+    /// Distance of the regime's buy probability from 0.5 (buy or sell side, 50/50).
+    pub flow_regime_skew_min: f64,
+    pub flow_regime_skew_max: f64,
+    /// Regime length as a fraction of the simulation's steps.
+    pub flow_regime_len_frac_min: f64,
+    pub flow_regime_len_frac_max: f64,
+    /// Inclusive range to draw each simulation's `n_steps` from. `None` keeps the base
+    /// config's step count (and leaves the other draws untouched).
+    pub n_steps_range: Option<(u32, u32)>,
 }
 
 impl Default for HyperparameterVariance {
@@ -85,31 +1056,74 @@ impl Default for HyperparameterVariance {
             norm_fee_bps_max: 80,
             norm_liquidity_mult_min: 0.4,
             norm_liquidity_mult_max: 2.0,
+            flow_regime_prob: 0.0,
+            flow_regime_skew_min: 0.15,
+            flow_regime_skew_max: 0.3,
+            flow_regime_len_frac_min: 0.05,
+            flow_regime_len_frac_max: 0.2,
+            n_steps_range: None,
         }
     }
 }
 
 impl HyperparameterVariance {
     pub fn apply(&self, base: &SimulationConfig, seed: u64) -> SimulationConfig {
-        let mut rng = Pcg64::seed_from_u64(seed);
-        // Original 3 draws first — order preserved for seed reproducibility
+        let mut rng = Pcg64::seed_from_u64(substream_seed(seed, Component::Config, 0));
+        // New draws go after the existing ones, so those keep their values for a given seed.
         let gbm_sigma = rng.gen_range(self.gbm_sigma_min..self.gbm_sigma_max);
         let retail_arrival_rate = rng.gen_range(self.retail_arrival_rate_min..self.retail_arrival_rate_max);
         let retail_mean_size = rng.gen_range(self.retail_mean_size_min..self.retail_mean_size_max);
-        // New draws appended
         let norm_fee_bps = rng.gen_range(self.norm_fee_bps_min..=self.norm_fee_bps_max);
         let norm_liquidity_mult = rng.gen_range(self.norm_liquidity_mult_min..self.norm_liquidity_mult_max);
+        // Flow regime draws are always taken so every seed consumes the same stream.
+        let has_regime = rng.gen::<f64>() < self.flow_regime_prob;
+        let skew = rng.gen_range(self.flow_regime_skew_min..=self.flow_regime_skew_max);
+        let buy_side = rng.gen::<bool>();
+        let len_frac = rng.gen_range(self.flow_regime_len_frac_min..=self.flow_regime_len_frac_max);
+        let start_frac = rng.gen::<f64>();
+        let n_steps = match self.n_steps_range {
+            Some((min, max)) => rng.gen_range(min..=max),
+            None => base.n_steps,
+        };
+        let mut flow_regimes = base.flow_regimes.clone();
+        if has_regime && n_steps > 0 {
+            let len = ((n_steps as f64 * len_frac) as u32).clamp(1, n_steps);
+            let start_step = ((n_steps - len) as f64 * start_frac) as u32;
+            flow_regimes.push(FlowRegime {
+                start_step,
+                end_step: start_step + len,
+                buy_prob: if buy_side { 0.5 + skew } else { 0.5 - skew },
+            });
+        }
         SimulationConfig {
+            n_steps,
             gbm_sigma,
             retail_arrival_rate,
             retail_mean_size,
             norm_fee_bps,
             norm_liquidity_mult,
+            flow_regimes,
             seed,
             ..base.clone()
         }
     }
 
+    /// The box scaled by `difficulty`: volatility and normalizer liquidity ranges are
+    /// multiplied by it and normalizer fees divided by it (at least 1 bps), so values above 1
+    /// make the market harder for a submission and values below 1 easier.
+    pub fn scaled(&self, difficulty: f64) -> Self {
+        let fee = |bps: u16| ((bps as f64 / difficulty).round() as u16).max(1);
+        Self {
+            gbm_sigma_min: self.gbm_sigma_min * difficulty,
+            gbm_sigma_max: self.gbm_sigma_max * difficulty,
+            norm_fee_bps_min: fee(self.norm_fee_bps_min),
+            norm_fee_bps_max: fee(self.norm_fee_bps_max),
+            norm_liquidity_mult_min: self.norm_liquidity_mult_min * difficulty,
+            norm_liquidity_mult_max: self.norm_liquidity_mult_max * difficulty,
+            ..self.clone()
+        }
+    }
+
     pub fn generate_configs(&self, n: u32) -> Vec<SimulationConfig> {
         let base = SimulationConfig::default();
         (0..n).map(|i| self.apply(&base, i as u64)).collect()
@@ -118,11 +1132,526 @@ impl HyperparameterVariance {
 
 #[cfg(test)]
 mod tests {
-    use super::SimulationConfig;
+    use super::{
+        ArbInventory, ArrivalCycle, ArrivalWindow, BaselineVenue, Clock, FlowRegime,
+        HyperparameterVariance, LiquidityEvent, MomentumCohort, PriceGap, RetailClass,
+        RetailProfile, Scenario, ScriptedOrder, ScriptedPrice, SimulationConfig, TokenDecimals,
+        VolatilityRegimes, INITIAL_LIQUIDITY, INITIAL_X, INITIAL_Y, MAX_EXTRA_VENUES,
+    };
+
+    #[test]
+    fn initial_pool_sets_depth_and_mispricing_independently() {
+        let mut config = SimulationConfig::default();
+        assert_eq!(config.initial_liquidity(), INITIAL_LIQUIDITY);
+        assert_eq!(config.initial_mispricing(), 0.0);
+
+        config.set_initial_pool(INITIAL_LIQUIDITY, 0.0);
+        assert_eq!((config.initial_x, config.initial_y), (INITIAL_X, INITIAL_Y));
+
+        config.set_initial_pool(50_000.0, -0.1);
+        assert_eq!(config.initial_liquidity(), 50_000.0);
+        assert!((config.initial_mispricing() + 0.1).abs() < 1e-12);
+        assert!((config.initial_x * 90.0 - config.initial_y).abs() < 1e-9);
+        assert_eq!(config.initial_price, 100.0);
+    }
+
+    #[test]
+    fn validate_names_the_offending_field() {
+        assert_eq!(SimulationConfig::default().validate(), Ok(()));
+
+        let field = |config: SimulationConfig| config.validate().unwrap_err().field;
+        let base = SimulationConfig::default;
+        assert_eq!(
+            field(SimulationConfig {
+                gbm_sigma: -0.1,
+                ..base()
+            }),
+            "gbm_sigma"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                initial_y: 0.0,
+                ..base()
+            }),
+            "initial_y"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                retail_buy_prob: 1.5,
+                ..base()
+            }),
+            "retail_buy_prob"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                min_trade_size: f64::NAN,
+                ..base()
+            }),
+            "min_trade_size"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                max_trade_fraction: Some(0.0),
+                ..base()
+            }),
+            "max_trade_fraction"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                price_guard_bps: Some(-1.0),
+                ..base()
+            }),
+            "price_guard_bps"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                norm_fee_bps: 10_001,
+                ..base()
+            }),
+            "norm_fee_bps"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                flow_regimes: vec![FlowRegime {
+                    start_step: 0,
+                    end_step: 10,
+                    buy_prob: f64::NAN,
+                }],
+                ..base()
+            }),
+            "flow_regimes[0].buy_prob"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                retail_mix: vec![RetailProfile {
+                    buy_prob: Some(-0.2),
+                    ..RetailProfile::SMALL_FREQUENT
+                }],
+                ..base()
+            }),
+            "retail_mix[0].buy_prob"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                extra_venues: vec![BaselineVenue {
+                    fee_bps: 0,
+                    liquidity_mult: 1.0,
+                }],
+                ..base()
+            }),
+            "extra_venues[0].fee_bps"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                extra_venues: vec![
+                    BaselineVenue {
+                        fee_bps: 30,
+                        liquidity_mult: 1.0,
+                    };
+                    MAX_EXTRA_VENUES + 1
+                ],
+                ..base()
+            }),
+            "extra_venues"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                liquidity_events: vec![LiquidityEvent {
+                    step: 100,
+                    scale: 0.0,
+                }],
+                ..base()
+            }),
+            "liquidity_events[0].scale"
+        );
+        let point = |step, price| ScriptedPrice { step, price };
+        assert_eq!(
+            field(SimulationConfig {
+                scenario: Some(Scenario {
+                    prices: vec![point(10, 100.0), point(10, 90.0)],
+                    ..Scenario::default()
+                }),
+                ..base()
+            }),
+            "scenario.prices[1].step"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                scenario: Some(Scenario {
+                    orders: vec![ScriptedOrder {
+                        step: 0,
+                        is_buy: true,
+                        size: 10.0,
+                        max_slippage_bps: None,
+                        min_output: Some(0.0),
+                    }],
+                    ..Scenario::default()
+                }),
+                ..base()
+            }),
+            "scenario.orders[0].min_output"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                price_gaps: vec![PriceGap {
+                    step: 10,
+                    size: -1.0,
+                }],
+                ..base()
+            }),
+            "price_gaps[0].size"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                random_gap_size: 1.0,
+                ..base()
+            }),
+            "random_gap_size"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                sessions: 0,
+                ..base()
+            }),
+            "sessions"
+        );
+        // Starting pools no positive reserves can hold: drained, or opened at a price <= 0.
+        let pool = |liquidity: f64, mispricing: f64| {
+            let mut config = base();
+            config.set_initial_pool(liquidity, mispricing);
+            config
+        };
+        assert_eq!(field(pool(20_000.0, -1.0)), "initial_x");
+        assert_eq!(field(pool(0.0, 0.0)), "initial_x");
+        assert_eq!(
+            field(SimulationConfig {
+                token_decimals: TokenDecimals { x: 9, y: 13 },
+                ..base()
+            }),
+            "token_decimals.y"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                volatility_regimes: Some(VolatilityRegimes {
+                    calm_sigma: 0.001,
+                    volatile_sigma: 0.005,
+                    calm_to_volatile: 0.01,
+                    volatile_to_calm: 1.5,
+                }),
+                ..base()
+            }),
+            "volatility_regimes.volatile_to_calm"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                gbm_shock_df: Some(2.0),
+                ..base()
+            }),
+            "gbm_shock_df"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                clock: Clock::EventDriven {
+                    arb_check_rate: -1.0
+                },
+                ..base()
+            }),
+            "clock.arb_check_rate"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                clock: Clock::EventDriven {
+                    arb_check_rate: 1.0
+                },
+                sessions: 2,
+                ..base()
+            }),
+            "clock"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                external_spread_bps: -1.0,
+                ..base()
+            }),
+            "external_spread_bps"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                arb_taker_fee_bps: 10_000.0,
+                ..base()
+            }),
+            "arb_taker_fee_bps"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                oracle_noise: -0.1,
+                ..base()
+            }),
+            "oracle_noise"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                arb_size_noise: f64::NAN,
+                ..base()
+            }),
+            "arb_size_noise"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                arb_arrival_prob: -0.5,
+                ..base()
+            }),
+            "arb_arrival_prob"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                arb_inventory: Some(ArbInventory {
+                    x: 10.0,
+                    y: f64::INFINITY,
+                    rebalance_steps: 0,
+                }),
+                ..base()
+            }),
+            "arb_inventory.y"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                flow_price_correlation: 1.5,
+                ..base()
+            }),
+            "flow_price_correlation"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                flow_persistence: 1.0,
+                ..base()
+            }),
+            "flow_persistence"
+        );
+        assert_eq!(
+            field(SimulationConfig {
+                momentum_traders: Some(MomentumCohort {
+                    arrival_scale: 0.5,
+                    lookback_steps: 0,
+                    weight: 0.5,
+                }),
+                ..base()
+            }),
+            "momentum_traders.lookback_steps"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_roundtrips_through_json() {
+        let config = SimulationConfig {
+            retail_mix: RetailProfile::two_tier(),
+            flow_regimes: vec![FlowRegime {
+                start_step: 5,
+                end_step: 50,
+                buy_prob: 0.7,
+            }],
+            ..SimulationConfig::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let back: SimulationConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.retail_mix, config.retail_mix);
+        assert_eq!(back.flow_regimes, config.flow_regimes);
+        assert_eq!(back.gbm_sigma, config.gbm_sigma);
+    }
+
+    #[test]
+    fn sampled_configs_are_valid() {
+        let variance = HyperparameterVariance::default();
+        for seed in 0..200 {
+            let config = variance.apply(&SimulationConfig::default(), seed);
+            assert_eq!(config.validate(), Ok(()), "seed {seed}");
+        }
+    }
 
     #[test]
     fn default_min_arb_profit_is_one_cent() {
         let config = SimulationConfig::default();
         assert!((config.min_arb_profit - 0.01).abs() < 1e-12);
     }
+
+    #[test]
+    fn retail_mix_scales_sampled_retail_params() {
+        let mut config = SimulationConfig {
+            retail_arrival_rate: 1.0,
+            retail_mean_size: 10.0,
+            ..SimulationConfig::default()
+        };
+        let resolved = |traders: Vec<RetailClass>| -> Vec<(f64, f64, f64)> {
+            traders
+                .iter()
+                .map(|t| (t.arrival_rate, t.mean_size, t.size_sigma))
+                .collect()
+        };
+        assert_eq!(
+            resolved(config.retail_traders()),
+            vec![(1.0, 10.0, config.retail_size_sigma)]
+        );
+
+        config.retail_mix = RetailProfile::two_tier();
+        let traders = config.retail_traders();
+        assert_eq!(resolved(traders.clone()), vec![(1.5, 4.0, 0.8), (0.1, 40.0, 1.0)]);
+        assert!(traders.iter().all(|t| t.buy_prob.is_none()));
+        let notional: f64 = traders.iter().map(|t| t.arrival_rate * t.mean_size).sum();
+        assert!((notional - 10.0).abs() < 1e-9);
+
+        config.retail_mix[1].buy_prob = Some(0.8);
+        assert_eq!(config.retail_traders()[1].buy_prob, Some(0.8));
+    }
+
+    #[test]
+    fn flow_regimes_override_buy_prob_within_their_window() {
+        let config = SimulationConfig {
+            flow_regimes: vec![
+                FlowRegime {
+                    start_step: 100,
+                    end_step: 200,
+                    buy_prob: 0.7,
+                },
+                FlowRegime {
+                    start_step: 150,
+                    end_step: 160,
+                    buy_prob: 0.2,
+                },
+            ],
+            ..SimulationConfig::default()
+        };
+        assert_eq!(config.retail_buy_prob_at(99), config.retail_buy_prob);
+        assert_eq!(config.retail_buy_prob_at(100), 0.7);
+        assert_eq!(config.retail_buy_prob_at(155), 0.2);
+        assert_eq!(config.retail_buy_prob_at(160), 0.7);
+        assert_eq!(config.retail_buy_prob_at(200), config.retail_buy_prob);
+    }
+
+    #[test]
+    fn arb_arrivals_compound_over_the_step_length() {
+        let mut config = SimulationConfig {
+            arb_arrival_prob: 0.3,
+            ..SimulationConfig::default()
+        };
+        assert_eq!(config.arb_arrival_prob_per_step(), 0.3);
+        // Missing both halves of a unit of time is missing the whole unit.
+        config.gbm_dt = 0.5;
+        let miss = 1.0 - config.arb_arrival_prob_per_step();
+        assert!((miss * miss - 0.7).abs() < 1e-12);
+        config.arb_arrival_prob = 1.0;
+        assert_eq!(config.arb_arrival_prob_per_step(), 1.0);
+    }
+
+    #[test]
+    fn scenario_prices_interpolate_log_linearly_and_hold_at_the_ends() {
+        let scenario = Scenario {
+            prices: vec![
+                ScriptedPrice {
+                    step: 10,
+                    price: 100.0,
+                },
+                ScriptedPrice {
+                    step: 20,
+                    price: 400.0,
+                },
+            ],
+            ..Scenario::default()
+        };
+        assert_eq!(scenario.price_at(0), Some(100.0));
+        assert_eq!(scenario.price_at(10), Some(100.0));
+        assert!((scenario.price_at(15).unwrap() - 200.0).abs() < 1e-9);
+        assert_eq!(scenario.price_at(20), Some(400.0));
+        assert_eq!(scenario.price_at(99), Some(400.0));
+        assert_eq!(Scenario::default().price_at(5), None);
+    }
+
+    #[test]
+    fn arrival_intensity_combines_windows_and_cycle() {
+        let mut config = SimulationConfig {
+            arrival_windows: vec![ArrivalWindow {
+                start_step: 100,
+                end_step: 200,
+                multiplier: 3.0,
+            }],
+            ..SimulationConfig::default()
+        };
+        assert_eq!(config.arrival_intensity_at(99), 1.0);
+        assert_eq!(config.arrival_intensity_at(100), 3.0);
+        assert_eq!(config.arrival_intensity_at(200), 1.0);
+
+        config.arrival_cycle = Some(ArrivalCycle {
+            period_steps: 400,
+            amplitude: 0.5,
+        });
+        assert!((config.arrival_intensity_at(0) - 1.0).abs() < 1e-12);
+        assert!((config.arrival_intensity_at(100) - 4.5).abs() < 1e-12);
+        assert!((config.arrival_intensity_at(300) - 0.5).abs() < 1e-12);
+        assert!((config.arrival_intensity_at(700) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sampled_flow_regimes_fit_the_simulation() {
+        let variance = HyperparameterVariance {
+            flow_regime_prob: 0.5,
+            ..HyperparameterVariance::default()
+        };
+        let configs = variance.generate_configs(200);
+        let with_regime = configs.iter().filter(|c| !c.flow_regimes.is_empty()).count();
+        assert!((50..150).contains(&with_regime), "{with_regime} of 200 have a regime");
+        for config in &configs {
+            for regime in &config.flow_regimes {
+                assert!(regime.start_step < regime.end_step);
+                assert!(regime.end_step <= config.n_steps);
+                let skew = (regime.buy_prob - 0.5).abs();
+                assert!((0.15 - 1e-12..=0.3 + 1e-12).contains(&skew));
+            }
+        }
+    }
+
+    #[test]
+    fn flow_regimes_are_opt_in() {
+        let base = SimulationConfig::default();
+        let off = HyperparameterVariance::default();
+        let on = HyperparameterVariance {
+            flow_regime_prob: 1.0,
+            ..HyperparameterVariance::default()
+        };
+        for seed in 0..50 {
+            let a = off.apply(&base, seed);
+            let b = on.apply(&base, seed);
+            assert!(a.flow_regimes.is_empty());
+            assert_eq!(b.flow_regimes.len(), 1);
+            assert_eq!(a.gbm_sigma, b.gbm_sigma);
+            assert_eq!(a.norm_liquidity_mult, b.norm_liquidity_mult);
+        }
+    }
+
+    #[test]
+    fn n_steps_range_is_opt_in() {
+        let base = SimulationConfig {
+            n_steps: 10_000,
+            ..SimulationConfig::default()
+        };
+        let fixed = HyperparameterVariance::default();
+        let ranged = HyperparameterVariance {
+            n_steps_range: Some((5_000, 20_000)),
+            ..HyperparameterVariance::default()
+        };
+        let mut distinct = std::collections::HashSet::new();
+        for seed in 0..50 {
+            let a = fixed.apply(&base, seed);
+            let b = ranged.apply(&base, seed);
+            assert_eq!(a.n_steps, 10_000);
+            assert!((5_000..=20_000).contains(&b.n_steps));
+            // The existing draws are unchanged by the extra one.
+            assert_eq!(a.gbm_sigma, b.gbm_sigma);
+            assert_eq!(a.norm_fee_bps, b.norm_fee_bps);
+            distinct.insert(b.n_steps);
+            for regime in &b.flow_regimes {
+                assert!(regime.end_step <= b.n_steps);
+            }
+        }
+        assert!(distinct.len() > 40);
+    }
 }
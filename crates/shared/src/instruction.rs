@@ -6,7 +6,6 @@
 /// | 9         | 8    | reserve_x    | u64  | Current X reserve (1e9 scale)  |
 /// | 17        | 8    | reserve_y    | u64  | Current Y reserve (1e9 scale)  |
 /// | 25        | 1024 | storage      | [u8] | Read-only strategy storage     |
-
 pub const INSTRUCTION_SIZE: usize = 25;
 pub const STORAGE_SIZE: usize = 1024;
 pub const SWAP_INSTRUCTION_SIZE: usize = INSTRUCTION_SIZE + STORAGE_SIZE; // 1049
@@ -14,8 +13,8 @@ pub const SWAP_INSTRUCTION_SIZE: usize = INSTRUCTION_SIZE + STORAGE_SIZE; // 104
 /// after_swap instruction layout (1066 bytes):
 /// | Offset    | Size | Field         | Type | Description                    |
 /// |-----------|------|---------------|------|--------------------------------|
-/// | 0         | 1    | tag           | u8   | Always 2                       |
-/// | 1         | 1    | side          | u8   | 0=buy X, 1=sell X              |
+/// | 0         | 1    | tag           | u8   | 2, or 5 for an LP event        |
+/// | 1         | 1    | side          | u8   | 0=buy X, 1=sell X, 2/3=LP event|
 /// | 2         | 8    | input_amount  | u64  | Input token amount (1e9 scale) |
 /// | 10        | 8    | output_amount | u64  | Output token amount            |
 /// | 18        | 8    | reserve_x     | u64  | Post-trade X reserve           |
@@ -24,6 +23,82 @@ pub const SWAP_INSTRUCTION_SIZE: usize = INSTRUCTION_SIZE + STORAGE_SIZE; // 104
 /// | 42        | 1024 | storage       | [u8] | Current storage state          |
 pub const AFTER_SWAP_SIZE: usize = 42 + STORAGE_SIZE; // 1066
 
+pub const AFTER_SWAP_TAG: u8 = 2;
+/// Tag of the LP event call, sent in the after_swap layout only when
+/// `SimulationConfig::liquidity_event_callbacks` is set, since after_swap handlers that
+/// skip the tag check would read it as a sell.
+pub const LIQUIDITY_EVENT_TAG: u8 = 5;
+
+/// LP event `side` for a deposit: `input_amount` and `output_amount` are the X and Y added,
+/// and the reserves are the new, deeper ones.
+pub const SIDE_DEPOSIT: u8 = 2;
+/// LP event `side` for a withdrawal: the X and Y removed.
+pub const SIDE_WITHDRAW: u8 = 3;
+
+/// One field of an instruction layout. Integers are little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayoutField {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+    /// `u8`, `u64`, or `bytes`.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub ty: &'static str,
+}
+
+/// Machine-readable form of the layout tables above, for tooling that would otherwise
+/// re-declare the offsets by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InstructionLayout {
+    pub name: &'static str,
+    pub size: usize,
+    pub fields: &'static [LayoutField],
+}
+
+const fn field(name: &'static str, offset: usize, size: usize, ty: &'static str) -> LayoutField {
+    LayoutField {
+        name,
+        offset,
+        size,
+        ty,
+    }
+}
+
+pub const SWAP_LAYOUT: InstructionLayout = InstructionLayout {
+    name: "compute_swap",
+    size: SWAP_INSTRUCTION_SIZE,
+    fields: &[
+        field("side", 0, 1, "u8"),
+        field("input_amount", 1, 8, "u64"),
+        field("reserve_x", 9, 8, "u64"),
+        field("reserve_y", 17, 8, "u64"),
+        field("storage", INSTRUCTION_SIZE, STORAGE_SIZE, "bytes"),
+    ],
+};
+
+pub const AFTER_SWAP_LAYOUT: InstructionLayout = InstructionLayout {
+    name: "after_swap",
+    size: AFTER_SWAP_SIZE,
+    fields: &[
+        field("tag", 0, 1, "u8"),
+        field("side", 1, 1, "u8"),
+        field("input_amount", 2, 8, "u64"),
+        field("output_amount", 10, 8, "u64"),
+        field("reserve_x", 18, 8, "u64"),
+        field("reserve_y", 26, 8, "u64"),
+        field("step", 34, 8, "u64"),
+        field("storage", 42, STORAGE_SIZE, "bytes"),
+    ],
+};
+
+impl InstructionLayout {
+    pub fn field(&self, name: &str) -> Option<&LayoutField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
 pub fn encode_instruction(
     side: u8,
     input_amount: u64,
@@ -71,9 +146,34 @@ pub fn encode_after_swap(
     reserve_y: u64,
     step: u64,
     storage: &[u8],
+) -> Vec<u8> {
+    encode_after_swap_tagged(
+        AFTER_SWAP_TAG,
+        side,
+        input_amount,
+        output_amount,
+        reserve_x,
+        reserve_y,
+        step,
+        storage,
+    )
+}
+
+/// The after_swap layout under `tag`: [`AFTER_SWAP_TAG`] for trades, [`LIQUIDITY_EVENT_TAG`]
+/// for LP events.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_after_swap_tagged(
+    tag: u8,
+    side: u8,
+    input_amount: u64,
+    output_amount: u64,
+    reserve_x: u64,
+    reserve_y: u64,
+    step: u64,
+    storage: &[u8],
 ) -> Vec<u8> {
     let mut data = vec![0u8; AFTER_SWAP_SIZE];
-    data[0] = 2; // tag
+    data[0] = tag;
     data[1] = side;
     data[2..10].copy_from_slice(&input_amount.to_le_bytes());
     data[10..18].copy_from_slice(&output_amount.to_le_bytes());
@@ -137,6 +237,42 @@ mod tests {
         assert_eq!(&data[25..], &storage[..]);
     }
 
+    #[test]
+    fn test_layouts_match_encoders() {
+        let read_u64 = |data: &[u8], layout: &InstructionLayout, name: &str| {
+            let f = layout.field(name).unwrap();
+            u64::from_le_bytes(data[f.offset..f.offset + f.size].try_into().unwrap())
+        };
+        for layout in [SWAP_LAYOUT, AFTER_SWAP_LAYOUT] {
+            let last = layout.fields.last().unwrap();
+            assert_eq!(last.offset + last.size, layout.size, "{}", layout.name);
+            for pair in layout.fields.windows(2) {
+                assert_eq!(pair[0].offset + pair[0].size, pair[1].offset, "{}", layout.name);
+            }
+        }
+
+        let swap = encode_swap_instruction(1, 11, 22, 33, &[0xEE; STORAGE_SIZE]);
+        assert_eq!(swap[SWAP_LAYOUT.field("side").unwrap().offset], 1);
+        assert_eq!(read_u64(&swap, &SWAP_LAYOUT, "input_amount"), 11);
+        assert_eq!(read_u64(&swap, &SWAP_LAYOUT, "reserve_x"), 22);
+        assert_eq!(read_u64(&swap, &SWAP_LAYOUT, "reserve_y"), 33);
+
+        let after = encode_after_swap(1, 11, 22, 33, 44, 55, &[0xEE; STORAGE_SIZE]);
+        assert_eq!(after[AFTER_SWAP_LAYOUT.field("tag").unwrap().offset], 2);
+        assert_eq!(read_u64(&after, &AFTER_SWAP_LAYOUT, "output_amount"), 22);
+        assert_eq!(read_u64(&after, &AFTER_SWAP_LAYOUT, "reserve_y"), 44);
+        assert_eq!(read_u64(&after, &AFTER_SWAP_LAYOUT, "step"), 55);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_layout_serializes_field_types() {
+        let json = serde_json::to_value(SWAP_LAYOUT).unwrap();
+        assert_eq!(json["fields"][1]["name"], "input_amount");
+        assert_eq!(json["fields"][1]["type"], "u64");
+        assert_eq!(json["size"], SWAP_INSTRUCTION_SIZE);
+    }
+
     #[test]
     fn test_after_swap_roundtrip() {
         let storage = [0xCD; STORAGE_SIZE];
@@ -1,5 +1,8 @@
 pub mod config;
+pub mod hash;
 pub mod instruction;
 pub mod nano;
 pub mod normalizer;
 pub mod result;
+pub mod rng;
+pub mod scoring;
@@ -22,6 +22,74 @@ pub fn nano_to_f64(value: u64) -> f64 {
     value as f64 / NANO_SCALE_F64
 }
 
+/// Decimals below which the default 20,000 Y pool still fits in a `u64` with room to trade.
+pub const MAX_TOKEN_DECIMALS: u8 = 12;
+
+/// Decimals of the integer amounts programs see for each token, e.g. 6 for a USDC-like
+/// quote. Both default to 9, the nano scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenDecimals {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Default for TokenDecimals {
+    fn default() -> Self {
+        Self { x: 9, y: 9 }
+    }
+}
+
+impl TokenDecimals {
+    #[inline]
+    pub fn x_to_units(&self, value: f64) -> u64 {
+        f64_to_units(value, self.x)
+    }
+
+    #[inline]
+    pub fn y_to_units(&self, value: f64) -> u64 {
+        f64_to_units(value, self.y)
+    }
+
+    #[inline]
+    pub fn units_to_x(&self, units: u64) -> f64 {
+        units_to_f64(units, self.x)
+    }
+
+    #[inline]
+    pub fn units_to_y(&self, units: u64) -> f64 {
+        units_to_f64(units, self.y)
+    }
+}
+
+#[inline]
+fn scale(decimals: u8) -> f64 {
+    10f64.powi(decimals as i32)
+}
+
+/// [`f64_to_nano`] for a token with `decimals` decimals; identical at 9.
+#[inline]
+pub fn f64_to_units(value: f64, decimals: u8) -> u64 {
+    if value.is_nan() || value <= 0.0 {
+        return 0;
+    }
+    if value.is_infinite() {
+        return u64::MAX;
+    }
+    let scaled = value * scale(decimals);
+    if scaled >= u64::MAX as f64 {
+        u64::MAX
+    } else {
+        scaled as u64
+    }
+}
+
+/// [`nano_to_f64`] for a token with `decimals` decimals; identical at 9.
+#[inline]
+pub fn units_to_f64(value: u64, decimals: u8) -> f64 {
+    value as f64 / scale(decimals)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,6 +109,24 @@ mod tests {
         assert_eq!(nano_to_f64(NANO_SCALE), 1.0);
     }
 
+    #[test]
+    fn test_units_match_nano_at_nine_decimals() {
+        for value in [0.0, 1e-9, 0.1, 123.456789, 20_000.0, 1e12] {
+            assert_eq!(f64_to_units(value, 9), f64_to_nano(value));
+            let units = f64_to_nano(value);
+            assert_eq!(units_to_f64(units, 9), nano_to_f64(units));
+        }
+    }
+
+    #[test]
+    fn test_token_decimals_scale_each_side() {
+        let decimals = TokenDecimals { x: 9, y: 6 };
+        assert_eq!(decimals.x_to_units(1.5), 1_500_000_000);
+        assert_eq!(decimals.y_to_units(1.5), 1_500_000);
+        assert_eq!(decimals.units_to_y(2_500_000), 2.5);
+        assert_eq!(decimals.units_to_x(NANO_SCALE), 1.0);
+    }
+
     #[test]
     fn test_invalid_values_clamp_to_zero() {
         assert_eq!(f64_to_nano(-1.0), 0);
@@ -33,12 +33,12 @@ pub fn compute_swap(data: &[u8]) -> u64 {
         0 => {
             let net = input_amount * (10000 - fee_bps) / 10000;
             let new_ry = reserve_y + net;
-            reserve_x.saturating_sub((k + new_ry - 1) / new_ry) as u64
+            reserve_x.saturating_sub(k.div_ceil(new_ry)) as u64
         }
         1 => {
             let net = input_amount * (10000 - fee_bps) / 10000;
             let new_rx = reserve_x + net;
-            reserve_y.saturating_sub((k + new_rx - 1) / new_rx) as u64
+            reserve_y.saturating_sub(k.div_ceil(new_rx)) as u64
         }
         _ => 0,
     }
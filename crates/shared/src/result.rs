@@ -1,10 +1,714 @@
+use std::fmt;
+
+use crate::hash::Fnv64;
+use crate::scoring::RiskPolicy;
+
+/// Maximum number of individual shape issues kept per simulation; the rest are only counted.
+pub const MAX_SHAPE_EXAMPLES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeIssueKind {
+    Monotonicity,
+    Concavity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeSeverity {
+    /// Within the checker's tolerance, but closer to a violation than a clean curve gets.
+    Warning,
+    /// Outside the checker's tolerance.
+    Violation,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShapeIssue {
+    pub kind: ShapeIssueKind,
+    pub severity: ShapeSeverity,
+    /// Which search sampled the offending points, e.g. "router buy split search".
+    pub context: String,
+    pub message: String,
+}
+
+impl fmt::Display for ShapeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Shape-check findings for the submission over one simulation.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShapeReport {
+    pub warnings: u32,
+    pub violations: u32,
+    /// The first `MAX_SHAPE_EXAMPLES` issues, in the order they were found.
+    pub examples: Vec<ShapeIssue>,
+}
+
+impl ShapeReport {
+    pub fn record(&mut self, issue: ShapeIssue) {
+        match issue.severity {
+            ShapeSeverity::Warning => self.warnings += 1,
+            ShapeSeverity::Violation => self.violations += 1,
+        }
+        if self.examples.len() < MAX_SHAPE_EXAMPLES {
+            self.examples.push(issue);
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.warnings == 0 && self.violations == 0
+    }
+}
+
+/// The agent on the other side of a submission trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Counterparty {
+    Arbitrageur,
+    /// Uninformed retail orders.
+    Retail,
+    /// Retail orders tagged informed, whose direction follows the next price move.
+    Informed,
+}
+
+impl Counterparty {
+    pub const ALL: [Counterparty; 3] = [
+        Counterparty::Arbitrageur,
+        Counterparty::Retail,
+        Counterparty::Informed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Counterparty::Arbitrageur => "arbitrageur",
+            Counterparty::Retail => "retail",
+            Counterparty::Informed => "informed",
+        }
+    }
+}
+
+/// A venue's edge and trade count split by counterparty; the edges sum to the net flow
+/// edge, before any inventory revaluation.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EdgeBreakdown {
+    edges: [f64; Counterparty::ALL.len()],
+    trades: [u64; Counterparty::ALL.len()],
+}
+
+impl EdgeBreakdown {
+    /// Record one trade against `counterparty`.
+    #[inline]
+    pub fn add(&mut self, counterparty: Counterparty, edge: f64) {
+        self.edges[counterparty as usize] += edge;
+        self.trades[counterparty as usize] += 1;
+    }
+
+    pub fn get(&self, counterparty: Counterparty) -> f64 {
+        self.edges[counterparty as usize]
+    }
+
+    pub fn trades(&self, counterparty: Counterparty) -> u64 {
+        self.trades[counterparty as usize]
+    }
+
+    pub fn total(&self) -> f64 {
+        self.edges.iter().sum()
+    }
+
+    pub fn merge(&mut self, other: &EdgeBreakdown) {
+        for (total, edge) in self.edges.iter_mut().zip(other.edges) {
+            *total += edge;
+        }
+        for (total, trades) in self.trades.iter_mut().zip(other.trades) {
+            *total += trades;
+        }
+    }
+}
+
+/// A venue's traded volume split by counterparty, in each asset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VolumeBreakdown {
+    x: [f64; Counterparty::ALL.len()],
+    y: [f64; Counterparty::ALL.len()],
+}
+
+impl VolumeBreakdown {
+    #[inline]
+    pub fn add(&mut self, counterparty: Counterparty, amount_x: f64, amount_y: f64) {
+        self.x[counterparty as usize] += amount_x;
+        self.y[counterparty as usize] += amount_y;
+    }
+
+    pub fn x(&self, counterparty: Counterparty) -> f64 {
+        self.x[counterparty as usize]
+    }
+
+    pub fn y(&self, counterparty: Counterparty) -> f64 {
+        self.y[counterparty as usize]
+    }
+
+    /// Y volume from all retail orders, informed or not.
+    pub fn retail_y(&self) -> f64 {
+        self.y(Counterparty::Retail) + self.y(Counterparty::Informed)
+    }
+
+    pub fn total_x(&self) -> f64 {
+        self.x.iter().sum()
+    }
+
+    pub fn total_y(&self) -> f64 {
+        self.y.iter().sum()
+    }
+
+    pub fn merge(&mut self, other: &VolumeBreakdown) {
+        for (total, x) in self.x.iter_mut().zip(other.x) {
+            *total += x;
+        }
+        for (total, y) in self.y.iter_mut().zip(other.y) {
+            *total += y;
+        }
+    }
+}
+
+/// BPF compute units consumed by an AMM's program calls (`compute_swap` or `after_swap`).
+/// Stays zero for native backends, which are not metered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComputeUsage {
+    pub calls: u64,
+    pub total_cu: u64,
+    /// Fewest CU any call consumed; zero until a call is recorded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_cu: u64,
+    pub max_cu: u64,
+}
+
+impl ComputeUsage {
+    #[inline]
+    pub fn record(&mut self, cu: u64) {
+        self.min_cu = if self.calls == 0 { cu } else { self.min_cu.min(cu) };
+        self.calls += 1;
+        self.total_cu += cu;
+        self.max_cu = self.max_cu.max(cu);
+    }
+
+    /// Fold in usage counted separately, e.g. another simulation's.
+    pub fn merge(&mut self, other: &ComputeUsage) {
+        if other.calls == 0 {
+            return;
+        }
+        self.min_cu = if self.calls == 0 { other.min_cu } else { self.min_cu.min(other.min_cu) };
+        self.calls += other.calls;
+        self.total_cu += other.total_cu;
+        self.max_cu = self.max_cu.max(other.max_cu);
+    }
+
+    pub fn avg_cu(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.total_cu as f64 / self.calls as f64
+        }
+    }
+
+    /// Usage accumulated since an `earlier` snapshot of the same counter (`min_cu` and
+    /// `max_cu` are not windowed and are carried over as-is).
+    pub fn since(&self, earlier: &ComputeUsage) -> ComputeUsage {
+        ComputeUsage {
+            calls: self.calls - earlier.calls,
+            total_cu: self.total_cu - earlier.total_cu,
+            min_cu: self.min_cu,
+            max_cu: self.max_cu,
+        }
+    }
+}
+
+/// Trades whose executed output differed from the quote their sizing was planned on, e.g.
+/// because the submission's `compute_swap` behaves differently at execution time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuoteDivergence {
+    /// Trades checked against their quote.
+    pub checked: u32,
+    /// Of those, trades whose output differed.
+    pub diverged: u32,
+    /// Largest difference, relative to the quoted output.
+    pub max_rel: f64,
+    /// Realized less quoted output, valued in Y: positive when the trader got more.
+    pub net_y: f64,
+}
+
+impl QuoteDivergence {
+    /// Check a trade that was quoted `quoted` and delivered `realized`, in an output token
+    /// worth `y_per_unit` Y.
+    pub fn record(&mut self, quoted: f64, realized: f64, y_per_unit: f64) {
+        self.checked += 1;
+        if realized == quoted {
+            return;
+        }
+        self.diverged += 1;
+        if quoted > 0.0 {
+            self.max_rel = self.max_rel.max((realized - quoted).abs() / quoted);
+        }
+        self.net_y += (realized - quoted) * y_per_unit;
+    }
+}
+
+/// Wall-clock time one simulation spent in each engine component, in nanoseconds. Only
+/// measured under `SimulationConfig::profile_components`; all zero otherwise. The searches'
+/// times include the program calls they make, so the fields overlap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComponentTiming {
+    /// Arbitrage searches, including the trades they execute.
+    pub arb_ns: u64,
+    /// Retail order routing: split searches and fills.
+    pub router_ns: u64,
+    /// Submission program calls (quotes and `after_swap`), wherever they were made.
+    pub submission_program_ns: u64,
+    /// Program calls of every baseline pool.
+    pub baseline_program_ns: u64,
+}
+
+impl ComponentTiming {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn merge(&mut self, other: &ComponentTiming) {
+        self.arb_ns += other.arb_ns;
+        self.router_ns += other.router_ns;
+        self.submission_program_ns += other.submission_program_ns;
+        self.baseline_program_ns += other.baseline_program_ns;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DepletionCause {
+    /// A reserve fell below `SimulationConfig::depletion_reserve_frac` of its starting value.
+    Reserves,
+    /// The pool quoted zero output for every probe size on both sides.
+    NoQuotes,
+}
+
+/// A venue that stopped being able to trade, which ends the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Depletion {
+    /// Engine step (counting warm-up) after which the venue was found dead.
+    pub step: u32,
+    /// 0 for the submission, `1 + i` for baseline `i` (the normalizer is baseline 0).
+    pub venue: u8,
+    pub cause: DepletionCause,
+}
+
+impl fmt::Display for Depletion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let venue = match self.venue {
+            0 => "submission".to_string(),
+            1 => "normalizer".to_string(),
+            v => format!("baseline {}", v - 1),
+        };
+        let cause = match self.cause {
+            DepletionCause::Reserves => "reserves collapsed",
+            DepletionCause::NoQuotes => "no quotes at any size",
+        };
+        write!(f, "{} depleted at step {} ({})", venue, self.step, cause)
+    }
+}
+
+/// A pool's reserves marked at a fair price.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InventoryMark {
+    pub reserve_x: f64,
+    pub reserve_y: f64,
+    pub fair_price: f64,
+}
+
+impl InventoryMark {
+    /// Inventory value in Y.
+    pub fn value(&self) -> f64 {
+        self.reserve_x * self.fair_price + self.reserve_y
+    }
+}
+
+/// Submission pool state at the end of one step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReserveSnapshot {
+    pub step: u32,
+    pub reserve_x: f64,
+    pub reserve_y: f64,
+    pub fair_price: f64,
+}
+
+/// One executed trade on any venue, as recorded on the trade tape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TapeTrade {
+    pub step: u32,
+    /// 0 for the submission, `1 + i` for baseline `i` (the normalizer is baseline 0).
+    pub venue: u8,
+    pub counterparty: Counterparty,
+    pub amm_buys_x: bool,
+    pub amount_x: f64,
+    pub amount_y: f64,
+    pub fair_price: f64,
+}
+
+impl TapeTrade {
+    /// The trader's side in `compute_swap` terms: 0 buys X with Y, 1 sells X for Y.
+    pub fn side(&self) -> u8 {
+        self.amm_buys_x as u8
+    }
+
+    /// What the trader paid the pool.
+    pub fn amount_in(&self) -> f64 {
+        if self.amm_buys_x {
+            self.amount_x
+        } else {
+            self.amount_y
+        }
+    }
+
+    /// What the pool paid the trader.
+    pub fn amount_out(&self) -> f64 {
+        if self.amm_buys_x {
+            self.amount_y
+        } else {
+            self.amount_x
+        }
+    }
+}
+
+/// Upper bounds, in basis points, of the [`CostDistribution`] histogram buckets. A final
+/// bucket holds everything above the last bound.
+pub const COST_BUCKETS_BPS: [f64; 11] =
+    [0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+/// Distribution of a per-fill trading cost in basis points; positive is worse for the trader.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CostDistribution {
+    pub count: u64,
+    pub sum: f64,
+    pub sum_sq: f64,
+    /// Largest cost recorded; meaningless while `count` is 0.
+    pub max: f64,
+    /// Fills per [`COST_BUCKETS_BPS`] bucket.
+    pub buckets: [u64; COST_BUCKETS_BPS.len() + 1],
+}
+
+impl CostDistribution {
+    pub fn record(&mut self, bps: f64) {
+        self.max = if self.count == 0 { bps } else { self.max.max(bps) };
+        self.count += 1;
+        self.sum += bps;
+        self.sum_sq += bps * bps;
+        let bucket = COST_BUCKETS_BPS
+            .iter()
+            .position(|&bound| bps <= bound)
+            .unwrap_or(COST_BUCKETS_BPS.len());
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        let n = self.count as f64;
+        ((self.sum_sq - self.sum * self.sum / n) / (n - 1.0)).max(0.0).sqrt()
+    }
+
+    /// Upper bound of the bucket holding quantile `q` (in [0, 1]): at least a `q` fraction of
+    /// fills cost no more than this. Infinite when the quantile lies past the last bound.
+    pub fn percentile(&self, q: f64) -> f64 {
+        let target = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return COST_BUCKETS_BPS.get(i).copied().unwrap_or(f64::INFINITY);
+            }
+        }
+        f64::INFINITY
+    }
+
+    pub fn merge(&mut self, other: &CostDistribution) {
+        if other.count == 0 {
+            return;
+        }
+        self.max = if self.count == 0 {
+            other.max
+        } else {
+            self.max.max(other.max)
+        };
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        for (total, n) in self.buckets.iter_mut().zip(&other.buckets) {
+            *total += n;
+        }
+    }
+}
+
+/// Execution quality of the retail fills (informed or not) on one venue.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionStats {
+    /// Fill price against the fair price: the trader's all-in cost, fee included.
+    pub slippage: CostDistribution,
+    /// Fill price against the venue's spot price before the order: the fee plus the
+    /// curve's price impact.
+    pub impact: CostDistribution,
+}
+
+impl ExecutionStats {
+    /// Record a fill of `amount_x` for `amount_y`. `amm_buys_x` means the trader sold X.
+    pub fn record(
+        &mut self,
+        amm_buys_x: bool,
+        amount_x: f64,
+        amount_y: f64,
+        fair_price: f64,
+        spot_before: f64,
+    ) {
+        if amount_x <= 0.0 {
+            return;
+        }
+        let price = amount_y / amount_x;
+        let cost_bps = |reference: f64| {
+            let ratio = price / reference;
+            if amm_buys_x {
+                (1.0 - ratio) * 10_000.0
+            } else {
+                (ratio - 1.0) * 10_000.0
+            }
+        };
+        self.slippage.record(cost_bps(fair_price));
+        if spot_before.is_finite() && spot_before > 0.0 {
+            self.impact.record(cost_bps(spot_before));
+        }
+    }
+
+    pub fn trades(&self) -> u64 {
+        self.slippage.count
+    }
+
+    pub fn merge(&mut self, other: &ExecutionStats) {
+        self.slippage.merge(&other.slippage);
+        self.impact.merge(&other.impact);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimResult {
     pub seed: u64,
+    /// Net edge over the scored steps, including `inventory_revaluation`. Zero, like
+    /// `edge_by_counterparty`, when `shape` records a violation (only possible under
+    /// `ShapeViolationPolicy::ZeroEdge`).
     pub submission_edge: f64,
+    pub edge_by_counterparty: EdgeBreakdown,
+    /// Volume traded against the submission over scored steps.
+    pub volume_by_counterparty: VolumeBreakdown,
+    /// Largest fall of cumulative submission edge from its running peak (starting at zero)
+    /// over the scored steps.
+    pub max_drawdown: f64,
+    /// Loss-versus-rebalancing of the submission: `sum of sigma^2 * P^2 / 2 * |dx/dP|` over
+    /// scored steps, with `|dx/dP|` measured from its curve after each step's arbitrage.
+    pub lvr: f64,
+    /// Implied fee revenue (Y) of each venue: zero-fee constant-product output minus quoted
+    /// output on every scored trade, valued at the step's fair price.
+    pub fee_revenue: f64,
+    pub normalizer_fee_revenue: f64,
+    /// Submission reserves when scoring starts (after warm-up), at the fair price then.
+    pub inventory_start: InventoryMark,
+    /// Submission reserves after the last step, at the terminal fair price.
+    pub inventory_end: InventoryMark,
+    /// Value in Y of the reserves liquidity events added to the submission pool during the
+    /// scored steps, at the fair price when each happened; negative for net withdrawals.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub liquidity_added: f64,
+    /// Inventory revaluation included in `submission_edge`; zero unless the config's
+    /// `edge_mode` is `InventoryAdjusted`, which makes the edge equal `inventory_pnl()`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub inventory_revaluation: f64,
+    /// The same for the normalizer, included in `normalizer_edge()`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub normalizer_inventory_revaluation: f64,
+    /// Snapshots every `trajectory_stride` steps; empty unless the config asks for them.
+    pub trajectory: Vec<ReserveSnapshot>,
+    /// Every executed trade in execution order; empty unless the config sets `record_tape`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tape: Vec<TapeTrade>,
+    /// The normalizer's edge and trades over the same simulation, measured the same way.
+    pub normalizer_edge_by_counterparty: EdgeBreakdown,
+    pub normalizer_volume_by_counterparty: VolumeBreakdown,
+    /// The same for each of `SimulationConfig::extra_venues`, in order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_venue_edge_by_counterparty: Vec<EdgeBreakdown>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_venue_volume_by_counterparty: Vec<VolumeBreakdown>,
+    /// Retail execution quality on every venue over the scored steps, indexed like
+    /// `TapeTrade::venue`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub execution_by_venue: Vec<ExecutionStats>,
+    /// Rolling hash of every executed trade (both venues) in execution order.
+    pub trade_hash: u64,
+    /// `trade_hash` snapshots taken every `TRADE_HASH_CHECKPOINT_STEPS` steps.
+    pub trade_hash_checkpoints: Vec<u64>,
+    /// `trade_hash` extended with every venue's final reserves and the submission's final
+    /// storage. Equal checksums mean two runs (e.g. native and BPF) made byte-identical
+    /// decisions and ended in the same state.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub checksum: u64,
+    pub shape: ShapeReport,
+    pub submission_compute: ComputeUsage,
+    /// BPF compute units consumed by the submission's `after_swap` calls.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub submission_after_swap_compute: ComputeUsage,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timing: ComponentTiming,
+    /// Failed BPF `after_swap` calls by the submission; only counted under
+    /// `AfterSwapFailurePolicy::CountAndReport`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub after_swap_failures: u32,
+    /// Scored retail orders abandoned because their best routed execution would have cost
+    /// more than their slippage limit or delivered less than their minimum output.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub abandoned_orders: u32,
+    /// Scored retail orders only partly filled, under `SimulationConfig::retail_partial_fills`
+    /// or cut to the venues' `SimulationConfig::max_trade_fraction` caps.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub partial_fills: u32,
+    /// Scored retail orders dropped before routing because their input was at or below
+    /// `SimulationConfig::min_trade_size`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dust_orders: u32,
+    /// Total Y notional of the orders counted in `dust_orders`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dust_notional: f64,
+    /// Scored retail orders left unfilled because their best split was quoted beyond
+    /// `SimulationConfig::price_guard_bps` from the fair price, or at nothing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub guarded_orders: u32,
+    /// Scored steps (events, under the event-driven clock) in which the submission was down:
+    /// it quoted nothing at any probe size, so arbitrage and routing skipped it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub submission_down_steps: u32,
+    /// Scored arbitrage trades on the submission checked against the quote they were sized
+    /// on; any divergence means edge was booked on a price the search never saw.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arb_quote_divergence: QuoteDivergence,
+    /// Scored retail fills on the submission checked against the output the split search
+    /// quoted for them. Fills that would have fallen materially short of it were re-routed
+    /// to the baselines instead, so what remains is mostly the submission paying more.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retail_quote_divergence: QuoteDivergence,
+    /// Set when a venue died and the simulation stopped early; the remaining steps never ran.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub depletion: Option<Depletion>,
+}
+
+impl SimResult {
+    /// Submission edge from trades with the arbitrageur (normally negative).
+    pub fn arb_edge(&self) -> f64 {
+        self.edge_by_counterparty.get(Counterparty::Arbitrageur)
+    }
+
+    /// Submission edge from uninformed retail fills.
+    pub fn retail_edge(&self) -> f64 {
+        self.edge_by_counterparty.get(Counterparty::Retail)
+    }
+
+    /// Submission edge from informed retail fills (normally negative: adverse selection).
+    pub fn informed_edge(&self) -> f64 {
+        self.edge_by_counterparty.get(Counterparty::Informed)
+    }
+
+    pub fn normalizer_edge(&self) -> f64 {
+        self.normalizer_edge_by_counterparty.total() + self.normalizer_inventory_revaluation
+    }
+
+    /// Submission edge minus normalizer edge. Both venues face the same price path and
+    /// flow, so much of the per-seed noise cancels.
+    pub fn relative_edge(&self) -> f64 {
+        self.submission_edge - self.normalizer_edge()
+    }
+
+    /// Edge earned by every venue together: the submission, the normalizer and any extra
+    /// venues.
+    pub fn market_edge(&self) -> f64 {
+        self.submission_edge
+            + self.normalizer_edge()
+            + self
+                .extra_venue_edge_by_counterparty
+                .iter()
+                .map(EdgeBreakdown::total)
+                .sum::<f64>()
+    }
+
+    /// Submission share of [`Self::market_edge`], or `None` when the venues earned nothing
+    /// between them. Unlike raw edge, it stays comparable across hyperparameter draws that
+    /// grow or shrink the whole pie.
+    pub fn edge_share(&self) -> Option<f64> {
+        let market = self.market_edge();
+        (market > 0.0).then(|| self.submission_edge / market)
+    }
+
+    /// Mark-to-market PnL of the submission's inventory over the scored steps. Unlike edge,
+    /// which values each trade at the fair price when it happens, this includes the drift of
+    /// the inventory's value as the price moves. LP deposits and withdrawals are not PnL.
+    pub fn inventory_pnl(&self) -> f64 {
+        self.inventory_end.value() - self.inventory_start.value() - self.liquidity_added
+    }
+
+    /// Submission share of the retail volume (Y) routed to all venues.
+    pub fn retail_volume_share(&self) -> f64 {
+        let submission = self.volume_by_counterparty.retail_y();
+        let total = submission
+            + self.normalizer_volume_by_counterparty.retail_y()
+            + self
+                .extra_venue_volume_by_counterparty
+                .iter()
+                .map(VolumeBreakdown::retail_y)
+                .sum::<f64>();
+        if total > 0.0 {
+            submission / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Index of the first checkpoint at which two runs' trade sequences differ, or `None`
+    /// when every shared checkpoint matches. Divergence lies in the steps leading up to it.
+    pub fn first_divergent_checkpoint(&self, other: &SimResult) -> Option<usize> {
+        self.trade_hash_checkpoints
+            .iter()
+            .zip(&other.trade_hash_checkpoints)
+            .position(|(a, b)| a != b)
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BatchResult {
     pub results: Vec<SimResult>,
     pub total_edge: f64,
@@ -23,6 +727,128 @@ impl BatchResult {
         self.results.len()
     }
 
+    /// Combined trade hash over all simulations, in result order.
+    pub fn trade_hash(&self) -> u64 {
+        let mut hasher = Fnv64::new();
+        for result in &self.results {
+            hasher.write_u64(result.seed);
+            hasher.write_u64(result.trade_hash);
+        }
+        hasher.finish()
+    }
+
+    /// Combined engine checksum over all simulations, in result order.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv64::new();
+        for result in &self.results {
+            hasher.write_u64(result.seed);
+            hasher.write_u64(result.checksum);
+        }
+        hasher.finish()
+    }
+
+    /// Submission edge by counterparty summed over all simulations.
+    pub fn edge_by_counterparty(&self) -> EdgeBreakdown {
+        let mut total = EdgeBreakdown::default();
+        for result in &self.results {
+            total.merge(&result.edge_by_counterparty);
+        }
+        total
+    }
+
+    /// Normalizer edge by counterparty summed over all simulations.
+    pub fn normalizer_edge_by_counterparty(&self) -> EdgeBreakdown {
+        let mut total = EdgeBreakdown::default();
+        for result in &self.results {
+            total.merge(&result.normalizer_edge_by_counterparty);
+        }
+        total
+    }
+
+    /// Submission volume by counterparty summed over all simulations.
+    pub fn volume_by_counterparty(&self) -> VolumeBreakdown {
+        let mut total = VolumeBreakdown::default();
+        for result in &self.results {
+            total.merge(&result.volume_by_counterparty);
+        }
+        total
+    }
+
+    /// Normalizer volume by counterparty summed over all simulations.
+    pub fn normalizer_volume_by_counterparty(&self) -> VolumeBreakdown {
+        let mut total = VolumeBreakdown::default();
+        for result in &self.results {
+            total.merge(&result.normalizer_volume_by_counterparty);
+        }
+        total
+    }
+
+    /// Edge by counterparty of each extra venue, summed over all simulations.
+    pub fn extra_venue_edge_by_counterparty(&self) -> Vec<EdgeBreakdown> {
+        let mut totals: Vec<EdgeBreakdown> = Vec::new();
+        for result in &self.results {
+            let venues = &result.extra_venue_edge_by_counterparty;
+            totals.resize_with(totals.len().max(venues.len()), EdgeBreakdown::default);
+            for (total, venue) in totals.iter_mut().zip(venues) {
+                total.merge(venue);
+            }
+        }
+        totals
+    }
+
+    /// Volume by counterparty of each extra venue, summed over all simulations.
+    pub fn extra_venue_volume_by_counterparty(&self) -> Vec<VolumeBreakdown> {
+        let mut totals: Vec<VolumeBreakdown> = Vec::new();
+        for result in &self.results {
+            let venues = &result.extra_venue_volume_by_counterparty;
+            totals.resize_with(totals.len().max(venues.len()), VolumeBreakdown::default);
+            for (total, venue) in totals.iter_mut().zip(venues) {
+                total.merge(venue);
+            }
+        }
+        totals
+    }
+
+    /// Retail execution quality of each venue, merged over all simulations.
+    pub fn execution_by_venue(&self) -> Vec<ExecutionStats> {
+        let mut totals: Vec<ExecutionStats> = Vec::new();
+        for result in &self.results {
+            let venues = &result.execution_by_venue;
+            totals.resize_with(totals.len().max(venues.len()), ExecutionStats::default);
+            for (total, venue) in totals.iter_mut().zip(venues) {
+                total.merge(venue);
+            }
+        }
+        totals
+    }
+
+    /// Component timings summed over all simulations.
+    pub fn timing(&self) -> ComponentTiming {
+        let mut total = ComponentTiming::default();
+        for result in &self.results {
+            total.merge(&result.timing);
+        }
+        total
+    }
+
+    /// Submission quote CU summed over all simulations.
+    pub fn submission_compute(&self) -> ComputeUsage {
+        let mut total = ComputeUsage::default();
+        for result in &self.results {
+            total.merge(&result.submission_compute);
+        }
+        total
+    }
+
+    /// Submission `after_swap` CU summed over all simulations.
+    pub fn submission_after_swap_compute(&self) -> ComputeUsage {
+        let mut total = ComputeUsage::default();
+        for result in &self.results {
+            total.merge(&result.submission_after_swap_compute);
+        }
+        total
+    }
+
     pub fn avg_edge(&self) -> f64 {
         if self.results.is_empty() {
             0.0
@@ -30,4 +856,183 @@ impl BatchResult {
             self.total_edge / self.results.len() as f64
         }
     }
+
+    pub fn avg_normalizer_edge(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results.iter().map(SimResult::normalizer_edge).sum::<f64>()
+                / self.results.len() as f64
+        }
+    }
+
+    pub fn avg_lvr(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results.iter().map(|r| r.lvr).sum::<f64>() / self.results.len() as f64
+        }
+    }
+
+    pub fn avg_fee_revenue(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results.iter().map(|r| r.fee_revenue).sum::<f64>() / self.results.len() as f64
+        }
+    }
+
+    pub fn avg_normalizer_fee_revenue(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results
+                .iter()
+                .map(|r| r.normalizer_fee_revenue)
+                .sum::<f64>()
+                / self.results.len() as f64
+        }
+    }
+
+    pub fn avg_inventory_pnl(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results.iter().map(|r| r.inventory_pnl()).sum::<f64>() / self.results.len() as f64
+        }
+    }
+
+    fn edges(&self) -> Vec<f64> {
+        self.results.iter().map(|r| r.submission_edge).collect()
+    }
+
+    /// Sample standard deviation of per-simulation edge.
+    pub fn edge_std_dev(&self) -> f64 {
+        let n = self.results.len();
+        if n < 2 {
+            return 0.0;
+        }
+        let mean = self.avg_edge();
+        let var = self
+            .results
+            .iter()
+            .map(|r| (r.submission_edge - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1) as f64;
+        var.sqrt()
+    }
+
+    /// Half-width of the normal-approximation confidence interval on average edge, for a
+    /// two-sided critical value `z` (1.96 for 95%). Infinite below two simulations.
+    pub fn edge_ci_half_width(&self, z: f64) -> f64 {
+        let n = self.results.len();
+        if n < 2 {
+            return f64::INFINITY;
+        }
+        z * self.edge_std_dev() / (n as f64).sqrt()
+    }
+
+    /// Per-simulation edge at quantile `q` in [0, 1] (nearest rank), e.g. 0.05 for the
+    /// 5th percentile.
+    pub fn edge_percentile(&self, q: f64) -> f64 {
+        let mut edges = self.edges();
+        if edges.is_empty() {
+            return 0.0;
+        }
+        edges.sort_by(f64::total_cmp);
+        let rank = (q.clamp(0.0, 1.0) * edges.len() as f64).ceil() as usize;
+        edges[rank.clamp(1, edges.len()) - 1]
+    }
+
+    /// Expected loss over the worst `tail_fraction` of simulations, as in [`RiskPolicy`].
+    pub fn edge_cvar(&self, tail_fraction: f64) -> f64 {
+        RiskPolicy {
+            lambda: 0.0,
+            tail_fraction,
+        }
+        .score_edges(&self.edges())
+        .cvar
+    }
+
+    /// The `n` lowest-edge simulations as `(seed, edge)`, worst first.
+    pub fn worst_seeds(&self, n: usize) -> Vec<(u64, f64)> {
+        let mut seeds: Vec<(u64, f64)> = self
+            .results
+            .iter()
+            .map(|r| (r.seed, r.submission_edge))
+            .collect();
+        seeds.sort_by(|a, b| a.1.total_cmp(&b.1));
+        seeds.truncate(n);
+        seeds
+    }
+
+    pub fn avg_max_drawdown(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.results.iter().map(|r| r.max_drawdown).sum::<f64>() / self.results.len() as f64
+        }
+    }
+
+    /// Deepest single-simulation drawdown in the batch.
+    pub fn max_drawdown(&self) -> f64 {
+        self.results.iter().map(|r| r.max_drawdown).fold(0.0, f64::max)
+    }
+
+    /// Submission and normalizer shares of the edge earned by all venues, pooled over the
+    /// batch (total edge over total market edge, not a mean of per-seed shares), or `None`
+    /// when the venues earned nothing between them.
+    pub fn edge_shares(&self) -> Option<(f64, f64)> {
+        let market: f64 = self.results.iter().map(SimResult::market_edge).sum();
+        let normalizer: f64 = self.results.iter().map(SimResult::normalizer_edge).sum();
+        (market > 0.0).then(|| (self.total_edge / market, normalizer / market))
+    }
+
+    /// Average of [`SimResult::relative_edge`].
+    pub fn avg_relative_edge(&self) -> f64 {
+        self.avg_edge() - self.avg_normalizer_edge()
+    }
+}
+
+/// Two submissions run over the same configs, in the same order. A seed fixes the price
+/// path and retail order stream, so per-seed edge differences cancel most market noise.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PairedBatch {
+    pub a: BatchResult,
+    pub b: BatchResult,
+}
+
+impl PairedBatch {
+    /// `(seed, b edge - a edge)` for each simulation.
+    pub fn edge_differences(&self) -> Vec<(u64, f64)> {
+        self.a
+            .results
+            .iter()
+            .zip(&self.b.results)
+            .map(|(a, b)| (a.seed, b.submission_edge - a.submission_edge))
+            .collect()
+    }
+
+    /// Average per-seed edge of B minus A.
+    pub fn avg_edge_difference(&self) -> f64 {
+        let diffs = self.edge_differences();
+        if diffs.is_empty() {
+            return 0.0;
+        }
+        diffs.iter().map(|(_, d)| d).sum::<f64>() / diffs.len() as f64
+    }
+
+    /// Standard error of [`Self::avg_edge_difference`], from the spread of the per-seed
+    /// differences rather than of either batch.
+    pub fn edge_difference_std_error(&self) -> f64 {
+        let diffs = self.edge_differences();
+        let n = diffs.len();
+        if n < 2 {
+            return 0.0;
+        }
+        let mean = self.avg_edge_difference();
+        let var = diffs.iter().map(|(_, d)| (d - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        (var / n as f64).sqrt()
+    }
 }
@@ -0,0 +1,93 @@
+//! Seeds of the independent RNG streams one simulation draws from.
+//!
+//! Each stochastic component gets its own stream, seeded by hashing the simulation seed
+//! with a fixed component id (and an instance index, for components that come in several).
+//! A stream therefore depends only on the seed and its own id: adding a component takes a
+//! new id and leaves every existing stream, and so every recorded result, unchanged.
+
+use crate::hash::Fnv64;
+
+/// Stable ids of the stochastic components. Never renumber or reuse an id; it is hashed
+/// into every stream seed derived for that component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum Component {
+    /// Hyperparameter draws (`HyperparameterVariance::apply`).
+    Config = 0,
+    /// The fair price path.
+    Price = 1,
+    /// Retail order arrivals and sizes; one instance per retail trader.
+    Retail = 2,
+    /// Arbitrageur profit thresholds and sizing.
+    Arbitrageur = 3,
+    /// Tagging of informed retail orders.
+    Informed = 4,
+    /// Steps and directions of random price gaps.
+    PriceGaps = 5,
+    /// Switches between calm and volatile price regimes.
+    VolatilityRegimes = 6,
+    /// Arrivals, sizes and directions of momentum retail traders.
+    Momentum = 7,
+    /// Whether the arbitrageur is active in each step.
+    ArbArrival = 8,
+    /// Perturbations of the arbitrageur's trade sizes.
+    ArbSizeNoise = 9,
+    /// Observation noise on the fair price the router sees.
+    OracleNoise = 10,
+    /// Arrival times and kinds of events under the event-driven clock.
+    EventClock = 11,
+    /// Where the arbitrageur acts among each step's retail orders.
+    Sequencing = 12,
+}
+
+/// Seed of instance `index` of `component`'s stream for simulation `seed`. Components with
+/// a single stream use index 0.
+pub fn substream_seed(seed: u64, component: Component, index: u32) -> u64 {
+    let mut hasher = Fnv64::new();
+    hasher.write_u64(seed);
+    hasher.write_u32(component as u32);
+    hasher.write_u32(index);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Component; 13] = [
+        Component::Config,
+        Component::Price,
+        Component::Retail,
+        Component::Arbitrageur,
+        Component::Informed,
+        Component::PriceGaps,
+        Component::VolatilityRegimes,
+        Component::Momentum,
+        Component::ArbArrival,
+        Component::ArbSizeNoise,
+        Component::OracleNoise,
+        Component::EventClock,
+        Component::Sequencing,
+    ];
+
+    #[test]
+    fn test_streams_are_distinct_across_components_indices_and_seeds() {
+        let mut seen = std::collections::HashSet::new();
+        for seed in 0..64 {
+            for component in ALL {
+                for index in 0..4 {
+                    assert!(
+                        seen.insert(substream_seed(seed, component, index)),
+                        "collision at seed {seed}, {component:?}[{index}]"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_derivation_is_pinned() {
+        // Changing this changes every simulation; update deliberately or not at all.
+        assert_eq!(substream_seed(0, Component::Config, 0), 0x8820_1fb9_60ff_6465);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::result::{BatchResult, SimResult};
+
+/// Contest rule for curves the shape checker flags: turns recorded warnings and violations
+/// into edge deductions and disqualification.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShapePenaltyPolicy {
+    /// Edge deducted per recorded warning.
+    pub warning_penalty: f64,
+    /// Edge deducted per recorded violation.
+    pub violation_penalty: f64,
+    /// Disqualify a simulation that records more than this many warnings.
+    pub max_warnings: Option<u32>,
+    /// Disqualify a simulation that records any violation.
+    pub disqualify_on_violation: bool,
+}
+
+impl Default for ShapePenaltyPolicy {
+    /// Warnings are informational; any violation disqualifies.
+    fn default() -> Self {
+        Self {
+            warning_penalty: 0.0,
+            violation_penalty: 0.0,
+            max_warnings: None,
+            disqualify_on_violation: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimScore {
+    pub seed: u64,
+    pub edge: f64,
+    pub penalty: f64,
+    pub disqualified: bool,
+}
+
+impl SimScore {
+    /// Penalized edge; disqualified simulations score zero.
+    pub fn score(&self) -> f64 {
+        if self.disqualified {
+            0.0
+        } else {
+            self.edge - self.penalty
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchScore {
+    pub sims: Vec<SimScore>,
+    pub total_penalty: f64,
+    pub avg_score: f64,
+    pub disqualified_seeds: Vec<u64>,
+}
+
+impl BatchScore {
+    pub fn is_disqualified(&self) -> bool {
+        !self.disqualified_seeds.is_empty()
+    }
+}
+
+impl ShapePenaltyPolicy {
+    pub fn score_sim(&self, result: &SimResult) -> SimScore {
+        let shape = &result.shape;
+        let penalty = shape.warnings as f64 * self.warning_penalty
+            + shape.violations as f64 * self.violation_penalty;
+        let too_many_warnings = self.max_warnings.is_some_and(|max| shape.warnings > max);
+        let violated = self.disqualify_on_violation && shape.violations > 0;
+        SimScore {
+            seed: result.seed,
+            edge: result.submission_edge,
+            penalty,
+            disqualified: too_many_warnings || violated,
+        }
+    }
+
+    pub fn score_batch(&self, batch: &BatchResult) -> BatchScore {
+        let sims: Vec<SimScore> = batch.results.iter().map(|r| self.score_sim(r)).collect();
+        let total_penalty = sims.iter().map(|s| s.penalty).sum();
+        let avg_score = if sims.is_empty() {
+            0.0
+        } else {
+            sims.iter().map(|s| s.score()).sum::<f64>() / sims.len() as f64
+        };
+        let disqualified_seeds = sims
+            .iter()
+            .filter(|s| s.disqualified)
+            .map(|s| s.seed)
+            .collect();
+        BatchScore {
+            sims,
+            total_penalty,
+            avg_score,
+            disqualified_seeds,
+        }
+    }
+}
+
+/// Risk-adjusted contest score: `score = mean(edge) - lambda * CVaR`, where
+/// `CVaR = -mean(worst tail_fraction of per-seed edges)` is the expected loss in the tail
+/// (the worst `ceil(tail_fraction * n)` seeds, at least one).
+///
+/// `lambda = 0` recovers the plain mean. Losing tails (negative tail mean) lower the score;
+/// a tail that still earns raises it, so strategies with no losing seeds are rewarded.
+#[derive(Debug, Clone, Copy)]
+pub struct RiskPolicy {
+    pub lambda: f64,
+    pub tail_fraction: f64,
+}
+
+impl Default for RiskPolicy {
+    /// Half-weight on the expected loss of the worst decile.
+    fn default() -> Self {
+        Self {
+            lambda: 0.5,
+            tail_fraction: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskAdjustedScore {
+    pub mean: f64,
+    pub cvar: f64,
+    pub score: f64,
+}
+
+impl RiskPolicy {
+    pub fn score_edges(&self, edges: &[f64]) -> RiskAdjustedScore {
+        if edges.is_empty() {
+            return RiskAdjustedScore {
+                mean: 0.0,
+                cvar: 0.0,
+                score: 0.0,
+            };
+        }
+        let mean = edges.iter().sum::<f64>() / edges.len() as f64;
+
+        let mut sorted = edges.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let tail_len = ((self.tail_fraction * sorted.len() as f64).ceil() as usize)
+            .clamp(1, sorted.len());
+        let tail_mean = sorted[..tail_len].iter().sum::<f64>() / tail_len as f64;
+        let cvar = -tail_mean;
+
+        RiskAdjustedScore {
+            mean,
+            cvar,
+            score: mean - self.lambda * cvar,
+        }
+    }
+
+    pub fn score_batch(&self, batch: &BatchResult) -> RiskAdjustedScore {
+        let edges: Vec<f64> = batch.results.iter().map(|r| r.submission_edge).collect();
+        self.score_edges(&edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::{ShapeIssue, ShapeIssueKind, ShapeReport, ShapeSeverity};
+
+    fn sim(seed: u64, edge: f64, warnings: u32, violations: u32) -> SimResult {
+        let mut shape = ShapeReport::default();
+        for (n, severity) in [
+            (warnings, ShapeSeverity::Warning),
+            (violations, ShapeSeverity::Violation),
+        ] {
+            for _ in 0..n {
+                shape.record(ShapeIssue {
+                    kind: ShapeIssueKind::Concavity,
+                    severity,
+                    context: "test".to_string(),
+                    message: String::new(),
+                });
+            }
+        }
+        SimResult {
+            seed,
+            submission_edge: edge,
+            shape,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_policy_only_disqualifies_violations() {
+        let policy = ShapePenaltyPolicy::default();
+        let clean = policy.score_sim(&sim(0, 10.0, 3, 0));
+        assert!(!clean.disqualified);
+        assert_eq!(clean.score(), 10.0);
+
+        let violated = policy.score_sim(&sim(1, 10.0, 0, 1));
+        assert!(violated.disqualified);
+        assert_eq!(violated.score(), 0.0);
+    }
+
+    #[test]
+    fn test_penalties_and_warning_cap() {
+        let policy = ShapePenaltyPolicy {
+            warning_penalty: 0.5,
+            violation_penalty: 4.0,
+            max_warnings: Some(2),
+            disqualify_on_violation: false,
+        };
+        let batch = BatchResult::from_results(vec![
+            sim(0, 10.0, 2, 1),
+            sim(1, 10.0, 3, 0),
+            sim(2, 6.0, 0, 0),
+        ]);
+        let score = policy.score_batch(&batch);
+
+        assert!((score.sims[0].score() - 5.0).abs() < 1e-12);
+        assert!(score.sims[1].disqualified);
+        assert!((score.total_penalty - 6.5).abs() < 1e-12);
+        assert!((score.avg_score - 11.0 / 3.0).abs() < 1e-12);
+        assert_eq!(score.disqualified_seeds, vec![1]);
+        assert!(score.is_disqualified());
+    }
+
+    #[test]
+    fn test_risk_score_penalizes_losing_tail() {
+        let policy = RiskPolicy {
+            lambda: 0.5,
+            tail_fraction: 0.1,
+        };
+        // 20 seeds: the worst 2 (10%) average -15.
+        let mut edges = vec![10.0; 18];
+        edges.extend([-10.0, -20.0]);
+        let score = policy.score_edges(&edges);
+
+        assert!((score.mean - 7.5).abs() < 1e-12);
+        assert!((score.cvar - 15.0).abs() < 1e-12);
+        assert!((score.score - 0.0).abs() < 1e-12);
+
+        let plain = RiskPolicy {
+            lambda: 0.0,
+            ..policy
+        };
+        assert_eq!(plain.score_edges(&edges).score, score.mean);
+    }
+
+    #[test]
+    fn test_batch_dispersion_metrics() {
+        let edges = [4.0, -2.0, 6.0, 0.0, 12.0];
+        let batch = BatchResult::from_results(
+            edges
+                .iter()
+                .enumerate()
+                .map(|(i, &edge)| sim(i as u64, edge, 0, 0))
+                .collect(),
+        );
+
+        assert!((batch.edge_std_dev() - 30.0_f64.sqrt()).abs() < 1e-12);
+        assert!((batch.edge_ci_half_width(2.0) - 2.0 * 6.0_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(
+            BatchResult::from_results(vec![sim(0, 1.0, 0, 0)]).edge_ci_half_width(1.96),
+            f64::INFINITY
+        );
+        assert_eq!(batch.edge_percentile(0.05), -2.0);
+        assert_eq!(batch.edge_percentile(0.5), 4.0);
+        assert_eq!(batch.edge_percentile(1.0), 12.0);
+        assert_eq!(batch.worst_seeds(2), vec![(1, -2.0), (3, 0.0)]);
+        assert!((batch.edge_cvar(0.4) - 1.0).abs() < 1e-12);
+        assert_eq!(batch.edge_cvar(0.1), RiskPolicy::default().score_batch(&batch).cvar);
+    }
+
+    #[test]
+    fn test_risk_score_tail_has_at_least_one_seed() {
+        let score = RiskPolicy::default().score_edges(&[4.0, -2.0, 6.0]);
+        assert!((score.cvar - 2.0).abs() < 1e-12);
+        assert_eq!(RiskPolicy::default().score_edges(&[]).score, 0.0);
+    }
+}
@@ -0,0 +1,84 @@
+//! Builds the reference programs embedded by the `prebuilt-programs` feature.
+//!
+//! With the feature on, `programs/normalizer` and `programs/starter` are compiled with
+//! `cargo build-sbf` into `OUT_DIR`, where `src/prebuilt.rs` embeds them. Set
+//! `PROP_AMM_PREBUILT_DIR` to a directory holding `normalizer.so` and `starter.so` to embed
+//! artifacts built elsewhere instead, e.g. on a machine without the Solana SBF toolchain.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PROGRAMS: [&str; 2] = ["normalizer", "starter"];
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PROP_AMM_PREBUILT_DIR");
+    if std::env::var_os("CARGO_FEATURE_PREBUILT_PROGRAMS").is_none() {
+        return;
+    }
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    match std::env::var_os("PROP_AMM_PREBUILT_DIR") {
+        Some(dir) => copy_prebuilt(Path::new(&dir), &out_dir),
+        None => build_programs(&out_dir),
+    }
+}
+
+fn copy_prebuilt(dir: &Path, out_dir: &Path) {
+    for name in PROGRAMS {
+        let artifact = dir.join(format!("{name}.so"));
+        println!("cargo:rerun-if-changed={}", artifact.display());
+        if let Err(err) = std::fs::copy(&artifact, out_dir.join(format!("{name}.so"))) {
+            fail(&format!(
+                "cannot read {} from PROP_AMM_PREBUILT_DIR: {err}",
+                artifact.display()
+            ));
+        }
+    }
+}
+
+fn build_programs(out_dir: &Path) {
+    let manifest_dir = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let programs_dir = manifest_dir.join("../../programs");
+    for name in PROGRAMS {
+        let program = programs_dir.join(name);
+        println!("cargo:rerun-if-changed={}", program.join("src").display());
+        println!(
+            "cargo:rerun-if-changed={}",
+            program.join("Cargo.toml").display()
+        );
+        let mut cargo = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+        cargo
+            .arg("build-sbf")
+            .arg("--manifest-path")
+            .arg(program.join("Cargo.toml"))
+            .arg("--sbf-out-dir")
+            .arg(out_dir)
+            // A target directory of its own, so the nested build never waits on the outer
+            // build's lock.
+            .env("CARGO_TARGET_DIR", out_dir.join("sbf-target"));
+        // The outer build's compiler settings are for the host, not the SBF target.
+        for var in [
+            "RUSTC",
+            "RUSTC_WRAPPER",
+            "RUSTFLAGS",
+            "CARGO_ENCODED_RUSTFLAGS",
+        ] {
+            cargo.env_remove(var);
+        }
+        match cargo.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => fail(&format!(
+                "`cargo build-sbf` failed for programs/{name} ({status})"
+            )),
+            Err(err) => fail(&format!(
+                "cannot run `cargo build-sbf` for programs/{name}: {err}"
+            )),
+        }
+    }
+}
+
+fn fail(reason: &str) -> ! {
+    panic!(
+        "prebuilt-programs: {reason}\n\
+         Install the Solana SBF toolchain so `cargo build-sbf` is on PATH, or set \
+         PROP_AMM_PREBUILT_DIR to a directory holding normalizer.so and starter.so."
+    );
+}
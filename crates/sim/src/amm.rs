@@ -1,14 +1,45 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use prop_amm_executor::{AfterSwapFn, BpfExecutor, BpfProgram, NativeExecutor, SwapFn};
-use prop_amm_shared::instruction::STORAGE_SIZE;
-use prop_amm_shared::nano::{f64_to_nano, nano_to_f64};
+use prop_amm_shared::config::MIN_TRADE_SIZE;
+use prop_amm_shared::instruction::{
+    AFTER_SWAP_TAG, LIQUIDITY_EVENT_TAG, SIDE_DEPOSIT, SIDE_WITHDRAW, STORAGE_SIZE,
+};
+use prop_amm_shared::nano::TokenDecimals;
+use prop_amm_shared::result::{ComputeUsage, ShapeIssue, ShapeReport};
 
 const MIN_RESERVE: f64 = 1e-12;
+/// Probe size for `local_liquidity`, as a fraction of reserve X.
+const LIQUIDITY_PROBE_FRAC: f64 = 1e-3;
+/// Input sizes `quotes_any_size` tries on each side, as fractions of the input reserve.
+const LIVENESS_PROBE_FRACS: [f64; 3] = [1e-4, 1e-2, 1e-1];
+
+/// Implied fees taken on executed trades: zero-fee constant-product output minus the quoted
+/// output, in the output asset. Negative when the curve pays more than constant product.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeeTake {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl FeeTake {
+    /// Fees taken since an `earlier` snapshot, valued in Y at `fair_price`.
+    pub fn value_since(&self, earlier: &FeeTake, fair_price: f64) -> f64 {
+        (self.x - earlier.x) * fair_price + (self.y - earlier.y)
+    }
+}
 
 enum Backend {
     Bpf(BpfExecutor),
     Native(NativeExecutor),
 }
 
+/// Output and CU of a step's quote calls by `(side, amount, reserve X, reserve Y)`, all in
+/// program units. Cleared whenever storage may change, so storage is part of the key
+/// implicitly.
+type QuoteCache = HashMap<(u8, u64, u64, u64), (u64, u64)>;
+
 pub struct BpfAmm {
     backend: Backend,
     pub reserve_x: f64,
@@ -16,6 +47,29 @@ pub struct BpfAmm {
     pub name: String,
     storage: Vec<u8>,
     current_step: u64,
+    /// Step of the latest liveness probe and whether the venue quoted anything then.
+    liveness: Option<(u64, bool)>,
+    shape_report: ShapeReport,
+    /// Whether a shape violation panics or is only recorded in `shape_report`.
+    shape_violations_fatal: bool,
+    quote_compute: ComputeUsage,
+    after_swap_compute: ComputeUsage,
+    fee_take: FeeTake,
+    after_swap_failures: u32,
+    first_after_swap_error: Option<String>,
+    /// Wall-clock time in program calls; `None` unless profiling was enabled.
+    program_time: Option<Duration>,
+    /// Scales between the engine's f64 amounts and the integers programs see.
+    decimals: TokenDecimals,
+    /// Smallest input the router sends this venue; smaller parts of an order go elsewhere
+    /// or nowhere.
+    min_trade_size: f64,
+    /// Largest input the router sends this venue in one trade, as a fraction of its
+    /// input-side reserve; `None` for no cap.
+    max_trade_fraction: Option<f64>,
+    /// `None` unless the cache was enabled.
+    quote_cache: Option<QuoteCache>,
+    quote_cache_hits: u64,
 }
 
 impl BpfAmm {
@@ -27,6 +81,20 @@ impl BpfAmm {
             name,
             storage: vec![0u8; STORAGE_SIZE],
             current_step: 0,
+            liveness: None,
+            shape_report: ShapeReport::default(),
+            shape_violations_fatal: true,
+            quote_compute: ComputeUsage::default(),
+            after_swap_compute: ComputeUsage::default(),
+            fee_take: FeeTake::default(),
+            after_swap_failures: 0,
+            first_after_swap_error: None,
+            program_time: None,
+            decimals: TokenDecimals::default(),
+            min_trade_size: MIN_TRADE_SIZE,
+            max_trade_fraction: None,
+            quote_cache: None,
+            quote_cache_hits: 0,
         }
     }
 
@@ -44,31 +112,86 @@ impl BpfAmm {
             name,
             storage: vec![0u8; STORAGE_SIZE],
             current_step: 0,
+            liveness: None,
+            shape_report: ShapeReport::default(),
+            shape_violations_fatal: true,
+            quote_compute: ComputeUsage::default(),
+            after_swap_compute: ComputeUsage::default(),
+            fee_take: FeeTake::default(),
+            after_swap_failures: 0,
+            first_after_swap_error: None,
+            program_time: None,
+            decimals: TokenDecimals::default(),
+            min_trade_size: MIN_TRADE_SIZE,
+            max_trade_fraction: None,
+            quote_cache: None,
+            quote_cache_hits: 0,
         }
     }
 
     #[inline]
     fn call(&mut self, side: u8, amount: u64, rx: u64, ry: u64) -> u64 {
-        match &mut self.backend {
-            Backend::Bpf(exec) => exec
-                .execute(side, amount, rx, ry, &self.storage)
-                .unwrap_or(0),
-            Backend::Native(exec) => exec.execute(side, amount, rx, ry, &self.storage),
+        let key = (side, amount, rx, ry);
+        if let Some(&(output, cu)) = self.quote_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            // Metered as if the program ran again, so the cache never changes results.
+            if self.uses_bpf_backend() {
+                self.quote_compute.record(cu);
+            }
+            self.quote_cache_hits += 1;
+            return output;
+        }
+        let started = self.program_time.is_some().then(Instant::now);
+        let (output, cu) = match &mut self.backend {
+            Backend::Bpf(exec) => {
+                let (output, cu) = match exec.execute(side, amount, rx, ry, &self.storage) {
+                    Ok(metered) => (metered.value, metered.compute_units),
+                    Err(err) => {
+                        tracing::debug!(amm = %self.name, side, amount, %err, "quote failed");
+                        (0, exec.last_compute_units())
+                    }
+                };
+                self.quote_compute.record(cu);
+                (output, cu)
+            }
+            Backend::Native(exec) => (exec.execute(side, amount, rx, ry, &self.storage), 0),
+        };
+        self.record_program_time(started);
+        if let Some(cache) = &mut self.quote_cache {
+            cache.insert(key, (output, cu));
+        }
+        output
+    }
+
+    /// Forget cached quotes; called whenever storage may change and at every step.
+    fn clear_quote_cache(&mut self) {
+        if let Some(cache) = &mut self.quote_cache {
+            cache.clear();
+        }
+    }
+
+    #[inline]
+    fn record_program_time(&mut self, started: Option<Instant>) {
+        if let (Some(total), Some(started)) = (&mut self.program_time, started) {
+            *total += started.elapsed();
         }
     }
 
     #[inline]
     fn call_after_swap(
         &mut self,
+        tag: u8,
         side: u8,
         input_amount: u64,
         output_amount: u64,
         rx: u64,
         ry: u64,
     ) {
+        self.clear_quote_cache();
+        let started = self.program_time.is_some().then(Instant::now);
         match &mut self.backend {
             Backend::Bpf(exec) => {
-                let _ = exec.execute_after_swap(
+                let result = exec.execute_after_swap_tagged(
+                    tag,
                     side,
                     input_amount,
                     output_amount,
@@ -77,9 +200,21 @@ impl BpfAmm {
                     self.current_step,
                     &mut self.storage,
                 );
+                match result {
+                    Ok(metered) => self.after_swap_compute.record(metered.compute_units),
+                    Err(err) => {
+                        self.after_swap_compute.record(exec.last_compute_units());
+                        tracing::debug!(amm = %self.name, side, %err, "after_swap failed");
+                        self.after_swap_failures += 1;
+                        if self.first_after_swap_error.is_none() {
+                            self.first_after_swap_error = Some(err.to_string());
+                        }
+                    }
+                }
             }
             Backend::Native(exec) => {
-                exec.execute_after_swap(
+                exec.execute_after_swap_tagged(
+                    tag,
                     side,
                     input_amount,
                     output_amount,
@@ -90,9 +225,13 @@ impl BpfAmm {
                 );
             }
         }
+        self.record_program_time(started);
     }
 
     pub fn set_current_step(&mut self, step: u64) {
+        if step != self.current_step {
+            self.clear_quote_cache();
+        }
         self.current_step = step;
     }
 
@@ -101,22 +240,23 @@ impl BpfAmm {
         if input_y <= 0.0 || !input_y.is_finite() {
             return 0.0;
         }
-        if self.reserve_x <= MIN_RESERVE
-            || self.reserve_y <= MIN_RESERVE
-            || !self.reserve_x.is_finite()
-            || !self.reserve_y.is_finite()
-        {
-            return 0.0;
-        }
+        let d = self.decimals;
+        d.units_to_x(self.quote_buy_x_units(d.y_to_units(input_y)))
+    }
 
-        let quoted = nano_to_f64(self.call(
-            0,
-            f64_to_nano(input_y),
-            f64_to_nano(self.reserve_x),
-            f64_to_nano(self.reserve_y),
-        ));
-        if !quoted.is_finite() || quoted <= 0.0 || quoted > self.reserve_x {
-            0.0
+    /// [`Self::quote_buy_x`] in the integer units the program sees (see
+    /// [`Self::token_decimals`]), with no rounding of the input.
+    #[inline]
+    pub fn quote_buy_x_units(&mut self, input_y: u64) -> u64 {
+        if !self.reserves_tradable() {
+            return 0;
+        }
+        let d = self.decimals;
+        let (rx, ry) = (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y));
+        let quoted = self.call(0, input_y, rx, ry);
+        let value = d.units_to_x(quoted);
+        if !value.is_finite() || value <= 0.0 || value > self.reserve_x {
+            0
         } else {
             quoted
         }
@@ -127,33 +267,109 @@ impl BpfAmm {
         if input_x <= 0.0 || !input_x.is_finite() {
             return 0.0;
         }
-        if self.reserve_x <= MIN_RESERVE
-            || self.reserve_y <= MIN_RESERVE
-            || !self.reserve_x.is_finite()
-            || !self.reserve_y.is_finite()
-        {
-            return 0.0;
-        }
+        let d = self.decimals;
+        d.units_to_y(self.quote_sell_x_units(d.x_to_units(input_x)))
+    }
 
-        let quoted = nano_to_f64(self.call(
-            1,
-            f64_to_nano(input_x),
-            f64_to_nano(self.reserve_x),
-            f64_to_nano(self.reserve_y),
-        ));
-        if !quoted.is_finite() || quoted <= 0.0 || quoted > self.reserve_y {
-            0.0
+    /// [`Self::quote_sell_x`] in the integer units the program sees, with no rounding of
+    /// the input.
+    #[inline]
+    pub fn quote_sell_x_units(&mut self, input_x: u64) -> u64 {
+        if !self.reserves_tradable() {
+            return 0;
+        }
+        let d = self.decimals;
+        let (rx, ry) = (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y));
+        let quoted = self.call(1, input_x, rx, ry);
+        let value = d.units_to_y(quoted);
+        if !value.is_finite() || value <= 0.0 || value > self.reserve_y {
+            0
         } else {
             quoted
         }
     }
 
+    /// The smallest Y input the program pays at least `output_x` X for, or `None` if no input
+    /// does. Found by binary search over the integer input, so it is exact to a unit; the
+    /// result sits half a unit above it so that converting it back truncates to that unit.
+    pub fn quote_buy_x_exact_out(&mut self, output_x: f64) -> Option<f64> {
+        let d = self.decimals;
+        let input = self.exact_out_units(true, d.x_to_units(output_x))?;
+        Some(if input == 0 { 0.0 } else { d.units_to_y(input) + 0.5 * d.units_to_y(1) })
+    }
+
+    /// The smallest X input the program pays at least `output_y` Y for, or `None` if no input
+    /// does; see [`Self::quote_buy_x_exact_out`].
+    pub fn quote_sell_x_exact_out(&mut self, output_y: f64) -> Option<f64> {
+        let d = self.decimals;
+        let input = self.exact_out_units(false, d.y_to_units(output_y))?;
+        Some(if input == 0 { 0.0 } else { d.units_to_x(input) + 0.5 * d.units_to_x(1) })
+    }
+
+    /// The smallest input, in units, quoted at least `output` units: bracketed by doubling
+    /// the input that pays `output` at the spot price, then bisected.
+    fn exact_out_units(&mut self, buy_x: bool, output: u64) -> Option<u64> {
+        if output == 0 {
+            return Some(0);
+        }
+        let d = self.decimals;
+        let (reserve_in, reserve_out) = if buy_x {
+            (d.y_to_units(self.reserve_y), d.x_to_units(self.reserve_x))
+        } else {
+            (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y))
+        };
+        if !self.reserves_tradable() || output >= reserve_out {
+            return None;
+        }
+        let mut quote = |input: u64| {
+            if buy_x {
+                self.quote_buy_x_units(input)
+            } else {
+                self.quote_sell_x_units(input)
+            }
+        };
+        let spot_input = (output as f64 * reserve_in as f64 / reserve_out as f64) as u64;
+        let (mut lo, mut hi) = (0, spot_input.max(1));
+        while quote(hi) < output {
+            if hi > u64::MAX / 2 {
+                return None;
+            }
+            (lo, hi) = (hi, hi * 2);
+        }
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if quote(mid) >= output {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Some(hi)
+    }
+
+    #[inline]
+    fn reserves_tradable(&self) -> bool {
+        self.reserve_x > MIN_RESERVE
+            && self.reserve_y > MIN_RESERVE
+            && self.reserve_x.is_finite()
+            && self.reserve_y.is_finite()
+    }
+
     #[inline]
     pub fn execute_buy_x(&mut self, input_y: f64) -> f64 {
+        self.execute_buy_x_at_least(input_y, 0.0)
+    }
+
+    /// [`Self::execute_buy_x`], but nothing executes, and 0 is returned, if the program
+    /// would pay out less than `min_output_x`.
+    pub fn execute_buy_x_at_least(&mut self, input_y: f64, min_output_x: f64) -> f64 {
         let output_x = self.quote_buy_x(input_y);
         if input_y <= 0.0 || output_x <= 0.0 || !input_y.is_finite() || !output_x.is_finite() {
             return 0.0;
         }
+        if output_x < min_output_x {
+            return 0.0;
+        }
         if output_x >= self.reserve_x {
             return 0.0;
         }
@@ -168,21 +384,31 @@ impl BpfAmm {
             return 0.0;
         }
 
+        self.fee_take.x += self.reserve_x * input_y / (self.reserve_y + input_y) - output_x;
         self.reserve_x = new_rx;
         self.reserve_y = new_ry;
 
-        let rx = f64_to_nano(self.reserve_x);
-        let ry = f64_to_nano(self.reserve_y);
-        self.call_after_swap(0, f64_to_nano(input_y), f64_to_nano(output_x), rx, ry);
+        let d = self.decimals;
+        let (rx, ry) = (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y));
+        self.call_after_swap(AFTER_SWAP_TAG, 0, d.y_to_units(input_y), d.x_to_units(output_x), rx, ry);
         output_x
     }
 
     #[inline]
     pub fn execute_sell_x(&mut self, input_x: f64) -> f64 {
+        self.execute_sell_x_at_least(input_x, 0.0)
+    }
+
+    /// [`Self::execute_sell_x`], but nothing executes, and 0 is returned, if the program
+    /// would pay out less than `min_output_y`.
+    pub fn execute_sell_x_at_least(&mut self, input_x: f64, min_output_y: f64) -> f64 {
         let output_y = self.quote_sell_x(input_x);
         if input_x <= 0.0 || output_y <= 0.0 || !input_x.is_finite() || !output_y.is_finite() {
             return 0.0;
         }
+        if output_y < min_output_y {
+            return 0.0;
+        }
         if output_y >= self.reserve_y {
             return 0.0;
         }
@@ -197,15 +423,50 @@ impl BpfAmm {
             return 0.0;
         }
 
+        self.fee_take.y += self.reserve_y * input_x / (self.reserve_x + input_x) - output_y;
         self.reserve_x = new_rx;
         self.reserve_y = new_ry;
 
-        let rx = f64_to_nano(self.reserve_x);
-        let ry = f64_to_nano(self.reserve_y);
-        self.call_after_swap(1, f64_to_nano(input_x), f64_to_nano(output_y), rx, ry);
+        let d = self.decimals;
+        let (rx, ry) = (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y));
+        self.call_after_swap(AFTER_SWAP_TAG, 1, d.x_to_units(input_x), d.y_to_units(output_y), rx, ry);
         output_y
     }
 
+    /// Multiply both reserves by `factor`, as an LP deposit (`factor > 1`) or withdrawal, and
+    /// with `notify` send the program a [`LIQUIDITY_EVENT_TAG`] call with the amounts moved.
+    /// Returns the X and Y added, negative for a withdrawal.
+    pub fn scale_liquidity(&mut self, factor: f64, notify: bool) -> (f64, f64) {
+        if factor == 1.0 || !factor.is_finite() || factor <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let added_x = self.reserve_x * (factor - 1.0);
+        let added_y = self.reserve_y * (factor - 1.0);
+        self.reserve_x *= factor;
+        self.reserve_y *= factor;
+        if !notify {
+            return (added_x, added_y);
+        }
+
+        let side = if factor > 1.0 {
+            SIDE_DEPOSIT
+        } else {
+            SIDE_WITHDRAW
+        };
+        let d = self.decimals;
+        let (rx, ry) = (d.x_to_units(self.reserve_x), d.y_to_units(self.reserve_y));
+        let (moved_x, moved_y) = (d.x_to_units(added_x.abs()), d.y_to_units(added_y.abs()));
+        self.call_after_swap(LIQUIDITY_EVENT_TAG, side, moved_x, moved_y, rx, ry);
+        (added_x, added_y)
+    }
+
+    /// Reopen the pool at the given reserves for a new session; unlike [`Self::reset`],
+    /// storage and counters carry over.
+    pub fn start_session(&mut self, (reserve_x, reserve_y): (f64, f64)) {
+        self.reserve_x = reserve_x;
+        self.reserve_y = reserve_y;
+    }
+
     #[inline]
     pub fn spot_price(&self) -> f64 {
         if self.reserve_x <= MIN_RESERVE
@@ -218,9 +479,102 @@ impl BpfAmm {
         }
     }
 
+    /// Local depth `|dx/dP|` of the bid side at the current reserves, from the second
+    /// difference of two sell-X quotes. These probe quotes are not metered as quote compute.
+    pub fn local_liquidity(&mut self) -> Option<f64> {
+        let delta = self.reserve_x * LIQUIDITY_PROBE_FRAC;
+        let (out_1, out_2) =
+            self.unmetered(|amm| (amm.quote_sell_x(delta), amm.quote_sell_x(2.0 * delta)));
+
+        // Marginal price falls by `dp` over the second probe step.
+        let dp_dx = (2.0 * out_1 - out_2) / (delta * delta);
+        if out_1 > 0.0 && dp_dx.is_finite() && dp_dx > 0.0 {
+            Some(1.0 / dp_dx)
+        } else {
+            None
+        }
+    }
+
+    /// Whether any probe size on either side gets a nonzero quote. These probe quotes are not
+    /// metered as quote compute.
+    pub fn quotes_any_size(&mut self) -> bool {
+        self.unmetered(|amm| {
+            LIVENESS_PROBE_FRACS.iter().any(|&frac| {
+                amm.quote_buy_x(amm.reserve_y * frac) > 0.0
+                    || amm.quote_sell_x(amm.reserve_x * frac) > 0.0
+            })
+        })
+    }
+
+    /// Whether the venue is down this step: no probe size of [`Self::quotes_any_size`] gets a
+    /// quote on either side (e.g. the program runs out of compute on every call). Probed at
+    /// most once per step; callers ask only after a quote came back 0.
+    pub fn is_down(&mut self) -> bool {
+        match self.liveness {
+            Some((step, quoting)) if step == self.current_step => !quoting,
+            _ => {
+                let quoting = self.quotes_any_size();
+                self.liveness = Some((self.current_step, quoting));
+                if !quoting {
+                    tracing::debug!(amm = %self.name, step = self.current_step, "venue down");
+                }
+                !quoting
+            }
+        }
+    }
+
+    /// Whether [`Self::is_down`] already found the venue down this step. Never probes.
+    pub fn known_down(&self) -> bool {
+        self.liveness == Some((self.current_step, false))
+    }
+
+    /// Run diagnostic quotes that should not count toward `quote_compute`.
+    pub(crate) fn unmetered<T>(&mut self, probe: impl FnOnce(&mut Self) -> T) -> T {
+        let metered = self.quote_compute;
+        let result = probe(self);
+        self.quote_compute = metered;
+        result
+    }
+
+    /// Present amounts to the program with these decimals instead of the 1e9 nano scale.
+    pub fn set_token_decimals(&mut self, decimals: TokenDecimals) {
+        self.decimals = decimals;
+    }
+
+    pub fn token_decimals(&self) -> TokenDecimals {
+        self.decimals
+    }
+
+    pub fn set_min_trade_size(&mut self, size: f64) {
+        self.min_trade_size = size;
+    }
+
+    #[inline]
+    pub fn min_trade_size(&self) -> f64 {
+        self.min_trade_size
+    }
+
+    pub fn set_max_trade_fraction(&mut self, fraction: Option<f64>) {
+        self.max_trade_fraction = fraction;
+    }
+
+    /// Largest input the router sends this venue in one trade buying X (paying Y) or selling
+    /// X: infinite unless capped by [`Self::set_max_trade_fraction`].
+    #[inline]
+    pub fn max_trade_input(&self, buy_x: bool) -> f64 {
+        let reserve_in = if buy_x { self.reserve_y } else { self.reserve_x };
+        self.max_trade_fraction.map_or(f64::INFINITY, |fraction| fraction * reserve_in)
+    }
+
+    /// The coarsest amount unit the program sees, on either side.
+    pub fn amount_quantum(&self) -> f64 {
+        self.decimals.units_to_x(1).max(self.decimals.units_to_y(1))
+    }
+
     pub fn set_initial_storage(&mut self, bytes: &[u8]) {
         let n = bytes.len().min(self.storage.len());
         self.storage[..n].copy_from_slice(&bytes[..n]);
+        self.clear_quote_cache();
     }
 
     #[inline]
@@ -232,7 +586,92 @@ impl BpfAmm {
         self.reserve_x = reserve_x;
         self.reserve_y = reserve_y;
         self.storage.fill(0);
+        self.clear_quote_cache();
+        self.quote_cache_hits = 0;
         self.current_step = 0;
+        self.liveness = None;
+        self.shape_report = ShapeReport::default();
+        self.quote_compute = ComputeUsage::default();
+        self.after_swap_compute = ComputeUsage::default();
+        self.fee_take = FeeTake::default();
+        self.after_swap_failures = 0;
+        self.first_after_swap_error = None;
+        if self.program_time.is_some() {
+            self.program_time = Some(Duration::ZERO);
+        }
+    }
+
+    /// Serve repeated quotes with the same amount and reserves from a cache instead of
+    /// re-running the program, until storage may change or the step ends. A quote depends
+    /// on nothing else, so results are unchanged; cached quotes still count toward
+    /// `quote_compute`.
+    pub fn enable_quote_cache(&mut self) {
+        self.quote_cache.get_or_insert_with(HashMap::new);
+    }
+
+    /// Quotes served from the cache since the last reset.
+    pub fn quote_cache_hits(&self) -> u64 {
+        self.quote_cache_hits
+    }
+
+    /// Start timing program calls (quotes and `after_swap`), for [`Self::program_time`].
+    pub fn enable_profiling(&mut self) {
+        self.program_time.get_or_insert(Duration::ZERO);
+    }
+
+    /// Wall-clock time spent in program calls since profiling was enabled (or the last
+    /// reset); zero when it never was.
+    pub fn program_time(&self) -> Duration {
+        self.program_time.unwrap_or_default()
+    }
+
+    /// Failed `after_swap` calls since the last reset (BPF backend only).
+    #[inline]
+    pub fn after_swap_failures(&self) -> u32 {
+        self.after_swap_failures
+    }
+
+    /// The error of the first failed `after_swap` call since the last reset.
+    pub fn first_after_swap_error(&self) -> Option<&str> {
+        self.first_after_swap_error.as_deref()
+    }
+
+    /// CU consumed by quote calls since the last reset (BPF backend only).
+    #[inline]
+    pub fn quote_compute(&self) -> ComputeUsage {
+        self.quote_compute
+    }
+
+    /// CU consumed by `after_swap` calls since the last reset (BPF backend only).
+    #[inline]
+    pub fn after_swap_compute(&self) -> ComputeUsage {
+        self.after_swap_compute
+    }
+
+    /// Implied fees taken on trades executed since the last reset.
+    #[inline]
+    pub fn fee_take(&self) -> FeeTake {
+        self.fee_take
+    }
+
+    pub(crate) fn record_shape_issue(&mut self, issue: ShapeIssue) {
+        self.shape_report.record(issue);
+    }
+
+    /// Record shape violations like warnings instead of panicking on them.
+    pub fn set_shape_violations_fatal(&mut self, fatal: bool) {
+        self.shape_violations_fatal = fatal;
+    }
+
+    #[inline]
+    pub fn shape_violations_fatal(&self) -> bool {
+        self.shape_violations_fatal
+    }
+
+    /// Shape warnings, and violations when they are not fatal, recorded since the last
+    /// reset (or take).
+    pub fn take_shape_report(&mut self) -> ShapeReport {
+        std::mem::take(&mut self.shape_report)
     }
 
     #[inline]
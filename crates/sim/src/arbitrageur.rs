@@ -3,7 +3,7 @@ use crate::curve_checks;
 use crate::search_stats;
 use prop_amm_shared::nano::NANO_SCALE_F64;
 use rand::SeedableRng;
-use rand_distr::{Distribution, LogNormal};
+use rand_distr::{Distribution, LogNormal, StandardNormal};
 use rand_pcg::Pcg64;
 
 const MIN_INPUT: f64 = 0.001;
@@ -16,6 +16,18 @@ const BRACKET_GROWTH: f64 = 2.0;
 const MAX_INPUT_AMOUNT: f64 = (u64::MAX as f64 / NANO_SCALE_F64) * 0.999_999;
 // Ignore micro-arbs by requiring a minimum quote-token (Y) notional.
 const MIN_ARB_NOTIONAL_Y: f64 = 0.01;
+// Log-spaced sizes added to each search's samples for the shape check, which otherwise only
+// sees points clustered along the optimizer's path.
+const SHAPE_PROBES: usize = 4;
+// A probe this close (relatively) to an existing sample is skipped: over so small a step,
+// nano rounding dominates the slope the shape check measures.
+const SHAPE_PROBE_MIN_REL_GAP: f64 = 5e-2;
+// The CP fast path fits a submission's fee from a quote at this fraction of the input
+// reserve and checks the fit at a second, larger one.
+const CP_FIT_SMALL_FRAC: f64 = 1e-3;
+const CP_FIT_LARGE_FRAC: f64 = 3e-2;
+// Largest relative miss of the second quote for the fit to count; well above nano rounding.
+const CP_FIT_REL_TOL: f64 = 1e-6;
 
 #[derive(Clone, Copy)]
 enum ArbSide {
@@ -28,6 +40,28 @@ struct ArbCandidate {
     side: ArbSide,
     input_amount: f64,
     expected_profit: f64,
+    /// Output the planning quote promised for `input_amount`, if one was taken at that size.
+    quoted_output: Option<f64>,
+}
+
+/// Whether the arbitrageur shows up in a step: active with probability `prob`, drawn from a
+/// stream of its own so arbitrage sizing draws are unchanged on the steps it is active.
+pub struct ArbArrivals {
+    prob: f64,
+    rng: Pcg64,
+}
+
+impl ArbArrivals {
+    pub fn new(prob: f64, seed: u64) -> Self {
+        Self {
+            prob,
+            rng: Pcg64::seed_from_u64(seed),
+        }
+    }
+
+    pub fn is_active(&mut self) -> bool {
+        rand::Rng::gen::<f64>(&mut self.rng) < self.prob
+    }
 }
 
 pub struct ArbResult {
@@ -35,10 +69,42 @@ pub struct ArbResult {
     pub amount_x: f64,
     pub amount_y: f64,
     pub edge: f64,
+    /// Output the trade was quoted at when it was sized (X when buying X, Y when selling),
+    /// for checking against what execution delivered. `None` if the executed size was
+    /// never quoted exactly, e.g. after size noise.
+    pub quoted_output: Option<f64>,
+}
+
+/// A round trip between two pools: X bought on one and sold on the other.
+pub struct CrossArb {
+    /// Whether X was bought on the first pool passed to `execute_cross_arb`.
+    pub buys_on_first: bool,
+    pub buy: ArbResult,
+    /// `None` if the richer pool would not take the X after all, leaving it held.
+    pub sell: Option<ArbResult>,
+}
+
+/// X and Y the arbitrageur holds, and what it is restocked to.
+#[derive(Clone, Copy)]
+struct Inventory {
+    x: f64,
+    y: f64,
+    start_x: f64,
+    start_y: f64,
 }
 
 pub struct Arbitrageur {
     min_arb_profit: f64,
+    /// Half the external market's bid/ask spread, as a fraction of mid.
+    half_spread: f64,
+    /// Fee on the external hedge, as a fraction of its notional.
+    taker_fee: f64,
+    /// `None`: unlimited capital.
+    inventory: Option<Inventory>,
+    /// Size trades in closed form on pools that quote like constant product with a fee.
+    cp_fast_path: bool,
+    /// Log-scale noise on every chosen trade size, and the stream it is drawn from.
+    size_noise: Option<(f64, Pcg64)>,
     rng: Pcg64,
     retail_size_dist: LogNormal<f64>,
 }
@@ -54,23 +120,90 @@ impl Arbitrageur {
         let mu_ln = retail_mean_size.max(0.01).ln() - 0.5 * sigma * sigma;
         Self {
             min_arb_profit: min_arb_profit.max(0.0),
+            half_spread: 0.0,
+            taker_fee: 0.0,
+            inventory: None,
+            cp_fast_path: false,
+            size_noise: None,
             rng: Pcg64::seed_from_u64(seed),
             retail_size_dist: LogNormal::new(mu_ln, sigma).unwrap(),
         }
     }
 
+    /// Unwind against an external market quoting `spread_bps` wide around the fair price:
+    /// X bought from a pool is sold at the bid, X sold to a pool is bought at the ask.
+    pub fn with_external_spread(mut self, spread_bps: f64) -> Self {
+        self.half_spread = (spread_bps / 20_000.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Pay `fee_bps` of the notional on every external hedge: X bought from a pool nets
+    /// `1 - fee` of its sale price, X sold to a pool costs `1 + fee` of its purchase price.
+    pub fn with_taker_fee(mut self, fee_bps: f64) -> Self {
+        self.taker_fee = (fee_bps / 10_000.0).clamp(0.0, 1.0);
+        self
+    }
+
+    /// Size trades on a pool that quotes like constant product with a fee the way the
+    /// normalizer's are sized, in closed form, instead of searching. The fee is fitted from
+    /// two quotes per side each time; a pool that does not fit is searched as usual.
+    pub fn with_cp_fast_path(mut self, enabled: bool) -> Self {
+        self.cp_fast_path = enabled;
+        self
+    }
+
+    /// Trade suboptimally: multiply every chosen trade size by `exp(noise * z)`, z standard
+    /// normal, drawn from a stream seeded by `seed` so the sizing draws are unchanged.
+    pub fn with_size_noise(mut self, noise: f64, seed: u64) -> Self {
+        self.size_noise = Some((noise, Pcg64::seed_from_u64(seed)));
+        self
+    }
+
+    /// Trade from a finite inventory of `x` and `y`: a trade never spends more than the
+    /// arbitrageur holds, and what it receives is added to its holdings.
+    pub fn with_inventory(mut self, x: f64, y: f64) -> Self {
+        self.inventory = Some(Inventory {
+            x,
+            y,
+            start_x: x,
+            start_y: y,
+        });
+        self
+    }
+
+    /// Restock the inventory to its starting amounts. No-op with unlimited capital.
+    pub fn rebalance(&mut self) {
+        if let Some(inventory) = &mut self.inventory {
+            inventory.x = inventory.start_x;
+            inventory.y = inventory.start_y;
+        }
+    }
+
+    /// Current `(x, y)` holdings, or `None` with unlimited capital.
+    pub fn inventory(&self) -> Option<(f64, f64)> {
+        self.inventory.map(|inventory| (inventory.x, inventory.y))
+    }
+
+    /// Edge is still valued at `fair_price`, the mid; the spread only narrows which trades
+    /// pay and how large they are.
     pub fn execute_arb(&mut self, amm: &mut BpfAmm, fair_price: f64) -> Option<ArbResult> {
-        if !fair_price.is_finite() || fair_price <= 0.0 {
+        if !fair_price.is_finite() || fair_price <= 0.0 || amm.known_down() {
             return None;
         }
+        // Where the hedge actually unwinds, net of the taker fee.
+        let bid = fair_price * (1.0 - self.half_spread) * (1.0 - self.taker_fee);
+        let ask = fair_price * (1.0 + self.half_spread) * (1.0 + self.taker_fee);
 
-        let best = if amm.name == "normalizer" {
+        let (buy, sell) = if amm.name == "normalizer" {
             // The normalizer is a known constant-product-with-fee curve. Keep it closed-form,
             // but evaluate both sides and execute whichever quote-implied trade is better.
-            Self::best_candidate(
-                self.plan_normalizer_buy_x(amm, fair_price),
-                self.plan_normalizer_sell_x(amm, fair_price),
+            let gamma = Self::normalizer_gamma(amm);
+            (
+                self.plan_cp_buy_x(amm, bid, gamma),
+                self.plan_cp_sell_x(amm, ask, gamma),
             )
+        } else if let Some(planned) = self.plan_fitted_cp(amm, bid, ask) {
+            planned
         } else {
             // Evaluate both book sides from compute_swap quotes; reserve_y/reserve_x can be a
             // misleading directional signal for non-CP strategies.
@@ -83,13 +216,143 @@ impl Arbitrageur {
             let start_x = (start_y / fair_price.max(1e-9))
                 .max(min_sell_input)
                 .min(MAX_INPUT_AMOUNT);
-            Self::best_candidate(
-                self.plan_arb_buy_x(amm, fair_price, start_y, min_buy_input),
-                self.plan_arb_sell_x(amm, fair_price, start_x, min_sell_input),
+            (
+                self.plan_arb_buy_x(amm, bid, start_y, min_buy_input),
+                self.plan_arb_sell_x(amm, ask, start_x, min_sell_input),
             )
-        }?;
+        };
+        let mut best = Self::best_candidate(
+            buy.and_then(|c| self.within_inventory(amm, c, bid)),
+            sell.and_then(|c| self.within_inventory(amm, c, ask)),
+        )?;
+        if let Some((noise, rng)) = &mut self.size_noise {
+            let z: f64 = StandardNormal.sample(rng);
+            best.input_amount = (best.input_amount * (*noise * z).exp()).min(MAX_INPUT_AMOUNT);
+            best.quoted_output = None;
+            if let Some(inventory) = &self.inventory {
+                let held = match best.side {
+                    ArbSide::BuyX => inventory.y,
+                    ArbSide::SellX => inventory.x,
+                };
+                best.input_amount = best.input_amount.min(held);
+            }
+        }
 
-        self.execute_candidate(amm, fair_price, best)
+        let result = self.execute_candidate(amm, fair_price, best)?;
+        self.settle(&result);
+        Some(result)
+    }
+
+    /// Arbitrage two pools against each other: buy X with Y on the cheaper and sell it on
+    /// the richer, sized to maximize the Y that comes back. Nothing is hedged externally, so
+    /// neither the external spread nor the taker fee applies; the round trip must clear
+    /// `min_arb_profit`. Each leg's edge is valued at `fair_price` like any arbitrage.
+    pub fn execute_cross_arb(
+        &mut self,
+        first: &mut BpfAmm,
+        second: &mut BpfAmm,
+        fair_price: f64,
+    ) -> Option<CrossArb> {
+        if first.known_down() || second.known_down() {
+            return None;
+        }
+        let buys_on_first = first.spot_price() < second.spot_price();
+        let (cheap, rich) = if buys_on_first {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let min_input = Self::min_buy_input_y();
+        let max_input = self.inventory.map_or(MAX_INPUT_AMOUNT, |i| i.y.min(MAX_INPUT_AMOUNT));
+        if max_input < min_input {
+            return None;
+        }
+        let start = (cheap.reserve_y * 1e-3).clamp(min_input, max_input);
+        let mut round_trip = |input_y: f64| {
+            let output_x = cheap.quote_buy_x(input_y);
+            rich.quote_sell_x(output_x) - input_y
+        };
+        let (lo, hi) = Self::bracket_maximum(start, min_input, max_input, &mut round_trip);
+        let (input_y, profit) = Self::golden_section_max(lo, hi, &mut round_trip);
+        if input_y < min_input || profit < self.min_arb_profit {
+            return None;
+        }
+
+        let buy_leg = ArbCandidate {
+            side: ArbSide::BuyX,
+            input_amount: input_y,
+            expected_profit: profit,
+            quoted_output: None,
+        };
+        let buy = self.execute_candidate(cheap, fair_price, buy_leg)?;
+        self.settle(&buy);
+        let sell_leg = ArbCandidate {
+            side: ArbSide::SellX,
+            input_amount: buy.amount_x,
+            expected_profit: profit,
+            quoted_output: None,
+        };
+        let sell = self.execute_candidate(rich, fair_price, sell_leg);
+        if let Some(sell) = &sell {
+            self.settle(sell);
+        }
+        Some(CrossArb {
+            buys_on_first,
+            buy,
+            sell,
+        })
+    }
+
+    /// Book an executed trade against the inventory, if there is one.
+    fn settle(&mut self, result: &ArbResult) {
+        if let Some(inventory) = &mut self.inventory {
+            if result.amm_buys_x {
+                inventory.x -= result.amount_x;
+                inventory.y += result.amount_y;
+            } else {
+                inventory.x += result.amount_x;
+                inventory.y -= result.amount_y;
+            }
+        }
+    }
+
+    /// `candidate`, shrunk to what the inventory can pay for: Y to buy X with, X to sell.
+    /// The shrunk trade is re-quoted and dropped if it no longer clears `min_arb_profit`.
+    fn within_inventory(
+        &self,
+        amm: &mut BpfAmm,
+        candidate: ArbCandidate,
+        exit_price: f64,
+    ) -> Option<ArbCandidate> {
+        let Some(inventory) = self.inventory else {
+            return Some(candidate);
+        };
+        let (held, min_input) = match candidate.side {
+            ArbSide::BuyX => (inventory.y, Self::min_buy_input_y()),
+            ArbSide::SellX => (inventory.x, Self::min_sell_input_x(exit_price)),
+        };
+        if candidate.input_amount <= held {
+            return Some(candidate);
+        }
+        if held < min_input {
+            return None;
+        }
+        let (output, expected_profit) = match candidate.side {
+            ArbSide::BuyX => {
+                let output_x = amm.quote_buy_x(held);
+                (output_x, output_x * exit_price - held)
+            }
+            ArbSide::SellX => {
+                let output_y = amm.quote_sell_x(held);
+                (output_y, output_y - held * exit_price)
+            }
+        };
+        (expected_profit >= self.min_arb_profit).then_some(ArbCandidate {
+            input_amount: held,
+            expected_profit,
+            quoted_output: Some(output),
+            ..candidate
+        })
     }
 
     fn sample_retail_size_y(&mut self) -> f64 {
@@ -106,11 +369,94 @@ impl Arbitrageur {
         MIN_INPUT.max(MIN_ARB_NOTIONAL_Y / fair_price.max(1e-9))
     }
 
-    fn plan_normalizer_buy_x(&self, amm: &mut BpfAmm, fair_price: f64) -> Option<ArbCandidate> {
-        debug_assert_eq!(amm.name, "normalizer");
+    /// Closed-form buy and sell candidates on a submission whose quotes fit constant product
+    /// with a fee, or `None` when the fast path is off or either side does not fit.
+    fn plan_fitted_cp(
+        &self,
+        amm: &mut BpfAmm,
+        bid: f64,
+        ask: f64,
+    ) -> Option<(Option<ArbCandidate>, Option<ArbCandidate>)> {
+        if !self.cp_fast_path {
+            return None;
+        }
+        let mut sampled_buy = Vec::with_capacity(2);
+        let mut sampled_sell = Vec::with_capacity(2);
+        let fitted = Self::fit_cp_gamma(amm, true, &mut sampled_buy)
+            .zip(Self::fit_cp_gamma(amm, false, &mut sampled_sell));
+        // Too few points for the concavity check to bite, but a non-monotonic pair still
+        // fails the submission as it would during a search.
+        curve_checks::enforce_submission_monotonic_concave(
+            amm,
+            &sampled_buy,
+            Self::min_buy_input_y(),
+            "arbitrage CP fit",
+        );
+        curve_checks::enforce_submission_monotonic_concave(
+            amm,
+            &sampled_sell,
+            MIN_INPUT,
+            "arbitrage CP fit",
+        );
+        let Some((buy_gamma, sell_gamma)) = fitted else {
+            search_stats::inc_arb_cp_fit_miss();
+            return None;
+        };
+        search_stats::inc_arb_cp_fit_hit();
+        Some((
+            self.plan_cp_buy_x(amm, bid, buy_gamma),
+            self.plan_cp_sell_x(amm, ask, sell_gamma),
+        ))
+    }
 
-        let fee_bps = Self::normalizer_fee_bps(amm) as f64;
-        let gamma = (10_000.0 - fee_bps) / 10_000.0;
+    /// Fee factor `gamma` of the constant-product curve on the pool's reserves, charging the
+    /// fee on input, that reproduces its `buy_x` quotes at two sizes; `None` if none does.
+    /// The quotes taken are appended to `sampled`.
+    fn fit_cp_gamma(amm: &mut BpfAmm, buy_x: bool, sampled: &mut Vec<(f64, f64)>) -> Option<f64> {
+        let (reserve_in, reserve_out) = if buy_x {
+            (amm.reserve_y, amm.reserve_x)
+        } else {
+            (amm.reserve_x, amm.reserve_y)
+        };
+        if !reserve_in.is_finite() || !reserve_out.is_finite() {
+            return None;
+        }
+        if reserve_in <= 0.0 || reserve_out <= 0.0 {
+            return None;
+        }
+        let mut quote = |input: f64| {
+            let output = if buy_x {
+                amm.quote_buy_x(input)
+            } else {
+                amm.quote_sell_x(input)
+            };
+            sampled.push((input, output));
+            output
+        };
+        let small = reserve_in * CP_FIT_SMALL_FRAC;
+        let out_small = quote(small);
+        if out_small <= 0.0 || out_small >= reserve_out {
+            return None;
+        }
+        let gamma = out_small * reserve_in / (small * (reserve_out - out_small));
+        if !gamma.is_finite() || gamma <= 0.0 || gamma > 1.0 {
+            return None;
+        }
+        let large = reserve_in * CP_FIT_LARGE_FRAC;
+        let predicted = reserve_out * gamma * large / (reserve_in + gamma * large);
+        let out_large = quote(large);
+        ((out_large - predicted).abs() <= CP_FIT_REL_TOL * predicted).then_some(gamma)
+    }
+
+    // The planners value X at `exit_price`, where the arbitrageur unwinds: the bid for X
+    // bought from the pool, the ask for X sold to it. The closed-form ones size the trade
+    // for a constant-product curve on the pool's reserves with fee factor `gamma`.
+    fn plan_cp_buy_x(
+        &self,
+        amm: &mut BpfAmm,
+        exit_price: f64,
+        gamma: f64,
+    ) -> Option<ArbCandidate> {
         if !gamma.is_finite() || gamma <= 0.0 {
             return None;
         }
@@ -122,7 +468,7 @@ impl Arbitrageur {
         }
 
         // Buy X with Y
-        let target = (fair_price * rx * gamma * ry).sqrt();
+        let target = (exit_price * rx * gamma * ry).sqrt();
         if !target.is_finite() || target <= ry {
             None
         } else {
@@ -132,7 +478,7 @@ impl Arbitrageur {
             if expected_output_x <= 0.0 {
                 return None;
             }
-            let arb_profit = expected_output_x * fair_price - input_y;
+            let arb_profit = expected_output_x * exit_price - input_y;
             if arb_profit < self.min_arb_profit {
                 return None;
             }
@@ -140,15 +486,17 @@ impl Arbitrageur {
                 side: ArbSide::BuyX,
                 input_amount: input_y,
                 expected_profit: arb_profit,
+                quoted_output: Some(expected_output_x),
             })
         }
     }
 
-    fn plan_normalizer_sell_x(&self, amm: &mut BpfAmm, fair_price: f64) -> Option<ArbCandidate> {
-        debug_assert_eq!(amm.name, "normalizer");
-
-        let fee_bps = Self::normalizer_fee_bps(amm) as f64;
-        let gamma = (10_000.0 - fee_bps) / 10_000.0;
+    fn plan_cp_sell_x(
+        &self,
+        amm: &mut BpfAmm,
+        exit_price: f64,
+        gamma: f64,
+    ) -> Option<ArbCandidate> {
         if !gamma.is_finite() || gamma <= 0.0 {
             return None;
         }
@@ -160,17 +508,17 @@ impl Arbitrageur {
         }
 
         // Sell X for Y
-        let target = (ry * rx * gamma / fair_price).sqrt();
+        let target = (ry * rx * gamma / exit_price).sqrt();
         if !target.is_finite() || target <= rx {
             None
         } else {
-            let min_sell_input = Self::min_sell_input_x(fair_price);
+            let min_sell_input = Self::min_sell_input_x(exit_price);
             let input_x = ((target - rx) / gamma).clamp(min_sell_input, MAX_INPUT_AMOUNT);
             let expected_output_y = amm.quote_sell_x(input_x);
             if expected_output_y <= 0.0 {
                 return None;
             }
-            let arb_profit = expected_output_y - input_x * fair_price;
+            let arb_profit = expected_output_y - input_x * exit_price;
             if arb_profit < self.min_arb_profit {
                 return None;
             }
@@ -178,10 +526,18 @@ impl Arbitrageur {
                 side: ArbSide::SellX,
                 input_amount: input_x,
                 expected_profit: arb_profit,
+                quoted_output: Some(expected_output_y),
             })
         }
     }
 
+    #[inline]
+    fn normalizer_gamma(amm: &BpfAmm) -> f64 {
+        debug_assert_eq!(amm.name, "normalizer");
+        let fee_bps = Self::normalizer_fee_bps(amm) as f64;
+        (10_000.0 - fee_bps) / 10_000.0
+    }
+
     #[inline]
     fn normalizer_fee_bps(amm: &BpfAmm) -> u16 {
         // normalizer::compute_swap reads fee_bps from data[25..27], i.e. storage[0..2].
@@ -201,23 +557,33 @@ impl Arbitrageur {
     fn plan_arb_buy_x(
         &mut self,
         amm: &mut BpfAmm,
-        fair_price: f64,
+        exit_price: f64,
         start_y: f64,
         min_buy_input: f64,
     ) -> Option<ArbCandidate> {
+        if amm.known_down() {
+            return None;
+        }
         let mut sampled_curve = Vec::with_capacity(BRACKET_MAX_STEPS + GOLDEN_MAX_ITERS + 8);
         let (lo, hi) = Self::bracket_maximum(start_y, min_buy_input, MAX_INPUT_AMOUNT, |input_y| {
             let output_x = amm.quote_buy_x(input_y);
             sampled_curve.push((input_y, output_x));
-            output_x * fair_price - input_y
+            output_x * exit_price - input_y
         });
+        if Self::searching_dead_curve(amm, &sampled_curve) {
+            return None;
+        }
         let (optimal_y, _) = Self::golden_section_max(lo, hi, |input_y| {
             let output_x = amm.quote_buy_x(input_y);
             sampled_curve.push((input_y, output_x));
-            output_x * fair_price - input_y
+            output_x * exit_price - input_y
         });
+        for input_y in Self::shape_probe_inputs(min_buy_input, hi, &sampled_curve) {
+            let output_x = amm.unmetered(|amm| amm.quote_buy_x(input_y));
+            sampled_curve.push((input_y, output_x));
+        }
         curve_checks::enforce_submission_monotonic_concave(
-            &amm.name,
+            amm,
             &sampled_curve,
             min_buy_input,
             "arbitrage buy search",
@@ -232,7 +598,7 @@ impl Arbitrageur {
             return None;
         }
 
-        let arb_profit = expected_output_x * fair_price - optimal_y;
+        let arb_profit = expected_output_x * exit_price - optimal_y;
         if arb_profit < self.min_arb_profit {
             return None;
         }
@@ -241,30 +607,41 @@ impl Arbitrageur {
             side: ArbSide::BuyX,
             input_amount: optimal_y,
             expected_profit: arb_profit,
+            quoted_output: Some(expected_output_x),
         })
     }
 
     fn plan_arb_sell_x(
         &mut self,
         amm: &mut BpfAmm,
-        fair_price: f64,
+        exit_price: f64,
         start_x: f64,
         min_sell_input: f64,
     ) -> Option<ArbCandidate> {
+        if amm.known_down() {
+            return None;
+        }
         let mut sampled_curve = Vec::with_capacity(BRACKET_MAX_STEPS + GOLDEN_MAX_ITERS + 8);
         let (lo, hi) =
             Self::bracket_maximum(start_x, min_sell_input, MAX_INPUT_AMOUNT, |input_x| {
             let output_y = amm.quote_sell_x(input_x);
             sampled_curve.push((input_x, output_y));
-            output_y - input_x * fair_price
+            output_y - input_x * exit_price
         });
+        if Self::searching_dead_curve(amm, &sampled_curve) {
+            return None;
+        }
         let (optimal_x, _) = Self::golden_section_max(lo, hi, |input_x| {
             let output_y = amm.quote_sell_x(input_x);
             sampled_curve.push((input_x, output_y));
-            output_y - input_x * fair_price
+            output_y - input_x * exit_price
         });
+        for input_x in Self::shape_probe_inputs(min_sell_input, hi, &sampled_curve) {
+            let output_y = amm.unmetered(|amm| amm.quote_sell_x(input_x));
+            sampled_curve.push((input_x, output_y));
+        }
         curve_checks::enforce_submission_monotonic_concave(
-            &amm.name,
+            amm,
             &sampled_curve,
             min_sell_input,
             "arbitrage sell search",
@@ -279,7 +656,7 @@ impl Arbitrageur {
             return None;
         }
 
-        let arb_profit = expected_output_y - optimal_x * fair_price;
+        let arb_profit = expected_output_y - optimal_x * exit_price;
         if arb_profit < self.min_arb_profit {
             return None;
         }
@@ -288,9 +665,20 @@ impl Arbitrageur {
             side: ArbSide::SellX,
             input_amount: optimal_x,
             expected_profit: arb_profit,
+            quoted_output: Some(expected_output_y),
         })
     }
 
+    /// Whether the bracket's first quote came back 0 because the venue is down, in which case
+    /// the golden-section search would only sample zeros.
+    fn searching_dead_curve(amm: &mut BpfAmm, sampled_curve: &[(f64, f64)]) -> bool {
+        let dead = sampled_curve.first().is_some_and(|&(_, output)| output <= 0.0) && amm.is_down();
+        if dead {
+            search_stats::inc_arb_venue_down();
+        }
+        dead
+    }
+
     fn best_candidate(
         buy: Option<ArbCandidate>,
         sell: Option<ArbCandidate>,
@@ -326,6 +714,7 @@ impl Arbitrageur {
                     amount_x: output_x,
                     amount_y: candidate.input_amount,
                     edge: candidate.input_amount - output_x * fair_price,
+                    quoted_output: candidate.quoted_output,
                 })
             }
             ArbSide::SellX => {
@@ -338,11 +727,30 @@ impl Arbitrageur {
                     amount_x: candidate.input_amount,
                     amount_y: output_y,
                     edge: candidate.input_amount * fair_price - output_y,
+                    quoted_output: candidate.quoted_output,
                 })
             }
         }
     }
 
+    /// Up to `SHAPE_PROBES` log-spaced sizes strictly between `lo` and `hi`, leaving out
+    /// any that nearly coincide with an already `sampled` input.
+    fn shape_probe_inputs(lo: f64, hi: f64, sampled: &[(f64, f64)]) -> Vec<f64> {
+        let lo = lo.max(MIN_INPUT);
+        let ratio = hi / lo;
+        if !ratio.is_finite() || ratio <= 1.0 {
+            return Vec::new();
+        }
+        (1..=SHAPE_PROBES)
+            .map(|i| lo * ratio.powf(i as f64 / (SHAPE_PROBES + 1) as f64))
+            .filter(|&probe| {
+                sampled
+                    .iter()
+                    .all(|&(input, _)| (input - probe).abs() > SHAPE_PROBE_MIN_REL_GAP * probe)
+            })
+            .collect()
+    }
+
     fn bracket_maximum<F>(
         start: f64,
         min_input: f64,
@@ -486,6 +894,7 @@ impl Arbitrageur {
 mod tests {
     use super::Arbitrageur;
     use crate::amm::BpfAmm;
+    use prop_amm_executor::SwapFn;
     use prop_amm_shared::normalizer::compute_swap as normalizer_swap;
 
     const NANO_SCALE: f64 = 1_000_000_000.0;
@@ -581,6 +990,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn external_spread_shrinks_and_then_blocks_arbitrage() {
+        // The pool sits 3% below fair price, so the arbitrageur buys X from it.
+        let fair_price = 103.0;
+        let arb_size = |spread_bps: f64| {
+            let mut arb = Arbitrageur::new(0.01, 20.0, 1.2, 42).with_external_spread(spread_bps);
+            arb.execute_arb(&mut test_amm(), fair_price).map(|result| {
+                assert!(!result.amm_buys_x);
+                // Edge is valued at mid regardless of where the arbitrageur unwinds.
+                let mid_edge = result.amount_y - result.amount_x * fair_price;
+                assert!((result.edge - mid_edge).abs() < 1e-9);
+                result.amount_x
+            })
+        };
+        let frictionless = arb_size(0.0).expect("mispriced pool should be arbitraged");
+        let spread = arb_size(200.0).expect("a 2% spread leaves most of the gap to take");
+        assert!(spread < 0.7 * frictionless, "{spread} vs {frictionless}");
+        // A 6% spread puts the bid below the pool's ask: nothing left to take.
+        assert!(arb_size(600.0).is_none());
+    }
+
+    #[test]
+    fn taker_fee_prices_the_hedge_like_a_spread() {
+        // A 1% taker fee moves the exit price as far as a 2% spread's half does.
+        let fair_price = 103.0;
+        let trade = |arb: Arbitrageur| {
+            let mut arb = arb;
+            arb.execute_arb(&mut test_amm(), fair_price)
+                .map(|result| (result.amount_x, result.amount_y, result.edge))
+        };
+        let fee = trade(Arbitrageur::new(0.01, 20.0, 1.2, 42).with_taker_fee(100.0));
+        let spread = trade(Arbitrageur::new(0.01, 20.0, 1.2, 42).with_external_spread(200.0));
+        let free = trade(Arbitrageur::new(0.01, 20.0, 1.2, 42));
+        assert_eq!(fee, spread);
+        assert!(fee.unwrap().1 < free.unwrap().1);
+    }
+
+    #[test]
+    fn cp_fast_path_sizes_constant_product_pools_in_closed_form() {
+        let trade = |swap: SwapFn, fair_price: f64, fast: bool| {
+            let mut amm = BpfAmm::new_native(swap, None, 100.0, 10_000.0, "test".to_string());
+            let mut arb = Arbitrageur::new(0.01, 20.0, 1.2, 42).with_cp_fast_path(fast);
+            arb.execute_arb(&mut amm, fair_price)
+                .map(|result| (result.amm_buys_x, result.amount_x, result.amount_y, result.edge))
+        };
+        // A 30 bps constant-product pool fits: the closed form lands on the searched size
+        // and does at least as well, since the search stops within ~1% of the optimum.
+        for fair_price in [97.0, 103.0] {
+            let searched = trade(normalizer_swap, fair_price, false).unwrap();
+            let fitted = trade(normalizer_swap, fair_price, true).unwrap();
+            assert_eq!(fitted.0, searched.0);
+            assert!((fitted.2 / searched.2 - 1.0).abs() < 0.02, "{fitted:?} vs {searched:?}");
+            assert!(fitted.3 <= searched.3 + 1e-9, "{fitted:?} vs {searched:?}");
+        }
+        // A fixed-price pool does not fit, and is searched exactly as without the fast path.
+        assert_eq!(
+            trade(fixed_price_120_swap, 125.0, true),
+            trade(fixed_price_120_swap, 125.0, false)
+        );
+    }
+
+    #[test]
+    fn size_noise_scatters_trades_around_the_optimum() {
+        let fair_price = 103.0;
+        let optimal = Arbitrageur::new(0.01, 20.0, 1.2, 42)
+            .execute_arb(&mut test_amm(), fair_price)
+            .unwrap();
+        let mut noisy = Arbitrageur::new(0.01, 20.0, 1.2, 42).with_size_noise(0.5, 7);
+        let trades: Vec<_> = (0..200)
+            .map(|_| noisy.execute_arb(&mut test_amm(), fair_price).unwrap())
+            .collect();
+        assert!(trades.iter().any(|t| t.amount_y < 0.8 * optimal.amount_y));
+        assert!(trades.iter().any(|t| t.amount_y > 1.2 * optimal.amount_y));
+        // Every miss leaves profit on the table: the pool loses less on average.
+        let mean_edge = trades.iter().map(|t| t.edge).sum::<f64>() / trades.len() as f64;
+        assert!(mean_edge > optimal.edge, "{mean_edge} vs {}", optimal.edge);
+    }
+
+    #[test]
+    fn inventory_caps_arbitrage_until_rebalanced() {
+        // The pool sits 3% below fair price: unconstrained, the arbitrageur spends over 100 Y.
+        let fair_price = 103.0;
+        let mut amm = test_amm();
+        let mut arb = Arbitrageur::new(0.01, 20.0, 1.2, 42).with_inventory(0.0, 50.0);
+        let result = arb.execute_arb(&mut amm, fair_price).expect("50 Y still pays");
+        assert!(!result.amm_buys_x);
+        assert_eq!(result.amount_y, 50.0);
+        assert_eq!(arb.inventory(), Some((result.amount_x, 0.0)));
+
+        // Out of Y, it cannot take the rest of the gap; restocked, it can.
+        assert!(arb.execute_arb(&mut amm, fair_price).is_none());
+        arb.rebalance();
+        assert_eq!(arb.inventory(), Some((0.0, 50.0)));
+        assert!(arb.execute_arb(&mut amm, fair_price).is_some());
+    }
+
     #[test]
     fn explores_opposite_side_when_reserve_spot_direction_is_wrong() {
         let fair_price = 100.5;
@@ -661,4 +1166,34 @@ mod tests {
             "arb should ignore opportunities below 0.01 Y notional floor"
         );
     }
+
+    /// Constant product (100 X / 10,000 Y) above 0.1 Y, but a flatter linear quote below it:
+    /// convex at the kink, far below any size the optimizer visits.
+    fn small_size_convex_swap(data: &[u8]) -> u64 {
+        if data.len() < 25 || data[0] != 0 {
+            return 0;
+        }
+        let input = u64::from_le_bytes(data[1..9].try_into().expect("input")) as f64 / NANO_SCALE;
+        let output = if input < 0.1 {
+            input / 200.0
+        } else {
+            let excess = input - 0.1;
+            0.1 / 200.0 + 100.0 * excess / (10_000.0 + excess)
+        };
+        to_nano_u64(output)
+    }
+
+    #[test]
+    #[should_panic(expected = "concavity violated")]
+    fn spaced_probes_catch_convexity_away_from_the_optimum() {
+        let mut amm = BpfAmm::new_native(
+            small_size_convex_swap,
+            None,
+            100.0,
+            10_000.0,
+            "submission".to_string(),
+        );
+        let mut arb = Arbitrageur::new(0.01, 20.0, 1.2, 3);
+        arb.execute_arb(&mut amm, 101.0);
+    }
 }
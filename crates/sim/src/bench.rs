@@ -4,11 +4,24 @@ use prop_amm_shared::nano::f64_to_nano;
 use prop_amm_shared::normalizer::compute_swap as normalizer_swap;
 use std::time::Instant;
 
+#[cfg(not(feature = "prebuilt-programs"))]
 const NORMALIZER_SO_PATH: &str = concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/../../programs/normalizer/target/deploy/normalizer.so"
 );
 
+#[cfg(feature = "prebuilt-programs")]
+fn load_normalizer_program() -> Option<BpfProgram> {
+    match crate::prebuilt::normalizer_program() {
+        Ok(program) => Some(program),
+        Err(err) => {
+            eprintln!("Skipping BPF benchmark: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "prebuilt-programs"))]
 fn load_normalizer_program() -> Option<BpfProgram> {
     let bytes = match std::fs::read(NORMALIZER_SO_PATH) {
         Ok(bytes) => bytes,
@@ -0,0 +1,160 @@
+//! Season difficulty calibration: find how far to scale the hyperparameter variance box so
+//! a reference strategy earns a target average edge.
+//!
+//! Difficulty is a single multiplier (see [`HyperparameterVariance::scaled`]). The reference
+//! strategy's edge is assumed to fall as difficulty rises, and every evaluation uses the same
+//! seeds, so successive batches differ only through the box and bisection converges.
+
+use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
+
+use crate::runner;
+
+#[derive(Debug, Clone)]
+pub struct CalibrationSpec {
+    pub base: HyperparameterVariance,
+    pub target_avg_edge: f64,
+    pub seeds: Vec<u64>,
+    pub n_steps: u32,
+    /// Difficulty bracket searched; the target must lie between the edges at its ends.
+    pub min_difficulty: f64,
+    pub max_difficulty: f64,
+    /// Stop once the average edge is within this distance of the target.
+    pub edge_tolerance: f64,
+    pub max_iterations: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CalibrationStep {
+    pub difficulty: f64,
+    pub avg_edge: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// The evaluated step closest to the target.
+    pub best: CalibrationStep,
+    pub variance: HyperparameterVariance,
+    pub converged: bool,
+    /// Every batch evaluated, in order.
+    pub steps: Vec<CalibrationStep>,
+}
+
+/// Bisect difficulty (geometrically) until `avg_edge` returns an edge within tolerance of
+/// the target. `avg_edge` runs the reference strategy on a batch of configs.
+pub fn calibrate(
+    spec: &CalibrationSpec,
+    mut avg_edge: impl FnMut(Vec<SimulationConfig>) -> anyhow::Result<f64>,
+) -> anyhow::Result<Calibration> {
+    if !(spec.min_difficulty > 0.0 && spec.min_difficulty < spec.max_difficulty) {
+        anyhow::bail!(
+            "Invalid difficulty bracket [{}, {}]",
+            spec.min_difficulty,
+            spec.max_difficulty
+        );
+    }
+    if spec.seeds.is_empty() {
+        anyhow::bail!("Calibration needs at least one seed");
+    }
+
+    let mut steps = Vec::new();
+    let mut evaluate = |difficulty: f64| -> anyhow::Result<CalibrationStep> {
+        let variance = spec.base.scaled(difficulty);
+        let configs = runner::configs_for_variance(&variance, &spec.seeds, spec.n_steps);
+        let step = CalibrationStep {
+            difficulty,
+            avg_edge: avg_edge(configs)?,
+        };
+        tracing::info!(
+            "difficulty {:.4}: avg edge {:.2} (target {:.2})",
+            step.difficulty,
+            step.avg_edge,
+            spec.target_avg_edge
+        );
+        steps.push(step);
+        Ok(step)
+    };
+
+    let mut easy = evaluate(spec.min_difficulty)?;
+    let mut hard = evaluate(spec.max_difficulty)?;
+    if !(hard.avg_edge..=easy.avg_edge).contains(&spec.target_avg_edge) {
+        anyhow::bail!(
+            "Target avg edge {:.2} is outside [{:.2}, {:.2}] reached over difficulty [{}, {}]; \
+             widen the bracket",
+            spec.target_avg_edge,
+            hard.avg_edge,
+            easy.avg_edge,
+            spec.min_difficulty,
+            spec.max_difficulty
+        );
+    }
+
+    let miss = |s: &CalibrationStep| (s.avg_edge - spec.target_avg_edge).abs();
+    let mut best = if miss(&easy) <= miss(&hard) { easy } else { hard };
+    let mut iterations = 0;
+    while miss(&best) > spec.edge_tolerance && iterations < spec.max_iterations {
+        let mid = evaluate((easy.difficulty * hard.difficulty).sqrt())?;
+        if miss(&mid) < miss(&best) {
+            best = mid;
+        }
+        if mid.avg_edge > spec.target_avg_edge {
+            easy = mid;
+        } else {
+            hard = mid;
+        }
+        iterations += 1;
+    }
+
+    Ok(Calibration {
+        best,
+        variance: spec.base.scaled(best.difficulty),
+        converged: miss(&best) <= spec.edge_tolerance,
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: [u64; 3] = [1, 2, 3];
+
+    fn spec(target_avg_edge: f64) -> CalibrationSpec {
+        CalibrationSpec {
+            base: HyperparameterVariance::default(),
+            target_avg_edge,
+            seeds: SEEDS.to_vec(),
+            n_steps: 10,
+            min_difficulty: 0.25,
+            max_difficulty: 4.0,
+            edge_tolerance: 0.01,
+            max_iterations: 40,
+        }
+    }
+
+    /// Edge falling with mean sampled volatility, standing in for a batch run.
+    fn vol_penalized_edge(configs: Vec<SimulationConfig>) -> anyhow::Result<f64> {
+        let mean_sigma = configs.iter().map(|c| c.gbm_sigma).sum::<f64>() / configs.len() as f64;
+        Ok(100.0 - 10_000.0 * mean_sigma)
+    }
+
+    #[test]
+    fn bisects_to_target_edge() {
+        let base = HyperparameterVariance::default();
+        let baseline =
+            vol_penalized_edge(runner::configs_for_variance(&base, &SEEDS, 10)).unwrap();
+        let target = 100.0 - (100.0 - baseline) * 1.5;
+
+        let calibration = calibrate(&spec(target), vol_penalized_edge).unwrap();
+        assert!(calibration.converged);
+        assert!((calibration.best.avg_edge - target).abs() <= 0.01);
+        assert!((calibration.best.difficulty - 1.5).abs() < 1e-3);
+        let sigma_max = calibration.variance.gbm_sigma_max;
+        assert!((sigma_max / base.gbm_sigma_max - calibration.best.difficulty).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_target_outside_bracket() {
+        let err = calibrate(&spec(1_000.0), vol_penalized_edge).unwrap_err();
+        assert!(err.to_string().contains("widen the bracket"));
+    }
+}
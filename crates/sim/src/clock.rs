@@ -0,0 +1,117 @@
+//! The event-driven clock: retail orders and arbitrage checks arriving one at a time in
+//! continuous time, in place of the engine's fixed steps (see `Clock::EventDriven`).
+
+use prop_amm_shared::config::{Clock, SimulationConfig};
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Exp};
+use rand_pcg::Pcg64;
+
+use crate::engine::RngStreams;
+
+/// What happens at an [`Event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// One order from the retail class at this index of `SimulationConfig::retail_traders`.
+    Retail(usize),
+    /// The arbitrageur checks every venue.
+    ArbCheck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    /// Time since the simulation started, in the unit `gbm_dt` is measured in.
+    pub time: f64,
+    /// Time since the previous event, or since the start for the first.
+    pub elapsed: f64,
+    /// The step of `gbm_dt` the event falls in; step-indexed settings read this.
+    pub step: u32,
+    pub kind: EventKind,
+}
+
+/// Every event of one simulation, in time order, drawn up front.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventSchedule {
+    events: Vec<Event>,
+    horizon: f64,
+}
+
+impl EventSchedule {
+    /// The schedule a simulation of `config` follows, or `None` on fixed steps. Arrival
+    /// rates are constant within each step, so each step's events are drawn at its own rate;
+    /// the exponential gaps make that exact.
+    pub fn for_config(config: &SimulationConfig) -> Option<Self> {
+        let Clock::EventDriven { arb_check_rate } = config.clock else {
+            return None;
+        };
+        let seed = RngStreams::for_config(config).clock?;
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let class_rates: Vec<f64> =
+            config.retail_traders().iter().map(|class| class.arrival_rate).collect();
+        let mut events = Vec::new();
+        let mut previous = 0.0;
+        for step in 0..config.n_steps {
+            let intensity = config.arrival_intensity_at(step);
+            let total_rate = arb_check_rate + intensity * class_rates.iter().sum::<f64>();
+            if total_rate <= 0.0 {
+                continue;
+            }
+            let gaps = Exp::new(total_rate).expect("rates are finite and non-negative");
+            let start = step as f64 * config.gbm_dt;
+            let end = start + config.gbm_dt;
+            let mut time = start;
+            loop {
+                time += gaps.sample(&mut rng);
+                if time >= end {
+                    break;
+                }
+                let mut pick = rng.gen::<f64>() * total_rate - arb_check_rate;
+                let kind = if pick < 0.0 {
+                    EventKind::ArbCheck
+                } else {
+                    let class = class_rates.iter().position(|rate| {
+                        pick -= intensity * rate;
+                        pick < 0.0
+                    });
+                    EventKind::Retail(class.unwrap_or(class_rates.len() - 1))
+                };
+                events.push(Event {
+                    time,
+                    elapsed: time - previous,
+                    step,
+                    kind,
+                });
+                previous = time;
+            }
+        }
+        Some(Self {
+            events,
+            horizon: config.n_steps as f64 * config.gbm_dt,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn event(&self, index: u32) -> Event {
+        self.events[index as usize]
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Time the simulation covers: `n_steps * gbm_dt`.
+    pub fn horizon(&self) -> f64 {
+        self.horizon
+    }
+
+    /// Time from the last event to the end of the simulation.
+    pub fn remaining(&self) -> f64 {
+        self.horizon - self.events.last().map_or(0.0, |event| event.time)
+    }
+}
@@ -1,28 +1,54 @@
 use std::cmp::Ordering;
 
+use prop_amm_shared::result::{ShapeIssue, ShapeIssueKind, ShapeSeverity};
+
+use crate::amm::BpfAmm;
+
 const X_REL_EPS: f64 = 1e-9;
 const X_ABS_EPS: f64 = 1e-12;
 const OUTPUT_REL_TOL: f64 = 1e-9;
 const OUTPUT_ABS_TOL: f64 = 1e-9;
 const SLOPE_REL_TOL: f64 = 1e-2;
 const SLOPE_ABS_TOL: f64 = 1e-8;
+// Borderline band: a slope rise above this (but within SLOPE_REL_TOL) is recorded as a warning.
+const SLOPE_WARN_REL_TOL: f64 = 1e-3;
+// Flat output across at least this relative input increase is recorded as a warning.
+const FLAT_WARN_MIN_REL_DX: f64 = 1e-3;
+// Amounts cross the program boundary in nano units by default; slopes over tiny dx are
+// dominated by rounding. Coarser token decimals widen the quantum (`BpfAmm::amount_quantum`).
+const NANO_QUANTUM: f64 = 1e-9;
 
 pub(crate) fn enforce_submission_monotonic_concave(
-    amm_name: &str,
+    amm: &mut BpfAmm,
     points: &[(f64, f64)],
     min_input: f64,
     context: &str,
 ) {
-    if amm_name != "submission" {
+    if amm.name != "submission" {
         return;
     }
 
-    if let Some(message) = submission_shape_violation(points, min_input) {
-        panic!("submission shape violation during {context}: {message}");
+    let cleaned = cleaned_points(points, min_input);
+    let quantum = amm.amount_quantum();
+    if let Some(issue) = shape_violation(&cleaned, context, quantum) {
+        if amm.shape_violations_fatal() {
+            panic!("submission shape violation during {context}: {issue}");
+        }
+        amm.record_shape_issue(issue);
+        return;
+    }
+    if let Some(issue) = shape_warning(&cleaned, context, quantum) {
+        amm.record_shape_issue(issue);
     }
 }
 
-fn submission_shape_violation(points: &[(f64, f64)], min_input: f64) -> Option<String> {
+#[cfg(test)]
+fn submission_shape_violation(points: &[(f64, f64)], min_input: f64) -> Option<ShapeIssue> {
+    shape_violation(&cleaned_points(points, min_input), "test", NANO_QUANTUM)
+}
+
+/// Sort by input, drop invalid samples, and merge near-duplicate inputs (keeping the max output).
+fn cleaned_points(points: &[(f64, f64)], min_input: f64) -> Vec<(f64, f64)> {
     let mut sorted: Vec<(f64, f64)> = points
         .iter()
         .copied()
@@ -45,20 +71,89 @@ fn submission_shape_violation(points: &[(f64, f64)], min_input: f64) -> Option<S
         }
         cleaned.push((input, output));
     }
+    cleaned
+}
 
+fn issue(kind: ShapeIssueKind, severity: ShapeSeverity, context: &str, message: String) -> ShapeIssue {
+    ShapeIssue {
+        kind,
+        severity,
+        context: context.to_string(),
+        message,
+    }
+}
+
+/// `quantum` is the coarsest amount unit a program sees, on either side.
+fn shape_violation(cleaned: &[(f64, f64)], context: &str, quantum: f64) -> Option<ShapeIssue> {
+    let output_abs_tol = OUTPUT_ABS_TOL.max(quantum);
     for window in cleaned.windows(2) {
         let (in_a, out_a) = window[0];
         let (in_b, out_b) = window[1];
-        let allowed_drop = OUTPUT_ABS_TOL + OUTPUT_REL_TOL * out_a.abs().max(out_b.abs()).max(1.0);
+        let allowed_drop = output_abs_tol + OUTPUT_REL_TOL * out_a.abs().max(out_b.abs()).max(1.0);
         if in_b > in_a && out_b + allowed_drop < out_a {
-            return Some(format!(
-                "monotonicity violated: input {in_a:.6} -> output {out_a:.6}, \
-                 input {in_b:.6} -> output {out_b:.6}"
+            return Some(issue(
+                ShapeIssueKind::Monotonicity,
+                ShapeSeverity::Violation,
+                context,
+                format!(
+                    "monotonicity violated: input {in_a:.6} -> output {out_a:.6}, \
+                     input {in_b:.6} -> output {out_b:.6}"
+                ),
+            ));
+        }
+    }
+
+    let mut prev: Option<(f64, f64)> = None;
+    for window in cleaned.windows(2) {
+        let (in_a, out_a) = window[0];
+        let (in_b, out_b) = window[1];
+        let dx = in_b - in_a;
+        if dx <= X_ABS_EPS {
+            continue;
+        }
+        let slope = (out_b - out_a) / dx;
+        if let Some((prev, prev_dx)) = prev {
+            let scale = prev.abs().max(slope.abs()).max(1e-6);
+            let allowed_rise = SLOPE_ABS_TOL
+                + SLOPE_REL_TOL * scale
+                + rounding_noise(quantum, scale, dx, prev_dx);
+            if slope > prev + allowed_rise {
+                return Some(issue(
+                    ShapeIssueKind::Concavity,
+                    ShapeSeverity::Violation,
+                    context,
+                    format!(
+                        "concavity violated: slope rose from {prev:.9} to {slope:.9} \
+                         between inputs {in_a:.6} and {in_b:.6}"
+                    ),
+                ));
+            }
+        }
+        prev = Some((slope, dx));
+    }
+
+    None
+}
+
+/// Borderline shapes that pass `shape_violation`: flat output over a meaningful input range,
+/// or a slope rise inside the concavity tolerance band.
+fn shape_warning(cleaned: &[(f64, f64)], context: &str, quantum: f64) -> Option<ShapeIssue> {
+    for window in cleaned.windows(2) {
+        let (in_a, out_a) = window[0];
+        let (in_b, out_b) = window[1];
+        if out_a > 0.0 && out_b <= out_a && in_b - in_a >= FLAT_WARN_MIN_REL_DX * in_a.abs() {
+            return Some(issue(
+                ShapeIssueKind::Monotonicity,
+                ShapeSeverity::Warning,
+                context,
+                format!(
+                    "output flat at {out_a:.9} between inputs {in_a:.6} and {in_b:.6}"
+                ),
             ));
         }
     }
 
-    let mut prev_slope: Option<f64> = None;
+    let mut prev: Option<(f64, f64)> = None;
     for window in cleaned.windows(2) {
         let (in_a, out_a) = window[0];
         let (in_b, out_b) = window[1];
@@ -67,25 +162,43 @@ fn submission_shape_violation(points: &[(f64, f64)], min_input: f64) -> Option<S
             continue;
         }
         let slope = (out_b - out_a) / dx;
-        if let Some(prev) = prev_slope {
+        if let Some((prev, prev_dx)) = prev {
             let scale = prev.abs().max(slope.abs()).max(1e-6);
-            let allowed_rise = SLOPE_ABS_TOL + SLOPE_REL_TOL * scale;
+            let allowed_rise = SLOPE_ABS_TOL
+                + SLOPE_WARN_REL_TOL * scale
+                + rounding_noise(quantum, scale, dx, prev_dx);
             if slope > prev + allowed_rise {
-                return Some(format!(
-                    "concavity violated: slope rose from {prev:.9} to {slope:.9} \
-                     between inputs {in_a:.6} and {in_b:.6}"
+                return Some(issue(
+                    ShapeIssueKind::Concavity,
+                    ShapeSeverity::Warning,
+                    context,
+                    format!(
+                        "slope rose from {prev:.9} to {slope:.9} between inputs \
+                         {in_a:.6} and {in_b:.6} (within tolerance)"
+                    ),
                 ));
             }
         }
-        prev_slope = Some(slope);
+        prev = Some((slope, dx));
     }
 
     None
 }
 
+/// How far rounding to `quantum` can tilt a slope over `dx` against the previous one over
+/// `prev_dx`, for both shape tiers. Only rounding coarser than nano counts: nano-scale runs
+/// keep the tolerance they always had, so their outcomes are unchanged.
+fn rounding_noise(quantum: f64, scale: f64, dx: f64, prev_dx: f64) -> f64 {
+    let coarse = (quantum - NANO_QUANTUM).max(0.0);
+    4.0 * coarse * (1.0 + scale) * (1.0 / dx + 1.0 / prev_dx)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::submission_shape_violation;
+    use super::{
+        cleaned_points, shape_violation, shape_warning, submission_shape_violation,
+        ShapeIssueKind, ShapeSeverity, NANO_QUANTUM,
+    };
     use crate::amm::BpfAmm;
     use prop_amm_shared::normalizer::compute_swap as normalizer_swap;
     use rand::seq::SliceRandom;
@@ -355,7 +468,7 @@ mod tests {
         let err = submission_shape_violation(&naive_points, MIN_INPUT).expect(
             "expected checker to flag cancellation-prone evaluation despite legal underlying shape",
         );
-        assert!(err.contains("concavity"), "unexpected error: {err}");
+        assert!(err.message.contains("concavity"), "unexpected error: {err}");
 
         // Equivalent stable form: sqrt(C+x)-sqrt(C) = x / (sqrt(C+x)+sqrt(C)).
         let stable_points: Vec<(f64, f64)> = xs
@@ -372,14 +485,47 @@ mod tests {
     fn rejects_non_monotone_curve() {
         let points = vec![(0.1, 1.0), (0.2, 1.1), (0.3, 1.05), (0.4, 1.2)];
         let err = submission_shape_violation(&points, MIN_INPUT).expect("expected violation");
-        assert!(err.contains("monotonicity"), "unexpected error: {err}");
+        assert!(err.message.contains("monotonicity"), "unexpected error: {err}");
     }
 
     #[test]
     fn rejects_non_concave_curve() {
         let points = vec![(0.1, 0.1), (0.2, 0.18), (0.3, 0.31), (0.4, 0.45)];
         let err = submission_shape_violation(&points, MIN_INPUT).expect("expected violation");
-        assert!(err.contains("concavity"), "unexpected error: {err}");
+        assert!(err.message.contains("concavity"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn classifies_borderline_shapes_as_warnings() {
+        // Slope rises by ~0.5%: inside the violation tolerance, outside the warning band.
+        let rising = vec![(1.0, 1.0), (2.0, 1.5), (3.0, 2.0025)];
+        assert!(submission_shape_violation(&rising, MIN_INPUT).is_none());
+        let warning = shape_warning(&cleaned_points(&rising, MIN_INPUT), "test", NANO_QUANTUM)
+            .expect("expected concavity warning");
+        assert_eq!(warning.kind, ShapeIssueKind::Concavity);
+        assert_eq!(warning.severity, ShapeSeverity::Warning);
+
+        let flat = vec![(1.0, 1.0), (2.0, 1.5), (3.0, 1.5)];
+        assert!(submission_shape_violation(&flat, MIN_INPUT).is_none());
+        let warning = shape_warning(&cleaned_points(&flat, MIN_INPUT), "test", NANO_QUANTUM)
+            .expect("expected flat-output warning");
+        assert_eq!(warning.kind, ShapeIssueKind::Monotonicity);
+
+        let concave = vec![(1.0, 1.0), (2.0, 1.5), (3.0, 1.9)];
+        let concave = cleaned_points(&concave, MIN_INPUT);
+        assert!(shape_warning(&concave, "test", NANO_QUANTUM).is_none());
+
+        // At cent-sized amounts, a rise that rounding over dx = 1 can explain warns of nothing,
+        // while the same relative rise over wide steps still warns without a violation.
+        let cent = 1e-2;
+        let rising = cleaned_points(&rising, MIN_INPUT);
+        assert!(shape_violation(&rising, "test", cent).is_none());
+        assert!(shape_warning(&rising, "test", cent).is_none());
+        let wide = vec![(100.0, 100.0), (200.0, 150.0), (300.0, 200.25)];
+        let wide = cleaned_points(&wide, MIN_INPUT);
+        assert!(shape_violation(&wide, "test", cent).is_none());
+        let warning = shape_warning(&wide, "test", cent).expect("expected concavity warning");
+        assert_eq!(warning.kind, ShapeIssueKind::Concavity);
     }
 
     #[test]
@@ -1,72 +1,983 @@
+use std::time::{Duration, Instant};
+
 use prop_amm_executor::{AfterSwapFn, BpfProgram, SwapFn};
-use prop_amm_shared::config::SimulationConfig;
-use prop_amm_shared::result::SimResult;
+use prop_amm_shared::config::{
+    AfterSwapFailurePolicy, Clock, EdgeMode, ExecutionMode, ShapeViolationPolicy,
+    SimulationConfig, StepSequencing, TRADE_HASH_CHECKPOINT_STEPS,
+};
+use prop_amm_shared::hash::Fnv64;
+use prop_amm_shared::result::{
+    ComponentTiming, Counterparty, Depletion, DepletionCause, EdgeBreakdown, ExecutionStats,
+    InventoryMark, QuoteDivergence, ReserveSnapshot, SimResult, TapeTrade, VolumeBreakdown,
+};
+use prop_amm_shared::rng::{substream_seed, Component};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
 
 use crate::amm::BpfAmm;
-use crate::arbitrageur::Arbitrageur;
-use crate::price_process::GBMPriceProcess;
-use crate::retail::RetailTrader;
-use crate::router::OrderRouter;
+use crate::arbitrageur::{ArbArrivals, Arbitrageur};
+use crate::clock::{EventKind, EventSchedule};
+use crate::observer::{NoopObserver, SimObserver, StepEvent, TradeEvent};
+use crate::price_process::{PriceOracle, PricePath};
+use crate::retail::{
+    correlated_buy_prob, net_orders, InformedFlow, MomentumSignal, RetailFlow,
+    RetailOrder, RetailTrader,
+};
+use crate::router::{BaselinePool, OrderRouter, SUBMISSION_VENUE};
+use crate::search_stats;
+
+const VENUE_NORMALIZER: u8 = 1;
+/// Steps between probes for venues that no longer quote; reserves are checked every step.
+const DEPLETION_PROBE_STEPS: u32 = 100;
+
+/// Seeds of every RNG stream one simulation draws from, each derived from `config.seed` by
+/// [`substream_seed`]. The manifest can record these so a change to how they are derived is
+/// caught explicitly rather than only as a trade hash mismatch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RngStreams {
+    /// Seeds the hyperparameter draws (`HyperparameterVariance::apply`).
+    pub config: u64,
+    pub price: u64,
+    /// One stream per retail trader, in `SimulationConfig::retail_traders` order.
+    pub retail: Vec<u64>,
+    pub arb: u64,
+    /// Tags informed retail orders; only drawn from when `config.informed_prob > 0`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub informed: Option<u64>,
+    /// Places random price gaps; only drawn from when `config.random_gaps > 0`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub gaps: Option<u64>,
+    /// Switches volatility regimes; only drawn from when `config.volatility_regimes` is set.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub regimes: Option<u64>,
+    /// Momentum traders' orders; only drawn from when `config.momentum_traders` is set.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub momentum: Option<u64>,
+    /// Decides the arbitrageur's active steps; only drawn from when
+    /// `config.arb_arrival_prob < 1`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub arb_arrival: Option<u64>,
+    /// Perturbs arbitrage sizes; only drawn from when `config.arb_size_noise > 0`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub arb_noise: Option<u64>,
+    /// Perturbs the fair price the router observes; only drawn from when
+    /// `config.oracle_noise > 0`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub oracle: Option<u64>,
+    /// Draws event times and kinds; only drawn from under `Clock::EventDriven`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub clock: Option<u64>,
+    /// Places the arbitrageur among each step's retail orders; only drawn from under
+    /// `StepSequencing::Interleaved`.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub sequencing: Option<u64>,
+}
+
+impl RngStreams {
+    pub fn for_config(config: &SimulationConfig) -> Self {
+        let seed = config.seed;
+        Self {
+            config: substream_seed(seed, Component::Config, 0),
+            price: substream_seed(seed, Component::Price, 0),
+            retail: (0..config.retail_traders().len() as u32)
+                .map(|i| substream_seed(seed, Component::Retail, i))
+                .collect(),
+            arb: substream_seed(seed, Component::Arbitrageur, 0),
+            informed: (config.informed_prob > 0.0)
+                .then(|| substream_seed(seed, Component::Informed, 0)),
+            gaps: (config.random_gaps > 0)
+                .then(|| substream_seed(seed, Component::PriceGaps, 0)),
+            regimes: config
+                .volatility_regimes
+                .map(|_| substream_seed(seed, Component::VolatilityRegimes, 0)),
+            momentum: config
+                .momentum_traders
+                .map(|_| substream_seed(seed, Component::Momentum, 0)),
+            arb_arrival: (config.arb_arrival_prob < 1.0)
+                .then(|| substream_seed(seed, Component::ArbArrival, 0)),
+            arb_noise: (config.arb_size_noise > 0.0)
+                .then(|| substream_seed(seed, Component::ArbSizeNoise, 0)),
+            oracle: (config.oracle_noise > 0.0)
+                .then(|| substream_seed(seed, Component::OracleNoise, 0)),
+            clock: matches!(config.clock, Clock::EventDriven { .. })
+                .then(|| substream_seed(seed, Component::EventClock, 0)),
+            sequencing: (config.step_sequencing == StepSequencing::Interleaved)
+                .then(|| substream_seed(seed, Component::Sequencing, 0)),
+        }
+    }
+}
+
+#[inline]
+fn hash_trade(
+    hasher: &mut Fnv64,
+    step: u32,
+    venue: u8,
+    amm_buys_x: bool,
+    amount_x: f64,
+    amount_y: f64,
+) {
+    hasher.write_u32(step);
+    hasher.write_u8(venue);
+    hasher.write_u8(amm_buys_x as u8);
+    hasher.write_f64(amount_x);
+    hasher.write_f64(amount_y);
+}
 
+/// `baselines[0]` is the normalizer, followed by `config.extra_venues`. Draws the price path
+/// from `config` unless one is given.
 fn run_sim_inner(
     mut amm_sub: BpfAmm,
-    mut amm_norm: BpfAmm,
+    mut baselines: Vec<BaselinePool>,
     config: &SimulationConfig,
+    path: Option<&PricePath>,
+    observer: &mut dyn SimObserver,
 ) -> anyhow::Result<SimResult> {
-    let mut price = GBMPriceProcess::new(
-        config.initial_price,
-        config.gbm_mu,
-        config.gbm_sigma,
-        config.gbm_dt,
-        config.seed,
-    );
-    let mut retail = RetailTrader::new(
-        config.retail_arrival_rate,
-        config.retail_mean_size,
-        config.retail_size_sigma,
-        config.retail_buy_prob,
-        config.seed.wrapping_add(1),
+    let streams = RngStreams::for_config(config);
+    let mut retail = RetailFlow::new(
+        &config.retail_traders(),
+        config.flow_persistence,
+        &streams.retail,
     );
     let mut arb = Arbitrageur::new(
         config.min_arb_profit,
         config.retail_mean_size,
         config.retail_size_sigma,
-        config.seed.wrapping_add(2),
+        streams.arb,
+    )
+    .with_external_spread(config.external_spread_bps)
+    .with_taker_fee(config.arb_taker_fee_bps)
+    .with_cp_fast_path(config.arb_cp_fast_path);
+    if let Some(seed) = streams.arb_noise {
+        arb = arb.with_size_noise(config.arb_size_noise, seed);
+    }
+    if let Some(inventory) = config.arb_inventory {
+        arb = arb.with_inventory(inventory.x, inventory.y);
+    }
+    let mut arb_arrivals = streams
+        .arb_arrival
+        .map(|seed| ArbArrivals::new(config.arb_arrival_prob_per_step(), seed));
+    let mut informed = streams
+        .informed
+        .map(|seed| InformedFlow::new(config.informed_prob, seed));
+    let mut momentum = config.momentum_traders.zip(streams.momentum).map(|(cohort, seed)| {
+        let trader = RetailTrader::new(
+            config.retail_arrival_rate * cohort.arrival_scale,
+            config.retail_mean_size,
+            config.retail_size_sigma,
+            0.5,
+            seed,
+        );
+        (trader, MomentumSignal::new(cohort.lookback_steps, cohort.weight))
+    });
+    let mut oracle = streams.oracle.map(|seed| PriceOracle::new(config.oracle_noise, seed));
+    let mut sequencing = streams.sequencing.map(Pcg64::seed_from_u64);
+    let router = OrderRouter::for_kind(config.router)
+        .with_exact_sell_notional(config.retail_exact_sell_notional)
+        .with_price_guard(config.price_guard_bps);
+    amm_sub.set_token_decimals(config.token_decimals);
+    amm_sub.set_min_trade_size(config.min_trade_size);
+    amm_sub.set_max_trade_fraction(config.max_trade_fraction);
+    amm_sub.set_shape_violations_fatal(
+        config.shape_violation_policy == ShapeViolationPolicy::Abort,
     );
-    let router = OrderRouter::new();
+    for pool in &mut baselines {
+        pool.amm.set_token_decimals(config.token_decimals);
+        pool.amm.set_min_trade_size(config.min_trade_size);
+        pool.amm.set_max_trade_fraction(config.max_trade_fraction);
+    }
+    if config.quote_cache {
+        amm_sub.enable_quote_cache();
+        for pool in &mut baselines {
+            pool.amm.enable_quote_cache();
+        }
+    }
+    // Arbitrage and routing wall-clock time, when profiling.
+    let profile = config.profile_components;
+    if profile {
+        amm_sub.enable_profiling();
+        for pool in &mut baselines {
+            pool.amm.enable_profiling();
+        }
+    }
+    let mut arb_time = Duration::ZERO;
+    let mut router_time = Duration::ZERO;
 
     let mut submission_edge = 0.0_f64;
+    let mut edge_by_counterparty = EdgeBreakdown::default();
+    let mut normalizer_edge_by_counterparty = EdgeBreakdown::default();
+    let mut volume_by_counterparty = VolumeBreakdown::default();
+    let mut normalizer_volume_by_counterparty = VolumeBreakdown::default();
+    let mut extra_venue_edge_by_counterparty =
+        vec![EdgeBreakdown::default(); config.extra_venues.len()];
+    let mut extra_venue_volume_by_counterparty =
+        vec![VolumeBreakdown::default(); config.extra_venues.len()];
+    let mut execution_by_venue = vec![ExecutionStats::default(); 1 + baselines.len()];
+    // Each venue's spot price before the current retail order, indexed like the venue.
+    let mut spots_before = Vec::with_capacity(1 + baselines.len());
+    let mut lvr = 0.0_f64;
+    // Running peak of cumulative submission edge, for drawdown.
+    let mut peak_edge = 0.0_f64;
+    let mut max_drawdown = 0.0_f64;
+    let mut fee_revenue = 0.0_f64;
+    // Scored retail orders abandoned over `retail_max_slippage_bps`.
+    let mut abandoned_orders = 0_u32;
+    let (mut dust_orders, mut dust_notional) = (0_u32, 0.0);
+    // Scored retail orders the router's price guard left unfilled.
+    let mut guarded_orders = 0_u32;
+    // Scored retail orders only partly filled, under `retail_partial_fills` or the trade caps.
+    let mut partial_fills = 0_u32;
+    // Scored steps in which the submission was found down.
+    let mut submission_down_steps = 0_u32;
+    let mut arb_quote_divergence = QuoteDivergence::default();
+    let mut retail_quote_divergence = QuoteDivergence::default();
+    // Arbitrage trades of the current step, by venue, in execution order.
+    let mut arb_trades = Vec::with_capacity(4);
+    let mut normalizer_fee_revenue = 0.0_f64;
+    // Cumulative retail volume (Y) filled by the submission and by all baselines, for the
+    // market share trace.
+    let mut submission_volume_y = 0.0_f64;
+    let mut baseline_volume_y = 0.0_f64;
+    let mut trade_hash = Fnv64::new();
+    // Under the event-driven clock each iteration of the main loop is one event, and
+    // `scored_step` is the step of `gbm_dt` it falls in.
+    let schedule = EventSchedule::for_config(config);
+    let total_steps = schedule.as_ref().map_or(config.total_steps(), |s| s.len() as u32);
+    let session_step = |step: u32| match &schedule {
+        Some(schedule) => Some(schedule.event(step).step),
+        None => config.session_step(step),
+    };
+    // The first scored step whose one-off settings (liquidity events) have not applied yet.
+    let mut pending_step = 0_u32;
+    let mut trade_hash_checkpoints =
+        Vec::with_capacity((total_steps / TRADE_HASH_CHECKPOINT_STEPS) as usize);
+    // Latency handicap state: set when the previous step's quotes ran over `quote_cu_budget`.
+    let mut submission_late = false;
+    let mut step_start_compute = amm_sub.quote_compute();
+    let mut fair_price = config.initial_price;
+    let generated;
+    let path = match path {
+        Some(path) => {
+            if path.seed() != config.seed || path.steps() != total_steps {
+                anyhow::bail!(
+                    "price path for seed {} over {} steps does not fit seed {} over {} steps",
+                    path.seed(),
+                    path.steps(),
+                    config.seed,
+                    total_steps
+                );
+            }
+            path
+        }
+        None => {
+            generated = PricePath::generate(config);
+            &generated
+        }
+    };
+    // Fair price at absolute step `step`, the volatility of the move into it and whether
+    // that move came in the volatile regime.
+    let price_at = |step: u32| {
+        let point = path.point(step);
+        (point.price, point.sigma, point.volatile)
+    };
+    // One step of lookahead, so informed orders can trade toward the next move.
+    let (mut next_fair_price, mut next_sigma, mut next_volatile) = price_at(0);
+    // Arbitrage visits venues in this order; a late submission goes last.
+    let venues = 1 + baselines.len() as u8;
+    let on_time_order: Vec<u8> = (0..venues).collect();
+    let late_order: Vec<u8> = (1..venues).chain([SUBMISSION_VENUE]).collect();
+    let mark = |amm: &BpfAmm, fair_price: f64| InventoryMark {
+        reserve_x: amm.reserve_x,
+        reserve_y: amm.reserve_y,
+        fair_price,
+    };
+    let mut inventory_start = mark(&amm_sub, fair_price);
+    let mut normalizer_start = mark(&baselines[0].amm, fair_price);
+    let starting_reserves: Vec<(f64, f64)> = std::iter::once(&amm_sub)
+        .chain(baselines.iter().map(|pool| &pool.amm))
+        .map(|amm| (amm.reserve_x, amm.reserve_y))
+        .collect();
+    let mut depletion = None;
+    let mut tape = Vec::new();
+    let mut liquidity_added = 0.0_f64;
+    let mut normalizer_liquidity_added = 0.0_f64;
+    let mut trajectory = match config.trajectory_stride {
+        Some(stride) => Vec::with_capacity((total_steps / stride.max(1)) as usize + 1),
+        None => Vec::new(),
+    };
 
-    for step in 0..config.n_steps {
-        amm_sub.set_current_step(step as u64);
-        amm_norm.set_current_step(step as u64);
-        let fair_price = price.step();
+    for step in 0..total_steps {
+        if step == config.warmup_steps {
+            inventory_start = mark(&amm_sub, fair_price);
+            normalizer_start = mark(&baselines[0].amm, fair_price);
+        }
+        // `None` while warming up: trades execute but earn nothing.
+        let scored_step = session_step(step);
+        // Programs see the step an event falls in, as they would on fixed steps.
+        let program_step = match schedule {
+            Some(_) => scored_step.unwrap_or(step),
+            None => step,
+        };
+        amm_sub.set_current_step(program_step as u64);
+        for pool in &mut baselines {
+            pool.amm.set_current_step(program_step as u64);
+        }
+        let previous_fair_price = fair_price;
+        fair_price = next_fair_price;
+        let (sigma, volatile) = (next_sigma, next_volatile);
+        (next_fair_price, next_sigma, next_volatile) = price_at(step + 1);
+        if schedule.is_none() && scored_step == Some(0) && step > config.warmup_steps {
+            // A new session: every pool reopens at its starting reserves. What it gave up or
+            // received counts as liquidity, valued at the prices on either side of the reset.
+            let reopen = |amm: &mut BpfAmm, reserves: (f64, f64)| {
+                let before = mark(amm, previous_fair_price).value();
+                amm.start_session(reserves);
+                mark(amm, fair_price).value() - before
+            };
+            liquidity_added += reopen(&mut amm_sub, starting_reserves[0]);
+            normalizer_liquidity_added += reopen(&mut baselines[0].amm, starting_reserves[1]);
+            for (pool, &reserves) in baselines[1..].iter_mut().zip(&starting_reserves[2..]) {
+                reopen(&mut pool.amm, reserves);
+            }
+            if let Some((_, signal)) = &mut momentum {
+                signal.clear();
+            }
+            tracing::debug!(step, "session start");
+        }
+        if let Some(scored) = scored_step {
+            // Events apply once, at the first iteration at or after their step.
+            let due = if schedule.is_some() {
+                pending_step..=scored
+            } else {
+                scored..=scored
+            };
+            pending_step = pending_step.max(scored + 1);
+            for event in config.liquidity_events.iter().filter(|e| due.contains(&e.step)) {
+                let notify = config.liquidity_event_callbacks;
+                let (added_x, added_y) = amm_sub.scale_liquidity(event.scale, notify);
+                liquidity_added += added_x * fair_price + added_y;
+                let (added_x, added_y) = baselines[0].amm.scale_liquidity(event.scale, notify);
+                normalizer_liquidity_added += added_x * fair_price + added_y;
+                for pool in &mut baselines[1..] {
+                    pool.amm.scale_liquidity(event.scale, notify);
+                }
+                tracing::debug!(step, scale = event.scale, "liquidity event");
+            }
+        }
+        if let Some(inventory) = config.arb_inventory {
+            if inventory.rebalance_steps > 0 && step > 0 && step % inventory.rebalance_steps == 0 {
+                arb.rebalance();
+            }
+        }
+        let step_start_fees = (amm_sub.fee_take(), baselines[0].amm.fee_take());
 
-        if let Some(result) = arb.execute_arb(&mut amm_sub, fair_price) {
-            submission_edge += result.edge;
+        let event = schedule.as_ref().map(|schedule| schedule.event(step));
+        let buy_prob =
+            scored_step.map_or(config.retail_buy_prob, |s| config.retail_buy_prob_at(s));
+        retail.set_buy_prob(buy_prob, |buy_prob| {
+            correlated_buy_prob(
+                buy_prob,
+                config.flow_price_correlation,
+                previous_fair_price,
+                fair_price,
+            )
+        });
+        // Arrival rates are per unit of time; a step lasts `gbm_dt` of it.
+        let intensity =
+            scored_step.map_or(1.0, |s| config.arrival_intensity_at(s)) * config.gbm_dt;
+        let mut orders = match event.map(|event| event.kind) {
+            Some(EventKind::Retail(class)) => vec![retail.next_order(class)],
+            Some(EventKind::ArbCheck) => Vec::new(),
+            None => {
+                retail.set_intensity(intensity);
+                retail.generate_orders()
+            }
+        };
+        if let Some(informed) = &mut informed {
+            for order in &mut orders {
+                informed.tag(order, fair_price, next_fair_price);
+            }
+        }
+        // Momentum orders arrive after the noise traders' and are never informed.
+        if let Some((trader, signal)) = &mut momentum {
+            signal.observe(fair_price);
+            trader.set_buy_prob(signal.buy_prob());
+            trader.set_intensity(intensity);
+            orders.extend(trader.generate_orders());
+        }
+        // Scripted orders come last, in place of the generated flow unless kept as
+        // background.
+        if let Some(scenario) = &config.scenario {
+            if !scenario.background_retail {
+                orders.clear();
+            }
+            if let Some(scored) = scored_step {
+                let scripted = scenario.orders.iter().filter(|o| o.step == scored);
+                orders.extend(scripted.map(|o| RetailOrder {
+                    is_buy: o.is_buy,
+                    size: o.size,
+                    informed: false,
+                    max_slippage_bps: o.max_slippage_bps,
+                    min_output: o.min_output,
+                    partial_fill: false,
+                    exact_output: false,
+                }));
+            }
+        }
+        if config.execution_mode == ExecutionMode::BatchAuction {
+            orders = net_orders(&orders);
+        }
+        // What the router takes the fair price to be; edge stays marked at the true price.
+        let observed_price = oracle.as_mut().map_or(fair_price, |o| o.observe(fair_price));
+        for order in &mut orders {
+            order.max_slippage_bps = order.max_slippage_bps.or(config.retail_max_slippage_bps);
+            order.partial_fill = config.retail_partial_fills;
+            order.exact_output = config.retail_exact_output;
         }
-        arb.execute_arb(&mut amm_norm, fair_price);
 
-        let orders = retail.generate_orders();
-        for order in &orders {
-            let trades = router.route_order(order, &mut amm_sub, &mut amm_norm, fair_price);
-            for trade in trades {
-                if trade.is_submission {
-                    let trade_edge = if trade.amm_buys_x {
-                        trade.amount_x * fair_price - trade.amount_y
+        // Where in the step the arbitrageur acts, relative to the retail orders.
+        let arb_at = match config.step_sequencing {
+            StepSequencing::ArbFirst => 0,
+            StepSequencing::ArbLast => orders.len(),
+            StepSequencing::Interleaved => {
+                let rng = sequencing.as_mut().expect("interleaving draws from its own stream");
+                rng.gen_range(0..=orders.len())
+            }
+        };
+        // Under batch routing, the submission's share of each direction's first order this
+        // step, found for the direction's total input and reused by its later orders.
+        let mut batch_shares: [Option<f64>; 2] = [None; 2];
+        for slot in 0..=orders.len() {
+            if slot == arb_at {
+                let arb_active = match event {
+                    Some(event) => event.kind == EventKind::ArbCheck,
+                    None => arb_arrivals.as_mut().is_none_or(|arrivals| arrivals.is_active()),
+                };
+                let arb_order: &[u8] = if !arb_active {
+                    &[]
+                } else if submission_late {
+                    &late_order
+                } else {
+                    &on_time_order
+                };
+                for &venue in arb_order {
+                    let amm = if venue == SUBMISSION_VENUE {
+                        &mut amm_sub
                     } else {
-                        trade.amount_y - trade.amount_x * fair_price
+                        &mut baselines[venue as usize - 1].amm
                     };
-                    submission_edge += trade_edge;
+                    let started = profile.then(Instant::now);
+                    let arb_result = arb.execute_arb(amm, fair_price);
+                    arb_time += started.map_or(Duration::ZERO, |t| t.elapsed());
+                    arb_trades.extend(arb_result.map(|result| (venue, result)));
+                }
+                if config.arb_cross_venue && arb_active {
+                    let started = profile.then(Instant::now);
+                    let cross =
+                        arb.execute_cross_arb(&mut amm_sub, &mut baselines[0].amm, fair_price);
+                    arb_time += started.map_or(Duration::ZERO, |t| t.elapsed());
+                    if let Some(cross) = cross {
+                        let (buy_venue, sell_venue) = if cross.buys_on_first {
+                            (SUBMISSION_VENUE, VENUE_NORMALIZER)
+                        } else {
+                            (VENUE_NORMALIZER, SUBMISSION_VENUE)
+                        };
+                        arb_trades.push((buy_venue, cross.buy));
+                        arb_trades.extend(cross.sell.map(|sell| (sell_venue, sell)));
+                    }
+                }
+                for (venue, result) in arb_trades.drain(..) {
+                    tracing::trace!(
+                        step,
+                        venue,
+                        amm_buys_x = result.amm_buys_x,
+                        amount_x = result.amount_x,
+                        amount_y = result.amount_y,
+                        edge = result.edge,
+                        "arb"
+                    );
+                    hash_trade(
+                        &mut trade_hash,
+                        step,
+                        venue,
+                        result.amm_buys_x,
+                        result.amount_x,
+                        result.amount_y,
+                    );
+                    if config.record_tape {
+                        tape.push(TapeTrade {
+                            step,
+                            venue,
+                            counterparty: Counterparty::Arbitrageur,
+                            amm_buys_x: result.amm_buys_x,
+                            amount_x: result.amount_x,
+                            amount_y: result.amount_y,
+                            fair_price,
+                        });
+                    }
+                    observer.on_arb(&TradeEvent {
+                        step,
+                        scored: scored_step.is_some(),
+                        venue,
+                        is_submission: venue == SUBMISSION_VENUE,
+                        amm_buys_x: result.amm_buys_x,
+                        amount_x: result.amount_x,
+                        amount_y: result.amount_y,
+                        edge: result.edge,
+                        fair_price,
+                    });
+                    if scored_step.is_some() {
+                        if venue == SUBMISSION_VENUE {
+                            submission_edge += result.edge;
+                            if let Some(quoted) = result.quoted_output {
+                                let (realized, y_per_unit) = if result.amm_buys_x {
+                                    (result.amount_y, 1.0)
+                                } else {
+                                    (result.amount_x, fair_price)
+                                };
+                                if realized != quoted {
+                                    tracing::debug!(step, quoted, realized, "arb quote diverged");
+                                }
+                                arb_quote_divergence.record(quoted, realized, y_per_unit);
+                            }
+                        }
+                        let (edges, volumes) = match venue {
+                            SUBMISSION_VENUE => {
+                                (&mut edge_by_counterparty, &mut volume_by_counterparty)
+                            }
+                            VENUE_NORMALIZER => (
+                                &mut normalizer_edge_by_counterparty,
+                                &mut normalizer_volume_by_counterparty,
+                            ),
+                            extra => (
+                                &mut extra_venue_edge_by_counterparty[extra as usize - 2],
+                                &mut extra_venue_volume_by_counterparty[extra as usize - 2],
+                            ),
+                        };
+                        edges.add(Counterparty::Arbitrageur, result.edge);
+                        volumes.add(Counterparty::Arbitrageur, result.amount_x, result.amount_y);
+                    }
+                }
+
+                // Instantaneous LVR rate, taken once arbitrage has aligned the pool with
+                // fair price.
+                if scored_step.is_some() {
+                    if let Some(depth) = amm_sub.local_liquidity() {
+                        let step_dt = event.map_or(config.gbm_dt, |event| event.elapsed);
+                        let step_variance = sigma * sigma * step_dt;
+                        lvr += 0.5 * step_variance * fair_price * fair_price * depth;
+                    }
+                }
+            }
+            let Some(order) = orders.get(slot) else {
+                break;
+            };
+            let input = if order.is_buy {
+                order.size
+            } else {
+                order.size / observed_price
+            };
+            if input <= config.min_trade_size {
+                tracing::trace!(step, is_buy = order.is_buy, size = order.size, "dust");
+                if scored_step.is_some() {
+                    dust_orders += 1;
+                    dust_notional += order.size;
+                }
+                continue;
+            }
+            let counterparty = if order.informed {
+                Counterparty::Informed
+            } else {
+                Counterparty::Retail
+            };
+            spots_before.clear();
+            spots_before.push(amm_sub.spot_price());
+            spots_before.extend(baselines.iter().map(|pool| pool.amm.spot_price()));
+            let started = profile.then(Instant::now);
+            let routed = if config.execution_mode == ExecutionMode::BatchRouted {
+                let share = *batch_shares[order.is_buy as usize].get_or_insert_with(|| {
+                    let total_input: f64 = orders
+                        .iter()
+                        .filter(|other| other.is_buy == order.is_buy)
+                        .map(|other| {
+                            if other.is_buy {
+                                other.size
+                            } else {
+                                other.size / observed_price
+                            }
+                        })
+                        .filter(|&input| input > config.min_trade_size)
+                        .sum();
+                    router.split_share(
+                        total_input,
+                        order.is_buy,
+                        &mut amm_sub,
+                        &mut baselines,
+                        submission_late,
+                    )
+                });
+                router.route_order_at_share(
+                    order,
+                    share,
+                    &mut amm_sub,
+                    &mut baselines,
+                    observed_price,
+                )
+            } else {
+                router.route_order(
+                    order,
+                    &mut amm_sub,
+                    &mut baselines,
+                    observed_price,
+                    submission_late,
+                )
+            };
+            router_time += started.map_or(Duration::ZERO, |t| t.elapsed());
+            let Some(routed) = routed else {
+                tracing::trace!(step, is_buy = order.is_buy, size = order.size, "abandoned");
+                if scored_step.is_some() {
+                    abandoned_orders += 1;
+                }
+                continue;
+            };
+            if routed.filled == 0.0 {
+                tracing::trace!(step, is_buy = order.is_buy, size = order.size, "guarded");
+                if scored_step.is_some() {
+                    guarded_orders += 1;
+                }
+                continue;
+            }
+            if routed.filled < 1.0 && scored_step.is_some() {
+                partial_fills += 1;
+            }
+            for trade in routed.trades {
+                tracing::trace!(
+                    step,
+                    venue = trade.venue,
+                    amm_buys_x = trade.amm_buys_x,
+                    amount_x = trade.amount_x,
+                    amount_y = trade.amount_y,
+                    "retail"
+                );
+                hash_trade(
+                    &mut trade_hash,
+                    step,
+                    trade.venue,
+                    trade.amm_buys_x,
+                    trade.amount_x,
+                    trade.amount_y,
+                );
+                let trade_edge = if trade.amm_buys_x {
+                    trade.amount_x * fair_price - trade.amount_y
+                } else {
+                    trade.amount_y - trade.amount_x * fair_price
+                };
+                if config.record_tape {
+                    tape.push(TapeTrade {
+                        step,
+                        venue: trade.venue,
+                        counterparty,
+                        amm_buys_x: trade.amm_buys_x,
+                        amount_x: trade.amount_x,
+                        amount_y: trade.amount_y,
+                        fair_price,
+                    });
                 }
+                observer.on_retail_trade(&TradeEvent {
+                    step,
+                    scored: scored_step.is_some(),
+                    venue: trade.venue,
+                    is_submission: trade.is_submission(),
+                    amm_buys_x: trade.amm_buys_x,
+                    amount_x: trade.amount_x,
+                    amount_y: trade.amount_y,
+                    edge: trade_edge,
+                    fair_price,
+                });
+                if trade.is_submission() {
+                    submission_volume_y += trade.amount_y;
+                } else {
+                    baseline_volume_y += trade.amount_y;
+                }
+                if scored_step.is_some() {
+                    if trade.is_submission() {
+                        submission_edge += trade_edge;
+                    }
+                    if let Some(quoted) = trade.quoted_output {
+                        let (realized, y_per_unit) = if trade.amm_buys_x {
+                            (trade.amount_y, 1.0)
+                        } else {
+                            (trade.amount_x, fair_price)
+                        };
+                        if realized != quoted {
+                            tracing::debug!(step, quoted, realized, "retail quote diverged");
+                        }
+                        retail_quote_divergence.record(quoted, realized, y_per_unit);
+                    }
+                    let (edges, volumes) = match trade.venue {
+                        SUBMISSION_VENUE => {
+                            (&mut edge_by_counterparty, &mut volume_by_counterparty)
+                        }
+                        VENUE_NORMALIZER => (
+                            &mut normalizer_edge_by_counterparty,
+                            &mut normalizer_volume_by_counterparty,
+                        ),
+                        extra => (
+                            &mut extra_venue_edge_by_counterparty[extra as usize - 2],
+                            &mut extra_venue_volume_by_counterparty[extra as usize - 2],
+                        ),
+                    };
+                    edges.add(counterparty, trade_edge);
+                    volumes.add(counterparty, trade.amount_x, trade.amount_y);
+                    execution_by_venue[trade.venue as usize].record(
+                        trade.amm_buys_x,
+                        trade.amount_x,
+                        trade.amount_y,
+                        fair_price,
+                        spots_before[trade.venue as usize],
+                    );
+                }
+            }
+        }
+
+        if scored_step.is_some() {
+            fee_revenue += amm_sub.fee_take().value_since(&step_start_fees.0, fair_price);
+            normalizer_fee_revenue +=
+                baselines[0].amm.fee_take().value_since(&step_start_fees.1, fair_price);
+            if amm_sub.known_down() {
+                submission_down_steps += 1;
+            }
+        }
+
+        tracing::trace!(
+            seed = config.seed,
+            step,
+            submission_volume_y,
+            baseline_volume_y,
+            share = submission_volume_y / (submission_volume_y + baseline_volume_y).max(1e-12),
+            reserve_x = amm_sub.reserve_x,
+            reserve_y = amm_sub.reserve_y,
+            fair_price,
+            "market share"
+        );
+
+        if scored_step.is_some() {
+            peak_edge = peak_edge.max(submission_edge);
+            max_drawdown = max_drawdown.max(peak_edge - submission_edge);
+        }
+        observer.on_step(&StepEvent {
+            step,
+            scored: scored_step.is_some(),
+            fair_price,
+            volatile,
+            submission_reserves: (amm_sub.reserve_x, amm_sub.reserve_y),
+            normalizer_reserves: (baselines[0].amm.reserve_x, baselines[0].amm.reserve_y),
+            submission_edge,
+        });
+
+        if let Some(stride) = config.trajectory_stride {
+            if (step + 1) % stride == 0 || step + 1 == total_steps {
+                trajectory.push(ReserveSnapshot {
+                    step,
+                    reserve_x: amm_sub.reserve_x,
+                    reserve_y: amm_sub.reserve_y,
+                    fair_price,
+                });
+            }
+        }
+
+        if let Some(budget) = config.quote_cu_budget {
+            let usage = amm_sub.quote_compute();
+            let step_usage = usage.since(&step_start_compute);
+            submission_late = step_usage.calls > 0 && step_usage.avg_cu() > budget as f64;
+            step_start_compute = usage;
+        }
+
+        if config.after_swap_failure_policy == AfterSwapFailurePolicy::FailSim {
+            if let Some(err) = amm_sub.first_after_swap_error() {
+                anyhow::bail!("submission after_swap failed at step {}: {}", step, err);
             }
         }
+
+        if (step + 1) % TRADE_HASH_CHECKPOINT_STEPS == 0 {
+            trade_hash_checkpoints.push(trade_hash.finish());
+        }
+
+        depletion = find_depletion(
+            step,
+            &mut amm_sub,
+            &mut baselines,
+            &starting_reserves,
+            config.depletion_reserve_frac,
+        );
+        if let Some(found) = &depletion {
+            tracing::warn!(seed = config.seed, "{}; ending simulation", found);
+            break;
+        }
     }
 
-    Ok(SimResult {
+    let inventory_end = mark(&amm_sub, fair_price);
+    let normalizer_end = mark(&baselines[0].amm, fair_price);
+    // With no scored steps there is nothing to mark; report zero PnL.
+    if config.n_steps == 0 {
+        inventory_start = inventory_end;
+        normalizer_start = normalizer_end;
+    }
+    let (mut inventory_revaluation, normalizer_inventory_revaluation) = match config.edge_mode {
+        EdgeMode::Flow => (0.0, 0.0),
+        EdgeMode::InventoryAdjusted => (
+            inventory_end.value() - inventory_start.value() - liquidity_added - submission_edge,
+            normalizer_end.value()
+                - normalizer_start.value()
+                - normalizer_liquidity_added
+                - normalizer_edge_by_counterparty.total(),
+        ),
+    };
+    submission_edge += inventory_revaluation;
+    let shape = amm_sub.take_shape_report();
+    // Only recorded, rather than fatal, under `ShapeViolationPolicy::ZeroEdge`.
+    if shape.violations > 0 {
+        submission_edge = 0.0;
+        edge_by_counterparty = EdgeBreakdown::default();
+        inventory_revaluation = 0.0;
+    }
+    let checksum = engine_checksum(trade_hash, &amm_sub, &baselines);
+    let timing = ComponentTiming {
+        arb_ns: arb_time.as_nanos() as u64,
+        router_ns: router_time.as_nanos() as u64,
+        submission_program_ns: amm_sub.program_time().as_nanos() as u64,
+        baseline_program_ns: baselines
+            .iter()
+            .map(|pool| pool.amm.program_time().as_nanos() as u64)
+            .sum(),
+    };
+    search_stats::add_quote_cache_hits(
+        amm_sub.quote_cache_hits()
+            + baselines.iter().map(|pool| pool.amm.quote_cache_hits()).sum::<u64>(),
+    );
+    let after_swap_failures = match config.after_swap_failure_policy {
+        AfterSwapFailurePolicy::CountAndReport => amm_sub.after_swap_failures(),
+        AfterSwapFailurePolicy::Ignore | AfterSwapFailurePolicy::FailSim => 0,
+    };
+    tracing::debug!(
+        seed = config.seed,
+        submission_edge,
+        trade_hash = format_args!("{:016x}", trade_hash.finish()),
+        "simulation finished"
+    );
+    let result = SimResult {
         seed: config.seed,
         submission_edge,
-    })
+        edge_by_counterparty,
+        volume_by_counterparty,
+        max_drawdown,
+        lvr,
+        fee_revenue,
+        normalizer_fee_revenue,
+        inventory_start,
+        inventory_end,
+        liquidity_added,
+        inventory_revaluation,
+        normalizer_inventory_revaluation,
+        trajectory,
+        tape,
+        normalizer_edge_by_counterparty,
+        normalizer_volume_by_counterparty,
+        extra_venue_edge_by_counterparty,
+        extra_venue_volume_by_counterparty,
+        execution_by_venue,
+        trade_hash: trade_hash.finish(),
+        trade_hash_checkpoints,
+        checksum,
+        shape,
+        submission_compute: amm_sub.quote_compute(),
+        submission_after_swap_compute: amm_sub.after_swap_compute(),
+        timing,
+        after_swap_failures,
+        abandoned_orders,
+        partial_fills,
+        dust_orders,
+        dust_notional,
+        guarded_orders,
+        submission_down_steps,
+        arb_quote_divergence,
+        retail_quote_divergence,
+        depletion,
+    };
+    observer.on_finish(&result);
+    Ok(result)
+}
+
+/// The trade hash followed by each venue's final reserves (submission, then baselines in
+/// order) and the submission's final storage, all bit-exact.
+fn engine_checksum(mut hasher: Fnv64, amm_sub: &BpfAmm, baselines: &[BaselinePool]) -> u64 {
+    for amm in std::iter::once(amm_sub).chain(baselines.iter().map(|pool| &pool.amm)) {
+        hasher.write_f64(amm.reserve_x);
+        hasher.write_f64(amm.reserve_y);
+    }
+    hasher.write(amm_sub.storage());
+    hasher.finish()
+}
+
+/// The first venue (submission, then baselines in order) that can no longer trade, if any.
+fn find_depletion(
+    step: u32,
+    amm_sub: &mut BpfAmm,
+    baselines: &mut [BaselinePool],
+    starting_reserves: &[(f64, f64)],
+    reserve_frac: f64,
+) -> Option<Depletion> {
+    let probe_quotes = (step + 1).is_multiple_of(DEPLETION_PROBE_STEPS);
+    let amms = std::iter::once(amm_sub).chain(baselines.iter_mut().map(|pool| &mut pool.amm));
+    for (venue, (amm, &(x0, y0))) in amms.zip(starting_reserves).enumerate() {
+        // Negated so NaN reserves count as collapsed.
+        let collapsed = !(amm.reserve_x >= x0 * reserve_frac && amm.reserve_y >= y0 * reserve_frac);
+        let cause = if collapsed {
+            DepletionCause::Reserves
+        } else if probe_quotes && !amm.quotes_any_size() {
+            DepletionCause::NoQuotes
+        } else {
+            continue;
+        };
+        return Some(Depletion {
+            step,
+            venue: venue as u8,
+            cause,
+        });
+    }
+    None
+}
+
+/// The normalizer followed by one pool per `config.extra_venues` entry, each an instance of
+/// the normalizer program seeded with its own fee, routed as black boxes under
+/// `config.black_box_baselines`.
+fn baseline_pools(
+    config: &SimulationConfig,
+    mut make_amm: impl FnMut(f64, f64, String) -> BpfAmm,
+) -> Vec<BaselinePool> {
+    let normalizer = (config.norm_fee_bps, config.norm_liquidity_mult);
+    let extras = config.extra_venues.iter().map(|v| (v.fee_bps, v.liquidity_mult));
+    std::iter::once(normalizer)
+        .chain(extras)
+        .enumerate()
+        .map(|(i, (fee_bps, liquidity_mult))| {
+            let name = match i {
+                0 => "normalizer".to_string(),
+                i => format!("baseline {}", i),
+            };
+            let mut amm = make_amm(
+                config.initial_x * liquidity_mult,
+                config.initial_y * liquidity_mult,
+                name,
+            );
+            amm.set_initial_storage(&fee_bps.to_le_bytes());
+            BaselinePool {
+                amm,
+                fee_bps,
+                black_box: config.black_box_baselines,
+            }
+        })
+        .collect()
 }
 
 /// Run simulation with BPF programs (slow, for validation)
@@ -74,6 +985,21 @@ pub fn run_simulation(
     submission_program: BpfProgram,
     normalizer_program: BpfProgram,
     config: &SimulationConfig,
+) -> anyhow::Result<SimResult> {
+    run_simulation_observed(
+        submission_program,
+        normalizer_program,
+        config,
+        &mut NoopObserver,
+    )
+}
+
+/// [`run_simulation`], reporting every trade and step to `observer`.
+pub fn run_simulation_observed(
+    submission_program: BpfProgram,
+    normalizer_program: BpfProgram,
+    config: &SimulationConfig,
+    observer: &mut dyn SimObserver,
 ) -> anyhow::Result<SimResult> {
     let amm_sub = BpfAmm::new(
         submission_program,
@@ -81,16 +1007,10 @@ pub fn run_simulation(
         config.initial_y,
         "submission".to_string(),
     );
-    let norm_x = config.initial_x * config.norm_liquidity_mult;
-    let norm_y = config.initial_y * config.norm_liquidity_mult;
-    let mut amm_norm = BpfAmm::new(
-        normalizer_program,
-        norm_x,
-        norm_y,
-        "normalizer".to_string(),
-    );
-    amm_norm.set_initial_storage(&config.norm_fee_bps.to_le_bytes());
-    run_sim_inner(amm_sub, amm_norm, config)
+    let baselines = baseline_pools(config, |x, y, name| {
+        BpfAmm::new(normalizer_program.clone(), x, y, name)
+    });
+    run_sim_inner(amm_sub, baselines, config, None, observer)
 }
 
 /// Run simulation with native swap functions (fast, for production)
@@ -100,6 +1020,25 @@ pub fn run_simulation_native(
     normalizer_fn: SwapFn,
     normalizer_after_swap: Option<AfterSwapFn>,
     config: &SimulationConfig,
+) -> anyhow::Result<SimResult> {
+    run_simulation_native_observed(
+        submission_fn,
+        submission_after_swap,
+        normalizer_fn,
+        normalizer_after_swap,
+        config,
+        &mut NoopObserver,
+    )
+}
+
+/// [`run_simulation_native`], reporting every trade and step to `observer`.
+pub fn run_simulation_native_observed(
+    submission_fn: SwapFn,
+    submission_after_swap: Option<AfterSwapFn>,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    config: &SimulationConfig,
+    observer: &mut dyn SimObserver,
 ) -> anyhow::Result<SimResult> {
     let amm_sub = BpfAmm::new_native(
         submission_fn,
@@ -108,17 +1047,34 @@ pub fn run_simulation_native(
         config.initial_y,
         "submission".to_string(),
     );
-    let norm_x = config.initial_x * config.norm_liquidity_mult;
-    let norm_y = config.initial_y * config.norm_liquidity_mult;
-    let mut amm_norm = BpfAmm::new_native(
-        normalizer_fn,
-        normalizer_after_swap,
-        norm_x,
-        norm_y,
-        "normalizer".to_string(),
+    let baselines = baseline_pools(config, |x, y, name| {
+        BpfAmm::new_native(normalizer_fn, normalizer_after_swap, x, y, name)
+    });
+    run_sim_inner(amm_sub, baselines, config, None, observer)
+}
+
+/// [`run_simulation_native_observed`] along a pre-drawn `path`, which should come from
+/// [`PricePath::generate`] on this config; fails if its seed or length does not match.
+pub fn run_simulation_native_on_path(
+    submission_fn: SwapFn,
+    submission_after_swap: Option<AfterSwapFn>,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    config: &SimulationConfig,
+    path: &PricePath,
+    observer: &mut dyn SimObserver,
+) -> anyhow::Result<SimResult> {
+    let amm_sub = BpfAmm::new_native(
+        submission_fn,
+        submission_after_swap,
+        config.initial_x,
+        config.initial_y,
+        "submission".to_string(),
     );
-    amm_norm.set_initial_storage(&config.norm_fee_bps.to_le_bytes());
-    run_sim_inner(amm_sub, amm_norm, config)
+    let baselines = baseline_pools(config, |x, y, name| {
+        BpfAmm::new_native(normalizer_fn, normalizer_after_swap, x, y, name)
+    });
+    run_sim_inner(amm_sub, baselines, config, Some(path), observer)
 }
 
 /// Run simulation with BPF submission + native normalizer (mixed mode)
@@ -127,6 +1083,23 @@ pub fn run_simulation_mixed(
     normalizer_fn: SwapFn,
     normalizer_after_swap: Option<AfterSwapFn>,
     config: &SimulationConfig,
+) -> anyhow::Result<SimResult> {
+    run_simulation_mixed_observed(
+        submission_program,
+        normalizer_fn,
+        normalizer_after_swap,
+        config,
+        &mut NoopObserver,
+    )
+}
+
+/// [`run_simulation_mixed`], reporting every trade and step to `observer`.
+pub fn run_simulation_mixed_observed(
+    submission_program: BpfProgram,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    config: &SimulationConfig,
+    observer: &mut dyn SimObserver,
 ) -> anyhow::Result<SimResult> {
     let amm_sub = BpfAmm::new(
         submission_program,
@@ -134,15 +1107,8 @@ pub fn run_simulation_mixed(
         config.initial_y,
         "submission".to_string(),
     );
-    let norm_x = config.initial_x * config.norm_liquidity_mult;
-    let norm_y = config.initial_y * config.norm_liquidity_mult;
-    let mut amm_norm = BpfAmm::new_native(
-        normalizer_fn,
-        normalizer_after_swap,
-        norm_x,
-        norm_y,
-        "normalizer".to_string(),
-    );
-    amm_norm.set_initial_storage(&config.norm_fee_bps.to_le_bytes());
-    run_sim_inner(amm_sub, amm_norm, config)
+    let baselines = baseline_pools(config, |x, y, name| {
+        BpfAmm::new_native(normalizer_fn, normalizer_after_swap, x, y, name)
+    });
+    run_sim_inner(amm_sub, baselines, config, None, observer)
 }
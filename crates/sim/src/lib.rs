@@ -1,8 +1,13 @@
 pub mod amm;
 pub mod arbitrageur;
 pub mod bench;
+pub mod calibrate;
+pub mod clock;
 mod curve_checks;
 pub mod engine;
+pub mod observer;
+#[cfg(feature = "prebuilt-programs")]
+pub mod prebuilt;
 pub mod price_process;
 pub mod retail;
 pub mod router;
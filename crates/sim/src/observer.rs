@@ -0,0 +1,55 @@
+//! Per-step instrumentation hooks. The engine reports every trade and step to a
+//! [`SimObserver`], so metrics and logging can be collected without touching the engine.
+
+use prop_amm_shared::result::SimResult;
+
+/// One executed trade, from the pool's point of view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeEvent {
+    pub step: u32,
+    /// `false` during warmup steps, whose trades do not count toward edge.
+    pub scored: bool,
+    /// [`SUBMISSION_VENUE`](crate::router::SUBMISSION_VENUE), or `1 + i` for baseline `i`
+    /// (the normalizer is baseline 0).
+    pub venue: u8,
+    pub is_submission: bool,
+    pub amm_buys_x: bool,
+    pub amount_x: f64,
+    pub amount_y: f64,
+    /// Pool edge of this trade at `fair_price`.
+    pub edge: f64,
+    pub fair_price: f64,
+}
+
+/// State at the end of a step, after arbitrage and retail flow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepEvent {
+    pub step: u32,
+    pub scored: bool,
+    pub fair_price: f64,
+    /// Whether the move to `fair_price` came in the volatile regime of
+    /// `SimulationConfig::volatility_regimes`.
+    pub volatile: bool,
+    pub submission_reserves: (f64, f64),
+    pub normalizer_reserves: (f64, f64),
+    /// Cumulative scored submission edge so far.
+    pub submission_edge: f64,
+}
+
+/// Callbacks invoked by the engine during one simulation. Every method defaults to a no-op.
+pub trait SimObserver {
+    /// After each step's trades, once per step.
+    fn on_step(&mut self, _event: &StepEvent) {}
+    /// After each arbitrage trade, on any venue.
+    fn on_arb(&mut self, _trade: &TradeEvent) {}
+    /// After each retail fill; an order split across venues reports one fill per venue.
+    fn on_retail_trade(&mut self, _trade: &TradeEvent) {}
+    /// Once, with the finished result.
+    fn on_finish(&mut self, _result: &SimResult) {}
+}
+
+/// Observes nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl SimObserver for NoopObserver {}
@@ -0,0 +1,36 @@
+//! Reference BPF programs embedded at compile time (`prebuilt-programs` feature).
+//!
+//! The build script compiles `programs/normalizer` and `programs/starter` with
+//! `cargo build-sbf` (or takes them from `PROP_AMM_PREBUILT_DIR`); binaries compiled with
+//! the feature then carry them and never touch `programs/*/target` at runtime.
+use std::sync::OnceLock;
+
+use prop_amm_executor::BpfProgram;
+
+const NORMALIZER_SO: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/normalizer.so"));
+const STARTER_SO: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/starter.so"));
+
+// Verification and JIT compilation dominate load time, so each artifact is loaded once per
+// process and cloned (cheap Arc clones) for every simulation.
+static NORMALIZER: OnceLock<Result<BpfProgram, String>> = OnceLock::new();
+static STARTER: OnceLock<Result<BpfProgram, String>> = OnceLock::new();
+
+fn load_cached(
+    cell: &'static OnceLock<Result<BpfProgram, String>>,
+    bytes: &[u8],
+    name: &str,
+) -> anyhow::Result<BpfProgram> {
+    cell.get_or_init(|| BpfProgram::load(bytes).map_err(|e| e.to_string()))
+        .clone()
+        .map_err(|e| anyhow::anyhow!("failed to load prebuilt {name} program: {e}"))
+}
+
+/// The embedded normalizer (constant-product, fee read from storage).
+pub fn normalizer_program() -> anyhow::Result<BpfProgram> {
+    load_cached(&NORMALIZER, NORMALIZER_SO, "normalizer")
+}
+
+/// The embedded starter strategy (constant-product, 500 bps fee).
+pub fn starter_program() -> anyhow::Result<BpfProgram> {
+    load_cached(&STARTER, STARTER_SO, "starter")
+}
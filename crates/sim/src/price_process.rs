@@ -1,33 +1,374 @@
-use rand::SeedableRng;
-use rand_distr::{Distribution, StandardNormal};
+//! Fair price processes. The engine drives a `dyn PriceProcess` built by [`from_config`],
+//! so a new process is a new [`PriceProcessKind`] variant and an arm there.
+
+use prop_amm_shared::config::{
+    PriceGap, PriceProcessKind, Scenario, SimulationConfig, VolatilityRegimes,
+};
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, StandardNormal, StudentT};
 use rand_pcg::Pcg64;
 
+use crate::clock::EventSchedule;
+use crate::engine::RngStreams;
+
+/// A fair price path, advanced one step at a time.
+pub trait PriceProcess: Send {
+    /// Advance one step and return the new price.
+    fn step(&mut self) -> f64;
+
+    fn current_price(&self) -> f64;
+
+    /// Set the current price outright and return any internal state (e.g. the volatility
+    /// regime) to how a simulation starts, keeping the RNG streams where they are. Called
+    /// when a session opens.
+    fn reset(&mut self, price: f64);
+
+    /// Jump the current price by `factor`; later steps move from the gapped price.
+    fn gap(&mut self, factor: f64);
+
+    /// Volatility of the latest move, per unit of `gbm_dt`; drives the LVR rate.
+    fn sigma(&self) -> f64;
+
+    /// Make later steps last `dt` instead of the configured `gbm_dt`. The event-driven clock
+    /// calls this before every move.
+    fn set_dt(&mut self, dt: f64);
+
+    /// Whether the latest move came in a volatile regime.
+    fn is_volatile(&self) -> bool {
+        false
+    }
+}
+
+/// The process `config.price_process` selects, seeded from `streams`.
+pub fn from_config(config: &SimulationConfig, streams: &RngStreams) -> Box<dyn PriceProcess> {
+    match config.price_process {
+        PriceProcessKind::Gbm => {
+            let mut process = GBMPriceProcess::new(
+                config.initial_price,
+                config.gbm_mu,
+                config.gbm_sigma,
+                config.gbm_dt,
+                streams.price,
+            );
+            if let (Some(regimes), Some(seed)) = (config.volatility_regimes, streams.regimes) {
+                process = process.with_regimes(regimes, seed);
+            }
+            if let Some(df) = config.gbm_shock_df {
+                process = process.with_student_t(df);
+            }
+            Box::new(process)
+        }
+    }
+}
+
+/// Geometric Brownian motion, optionally with regime-switching volatility and fat-tailed
+/// shocks.
 pub struct GBMPriceProcess {
     current_price: f64,
+    mu: f64,
+    sigma: f64,
+    dt: f64,
     drift_term: f64,
     vol_term: f64,
     rng: Pcg64,
+    regimes: Option<RegimeSwitch>,
+    /// Student-t innovations in place of Gaussian ones, with the factor that scales them to
+    /// unit variance.
+    student_t: Option<(StudentT<f64>, f64)>,
+}
+
+/// Calm/volatile state of a regime-switching process, with its own RNG so the diffusion
+/// draws are the same with or without switching.
+struct RegimeSwitch {
+    params: VolatilityRegimes,
+    volatile: bool,
+    rng: Pcg64,
 }
 
 impl GBMPriceProcess {
     pub fn new(initial_price: f64, mu: f64, sigma: f64, dt: f64, seed: u64) -> Self {
-        Self {
+        let mut process = Self {
             current_price: initial_price,
-            drift_term: (mu - 0.5 * sigma * sigma) * dt,
-            vol_term: sigma * dt.sqrt(),
+            mu,
+            sigma,
+            dt,
+            drift_term: 0.0,
+            vol_term: 0.0,
             rng: Pcg64::seed_from_u64(seed),
+            regimes: None,
+            student_t: None,
+        };
+        process.set_sigma(sigma);
+        process
+    }
+
+    /// Switch volatility between `regimes.calm_sigma` and `regimes.volatile_sigma`, drawing
+    /// the transitions from `seed`. Starts calm.
+    pub fn with_regimes(mut self, regimes: VolatilityRegimes, seed: u64) -> Self {
+        self.set_sigma(regimes.calm_sigma);
+        self.regimes = Some(RegimeSwitch {
+            params: regimes,
+            volatile: false,
+            rng: Pcg64::seed_from_u64(seed),
+        });
+        self
+    }
+
+    /// Draw each step's shock from a Student-t distribution with `df` degrees of freedom,
+    /// rescaled to unit variance so `sigma` keeps its meaning. Needs `df > 2`.
+    pub fn with_student_t(mut self, df: f64) -> Self {
+        let dist = StudentT::new(df).expect("degrees of freedom must be positive");
+        self.student_t = Some((dist, ((df - 2.0) / df).sqrt()));
+        self
+    }
+
+    fn set_sigma(&mut self, sigma: f64) {
+        self.sigma = sigma;
+        self.drift_term = (self.mu - 0.5 * sigma * sigma) * self.dt;
+        self.vol_term = sigma * self.dt.sqrt();
+    }
+}
+
+impl PriceProcess for GBMPriceProcess {
+    #[inline]
+    fn step(&mut self) -> f64 {
+        if let Some(regimes) = &mut self.regimes {
+            let p = &regimes.params;
+            let switch = if regimes.volatile {
+                p.volatile_to_calm
+            } else {
+                p.calm_to_volatile
+            };
+            if regimes.rng.gen::<f64>() < switch {
+                regimes.volatile = !regimes.volatile;
+                let sigma = if regimes.volatile {
+                    p.volatile_sigma
+                } else {
+                    p.calm_sigma
+                };
+                self.set_sigma(sigma);
+            }
         }
+        let z: f64 = match &self.student_t {
+            Some((dist, scale)) => dist.sample(&mut self.rng) * scale,
+            None => StandardNormal.sample(&mut self.rng),
+        };
+        self.current_price *= (self.drift_term + self.vol_term * z).exp();
+        self.current_price
     }
 
     #[inline]
-    pub fn current_price(&self) -> f64 {
+    fn current_price(&self) -> f64 {
         self.current_price
     }
 
+    /// Also returns to the calm regime.
+    #[inline]
+    fn reset(&mut self, price: f64) {
+        self.current_price = price;
+        if let Some(calm_sigma) = self.regimes.as_mut().map(|r| {
+            r.volatile = false;
+            r.params.calm_sigma
+        }) {
+            self.set_sigma(calm_sigma);
+        }
+    }
+
+    #[inline]
+    fn gap(&mut self, factor: f64) {
+        self.current_price *= factor;
+    }
+
+    #[inline]
+    fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    #[inline]
+    fn set_dt(&mut self, dt: f64) {
+        self.dt = dt;
+        self.set_sigma(self.sigma);
+    }
+
+    /// Always false without regime switching.
     #[inline]
-    pub fn step(&mut self) -> f64 {
+    fn is_volatile(&self) -> bool {
+        self.regimes.as_ref().is_some_and(|r| r.volatile)
+    }
+}
+
+/// The fair price at every step of one simulation, drawn up front so runs on the same
+/// config (both sides of a paired comparison, a replay) can share one path instead of each
+/// redrawing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricePath {
+    seed: u64,
+    points: Vec<PathPoint>,
+}
+
+/// One step of a [`PricePath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    pub price: f64,
+    /// Volatility of the move into this step, per unit of `gbm_dt`.
+    pub sigma: f64,
+    /// Whether that move came in the volatile regime.
+    pub volatile: bool,
+}
+
+impl PricePath {
+    /// The path a simulation of `config` follows. Each step, warm-up included, is one
+    /// diffusion move (from `initial_price` when a later session opens) followed by any
+    /// gaps scheduled there; one extra step past the end serves the engine's lookahead. A
+    /// scenario that scripts prices replaces all of that.
+    pub fn generate(config: &SimulationConfig) -> Self {
+        if let Some(schedule) = EventSchedule::for_config(config) {
+            return Self::on_events(config, &schedule);
+        }
+        if let Some(scenario) = config.scenario.as_ref().filter(|s| !s.prices.is_empty()) {
+            return Self::scripted(config, scenario);
+        }
+        let streams = RngStreams::for_config(config);
+        let mut process = from_config(config, &streams);
+        let gaps = gap_schedule(config, streams.gaps);
+        let points = (0..=config.total_steps())
+            .map(|step| {
+                let session_step = config.session_step(step);
+                if session_step == Some(0) && step > config.warmup_steps {
+                    process.reset(config.initial_price);
+                }
+                process.step();
+                if let Some(scored) = session_step {
+                    for gap in gaps.iter().filter(|gap| gap.step == scored) {
+                        process.gap(1.0 + gap.size);
+                    }
+                }
+                PathPoint {
+                    price: process.current_price(),
+                    sigma: process.sigma(),
+                    volatile: process.is_volatile(),
+                }
+            })
+            .collect();
+        Self {
+            seed: config.seed,
+            points,
+        }
+    }
+
+    /// The price at every event of `schedule`, each move lasting the time since the previous
+    /// event, then at the end of the simulation as the lookahead. Gaps apply at the first
+    /// event at or after their step. Regimes switch once per move.
+    fn on_events(config: &SimulationConfig, schedule: &EventSchedule) -> Self {
+        let streams = RngStreams::for_config(config);
+        let mut process = from_config(config, &streams);
+        let gaps = gap_schedule(config, streams.gaps);
+        // The first step whose gaps have not applied yet.
+        let mut pending = 0;
+        let moves = schedule.events().iter().map(|event| (event.elapsed, event.step));
+        let points = moves
+            .chain([(schedule.remaining(), config.n_steps)])
+            .map(|(elapsed, step)| {
+                process.set_dt(elapsed);
+                process.step();
+                for gap in gaps.iter().filter(|gap| (pending..=step).contains(&gap.step)) {
+                    process.gap(1.0 + gap.size);
+                }
+                pending = pending.max(step + 1);
+                PathPoint {
+                    price: process.current_price(),
+                    sigma: process.sigma(),
+                    volatile: process.is_volatile(),
+                }
+            })
+            .collect();
+        Self {
+            seed: config.seed,
+            points,
+        }
+    }
+
+    /// `scenario`'s prices at every step, each move's volatility being its realized size.
+    fn scripted(config: &SimulationConfig, scenario: &Scenario) -> Self {
+        let mut previous = None;
+        let points = (0..=config.total_steps())
+            .map(|step| {
+                let session_step = config.session_step(step).unwrap_or(0);
+                let price = scenario.price_at(session_step).unwrap_or(config.initial_price);
+                let sigma = previous.map_or(0.0, |p: f64| (price / p).ln().abs())
+                    / config.gbm_dt.sqrt();
+                previous = Some(price);
+                PathPoint {
+                    price,
+                    sigma,
+                    volatile: false,
+                }
+            })
+            .collect();
+        Self {
+            seed: config.seed,
+            points,
+        }
+    }
+
+    /// Seed of the config the path was drawn for.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Steps the path covers (events, under the event-driven clock), not counting the
+    /// lookahead step.
+    pub fn steps(&self) -> u32 {
+        self.points.len() as u32 - 1
+    }
+
+    /// The point at absolute step `step`, up to and including [`Self::steps`].
+    pub fn point(&self, step: u32) -> PathPoint {
+        self.points[step as usize]
+    }
+
+    /// Fair prices at every step, lookahead included.
+    pub fn prices(&self) -> impl Iterator<Item = f64> + '_ {
+        self.points.iter().map(|point| point.price)
+    }
+}
+
+/// The fair price as an imperfect oracle reports it: the true price times a log-normal
+/// error, drawn afresh at every observation.
+pub struct PriceOracle {
+    noise: f64,
+    rng: Pcg64,
+}
+
+impl PriceOracle {
+    pub fn new(noise: f64, seed: u64) -> Self {
+        Self {
+            noise,
+            rng: Pcg64::seed_from_u64(seed),
+        }
+    }
+
+    pub fn observe(&mut self, fair_price: f64) -> f64 {
         let z: f64 = StandardNormal.sample(&mut self.rng);
-        self.current_price *= (self.drift_term + self.vol_term * z).exp();
-        self.current_price
+        fair_price * (self.noise * z).exp()
+    }
+}
+
+/// Every gap a simulation applies: `config.price_gaps`, then `config.random_gaps` drawn from
+/// `seed`, each at a uniform scored step and equally likely up or down.
+pub fn gap_schedule(config: &SimulationConfig, seed: Option<u64>) -> Vec<PriceGap> {
+    let mut gaps = config.price_gaps.clone();
+    if let Some(seed) = seed.filter(|_| config.n_steps > 0) {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        for _ in 0..config.random_gaps {
+            let step = rng.gen_range(0..config.n_steps);
+            let size = if rng.gen::<bool>() {
+                config.random_gap_size
+            } else {
+                -config.random_gap_size
+            };
+            gaps.push(PriceGap { step, size });
+        }
     }
+    gaps
 }
@@ -1,16 +1,43 @@
+use std::collections::VecDeque;
+
+use prop_amm_shared::config::RetailClass;
 use rand::SeedableRng;
 use rand_distr::{Distribution, LogNormal, Poisson};
 use rand_pcg::Pcg64;
 
+#[derive(Clone)]
 pub struct RetailOrder {
     pub is_buy: bool,
     pub size: f64,
+    /// Set by [`InformedFlow`]: the direction was chosen from the next price move.
+    pub informed: bool,
+    /// Worst all-in cost against the fair price, in bps, the trader accepts; an order whose
+    /// best routed execution costs more is abandoned. `None` trades at any price.
+    pub max_slippage_bps: Option<f64>,
+    /// Smallest total output the trader accepts, in X for buys and Y for sells; an order
+    /// routed for less is abandoned. `None` accepts any output.
+    pub min_output: Option<f64>,
+    /// An order that misses its limits fills the largest part of its size that meets them
+    /// instead of being abandoned; `min_output` scales with the part filled.
+    pub partial_fill: bool,
+    /// The order asks for an exact output instead of spending an exact input: a buy for
+    /// `size / fair_price` X, a sell for `size` Y. The router finds the input that pays it
+    /// out.
+    pub exact_output: bool,
 }
 
 pub struct RetailTrader {
+    arrival_rate: f64,
+    /// Multiplier currently applied to `arrival_rate`.
+    intensity: f64,
     buy_prob: f64,
+    /// Extra probability that an order repeats the previous order's direction.
+    persistence: f64,
+    last_buy: Option<bool>,
     rng: Pcg64,
-    poisson: Poisson<f64>,
+    /// Arrival count distribution; `None` while the scaled rate is zero, when no orders
+    /// arrive and no count is drawn.
+    poisson: Option<Poisson<f64>>,
     lognormal: LogNormal<f64>,
 }
 
@@ -25,25 +52,257 @@ impl RetailTrader {
         let sigma = size_sigma.max(0.01);
         let mu_ln = mean_size.max(0.01).ln() - 0.5 * sigma * sigma;
         Self {
+            arrival_rate,
+            intensity: 1.0,
             buy_prob,
+            persistence: 0.0,
+            last_buy: None,
             rng: Pcg64::seed_from_u64(seed),
-            poisson: Poisson::new(arrival_rate.max(0.01)).unwrap(),
+            poisson: Some(Poisson::new(arrival_rate.max(0.01)).unwrap()),
             lognormal: LogNormal::new(mu_ln, sigma).unwrap(),
         }
     }
 
+    /// Make order directions persistent: after a buy, the next order buys with probability
+    /// `persistence + (1 - persistence) * buy_prob`, after a sell with
+    /// `(1 - persistence) * buy_prob`. Each direction still takes a single draw, so sizes
+    /// and arrivals match a trader without persistence.
+    pub fn with_persistence(mut self, persistence: f64) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    pub fn set_buy_prob(&mut self, buy_prob: f64) {
+        self.buy_prob = buy_prob;
+    }
+
+    /// Scale the arrival rate by `intensity` from now on; at zero, no orders arrive.
+    pub fn set_intensity(&mut self, intensity: f64) {
+        if intensity != self.intensity {
+            self.intensity = intensity;
+            let rate = self.arrival_rate * intensity;
+            self.poisson = (rate > 0.0).then(|| Poisson::new(rate.max(0.01)).unwrap());
+        }
+    }
+
     #[inline]
     pub fn generate_orders(&mut self) -> Vec<RetailOrder> {
-        let n = self.poisson.sample(&mut self.rng) as usize;
+        let Some(poisson) = &self.poisson else {
+            return Vec::new();
+        };
+        let n = poisson.sample(&mut self.rng) as usize;
         if n == 0 {
             return Vec::new();
         }
-        (0..n)
-            .map(|_| {
-                let size = self.lognormal.sample(&mut self.rng);
-                let is_buy = rand::Rng::gen::<f64>(&mut self.rng) < self.buy_prob;
-                RetailOrder { is_buy, size }
+        (0..n).map(|_| self.next_order()).collect()
+    }
+
+    /// One order's size and direction, without drawing an arrival count.
+    pub fn next_order(&mut self) -> RetailOrder {
+        let size = self.lognormal.sample(&mut self.rng);
+        let buy_prob = match self.last_buy {
+            None => self.buy_prob,
+            Some(last_buy) => {
+                let repeat = if last_buy { self.persistence } else { 0.0 };
+                repeat + (1.0 - self.persistence) * self.buy_prob
+            }
+        };
+        let is_buy = rand::Rng::gen::<f64>(&mut self.rng) < buy_prob;
+        self.last_buy = Some(is_buy);
+        RetailOrder {
+            is_buy,
+            size,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        }
+    }
+}
+
+/// Every retail trader class, each drawing its orders from its own stream.
+pub struct RetailFlow {
+    /// Each class's trader and its own buy probability, if it has one.
+    classes: Vec<(RetailTrader, Option<f64>)>,
+}
+
+impl RetailFlow {
+    /// One trader per class, seeded from `seeds` in order, each with order directions
+    /// persisting by `persistence` (see [`RetailTrader::with_persistence`]).
+    pub fn new(classes: &[RetailClass], persistence: f64, seeds: &[u64]) -> Self {
+        let classes = classes
+            .iter()
+            .zip(seeds)
+            .map(|(class, &seed)| {
+                let trader = RetailTrader::new(
+                    class.arrival_rate,
+                    class.mean_size,
+                    class.size_sigma,
+                    class.buy_prob.unwrap_or(0.5),
+                    seed,
+                )
+                .with_persistence(persistence);
+                (trader, class.buy_prob)
             })
+            .collect();
+        Self { classes }
+    }
+
+    /// Set this step's buy probabilities: `buy_prob` for classes without their own, then
+    /// `adjust` applied to every class's.
+    pub fn set_buy_prob(&mut self, buy_prob: f64, adjust: impl Fn(f64) -> f64) {
+        for (trader, own) in &mut self.classes {
+            trader.set_buy_prob(adjust(own.unwrap_or(buy_prob)));
+        }
+    }
+
+    pub fn set_intensity(&mut self, intensity: f64) {
+        for (trader, _) in &mut self.classes {
+            trader.set_intensity(intensity);
+        }
+    }
+
+    /// This step's orders from every class, in class order.
+    pub fn generate_orders(&mut self) -> Vec<RetailOrder> {
+        self.classes
+            .iter_mut()
+            .flat_map(|(trader, _)| trader.generate_orders())
             .collect()
     }
+
+    /// One order from the class at index `class`, for the event-driven clock.
+    pub fn next_order(&mut self, class: usize) -> RetailOrder {
+        self.classes[class].0.next_order()
+    }
+}
+
+/// `buy_prob` tilted toward the direction of this step's price move, from `previous_price`
+/// to `price`: a share `|correlation|` of orders follow the move (or, for a negative
+/// correlation, lean against it) and the rest keep `buy_prob`. A flat price tilts nothing.
+pub fn correlated_buy_prob(
+    buy_prob: f64,
+    correlation: f64,
+    previous_price: f64,
+    price: f64,
+) -> f64 {
+    if correlation == 0.0 || price == previous_price {
+        return buy_prob;
+    }
+    let buy_with_move = (price > previous_price) == (correlation > 0.0);
+    let weight = correlation.abs();
+    (1.0 - weight) * buy_prob + if buy_with_move { weight } else { 0.0 }
+}
+
+/// The recent fair prices momentum traders trade on.
+pub struct MomentumSignal {
+    lookback: usize,
+    weight: f64,
+    /// The last `lookback + 1` prices observed, oldest first.
+    prices: VecDeque<f64>,
+}
+
+impl MomentumSignal {
+    pub fn new(lookback_steps: u32, weight: f64) -> Self {
+        let lookback = lookback_steps.max(1) as usize;
+        Self {
+            lookback,
+            weight,
+            prices: VecDeque::with_capacity(lookback + 1),
+        }
+    }
+
+    pub fn observe(&mut self, price: f64) {
+        if self.prices.len() > self.lookback {
+            self.prices.pop_front();
+        }
+        self.prices.push_back(price);
+    }
+
+    /// Forget the prices seen so far, e.g. when the price restarts with a new session.
+    pub fn clear(&mut self) {
+        self.prices.clear();
+    }
+
+    /// `0.5 + weight / 2` in the direction of the return over the lookback; 0.5 until a full
+    /// lookback has been observed, and while the price is back where it started.
+    pub fn buy_prob(&self) -> f64 {
+        let (Some(&first), Some(&last)) = (self.prices.front(), self.prices.back()) else {
+            return 0.5;
+        };
+        if self.prices.len() <= self.lookback || first == last {
+            return 0.5;
+        }
+        let tilt = 0.5 * self.weight;
+        if last > first {
+            0.5 + tilt
+        } else {
+            0.5 - tilt
+        }
+    }
+}
+
+/// Nets `orders` into at most one aggregate order per class (uninformed, then informed): the
+/// Y value of the buys less that of the sells, in the direction of whichever side is larger.
+/// Classes that net to zero send nothing. Orders with a limit of their own
+/// (`max_slippage_bps` or `min_output`) are not netted, as no aggregate could keep it; they
+/// follow the aggregates unchanged, in their original order.
+///
+/// An aggregate keeps only its class; its direction and size come from the net, and it has
+/// no limits, partial fills or exact output of its own. Engine-wide settings
+/// (`retail_max_slippage_bps`, `retail_partial_fills`, `retail_exact_output`) are applied to
+/// it after netting, as to any other order.
+pub fn net_orders(orders: &[RetailOrder]) -> Vec<RetailOrder> {
+    let limited =
+        |order: &RetailOrder| order.max_slippage_bps.is_some() || order.min_output.is_some();
+    let mut netted: Vec<RetailOrder> = [false, true]
+        .into_iter()
+        .filter_map(|informed| {
+            let net: f64 = orders
+                .iter()
+                .filter(|order| order.informed == informed && !limited(order))
+                .map(|order| if order.is_buy { order.size } else { -order.size })
+                .sum();
+            (net != 0.0).then(|| RetailOrder {
+                is_buy: net > 0.0,
+                size: net.abs(),
+                informed,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            })
+        })
+        .collect();
+    netted.extend(orders.iter().filter(|order| limited(order)).cloned());
+    netted
+}
+
+/// Turns a share of retail orders into informed ones. Draws come from a stream of their own,
+/// so the uninformed orders' sizes and timing match a run without informed flow.
+pub struct InformedFlow {
+    prob: f64,
+    rng: Pcg64,
+}
+
+impl InformedFlow {
+    pub fn new(prob: f64, seed: u64) -> Self {
+        Self {
+            prob,
+            rng: Pcg64::seed_from_u64(seed),
+        }
+    }
+
+    /// With probability `prob`, point `order` the way the price moves from `fair_price` to
+    /// `next_fair_price`: buy X ahead of a rise, sell ahead of a fall. An order tagged while
+    /// the price is flat keeps its direction.
+    pub fn tag(&mut self, order: &mut RetailOrder, fair_price: f64, next_fair_price: f64) {
+        if rand::Rng::gen::<f64>(&mut self.rng) >= self.prob {
+            return;
+        }
+        order.informed = true;
+        if next_fair_price != fair_price {
+            order.is_buy = next_fair_price > fair_price;
+        }
+    }
 }
@@ -1,16 +1,106 @@
+use std::sync::Mutex;
+
+use prop_amm_shared::config::RouterKind;
+
 use crate::amm::BpfAmm;
 use crate::curve_checks;
 use crate::retail::RetailOrder;
 use crate::search_stats;
 
 pub struct RoutedTrade {
-    pub is_submission: bool,
+    /// [`SUBMISSION_VENUE`], or `1 + i` for `baselines[i]`.
+    pub venue: u8,
     pub amm_buys_x: bool,
     pub amount_x: f64,
     pub amount_y: f64,
+    /// The output the split search quoted for this trade; only the submission's trades of a
+    /// routed order carry one.
+    pub quoted_output: Option<f64>,
+}
+
+impl RoutedTrade {
+    pub fn is_submission(&self) -> bool {
+        self.venue == SUBMISSION_VENUE
+    }
+}
+
+pub const SUBMISSION_VENUE: u8 = 0;
+
+/// An order's executed trades.
+pub struct RoutedOrder {
+    pub trades: Vec<RoutedTrade>,
+    /// Fraction of the order's size executed: 1 unless it was partially filled, and 0, with
+    /// no trades, if the price guard left it unfilled (see [`OrderRouter::with_price_guard`]).
+    pub filled: f64,
+}
+
+/// The limits an order's execution must meet, from its [`RetailOrder`].
+#[derive(Clone, Copy)]
+struct OrderLimits {
+    /// `(max_slippage_bps, fair_price)`.
+    slippage: Option<(f64, f64)>,
+    min_output: Option<f64>,
+}
+
+impl OrderLimits {
+    fn of(order: &RetailOrder, fair_price: f64) -> Self {
+        Self {
+            slippage: order.max_slippage_bps.map(|bps| (bps, fair_price)),
+            min_output: order.min_output,
+        }
+    }
+
+    /// Whether filling `input` for `output` misses the limits. `buy_x` orders pay Y for X;
+    /// the others sell X for Y. `fill` is the fraction of the order `input` is, which the
+    /// minimum output scales by.
+    fn missed(&self, input: f64, output: f64, buy_x: bool, fill: f64) -> bool {
+        if self.min_output.is_some_and(|min| output < min * fill) {
+            return true;
+        }
+        let Some((max_bps, fair_price)) = self.slippage else {
+            return false;
+        };
+        if output <= 0.0 {
+            return true;
+        }
+        let cost = if buy_x {
+            input / output / fair_price - 1.0
+        } else {
+            1.0 - output / input / fair_price
+        };
+        cost * 10_000.0 > max_bps
+    }
+}
+
+/// A constant-product pool the submission competes with, e.g. the normalizer. Its fee lets
+/// the router split flow across several such pools in closed form.
+pub struct BaselinePool {
+    pub amm: BpfAmm,
+    pub fee_bps: u16,
+    /// Split flow onto this pool by its quotes alone, not assuming it is constant-product:
+    /// if any baseline is a black box, the router hands the baselines' part of an order out
+    /// chunk by chunk (see [`OrderRouter::allocate_by_quotes`]).
+    pub black_box: bool,
+}
+
+impl BaselinePool {
+    /// Output per unit input at zero size is `gamma * r_out / r_in`.
+    fn gamma(&self) -> f64 {
+        // Matches the normalizer program, which reads a stored fee of 0 as its 30 bps default.
+        let fee_bps = if self.fee_bps == 0 { 30 } else { self.fee_bps };
+        1.0 - fee_bps as f64 / 10_000.0
+    }
+
+    /// `(input, output)` reserves for a trade buying X (paying Y) or selling X.
+    fn reserves(&self, buy_x: bool) -> (f64, f64) {
+        if buy_x {
+            (self.amm.reserve_y, self.amm.reserve_x)
+        } else {
+            (self.amm.reserve_x, self.amm.reserve_y)
+        }
+    }
 }
 
-const MIN_TRADE_SIZE: f64 = 0.001;
 const GOLDEN_RATIO_CONJUGATE: f64 = 0.618_033_988_749_894_8;
 const GOLDEN_MAX_ITERS: usize = 14;
 const GOLDEN_ALPHA_TOL: f64 = 1e-3;
@@ -18,206 +108,1403 @@ const GOLDEN_ALPHA_TOL: f64 = 1e-3;
 const GOLDEN_SUBMISSION_AMOUNT_REL_TOL: f64 = 1e-2;
 // Stop once the two evaluated total outputs are within 1% of each other.
 const GOLDEN_SCORE_REL_GAP_TOL: f64 = 1e-2;
+// Adaptive search only compares the endpoints for orders below this fraction of the venues'
+// input-side reserves, and runs the full golden-section search from `ADAPTIVE_FULL_REL_SIZE`.
+const ADAPTIVE_ENDPOINTS_REL_SIZE: f64 = 1e-4;
+const ADAPTIVE_FULL_REL_SIZE: f64 = 1e-2;
+// A warm-started search checks the previous order's share against shares this far either side
+// of it, halving the distance down to the minimum while the share holds.
+const WARM_START_MAX_HALF_WIDTH: f64 = 0.25;
+const WARM_START_MIN_HALF_WIDTH: f64 = 0.02;
+// An executed share this close to the previous one means the previous share held.
+const WARM_START_HELD_TOL: f64 = 1e-9;
+// Total output may fall short of a smaller order's by this much before the size monotonicity
+// check flags it; the split search itself is only accurate to about 1%.
+#[cfg(any(test, feature = "router-debug-checks"))]
+const SIZE_MONOTONIC_REL_TOL: f64 = 1e-2;
+// A late submission only gets flow if its best split beats the normalizer alone by more than this.
+const LATE_TIE_REL_TOL: f64 = 1e-4;
+// The submission's marginal is a central difference over this fraction of its input.
+const MARGINAL_REL_STEP: f64 = 1e-2;
+const MARGINAL_MAX_ITERS: usize = 12;
+// Stop once the marginal gap is this small relative to the baselines' marginal.
+const MARGINAL_GAP_REL_TOL: f64 = 2e-3;
+// A marginal may rise with size by this much before it counts as noise (see curve_checks).
+const MARGINAL_NOISE_REL_TOL: f64 = 1e-3;
+// `allocate_by_quotes` hands out the baselines' part of an order in this many equal chunks.
+const VENUE_SPLIT_CHUNKS: usize = 64;
+// Halvings of the size range a partial fill searches, i.e. fills resolve to 1/4096.
+const PARTIAL_FILL_ITERS: usize = 12;
+// Executing the submission's part may pay out this much less than its quote before the part
+// is re-routed to the baselines.
+const EXECUTION_SHORTFALL_REL_TOL: f64 = 1e-6;
+// An exact-output order stops once its routed output is this close to the target.
+const EXACT_OUTPUT_REL_TOL: f64 = 1e-6;
+const EXACT_OUTPUT_MAX_ITERS: usize = 40;
+// Doublings of the oracle-sized guess tried before a target counts as out of reach.
+const EXACT_OUTPUT_MAX_DOUBLINGS: usize = 20;
 
-pub struct OrderRouter;
+/// How the router divides an order between the submission and the baselines. Each call
+/// routes one side of one order: `buy_x` orders pay Y for X, the others sell X for Y.
+pub trait OrderRouterStrategy: Send + Sync {
+    /// Quote sending the fraction `alpha` of `total_input` to the submission and the rest
+    /// to the baselines.
+    fn quote(
+        &self,
+        total_input: f64,
+        alpha: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> QuotePoint {
+        OrderRouter::quote_split(total_input, alpha, buy_x, amm_sub, baselines)
+    }
+
+    /// Choose the split to execute. `sampled` must hold every point quoted on the way, for
+    /// the submission's curve checks.
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult;
+
+    /// Execute `point`, the split [`Self::split`] chose (or the normalizer-only fallback of
+    /// a late submission).
+    fn execute(
+        &self,
+        point: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> Vec<RoutedTrade> {
+        OrderRouter::execute_split(point, buy_x, amm_sub, baselines)
+    }
+}
+
+/// Golden-section search over the submission's share for the most total output.
+pub struct GoldenSectionSplit;
+
+impl OrderRouterStrategy for GoldenSectionSplit {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        let min_trade_size = amm_sub.min_trade_size();
+        OrderRouter::maximize_split(total_input, min_trade_size, |alpha| {
+            self.quote(total_input, alpha, buy_x, amm_sub, baselines)
+        })
+    }
+}
+
+/// Golden-section search like [`GoldenSectionSplit`], but over the integer amounts the
+/// submission's program receives: every probe hands it a whole number of units, and the
+/// baselines get exactly the rest, so no split is rounded on its way to the program.
+pub struct IntegerGoldenSplit;
+
+impl IntegerGoldenSplit {
+    /// The order's size in the units of its input token.
+    fn input_units(total_input: f64, buy_x: bool, amm_sub: &BpfAmm) -> u64 {
+        let decimals = amm_sub.token_decimals();
+        if buy_x {
+            decimals.y_to_units(total_input)
+        } else {
+            decimals.x_to_units(total_input)
+        }
+    }
+
+    /// Quote `units` of the order's `total` to the submission and the rest across the
+    /// baselines.
+    fn quote_units(
+        units: u64,
+        total: u64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> QuotePoint {
+        let decimals = amm_sub.token_decimals();
+        let (in_sub, in_norm) = if buy_x {
+            (decimals.units_to_y(units), decimals.units_to_y(total - units))
+        } else {
+            (decimals.units_to_x(units), decimals.units_to_x(total - units))
+        };
+        let min_trade_size = amm_sub.min_trade_size();
+        let out_sub = if in_sub <= min_trade_size || amm_sub.known_down() {
+            0.0
+        } else if buy_x {
+            decimals.units_to_x(amm_sub.quote_buy_x_units(units))
+        } else {
+            decimals.units_to_y(amm_sub.quote_sell_x_units(units))
+        };
+        let submission_down = in_sub > min_trade_size && out_sub <= 0.0 && amm_sub.is_down();
+        QuotePoint {
+            in_sub,
+            in_norm,
+            out_sub,
+            out_norm: OrderRouter::quote_baselines(in_norm, buy_x, baselines),
+            submission_down,
+        }
+    }
+}
+
+impl OrderRouterStrategy for IntegerGoldenSplit {
+    /// `alpha` of the order rounded to a whole number of units.
+    fn quote(
+        &self,
+        total_input: f64,
+        alpha: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> QuotePoint {
+        let total = Self::input_units(total_input, buy_x, amm_sub);
+        let units = (total as f64 * alpha.clamp(0.0, 1.0)).round() as u64;
+        Self::quote_units(units.min(total), total, buy_x, amm_sub, baselines)
+    }
+
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        let total = Self::input_units(total_input, buy_x, amm_sub);
+        let unit = total_input / total.max(1) as f64;
+        let min_trade_size = amm_sub.min_trade_size();
+        OrderRouter::maximize_split_units(total, unit, min_trade_size, |units| {
+            Self::quote_units(units, total, buy_x, amm_sub, baselines)
+        })
+    }
+}
+
+/// Golden-section search whose effort scales with the order's size against the venues'
+/// depth: orders too small for the split to matter only compare the two endpoints, like
+/// [`BestPriceRoute`], and iterations grow with size up to the full search.
+pub struct AdaptiveGoldenSplit;
+
+impl AdaptiveGoldenSplit {
+    /// Golden-section iterations for `total_input`, on a log scale of its share of every
+    /// venue's input-side reserves; `None` for an endpoint check only.
+    fn iterations(
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &BpfAmm,
+        baselines: &[BaselinePool],
+    ) -> Option<usize> {
+        let sub_reserve = if buy_x { amm_sub.reserve_y } else { amm_sub.reserve_x };
+        let depth = sub_reserve + baselines.iter().map(|pool| pool.reserves(buy_x).0).sum::<f64>();
+        let rel_size = total_input / depth;
+        if rel_size.is_nan() || rel_size < ADAPTIVE_ENDPOINTS_REL_SIZE {
+            return None;
+        }
+        let span = (rel_size / ADAPTIVE_ENDPOINTS_REL_SIZE).ln()
+            / (ADAPTIVE_FULL_REL_SIZE / ADAPTIVE_ENDPOINTS_REL_SIZE).ln();
+        Some(((span.min(1.0) * GOLDEN_MAX_ITERS as f64).ceil() as usize).max(1))
+    }
+}
+
+impl OrderRouterStrategy for AdaptiveGoldenSplit {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        let min_trade_size = amm_sub.min_trade_size();
+        match Self::iterations(total_input, buy_x, amm_sub, baselines) {
+            Some(max_iters) => {
+                OrderRouter::maximize_split_in(total_input, max_iters, min_trade_size, |alpha| {
+                    self.quote(total_input, alpha, buy_x, amm_sub, baselines)
+                })
+            }
+            None => BestPriceRoute.split(total_input, buy_x, amm_sub, baselines),
+        }
+    }
+}
+
+/// Golden-section search seeded from the previous order on the same side: reserves move
+/// little between orders, so the best share does too. Each order first quotes the share the
+/// last executed split gave the submission and the ends of a bracket around it; if that
+/// share still quotes the most, it is kept. Otherwise the order gets the full golden-section
+/// search. The bracket narrows while executed shares hold and resets when they move.
+///
+/// Only [`OrderRouterStrategy::execute`] updates the state, so splits that are quoted but
+/// never executed (partial-fill and exact-output searches, debug checks) do not steer later
+/// orders.
+#[derive(Default)]
+pub struct WarmStartGoldenSplit {
+    /// The last executed split on each side, indexed by `buy_x`.
+    last: Mutex<[Option<WarmStart>; 2]>,
+}
 
 #[derive(Clone, Copy)]
-struct QuotePoint {
-    in_sub: f64,
-    in_norm: f64,
-    out_sub: f64,
-    out_norm: f64,
+struct WarmStart {
+    alpha: f64,
+    half_width: f64,
+}
+
+impl WarmStartGoldenSplit {
+    fn last(&self) -> std::sync::MutexGuard<'_, [Option<WarmStart>; 2]> {
+        self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl OrderRouterStrategy for WarmStartGoldenSplit {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        let warm = self.last()[buy_x as usize];
+        let min_trade_size = amm_sub.min_trade_size();
+        let mut evaluate = |alpha| self.quote(total_input, alpha, buy_x, amm_sub, baselines);
+        let Some(WarmStart { alpha, half_width }) = warm else {
+            return OrderRouter::maximize_split(total_input, min_trade_size, &mut evaluate);
+        };
+        let bracket = ((alpha - half_width).max(0.0), (alpha + half_width).min(1.0));
+        match OrderRouter::maximize_split_near(alpha, bracket, &mut evaluate) {
+            Ok(search) => search,
+            Err(sampled) => {
+                search_stats::inc_router_warm_start_miss();
+                let mut search =
+                    OrderRouter::maximize_split(total_input, min_trade_size, &mut evaluate);
+                for point in sampled.into_iter().filter(|point| !point.submission_down) {
+                    search.best = OrderRouter::best_quote(search.best, point);
+                    search.sampled.push(point);
+                }
+                search
+            }
+        }
+    }
+
+    fn execute(
+        &self,
+        point: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> Vec<RoutedTrade> {
+        let alpha = point.in_sub / (point.in_sub + point.in_norm);
+        if alpha.is_finite() {
+            let mut last = self.last();
+            let side = &mut last[buy_x as usize];
+            let half_width = match *side {
+                Some(warm) if (alpha - warm.alpha).abs() <= WARM_START_HELD_TOL => {
+                    (0.5 * warm.half_width).max(WARM_START_MIN_HALF_WIDTH)
+                }
+                _ => WARM_START_MAX_HALF_WIDTH,
+            };
+            *side = Some(WarmStart { alpha, half_width });
+        }
+        OrderRouter::execute_split(point, buy_x, amm_sub, baselines)
+    }
+}
+
+/// Sends the whole order to whichever side, submission or baselines, quotes more for all
+/// of it; never splits.
+pub struct BestPriceRoute;
+
+impl OrderRouterStrategy for BestPriceRoute {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        search_stats::inc_router_call();
+        search_stats::inc_router_eval();
+        let baselines_only = self.quote(total_input, 0.0, buy_x, amm_sub, baselines);
+        search_stats::inc_router_eval();
+        let submission_only = self.quote(total_input, 1.0, buy_x, amm_sub, baselines);
+        if submission_only.submission_down {
+            search_stats::inc_router_venue_down();
+            return SplitSearchResult {
+                best: baselines_only,
+                sampled: vec![baselines_only],
+            };
+        }
+        SplitSearchResult {
+            best: OrderRouter::best_quote(baselines_only, submission_only),
+            sampled: vec![baselines_only, submission_only],
+        }
+    }
 }
 
-struct SplitSearchResult {
-    best: QuotePoint,
-    sampled: Vec<QuotePoint>,
+/// Sends the whole order to the single venue, the submission or one baseline, that quotes
+/// the most for all of it. Unlike [`BestPriceRoute`], the baselines are not split among
+/// themselves either; with only the normalizer the two agree.
+pub struct WinnerTakeAll;
+
+impl WinnerTakeAll {
+    /// The index of the baseline quoting the most for all of `input`, and its quote. Pools
+    /// whose trade cap `input` exceeds are passed over.
+    fn best_baseline(input: f64, buy_x: bool, baselines: &mut [BaselinePool]) -> (usize, f64) {
+        let mut best = (0, 0.0);
+        for (i, pool) in baselines.iter_mut().enumerate() {
+            if input <= pool.amm.min_trade_size() || input > pool.amm.max_trade_input(buy_x) {
+                continue;
+            }
+            let output = if buy_x {
+                pool.amm.quote_buy_x(input)
+            } else {
+                pool.amm.quote_sell_x(input)
+            };
+            if output > best.1 {
+                best = (i, output);
+            }
+        }
+        best
+    }
+}
+
+impl OrderRouterStrategy for WinnerTakeAll {
+    /// Whatever part of the order is left to the baselines goes to the best one alone.
+    fn quote(
+        &self,
+        total_input: f64,
+        alpha: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> QuotePoint {
+        let in_sub = total_input * alpha.clamp(0.0, 1.0);
+        let mut point = OrderRouter::quote_split(in_sub, 1.0, buy_x, amm_sub, baselines);
+        point.in_norm = total_input - point.in_sub;
+        point.out_norm = Self::best_baseline(point.in_norm, buy_x, baselines).1;
+        point
+    }
+
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        search_stats::inc_router_call();
+        search_stats::inc_router_eval();
+        let baselines_only = self.quote(total_input, 0.0, buy_x, amm_sub, baselines);
+        search_stats::inc_router_eval();
+        let submission_only = self.quote(total_input, 1.0, buy_x, amm_sub, baselines);
+        if submission_only.submission_down {
+            search_stats::inc_router_venue_down();
+            return SplitSearchResult {
+                best: baselines_only,
+                sampled: vec![baselines_only],
+            };
+        }
+        SplitSearchResult {
+            best: OrderRouter::best_quote(baselines_only, submission_only),
+            sampled: vec![baselines_only, submission_only],
+        }
+    }
+
+    fn execute(
+        &self,
+        point: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> Vec<RoutedTrade> {
+        let (trade, rerouted) = OrderRouter::execute_submission_part(point, buy_x, amm_sub);
+        let mut trades: Vec<RoutedTrade> = trade.into_iter().collect();
+        let planned = if point.out_norm > 0.0 {
+            point.in_norm
+        } else {
+            0.0
+        };
+        let in_norm = planned + rerouted;
+        let (i, output) = Self::best_baseline(in_norm, buy_x, baselines);
+        if output > 0.0 {
+            let (amm, venue) = (&mut baselines[i].amm, 1 + i as u8);
+            trades.extend(OrderRouter::execute_on(amm, venue, in_norm, buy_x, 0.0));
+        }
+        trades
+    }
+}
+
+/// Gives the submission its share of the input-side reserves across every venue, whatever
+/// its quotes say.
+pub struct ProportionalSplit;
+
+impl OrderRouterStrategy for ProportionalSplit {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        let reserve_in = |amm: &BpfAmm| if buy_x { amm.reserve_y } else { amm.reserve_x };
+        let sub_reserve = reserve_in(amm_sub).max(0.0);
+        let total_reserve = sub_reserve
+            + baselines.iter().map(|pool| reserve_in(&pool.amm).max(0.0)).sum::<f64>();
+        let alpha = if total_reserve > 0.0 { sub_reserve / total_reserve } else { 0.0 };
+
+        search_stats::inc_router_call();
+        search_stats::inc_router_eval();
+        let point = self.quote(total_input, alpha, buy_x, amm_sub, baselines);
+        if !point.submission_down {
+            return SplitSearchResult {
+                best: point,
+                sampled: vec![point],
+            };
+        }
+        search_stats::inc_router_venue_down();
+        search_stats::inc_router_eval();
+        let baselines_only = self.quote(total_input, 0.0, buy_x, amm_sub, baselines);
+        SplitSearchResult {
+            best: baselines_only,
+            sampled: vec![baselines_only],
+        }
+    }
+}
+
+/// Equalizes the submission's marginal output with the baselines': secant steps on the gap
+/// between the two, kept inside a shrinking bracket. The submission's marginal is a central
+/// difference of its quotes and the baselines' is closed-form, so smooth curves converge in
+/// a handful of quotes. Marginals that rise with size or quotes of nothing fall back to
+/// [`GoldenSectionSplit`].
+pub struct MarginalPriceSplit;
+
+impl MarginalPriceSplit {
+    /// The submission's output for `in_sub`, as an all-submission point of that size.
+    fn probe(in_sub: f64, buy_x: bool, amm_sub: &mut BpfAmm) -> QuotePoint {
+        search_stats::inc_router_eval();
+        let out_sub = if buy_x {
+            amm_sub.quote_buy_x(in_sub)
+        } else {
+            amm_sub.quote_sell_x(in_sub)
+        };
+        QuotePoint {
+            in_sub,
+            in_norm: 0.0,
+            out_sub,
+            out_norm: 0.0,
+            submission_down: false,
+        }
+    }
+
+    /// The submission's output per unit input just below `in_sub` (or just above zero), with
+    /// its output for `in_sub`; `None` if a probe quoted nothing. `quoted` is that output if
+    /// already known, saving a probe.
+    fn submission_marginal(
+        in_sub: f64,
+        quoted: Option<f64>,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        sampled: &mut Vec<QuotePoint>,
+    ) -> Option<(f64, f64)> {
+        let step = (MARGINAL_REL_STEP * in_sub).max(amm_sub.min_trade_size());
+        let high = in_sub.max(step);
+        let out_high = match quoted {
+            Some(out) => out,
+            None => {
+                let point = Self::probe(high, buy_x, amm_sub);
+                sampled.push(point);
+                point.out_sub
+            }
+        };
+        let low = high - step;
+        let out_low = if low > amm_sub.min_trade_size() {
+            let point = Self::probe(low, buy_x, amm_sub);
+            sampled.push(point);
+            point.out_sub
+        } else {
+            0.0
+        };
+        (out_high > 0.0).then(|| ((out_high - out_low) / (high - low), out_high))
+    }
+
+    /// The submission share at which the marginals meet, with the submission's output
+    /// there; `Some(None)` if they never do inside (0, 1) so the best split is an endpoint,
+    /// or `None` if the submission's marginals are too noisy to solve on.
+    fn equalize(
+        total_input: f64,
+        submission_only: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &[BaselinePool],
+        sampled: &mut Vec<QuotePoint>,
+    ) -> Option<Option<(f64, f64)>> {
+        // `(in_sub, marginal)` of every probe so far; concave curves never rise with size.
+        let mut marginals: Vec<(f64, f64)> = Vec::new();
+        // Submission minus baseline marginal at share `alpha`, with the baseline marginal and
+        // the submission's output; the gap falls as `alpha` grows.
+        let mut gap = |alpha: f64, quoted: Option<f64>, amm_sub: &mut BpfAmm| {
+            let in_sub = total_input * alpha;
+            let (sub, out_sub) =
+                Self::submission_marginal(in_sub, quoted, buy_x, amm_sub, sampled)?;
+            let noisy = marginals.iter().any(|&(input, marginal)| {
+                let (small, large) = if input < in_sub { (marginal, sub) } else { (sub, marginal) };
+                large > small + MARGINAL_NOISE_REL_TOL * small.abs()
+            });
+            marginals.push((in_sub, sub));
+            let base = OrderRouter::baseline_marginal(total_input - in_sub, buy_x, baselines);
+            (sub.is_finite() && !noisy).then_some((sub - base, base, out_sub))
+        };
+
+        let (mut lo, mut gap_lo) = (0.0, gap(0.0, None, amm_sub)?.0);
+        let (mut hi, mut gap_hi) = (1.0, gap(1.0, Some(submission_only.out_sub), amm_sub)?.0);
+        if gap_lo <= 0.0 || gap_hi >= 0.0 {
+            return Some(None);
+        }
+        let (mut alpha, mut out_sub) = (0.5, 0.0);
+        let mut kept_side = 0_i8;
+        for _ in 0..MARGINAL_MAX_ITERS {
+            search_stats::inc_router_iter();
+            alpha = (lo * gap_hi - hi * gap_lo) / (gap_hi - gap_lo);
+            if !(alpha > lo && alpha < hi) {
+                alpha = 0.5 * (lo + hi);
+            }
+            let (gap_mid, base, out) = gap(alpha, None, amm_sub)?;
+            out_sub = out;
+            if gap_mid.abs() <= MARGINAL_GAP_REL_TOL * base.abs() {
+                break;
+            }
+            // Illinois: halve the end that stays put twice, so the secant keeps moving.
+            if gap_mid > 0.0 {
+                (lo, gap_lo) = (alpha, gap_mid);
+                if kept_side == 1 {
+                    gap_hi *= 0.5;
+                }
+                kept_side = 1;
+            } else {
+                (hi, gap_hi) = (alpha, gap_mid);
+                if kept_side == -1 {
+                    gap_lo *= 0.5;
+                }
+                kept_side = -1;
+            }
+            let amount_scale = (total_input * alpha).max(amm_sub.min_trade_size());
+            if total_input * (hi - lo) <= GOLDEN_SUBMISSION_AMOUNT_REL_TOL * amount_scale {
+                break;
+            }
+        }
+        Some(Some((alpha, out_sub)))
+    }
+}
+
+impl OrderRouterStrategy for MarginalPriceSplit {
+    fn split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> SplitSearchResult {
+        if baselines.iter().any(|pool| pool.black_box) {
+            // The baselines' marginal is only known in closed form for constant-product pools.
+            return GoldenSectionSplit.split(total_input, buy_x, amm_sub, baselines);
+        }
+        search_stats::inc_router_call();
+        search_stats::inc_router_eval();
+        let baselines_only = self.quote(total_input, 0.0, buy_x, amm_sub, baselines);
+        search_stats::inc_router_eval();
+        let submission_only = self.quote(total_input, 1.0, buy_x, amm_sub, baselines);
+        let mut sampled = vec![baselines_only];
+        if submission_only.submission_down {
+            search_stats::inc_router_venue_down();
+            return SplitSearchResult {
+                best: baselines_only,
+                sampled,
+            };
+        }
+        sampled.push(submission_only);
+        let endpoints = OrderRouter::best_quote(baselines_only, submission_only);
+
+        let solved =
+            Self::equalize(total_input, &submission_only, buy_x, amm_sub, baselines, &mut sampled);
+        let Some(solved) = solved else {
+            search_stats::inc_router_marginal_fallback();
+            let mut fallback = GoldenSectionSplit.split(total_input, buy_x, amm_sub, baselines);
+            fallback.sampled.extend(sampled);
+            return fallback;
+        };
+        let Some((alpha, out_sub)) = solved else {
+            // The optimum is an endpoint.
+            return SplitSearchResult {
+                best: endpoints,
+                sampled,
+            };
+        };
+        // The last probe already quoted the submission's part.
+        let in_norm = total_input * (1.0 - alpha);
+        let chosen = QuotePoint {
+            in_sub: total_input * alpha,
+            in_norm,
+            out_sub,
+            out_norm: OrderRouter::quote_baselines(in_norm, buy_x, baselines),
+            submission_down: false,
+        };
+        sampled.push(chosen);
+        SplitSearchResult {
+            best: OrderRouter::best_quote(endpoints, chosen),
+            sampled,
+        }
+    }
+}
+
+pub struct OrderRouter {
+    strategy: Box<dyn OrderRouterStrategy>,
+    exact_sell_notional: bool,
+    price_guard_bps: Option<f64>,
+}
+
+impl Default for OrderRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One split of an order: `in_sub` to the submission, `in_norm` across the baselines.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotePoint {
+    pub in_sub: f64,
+    pub in_norm: f64,
+    pub out_sub: f64,
+    pub out_norm: f64,
+    /// The submission turned out to be down while quoting this point, so it gets no flow
+    /// this step.
+    pub submission_down: bool,
+}
+
+pub struct SplitSearchResult {
+    pub best: QuotePoint,
+    pub sampled: Vec<QuotePoint>,
+}
+
+/// How [`OrderRouter::best_split`] chooses a split.
+#[derive(Clone, Copy)]
+struct SplitPolicy {
+    /// Route everything to the normalizer unless the split beats it by more than a tie.
+    submission_late: bool,
+    /// Send the submission this fraction of the order instead of searching.
+    share: Option<f64>,
 }
 
 impl OrderRouter {
+    /// The golden-section router.
     pub fn new() -> Self {
-        Self
+        Self::with_strategy(Box::new(GoldenSectionSplit))
+    }
+
+    pub fn with_strategy(strategy: Box<dyn OrderRouterStrategy>) -> Self {
+        Self {
+            strategy,
+            exact_sell_notional: false,
+            price_guard_bps: None,
+        }
+    }
+
+    /// Size sell orders by the Y their routed execution pays out rather than by converting
+    /// `order.size` at the fair price; see [`Self::route_order`].
+    pub fn with_exact_sell_notional(mut self, exact: bool) -> Self {
+        self.exact_sell_notional = exact;
+        self
+    }
+
+    /// Leave unfilled any order whose best split averages more than `max_bps` from the fair
+    /// price against the trader, or pays out nothing, however the venues quote; `None` fills
+    /// at any price. Unlike an order's own limits, this protects every order.
+    pub fn with_price_guard(mut self, max_bps: Option<f64>) -> Self {
+        self.price_guard_bps = max_bps;
+        self
+    }
+
+    pub fn for_kind(kind: RouterKind) -> Self {
+        match kind {
+            RouterKind::GoldenSection => Self::new(),
+            RouterKind::BestPrice => Self::with_strategy(Box::new(BestPriceRoute)),
+            RouterKind::Proportional => Self::with_strategy(Box::new(ProportionalSplit)),
+            RouterKind::MarginalPrice => Self::with_strategy(Box::new(MarginalPriceSplit)),
+            RouterKind::WinnerTakeAll => Self::with_strategy(Box::new(WinnerTakeAll)),
+            RouterKind::IntegerGolden => Self::with_strategy(Box::new(IntegerGoldenSplit)),
+            RouterKind::AdaptiveGolden => Self::with_strategy(Box::new(AdaptiveGoldenSplit)),
+            RouterKind::WarmStartGolden => {
+                Self::with_strategy(Box::<WarmStartGoldenSplit>::default())
+            }
+        }
     }
 
+    /// Route `order` across the venues and execute it. `None` means the order was abandoned:
+    /// its best split would have missed `order.max_slippage_bps` or `order.min_output` (and
+    /// so would every part of it, for `order.partial_fill`), and nothing executed. An order
+    /// larger than the venues' trade caps (see [`BpfAmm::max_trade_input`]) combined fills
+    /// only up to them, and one quoted beyond the price guard is not filled at all.
+    ///
+    /// A sell's `order.size` is Y notional. It is converted to X at `fair_price` unless the
+    /// router sizes sells exactly (see [`Self::with_exact_sell_notional`]), in which case the
+    /// order sells however much X its routed execution needs to pay out that much Y. An
+    /// `order.exact_output` order is sized that way on either side: a buy pays however much
+    /// Y its routed execution needs to pay out `order.size / fair_price` X.
     pub fn route_order(
         &self,
         order: &RetailOrder,
         amm_sub: &mut BpfAmm,
-        amm_norm: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
         fair_price: f64,
-    ) -> Vec<RoutedTrade> {
-        if order.is_buy {
-            self.route_buy(order.size, amm_sub, amm_norm)
+        submission_late: bool,
+    ) -> Option<RoutedOrder> {
+        let policy = SplitPolicy {
+            submission_late,
+            share: None,
+        };
+        self.route(order, policy, amm_sub, baselines, fair_price)
+    }
+
+    /// Route `order` like [`Self::route_order`], but send the submission the fraction
+    /// `share` of every split quoted for it instead of searching for the best split, e.g. a
+    /// share from [`Self::split_share`] reused across a block of orders. The order's own
+    /// limits, partial fills, the trade caps and the price guard apply as there.
+    pub fn route_order_at_share(
+        &self,
+        order: &RetailOrder,
+        share: f64,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+        fair_price: f64,
+    ) -> Option<RoutedOrder> {
+        let policy = SplitPolicy {
+            submission_late: false,
+            share: Some(share),
+        };
+        self.route(order, policy, amm_sub, baselines, fair_price)
+    }
+
+    /// The submission's share of the best split of `total_input`, found as
+    /// [`Self::route_order`] would for an order of that size; nothing executes.
+    pub fn split_share(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+        submission_late: bool,
+    ) -> f64 {
+        let policy = SplitPolicy {
+            submission_late,
+            share: None,
+        };
+        let best = self.best_split(total_input, buy_x, amm_sub, baselines, policy);
+        let share = best.in_sub / (best.in_sub + best.in_norm);
+        if share.is_finite() {
+            share
         } else {
-            let total_x = order.size / fair_price;
-            self.route_sell(total_x, amm_sub, amm_norm)
+            0.0
         }
     }
 
-    fn route_buy(
+    fn route(
         &self,
-        total_y: f64,
+        order: &RetailOrder,
+        policy: SplitPolicy,
         amm_sub: &mut BpfAmm,
-        amm_norm: &mut BpfAmm,
-    ) -> Vec<RoutedTrade> {
-        let search = Self::maximize_split(total_y, |alpha| {
-            Self::quote_buy_split(total_y, alpha, amm_sub, amm_norm)
-        });
-        curve_checks::enforce_submission_monotonic_concave(
-            &amm_sub.name,
-            &search
-                .sampled
-                .iter()
-                .map(|p| (p.in_sub, p.out_sub))
-                .collect::<Vec<_>>(),
-            MIN_TRADE_SIZE,
-            "router buy split search",
-        );
-        let best = search.best;
-
-        let mut trades = Vec::new();
-        let y_sub = best.in_sub;
-        let y_norm = best.in_norm;
-
-        if y_sub > MIN_TRADE_SIZE && best.out_sub > 0.0 {
-            let x_out = amm_sub.execute_buy_x(y_sub);
-            if x_out > 0.0 {
-                trades.push(RoutedTrade {
-                    is_submission: true,
-                    amm_buys_x: false,
-                    amount_x: x_out,
-                    amount_y: y_sub,
+        baselines: &mut [BaselinePool],
+        fair_price: f64,
+    ) -> Option<RoutedOrder> {
+        let limits = OrderLimits::of(order, fair_price);
+        let buy_x = order.is_buy;
+        let (total_input, best) = if order.exact_output || (!buy_x && self.exact_sell_notional) {
+            let (target, guess) = if buy_x {
+                (order.size / fair_price, order.size)
+            } else {
+                (order.size, order.size / fair_price)
+            };
+            self.input_for_output(target, guess, buy_x, amm_sub, baselines, policy)
+        } else if buy_x {
+            (order.size, self.best_split(order.size, true, amm_sub, baselines, policy))
+        } else {
+            let input = order.size / fair_price;
+            (input, self.best_split(input, false, amm_sub, baselines, policy))
+        };
+        // The venues' trade caps cut an order larger than all of them combined.
+        let capacity =
+            amm_sub.max_trade_input(buy_x) + Self::baseline_max_input(buy_x, baselines);
+        let max_fill = if total_input > capacity { capacity / total_input } else { 1.0 };
+        let output = best.out_sub + best.out_norm;
+        if let Some(max_bps) = self.price_guard_bps {
+            let guard = OrderLimits {
+                slippage: Some((max_bps, fair_price)),
+                min_output: None,
+            };
+            if guard.missed(total_input * max_fill, output, buy_x, max_fill) {
+                return Some(RoutedOrder {
+                    trades: Vec::new(),
+                    filled: 0.0,
                 });
             }
         }
-        if y_norm > MIN_TRADE_SIZE && best.out_norm > 0.0 {
-            let x_out = amm_norm.execute_buy_x(y_norm);
-            if x_out > 0.0 {
-                trades.push(RoutedTrade {
-                    is_submission: false,
-                    amm_buys_x: false,
-                    amount_x: x_out,
-                    amount_y: y_norm,
-                });
+        if !limits.missed(total_input * max_fill, output, buy_x, max_fill) {
+            Self::record_split(&best, amm_sub, baselines);
+            let trades = self.strategy.execute(&best, buy_x, amm_sub, baselines);
+            return Some(RoutedOrder {
+                trades,
+                filled: max_fill,
+            });
+        }
+        if !order.partial_fill {
+            return None;
+        }
+        // Cost per unit rises with size, so bisect for the largest part within the limits.
+        let (mut lo, mut hi) = (0.0, max_fill);
+        let mut fill = None;
+        for _ in 0..PARTIAL_FILL_ITERS {
+            let mid = 0.5 * (lo + hi);
+            let input = total_input * mid;
+            if input <= amm_sub.min_trade_size() {
+                break;
+            }
+            let point = self.best_split(input, buy_x, amm_sub, baselines, policy);
+            if limits.missed(input, point.out_sub + point.out_norm, buy_x, mid) {
+                hi = mid;
+            } else {
+                lo = mid;
+                fill = Some((mid, point));
             }
         }
-        trades
+        let (filled, point) = fill?;
+        Self::record_split(&point, amm_sub, baselines);
+        let trades = self.strategy.execute(&point, buy_x, amm_sub, baselines);
+        Some(RoutedOrder { trades, filled })
+    }
+
+    fn record_split(point: &QuotePoint, amm_sub: &BpfAmm, baselines: &[BaselinePool]) {
+        search_stats::inc_router_split(
+            point.in_sub > amm_sub.min_trade_size() && point.out_sub > 0.0,
+            point.in_norm > Self::baseline_min_trade_size(baselines) && point.out_norm > 0.0,
+        );
     }
 
-    fn route_sell(
+    /// The input whose best split pays out `target`, and that split. Routed output rises
+    /// with input, so the root is bracketed by doubling the oracle-sized `guess` and then
+    /// found by regula falsi (Illinois variant). A target beyond what the venues can pay
+    /// falls back to the oracle-sized order.
+    fn input_for_output(
         &self,
-        total_x: f64,
+        target: f64,
+        guess: f64,
+        buy_x: bool,
         amm_sub: &mut BpfAmm,
-        amm_norm: &mut BpfAmm,
-    ) -> Vec<RoutedTrade> {
-        let search = Self::maximize_split(total_x, |alpha| {
-            Self::quote_sell_split(total_x, alpha, amm_sub, amm_norm)
-        });
+        baselines: &mut [BaselinePool],
+        policy: SplitPolicy,
+    ) -> (f64, QuotePoint) {
+        let min_input = amm_sub.min_trade_size();
+        let mut split = |input: f64| {
+            let point = self.best_split(input, buy_x, amm_sub, baselines, policy);
+            (point, point.out_sub + point.out_norm - target)
+        };
+        let (mut lo, mut gap_lo) = (0.0, -target);
+        let (mut hi, (mut point, mut gap_hi)) = (guess, split(guess));
+        let mut doublings = 0;
+        while gap_hi < 0.0 {
+            if doublings == EXACT_OUTPUT_MAX_DOUBLINGS {
+                let (point, _) = split(guess);
+                return (guess, point);
+            }
+            (lo, gap_lo) = (hi, gap_hi);
+            hi *= 2.0;
+            (point, gap_hi) = split(hi);
+            doublings += 1;
+        }
+        let mut best = (hi, point);
+        if gap_hi <= EXACT_OUTPUT_REL_TOL * target {
+            return best;
+        }
+        let mut last_side = 0;
+        for _ in 0..EXACT_OUTPUT_MAX_ITERS {
+            let input = hi - gap_hi * (hi - lo) / (gap_hi - gap_lo);
+            if input <= min_input || input - lo <= f64::EPSILON * hi {
+                break;
+            }
+            let (point, gap) = split(input);
+            if gap.abs() <= EXACT_OUTPUT_REL_TOL * target {
+                return (input, point);
+            }
+            if gap < 0.0 {
+                (lo, gap_lo) = (input, gap);
+                if last_side == -1 {
+                    gap_hi *= 0.5;
+                }
+                last_side = -1;
+            } else {
+                (hi, gap_hi) = (input, gap);
+                best = (input, point);
+                if last_side == 1 {
+                    gap_lo *= 0.5;
+                }
+                last_side = 1;
+            }
+        }
+        best
+    }
+
+    /// The split to execute for `total_input`: the strategy's choice (or the policy's fixed
+    /// share), checked against the submission's curve shape and kept within the venues'
+    /// trade caps, or the normalizer alone if a late submission only ties.
+    fn best_split(
+        &self,
+        total_input: f64,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+        policy: SplitPolicy,
+    ) -> QuotePoint {
+        let search = match policy.share {
+            Some(share) => {
+                search_stats::inc_router_eval();
+                let point = self.strategy.quote(total_input, share, buy_x, amm_sub, baselines);
+                SplitSearchResult {
+                    best: point,
+                    sampled: vec![point],
+                }
+            }
+            None => self.strategy.split(total_input, buy_x, amm_sub, baselines),
+        };
+        let min_input = amm_sub.min_trade_size();
         curve_checks::enforce_submission_monotonic_concave(
-            &amm_sub.name,
+            amm_sub,
             &search
                 .sampled
                 .iter()
                 .map(|p| (p.in_sub, p.out_sub))
                 .collect::<Vec<_>>(),
-            MIN_TRADE_SIZE,
-            "router sell split search",
+            min_input,
+            if buy_x { "router buy split search" } else { "router sell split search" },
         );
-        let best = search.best;
-
-        let mut trades = Vec::new();
-        let x_sub = best.in_sub;
-        let x_norm = best.in_norm;
-
-        if x_sub > MIN_TRADE_SIZE && best.out_sub > 0.0 {
-            let y_out = amm_sub.execute_sell_x(x_sub);
-            if y_out > 0.0 {
-                trades.push(RoutedTrade {
-                    is_submission: true,
-                    amm_buys_x: true,
-                    amount_x: x_sub,
-                    amount_y: y_out,
-                });
-            }
+        let mut best = search.best;
+        let (in_sub, in_norm) =
+            Self::within_caps(best.in_sub, best.in_norm, buy_x, amm_sub, baselines);
+        if (in_sub, in_norm) != (best.in_sub, best.in_norm) {
+            // Strategies quoting the venues themselves may pass a cap.
+            let routed = in_sub + in_norm;
+            best = self.strategy.quote(routed, in_sub / routed, buy_x, amm_sub, baselines);
         }
-        if x_norm > MIN_TRADE_SIZE && best.out_norm > 0.0 {
-            let y_out = amm_norm.execute_sell_x(x_norm);
-            if y_out > 0.0 {
-                trades.push(RoutedTrade {
-                    is_submission: false,
-                    amm_buys_x: true,
-                    amount_x: x_norm,
-                    amount_y: y_out,
-                });
+        if policy.submission_late {
+            let norm_input = total_input.min(Self::baseline_max_input(buy_x, baselines));
+            let min_trade_size = amm_sub.min_trade_size();
+            best = Self::normalizer_wins_tie(best, norm_input, min_trade_size, |input| {
+                Self::quote_baselines(input, buy_x, baselines)
+            });
+        }
+        #[cfg(feature = "router-debug-checks")]
+        self.check_size_monotonic(total_input, &best, buy_x, amm_sub, baselines);
+        best
+    }
+
+    /// Execute `point`: its submission part, then its baseline part split by
+    /// [`Self::allocate`]. Parts at or below the minimum trade size, or quoted at
+    /// nothing, are skipped. If the submission would pay out materially less than
+    /// `point.out_sub` when executing (its state or rounding changed since the quote), its
+    /// part goes to the baselines instead.
+    pub fn execute_split(
+        point: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) -> Vec<RoutedTrade> {
+        let (trade, rerouted) = Self::execute_submission_part(point, buy_x, amm_sub);
+        let mut trades: Vec<RoutedTrade> = trade.into_iter().collect();
+        let in_norm = point.in_norm + rerouted;
+        let min_trade_size = Self::baseline_min_trade_size(baselines);
+        if in_norm > min_trade_size && (point.out_norm > 0.0 || rerouted > 0.0) {
+            let allocation = Self::allocate(in_norm, buy_x, baselines);
+            for (i, (pool, input)) in baselines.iter_mut().zip(allocation).enumerate() {
+                if input <= pool.amm.min_trade_size() {
+                    continue;
+                }
+                trades.extend(Self::execute_on(&mut pool.amm, 1 + i as u8, input, buy_x, 0.0));
             }
         }
         trades
     }
 
-    fn quote_buy_split(
-        total_y: f64,
+    /// Execute `point`'s submission part, if any. Returns its trade, quoted at
+    /// `point.out_sub`, or the input to re-route to the baselines if the submission would pay
+    /// out materially less than that.
+    fn execute_submission_part(
+        point: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+    ) -> (Option<RoutedTrade>, f64) {
+        if point.in_sub <= amm_sub.min_trade_size() || point.out_sub <= 0.0 {
+            return (None, 0.0);
+        }
+        let min_output = point.out_sub * (1.0 - EXECUTION_SHORTFALL_REL_TOL);
+        match Self::execute_on(amm_sub, SUBMISSION_VENUE, point.in_sub, buy_x, min_output) {
+            Some(trade) => (
+                Some(RoutedTrade {
+                    quoted_output: Some(point.out_sub),
+                    ..trade
+                }),
+                0.0,
+            ),
+            None => {
+                search_stats::inc_router_execution_fallback();
+                (None, point.in_sub)
+            }
+        }
+    }
+
+    /// Execute `input` on `amm`; nothing executes if it would pay out less than
+    /// `min_output`.
+    fn execute_on(
+        amm: &mut BpfAmm,
+        venue: u8,
+        input: f64,
+        buy_x: bool,
+        min_output: f64,
+    ) -> Option<RoutedTrade> {
+        if buy_x {
+            let x_out = amm.execute_buy_x_at_least(input, min_output);
+            (x_out > 0.0).then_some(RoutedTrade {
+                venue,
+                amm_buys_x: false,
+                amount_x: x_out,
+                amount_y: input,
+                quoted_output: None,
+            })
+        } else {
+            let y_out = amm.execute_sell_x_at_least(input, min_output);
+            (y_out > 0.0).then_some(RoutedTrade {
+                venue,
+                amm_buys_x: true,
+                amount_x: input,
+                amount_y: y_out,
+                quoted_output: None,
+            })
+        }
+    }
+
+    /// Quote sending `alpha` of `total_input` to the submission and the rest across the
+    /// baselines, moved within the venues' trade caps by [`Self::within_caps`].
+    pub fn quote_split(
+        total_input: f64,
         alpha: f64,
+        buy_x: bool,
         amm_sub: &mut BpfAmm,
-        amm_norm: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
     ) -> QuotePoint {
         let alpha = alpha.clamp(0.0, 1.0);
-        let in_sub = total_y * alpha;
-        let in_norm = total_y * (1.0 - alpha);
+        let (in_sub, in_norm) = Self::within_caps(
+            total_input * alpha,
+            total_input * (1.0 - alpha),
+            buy_x,
+            amm_sub,
+            baselines,
+        );
 
-        let out_sub = if in_sub > MIN_TRADE_SIZE {
-            amm_sub.quote_buy_x(in_sub)
-        } else {
+        let min_trade_size = amm_sub.min_trade_size();
+        let out_sub = if in_sub <= min_trade_size || amm_sub.known_down() {
             0.0
-        };
-        let out_norm = if in_norm > MIN_TRADE_SIZE {
-            amm_norm.quote_buy_x(in_norm)
+        } else if buy_x {
+            amm_sub.quote_buy_x(in_sub)
         } else {
-            0.0
+            amm_sub.quote_sell_x(in_sub)
         };
+        let submission_down = in_sub > min_trade_size && out_sub <= 0.0 && amm_sub.is_down();
+        let out_norm = Self::quote_baselines(in_norm, buy_x, baselines);
 
         QuotePoint {
             in_sub,
             in_norm,
             out_sub,
             out_norm,
+            submission_down,
         }
     }
 
-    fn quote_sell_split(
-        total_x: f64,
-        alpha: f64,
-        amm_sub: &mut BpfAmm,
-        amm_norm: &mut BpfAmm,
-    ) -> QuotePoint {
-        let alpha = alpha.clamp(0.0, 1.0);
-        let in_sub = total_x * alpha;
-        let in_norm = total_x * (1.0 - alpha);
+    /// Total output of `input` split across the baselines by [`Self::allocate`].
+    fn quote_baselines(input: f64, buy_x: bool, baselines: &mut [BaselinePool]) -> f64 {
+        if input <= Self::baseline_min_trade_size(baselines) {
+            return 0.0;
+        }
+        if let [pool] = baselines {
+            let input = input.min(pool.amm.max_trade_input(buy_x));
+            return if buy_x {
+                pool.amm.quote_buy_x(input)
+            } else {
+                pool.amm.quote_sell_x(input)
+            };
+        }
+        if baselines.iter().any(|pool| pool.black_box) {
+            return Self::allocate_by_quotes(input, buy_x, baselines)
+                .into_iter()
+                .map(|(_, output)| output)
+                .sum();
+        }
+        let allocation = Self::allocate_baselines(input, buy_x, baselines);
+        baselines
+            .iter_mut()
+            .zip(allocation)
+            .filter(|(pool, part)| *part > pool.amm.min_trade_size())
+            .map(|(pool, part)| {
+                if buy_x {
+                    pool.amm.quote_buy_x(part)
+                } else {
+                    pool.amm.quote_sell_x(part)
+                }
+            })
+            .sum()
+    }
 
-        let out_sub = if in_sub > MIN_TRADE_SIZE {
-            amm_sub.quote_sell_x(in_sub)
+    /// `input` split across the baselines: in closed form by [`Self::allocate_baselines`] if
+    /// every baseline is constant-product, otherwise by [`Self::allocate_by_quotes`].
+    fn allocate(input: f64, buy_x: bool, baselines: &mut [BaselinePool]) -> Vec<f64> {
+        if baselines.iter().any(|pool| pool.black_box) {
+            return Self::allocate_by_quotes(input, buy_x, baselines)
+                .into_iter()
+                .map(|(part, _)| part)
+                .collect();
+        }
+        Self::allocate_baselines(input, buy_x, baselines)
+    }
+
+    /// Split `input` across black-box baselines by their quotes: hand it out in up to
+    /// `VENUE_SPLIT_CHUNKS` equal chunks, each to the pool whose next chunk pays the most,
+    /// which is optimal up to a chunk for concave curves. Small inputs get fewer chunks, so
+    /// each stays above the minimum trade size. A pool takes no chunk past its trade cap,
+    /// so input beyond every cap combined is left out.
+    fn allocate_by_quotes(
+        input: f64,
+        buy_x: bool,
+        baselines: &mut [BaselinePool],
+    ) -> Vec<(f64, f64)> {
+        let min_trade_size = Self::baseline_min_trade_size(baselines);
+        let chunks = if input > min_trade_size {
+            ((input / min_trade_size).ceil() as usize - 1).clamp(1, VENUE_SPLIT_CHUNKS)
         } else {
-            0.0
+            1
+        };
+        let chunk = input / chunks as f64;
+        let quote = |amm: &mut BpfAmm, input: f64| {
+            if input <= amm.min_trade_size() || amm.known_down() {
+                return 0.0;
+            }
+            let output = if buy_x {
+                amm.quote_buy_x(input)
+            } else {
+                amm.quote_sell_x(input)
+            };
+            if output <= 0.0 && amm.is_down() {
+                search_stats::inc_router_venue_down();
+            }
+            output
         };
-        let out_norm = if in_norm > MIN_TRADE_SIZE {
-            amm_norm.quote_sell_x(in_norm)
+        let mut allocation = vec![(0.0, 0.0); baselines.len()];
+        // What each pool's next chunk would add to its output.
+        let mut gains: Vec<f64> = baselines
+            .iter_mut()
+            .map(|pool| {
+                if chunk > pool.amm.max_trade_input(buy_x) {
+                    return 0.0;
+                }
+                quote(&mut pool.amm, chunk)
+            })
+            .collect();
+        for handed_out in 1..=chunks {
+            let Some((i, &gain)) = gains.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))
+            else {
+                break;
+            };
+            if gain <= 0.0 {
+                break;
+            }
+            let (part, output) = &mut allocation[i];
+            *part += chunk;
+            *output += gain;
+            if handed_out == chunks {
+                break;
+            }
+            let amm = &mut baselines[i].amm;
+            if *part + chunk > amm.max_trade_input(buy_x) {
+                gains[i] = 0.0;
+                continue;
+            }
+            gains[i] = quote(amm, *part + chunk) - *output;
+        }
+        allocation
+    }
+
+    /// Output per unit input of the baselines' next unit once `input` is split across them by
+    /// [`Self::allocate_baselines`]: the marginal every pool taking flow shares.
+    fn baseline_marginal(input: f64, buy_x: bool, baselines: &[BaselinePool]) -> f64 {
+        let input = if input > Self::baseline_min_trade_size(baselines) {
+            input
         } else {
             0.0
         };
+        let allocation = Self::allocate_baselines(input, buy_x, baselines);
+        baselines
+            .iter()
+            .zip(allocation)
+            .filter(|(pool, part)| *part < pool.amm.max_trade_input(buy_x))
+            .map(|(pool, part)| {
+                let (r_in, r_out) = pool.reserves(buy_x);
+                let gamma = pool.gamma();
+                let denom = r_in + gamma * part;
+                if denom > 0.0 {
+                    r_out * gamma * r_in / (denom * denom)
+                } else {
+                    0.0
+                }
+            })
+            .fold(0.0, f64::max)
+    }
 
-        QuotePoint {
-            in_sub,
-            in_norm,
-            out_sub,
-            out_norm,
+    /// The most the baselines take of one order: every baseline's trade cap combined.
+    fn baseline_max_input(buy_x: bool, baselines: &[BaselinePool]) -> f64 {
+        baselines.iter().map(|pool| pool.amm.max_trade_input(buy_x)).sum()
+    }
+
+    /// `in_sub` to the submission and `in_norm` to the baselines, with whatever either side's
+    /// trade caps (see [`BpfAmm::max_trade_input`]) leave over moved to the other side. The
+    /// order is cut to both sides' caps combined if it exceeds them.
+    fn within_caps(
+        in_sub: f64,
+        in_norm: f64,
+        buy_x: bool,
+        amm_sub: &BpfAmm,
+        baselines: &[BaselinePool],
+    ) -> (f64, f64) {
+        let sub_max = amm_sub.max_trade_input(buy_x);
+        let norm_max = Self::baseline_max_input(buy_x, baselines);
+        if in_sub <= sub_max && in_norm <= norm_max {
+            return (in_sub, in_norm);
+        }
+        let total = in_sub + in_norm;
+        let in_sub = in_sub.max(total - norm_max).min(sub_max);
+        (in_sub, (total - in_sub).min(norm_max))
+    }
+
+    /// The smallest input any baseline is sent; the baselines' part of an order at or below
+    /// it is not worth splitting.
+    fn baseline_min_trade_size(baselines: &[BaselinePool]) -> f64 {
+        baselines
+            .iter()
+            .map(|pool| pool.amm.min_trade_size())
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Split `input` across constant-product baselines to maximize their total output, each
+    /// pool taking no more than its trade cap: [`Self::water_fill`] the pools, then fix any
+    /// pool past its cap at the cap and water-fill the rest of the input over the others.
+    /// Input beyond every cap combined is left out.
+    fn allocate_baselines(input: f64, buy_x: bool, baselines: &[BaselinePool]) -> Vec<f64> {
+        let caps: Vec<f64> = baselines.iter().map(|pool| pool.amm.max_trade_input(buy_x)).collect();
+        let mut capped = vec![false; baselines.len()];
+        loop {
+            let fixed: f64 = caps.iter().zip(&capped).filter(|(_, &c)| c).map(|(cap, _)| cap).sum();
+            let rest = (input - fixed).max(0.0);
+            let mut allocation = Self::water_fill(rest, buy_x, baselines, &capped);
+            let mut saturated = false;
+            for ((part, &cap), is_capped) in allocation.iter_mut().zip(&caps).zip(&mut capped) {
+                if *is_capped {
+                    *part = cap;
+                } else if *part > cap {
+                    *is_capped = true;
+                    saturated = true;
+                }
+            }
+            if !saturated {
+                return allocation;
+            }
+        }
+    }
+
+    /// Split `input` across the constant-product baselines not `skipped` to maximize their
+    /// total output: water-fill until every pool that gets flow has the same marginal price,
+    /// leaving out pools whose best price is worse than that.
+    ///
+    /// A pool with reserves `(r_in, r_out)` and fee factor `g` pays
+    /// `r_out * g * d / (r_in + g * d)` for input `d`, so at marginal output `l` it takes
+    /// `d = s * sqrt(r_in * r_out / g) - r_in / g` with `s = 1 / sqrt(l)`.
+    fn water_fill(
+        input: f64,
+        buy_x: bool,
+        baselines: &[BaselinePool],
+        skipped: &[bool],
+    ) -> Vec<f64> {
+        let mut allocation = vec![0.0; baselines.len()];
+        if baselines.len() == 1 {
+            allocation[0] = input;
+            return allocation;
+        }
+        // (index, r_in / g, sqrt(r_in * r_out / g)); `s` must exceed their ratio to trade.
+        let mut pools: Vec<(usize, f64, f64)> = baselines
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !skipped[i])
+            .filter_map(|(i, pool)| {
+                let (r_in, r_out) = pool.reserves(buy_x);
+                let gamma = pool.gamma();
+                (r_in > 0.0 && r_out > 0.0 && gamma > 0.0)
+                    .then(|| (i, r_in / gamma, (r_in * r_out / gamma).sqrt()))
+            })
+            .collect();
+        if pools.is_empty() {
+            allocation[0] = input;
+            return allocation;
+        }
+        // Best marginal price first.
+        pools.sort_by(|a, b| (a.1 / a.2).total_cmp(&(b.1 / b.2)));
+
+        let (mut sum_a, mut sum_b) = (0.0, 0.0);
+        let mut active = 0;
+        let mut s = 0.0;
+        while active < pools.len() {
+            sum_a += pools[active].1;
+            sum_b += pools[active].2;
+            active += 1;
+            s = (input + sum_a) / sum_b;
+            if active == pools.len() || s <= pools[active].1 / pools[active].2 {
+                break;
+            }
+        }
+        let mut total = 0.0;
+        for &(i, a, b) in &pools[..active] {
+            allocation[i] = (s * b - a).max(0.0);
+            total += allocation[i];
+        }
+        if total > 0.0 {
+            // Remove the rounding drift so the parts sum to the order.
+            for part in &mut allocation {
+                *part *= input / total;
+            }
         }
+        allocation
     }
 
-    fn maximize_split<F>(total_input: f64, mut evaluate: F) -> SplitSearchResult
+    /// Golden-section search over the submission's share of `total_input`; amounts below
+    /// `min_trade_size` are the scale the search stops at.
+    fn maximize_split<F>(total_input: f64, min_trade_size: f64, evaluate: F) -> SplitSearchResult
+    where
+        F: FnMut(f64) -> QuotePoint,
+    {
+        Self::maximize_split_in(total_input, GOLDEN_MAX_ITERS, min_trade_size, evaluate)
+    }
+
+    /// [`Self::maximize_split`] stopping after at most `max_iters` golden-section steps.
+    fn maximize_split_in<F>(
+        total_input: f64,
+        max_iters: usize,
+        min_trade_size: f64,
+        mut evaluate: F,
+    ) -> SplitSearchResult
     where
         F: FnMut(f64) -> QuotePoint,
     {
         search_stats::inc_router_call();
-        let mut sampled = Vec::with_capacity(GOLDEN_MAX_ITERS + 6);
+        let mut sampled = Vec::with_capacity(max_iters + 6);
         let mut left = 0.0_f64;
         let mut right = 1.0_f64;
 
@@ -226,11 +1513,146 @@ impl OrderRouter {
         search_stats::inc_router_eval();
         let edge_right = evaluate(right);
         sampled.push(edge_left);
+        if edge_right.submission_down {
+            // Nothing to split: the baselines take the whole order.
+            search_stats::inc_router_venue_down();
+            return SplitSearchResult {
+                best: edge_left,
+                sampled,
+            };
+        }
+        sampled.push(edge_right);
+        let mut best = Self::best_quote(edge_left, edge_right);
+
+        let mut x1 = right - GOLDEN_RATIO_CONJUGATE * (right - left);
+        let mut x2 = left + GOLDEN_RATIO_CONJUGATE * (right - left);
+        search_stats::inc_router_eval();
+        let mut q1 = evaluate(x1);
+        search_stats::inc_router_eval();
+        let mut q2 = evaluate(x2);
+        sampled.push(q1);
+        sampled.push(q2);
+        best = Self::best_quote(best, q1);
+        best = Self::best_quote(best, q2);
+
+        for _ in 0..max_iters {
+            search_stats::inc_router_iter();
+            if right - left <= GOLDEN_ALPHA_TOL {
+                break;
+            }
+
+            let alpha_mid = 0.5 * (left + right);
+            let sub_mid_amount = total_input * alpha_mid;
+            let amount_width = total_input * (right - left);
+            let amount_scale = sub_mid_amount.abs().max(min_trade_size);
+            if amount_width <= GOLDEN_SUBMISSION_AMOUNT_REL_TOL * amount_scale {
+                break;
+            }
+
+            if Self::within_rel_gap(
+                Self::quote_score(&q1),
+                Self::quote_score(&q2),
+                GOLDEN_SCORE_REL_GAP_TOL,
+            ) {
+                search_stats::inc_router_early_stop_rel_gap();
+                break;
+            }
+
+            if Self::quote_score(&q1) < Self::quote_score(&q2) {
+                left = x1;
+                x1 = x2;
+                q1 = q2;
+                x2 = left + GOLDEN_RATIO_CONJUGATE * (right - left);
+                search_stats::inc_router_eval();
+                q2 = evaluate(x2);
+                sampled.push(q2);
+                best = Self::best_quote(best, q2);
+            } else {
+                right = x2;
+                x2 = x1;
+                q2 = q1;
+                x1 = right - GOLDEN_RATIO_CONJUGATE * (right - left);
+                search_stats::inc_router_eval();
+                q1 = evaluate(x1);
+                sampled.push(q1);
+                best = Self::best_quote(best, q1);
+            }
+        }
+
+        search_stats::inc_router_eval();
+        let center = evaluate((left + right) * 0.5);
+        sampled.push(center);
+        best = Self::best_quote(best, center);
+
+        SplitSearchResult { best, sampled }
+    }
+
+    /// Quote the share `alpha` and the ends of `bracket` around it, for a search seeded from
+    /// a nearby split. Returns `None`, with the points quoted, unless `alpha` quotes the most,
+    /// as the optimum may then lie beyond the bracket.
+    fn maximize_split_near<F>(
+        alpha: f64,
+        bracket: (f64, f64),
+        mut evaluate: F,
+    ) -> Result<SplitSearchResult, Vec<QuotePoint>>
+    where
+        F: FnMut(f64) -> QuotePoint,
+    {
+        search_stats::inc_router_call();
+        search_stats::inc_router_eval();
+        let best = evaluate(alpha);
+        let mut sampled = vec![best];
+        for edge in [bracket.0, bracket.1] {
+            if edge != alpha {
+                search_stats::inc_router_eval();
+                sampled.push(evaluate(edge));
+            }
+        }
+        let held = sampled.iter().all(|point| {
+            !point.submission_down && Self::quote_score(point) <= Self::quote_score(&best)
+        });
+        if held {
+            Ok(SplitSearchResult { best, sampled })
+        } else {
+            Err(sampled)
+        }
+    }
+
+    /// [`Self::maximize_split`] over whole units of the order's `total`, each `unit` in size,
+    /// going to the submission. Stops on the same tolerances, or when the bracket is down to
+    /// a few units.
+    fn maximize_split_units<F>(
+        total: u64,
+        unit: f64,
+        min_trade_size: f64,
+        mut evaluate: F,
+    ) -> SplitSearchResult
+    where
+        F: FnMut(u64) -> QuotePoint,
+    {
+        search_stats::inc_router_call();
+        let mut sampled = Vec::with_capacity(GOLDEN_MAX_ITERS + 6);
+        let (mut left, mut right) = (0_u64, total);
+        // The interior probe `GOLDEN_RATIO_CONJUGATE` of the way across `width` units.
+        let golden = |width: u64| (width as f64 * GOLDEN_RATIO_CONJUGATE).round() as u64;
+
+        search_stats::inc_router_eval();
+        let edge_left = evaluate(left);
+        search_stats::inc_router_eval();
+        let edge_right = evaluate(right);
+        sampled.push(edge_left);
+        if edge_right.submission_down {
+            search_stats::inc_router_venue_down();
+            return SplitSearchResult {
+                best: edge_left,
+                sampled,
+            };
+        }
         sampled.push(edge_right);
         let mut best = Self::best_quote(edge_left, edge_right);
 
-        let mut x1 = right - GOLDEN_RATIO_CONJUGATE * (right - left);
-        let mut x2 = left + GOLDEN_RATIO_CONJUGATE * (right - left);
+        let mut x1 = right - golden(right - left);
+        let mut x2 = left + golden(right - left);
         search_stats::inc_router_eval();
         let mut q1 = evaluate(x1);
         search_stats::inc_router_eval();
@@ -242,15 +1664,13 @@ impl OrderRouter {
 
         for _ in 0..GOLDEN_MAX_ITERS {
             search_stats::inc_router_iter();
-            if right - left <= GOLDEN_ALPHA_TOL {
+            let width = right - left;
+            if width <= 3 || width as f64 <= GOLDEN_ALPHA_TOL * total as f64 {
                 break;
             }
-
-            let alpha_mid = 0.5 * (left + right);
-            let sub_mid_amount = total_input * alpha_mid;
-            let amount_width = total_input * (right - left);
-            let amount_scale = sub_mid_amount.abs().max(MIN_TRADE_SIZE);
-            if amount_width <= GOLDEN_SUBMISSION_AMOUNT_REL_TOL * amount_scale {
+            let sub_mid_amount = (left + width / 2) as f64 * unit;
+            let amount_scale = sub_mid_amount.max(min_trade_size);
+            if width as f64 * unit <= GOLDEN_SUBMISSION_AMOUNT_REL_TOL * amount_scale {
                 break;
             }
 
@@ -267,7 +1687,7 @@ impl OrderRouter {
                 left = x1;
                 x1 = x2;
                 q1 = q2;
-                x2 = left + GOLDEN_RATIO_CONJUGATE * (right - left);
+                x2 = (left + golden(right - left)).max(x1 + 1);
                 search_stats::inc_router_eval();
                 q2 = evaluate(x2);
                 sampled.push(q2);
@@ -276,7 +1696,7 @@ impl OrderRouter {
                 right = x2;
                 x2 = x1;
                 q2 = q1;
-                x1 = right - GOLDEN_RATIO_CONJUGATE * (right - left);
+                x1 = (right - golden(right - left)).min(x2 - 1);
                 search_stats::inc_router_eval();
                 q1 = evaluate(x1);
                 sampled.push(q1);
@@ -285,14 +1705,70 @@ impl OrderRouter {
         }
 
         search_stats::inc_router_eval();
-        let center = evaluate((left + right) * 0.5);
+        let center = evaluate(left + (right - left) / 2);
         sampled.push(center);
         best = Self::best_quote(best, center);
 
         SplitSearchResult { best, sampled }
     }
 
+    /// Re-route half the order (quotes only) and warn if it would have delivered more than
+    /// the full order's chosen split, i.e. total output fell as order size grew.
+    #[cfg(feature = "router-debug-checks")]
+    fn check_size_monotonic(
+        &self,
+        total_input: f64,
+        best: &QuotePoint,
+        buy_x: bool,
+        amm_sub: &mut BpfAmm,
+        baselines: &mut [BaselinePool],
+    ) {
+        let half = 0.5 * total_input;
+        let smaller = self.strategy.split(half, buy_x, amm_sub, baselines).best;
+        let side = if buy_x { "buy" } else { "sell" };
+        let (full_score, half_score) = (Self::quote_score(best), Self::quote_score(&smaller));
+        if full_score + SIZE_MONOTONIC_REL_TOL * half_score.abs() < half_score {
+            tracing::warn!(
+                side,
+                total_input,
+                full_output = full_score,
+                half_output = half_score,
+                "router output decreased with order size"
+            );
+        }
+    }
+
     #[inline]
+    /// Routes everything to the normalizer unless the split beats it by more than
+    /// `LATE_TIE_REL_TOL`. Splits sending the submission no more than `min_trade_size` are
+    /// already normalizer-only.
+    fn normalizer_wins_tie<F>(
+        best: QuotePoint,
+        total_input: f64,
+        min_trade_size: f64,
+        quote_norm: F,
+    ) -> QuotePoint
+    where
+        F: FnOnce(f64) -> f64,
+    {
+        if best.in_sub <= min_trade_size {
+            return best;
+        }
+        let norm_only = QuotePoint {
+            in_sub: 0.0,
+            in_norm: total_input,
+            out_sub: 0.0,
+            out_norm: quote_norm(total_input),
+            submission_down: false,
+        };
+        let norm_score = Self::quote_score(&norm_only);
+        if Self::quote_score(&best) <= norm_score + LATE_TIE_REL_TOL * norm_score.abs() {
+            norm_only
+        } else {
+            best
+        }
+    }
+
     fn quote_score(point: &QuotePoint) -> f64 {
         let total = point.out_sub + point.out_norm;
         if total.is_finite() {
@@ -323,15 +1799,23 @@ impl OrderRouter {
 
 #[cfg(test)]
 mod tests {
-    use super::{OrderRouter, MIN_TRADE_SIZE};
+    use super::{
+        AdaptiveGoldenSplit, BaselinePool, BestPriceRoute, GoldenSectionSplit, IntegerGoldenSplit,
+        MarginalPriceSplit, OrderRouter, OrderRouterStrategy, ProportionalSplit, QuotePoint,
+        WarmStartGoldenSplit, WinnerTakeAll, SIZE_MONOTONIC_REL_TOL,
+    };
     use crate::amm::BpfAmm;
     use crate::retail::RetailOrder;
     use prop_amm_executor::SwapFn;
+    use prop_amm_shared::config::MIN_TRADE_SIZE;
     use prop_amm_shared::normalizer::compute_swap as normalizer_swap;
+    use prop_amm_shared::nano::TokenDecimals;
     use rand::seq::SliceRandom;
     use rand::Rng;
     use rand::SeedableRng;
     use rand_pcg::Pcg64;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicU64, Ordering};
 
     const BRUTE_FORCE_STEPS: usize = 4000;
     // Router search is intentionally approximate for speed; 1% relative error is acceptable.
@@ -360,12 +1844,12 @@ mod tests {
             0 => {
                 let net = input.saturating_mul(fee_numerator) / fee_denominator;
                 let new_ry = ry + net;
-                rx.saturating_sub((k + new_ry - 1) / new_ry) as u64
+                rx.saturating_sub(k.div_ceil(new_ry)) as u64
             }
             1 => {
                 let net = input.saturating_mul(fee_numerator) / fee_denominator;
                 let new_rx = rx + net;
-                ry.saturating_sub((k + new_rx - 1) / new_rx) as u64
+                ry.saturating_sub(k.div_ceil(new_rx)) as u64
             }
             _ => 0,
         }
@@ -485,14 +1969,22 @@ mod tests {
             sub_reserves.1,
             "sub".to_string(),
         );
-        let mut amm_norm = BpfAmm::new_native(
+        let amm_norm = BpfAmm::new_native(
             norm_swap,
             None,
             norm_reserves.0,
             norm_reserves.1,
             "norm".to_string(),
         );
-        let trades = router.route_order(order, &mut amm_sub, &mut amm_norm, fair_price);
+        let mut baselines = [BaselinePool {
+            amm: amm_norm,
+            fee_bps: 30,
+            black_box: false,
+        }];
+        let trades = router
+            .route_order(order, &mut amm_sub, &mut baselines, fair_price, false)
+            .expect("orders without a slippage limit are never abandoned")
+            .trades;
         total_output_from_trades(order, &trades)
     }
 
@@ -536,6 +2028,11 @@ mod tests {
             let order = RetailOrder {
                 is_buy: true,
                 size: rng.gen_range(0.5..2_500.0),
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
             };
 
             let router_output = run_router_once(
@@ -588,6 +2085,11 @@ mod tests {
             let order = RetailOrder {
                 is_buy: false,
                 size: rng.gen_range(0.5..2_500.0),
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
             };
 
             let router_output = run_router_once(
@@ -631,6 +2133,11 @@ mod tests {
             let order = RetailOrder {
                 is_buy: rng.gen_bool(0.5),
                 size: rng.gen_range(1.0..3_000.0),
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
             };
             let (sub_swap, norm_swap): (SwapFn, SwapFn) = if rng.gen_bool(0.5) {
                 (high_fee_swap, zero_fee_swap)
@@ -663,4 +2170,972 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn late_submission_loses_ties_but_keeps_clearly_better_quotes() {
+        let router = OrderRouter::new();
+        let order = RetailOrder {
+            is_buy: true,
+            size: 1.0,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        };
+        let route = |sub_swap: SwapFn, norm_swap: SwapFn, late: bool| {
+            let mut amm_sub =
+                BpfAmm::new_native(sub_swap, None, 100.0, 10_000.0, "submission".to_string());
+            let amm_norm =
+                BpfAmm::new_native(norm_swap, None, 100.0, 10_000.0, "normalizer".to_string());
+            let mut baselines = [BaselinePool {
+                amm: amm_norm,
+                fee_bps: 30,
+                black_box: false,
+            }];
+            router
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, late)
+                .unwrap()
+                .trades
+                .iter()
+                .any(|t| t.is_submission())
+        };
+
+        assert!(route(starter_fee_swap, starter_fee_swap, false));
+        assert!(!route(starter_fee_swap, starter_fee_swap, true));
+        assert!(route(low_fee_swap, high_fee_swap, true));
+    }
+
+    #[test]
+    fn late_ties_apply_down_to_the_configured_min_trade_size() {
+        // With 1e-6 as dust, selling 5e-6 X is a real trade; the submission pays about 0.5 bps
+        // more for it than the normalizer, inside the tie tolerance.
+        let min_trade_size = 1e-6;
+        let order = RetailOrder {
+            is_buy: false,
+            size: 5e-4,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        };
+        let route = |late: bool| {
+            let mut amm_sub =
+                BpfAmm::new_native(starter_fee_swap, None, 100.0, 10_000.5, "sub".to_string());
+            amm_sub.set_min_trade_size(min_trade_size);
+            let mut baselines = [cp_pool(50, (100.0, 10_000.0))];
+            baselines[0].amm.set_min_trade_size(min_trade_size);
+            OrderRouter::new()
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, late)
+                .unwrap()
+                .trades
+                .iter()
+                .any(|t| t.is_submission())
+        };
+
+        assert!(route(false));
+        assert!(!route(true));
+    }
+
+    #[test]
+    fn orders_over_their_slippage_limit_are_abandoned_untouched() {
+        let router = OrderRouter::new();
+        // Two 30 bps pools at fair price: a 2,000 Y order costs the fee plus about 5% impact.
+        let route = |is_buy: bool, max_slippage_bps: f64| {
+            let order = RetailOrder {
+                is_buy,
+                size: 2_000.0,
+                informed: false,
+                max_slippage_bps: Some(max_slippage_bps),
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let mut amm_sub =
+                BpfAmm::new_native(normalizer_swap, None, 100.0, 10_000.0, "sub".to_string());
+            let amm_norm =
+                BpfAmm::new_native(normalizer_swap, None, 100.0, 10_000.0, "norm".to_string());
+            let mut baselines = [BaselinePool {
+                amm: amm_norm,
+                fee_bps: 30,
+                black_box: false,
+            }];
+            let routed = router.route_order(&order, &mut amm_sub, &mut baselines, 100.0, false);
+            let untouched = amm_sub.reserve_x == 100.0 && baselines[0].amm.reserve_x == 100.0;
+            (routed, untouched)
+        };
+
+        for is_buy in [true, false] {
+            let (routed, untouched) = route(is_buy, 100.0);
+            assert!(routed.is_none() && untouched, "buy {is_buy}: should be abandoned");
+            let (routed, untouched) = route(is_buy, 2_000.0);
+            assert!(!routed.unwrap().trades.is_empty() && !untouched, "buy {is_buy}: should fill");
+        }
+    }
+
+    #[test]
+    fn trade_caps_bound_every_venue_and_cut_oversized_orders() {
+        use prop_amm_shared::config::RouterKind;
+
+        // Caps of 5% of the Y reserves: 500 Y on the submission, 500 + 1,000 Y on the pools. The
+        // submission's lower fee draws more than its share of reserves, past its cap.
+        for kind in [
+            RouterKind::GoldenSection,
+            RouterKind::BestPrice,
+            RouterKind::Proportional,
+            RouterKind::MarginalPrice,
+            RouterKind::WinnerTakeAll,
+            RouterKind::IntegerGolden,
+            RouterKind::AdaptiveGolden,
+            RouterKind::WarmStartGolden,
+        ] {
+            let router = OrderRouter::for_kind(kind);
+            for size in [300.0, 1_800.0, 5_000.0] {
+                let order = RetailOrder {
+                    is_buy: true,
+                    size,
+                    informed: false,
+                    max_slippage_bps: None,
+                    min_output: None,
+                    partial_fill: false,
+                    exact_output: false,
+                };
+                let mut amm_sub =
+                    BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+                let mut baselines =
+                    [cp_pool(30, (100.0, 10_000.0)), cp_pool(30, (200.0, 20_000.0))];
+                amm_sub.set_max_trade_fraction(Some(0.05));
+                for pool in &mut baselines {
+                    pool.amm.set_max_trade_fraction(Some(0.05));
+                }
+                let caps = [500.0, 500.0, 1_000.0];
+                let routed = router
+                    .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+                    .expect("no limits to miss");
+                for trade in &routed.trades {
+                    let cap = caps[trade.venue as usize];
+                    assert!(
+                        trade.amount_y <= cap * (1.0 + 1e-9),
+                        "{kind:?} size {size}: venue {} took {} Y over its {cap} cap",
+                        trade.venue,
+                        trade.amount_y,
+                    );
+                }
+                let expected_fill = (2_000.0 / size).min(1.0);
+                assert!((routed.filled - expected_fill).abs() < 1e-12, "{kind:?} size {size}");
+                if kind != RouterKind::WinnerTakeAll {
+                    // Winner-take-all never splits, so it cannot fill what no one venue takes.
+                    let spent: f64 = routed.trades.iter().map(|trade| trade.amount_y).sum();
+                    assert!(
+                        (spent - size * expected_fill).abs() < 1e-6 * size,
+                        "{kind:?} size {size}: spent {spent} Y"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn price_guard_leaves_absurdly_quoted_orders_unfilled() {
+        // Both pools price X at 100 Y but hold so little that a 50 Y order pays about 50% over
+        // the fair price.
+        let route = |guard: Option<f64>| {
+            let router = OrderRouter::new().with_price_guard(guard);
+            let order = RetailOrder {
+                is_buy: true,
+                size: 50.0,
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let mut amm_sub =
+                BpfAmm::new_native(normalizer_swap, None, 0.5, 50.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (0.5, 50.0))];
+            let routed = router.route_order(&order, &mut amm_sub, &mut baselines, 100.0, false);
+            let untouched = amm_sub.reserve_x == 0.5 && baselines[0].amm.reserve_x == 0.5;
+            (routed.expect("no limits to miss"), untouched)
+        };
+
+        let (routed, untouched) = route(None);
+        assert!(!routed.trades.is_empty() && routed.filled == 1.0 && !untouched);
+        let (routed, untouched) = route(Some(500.0));
+        assert!(routed.trades.is_empty() && routed.filled == 0.0 && untouched);
+        // A guard wider than the order's impact lets it through.
+        let (routed, _) = route(Some(20_000.0));
+        assert_eq!(routed.filled, 1.0);
+    }
+
+    #[test]
+    fn router_output_is_non_decreasing_in_order_size() {
+        let mut rng = Pcg64::seed_from_u64(23);
+        let curve_set: [SwapFn; 5] = [
+            normalizer_swap,
+            zero_fee_swap,
+            low_fee_swap,
+            starter_fee_swap,
+            high_fee_swap,
+        ];
+
+        for case_idx in 0..60 {
+            let sub_swap = *curve_set.choose(&mut rng).unwrap();
+            let norm_swap = *curve_set.choose(&mut rng).unwrap();
+            let sub_rx = rng.gen_range(20.0..400.0);
+            let sub_price = rng.gen_range(35.0..220.0);
+            let norm_rx = sub_rx * rng.gen_range(0.6..1.6);
+            let norm_price = sub_price * rng.gen_range(0.6..1.6);
+            let sub_reserves = (sub_rx, sub_rx * sub_price);
+            let norm_reserves = (norm_rx, norm_rx * norm_price);
+            let fair_price = ((sub_price + norm_price) * 0.5) * rng.gen_range(0.7..1.3);
+            let is_buy = rng.gen_bool(0.5);
+
+            let mut previous = 0.0_f64;
+            for i in 0..40 {
+                let order = RetailOrder {
+                    is_buy,
+                    size: 0.5 * 1.25_f64.powi(i),
+                    informed: false,
+                    max_slippage_bps: None,
+                    min_output: None,
+                    partial_fill: false,
+                    exact_output: false,
+                };
+                let output = run_router_once(
+                    &order,
+                    fair_price,
+                    sub_swap,
+                    norm_swap,
+                    sub_reserves,
+                    norm_reserves,
+                );
+                assert!(
+                    output + SIZE_MONOTONIC_REL_TOL * previous >= previous,
+                    "case {case_idx}: output fell from {previous} to {output} at size {}",
+                    order.size
+                );
+                previous = previous.max(output);
+            }
+        }
+    }
+
+    fn cp_pool(fee_bps: u16, reserves: (f64, f64)) -> BaselinePool {
+        let swap: SwapFn = match fee_bps {
+            10 => low_fee_swap,
+            50 => starter_fee_swap,
+            _ => normalizer_swap,
+        };
+        let amm = BpfAmm::new_native(swap, None, reserves.0, reserves.1, "pool".to_string());
+        BaselinePool {
+            amm,
+            fee_bps,
+            black_box: false,
+        }
+    }
+
+    #[test]
+    fn baseline_allocation_matches_bruteforce_split() {
+        let mut rng = Pcg64::seed_from_u64(31);
+        for case_idx in 0..40 {
+            let mut pools: Vec<BaselinePool> = [30, 10, 50]
+                .into_iter()
+                .map(|fee_bps| {
+                    let rx = rng.gen_range(20.0..400.0);
+                    cp_pool(fee_bps, (rx, rx * rng.gen_range(80.0..120.0)))
+                })
+                .collect();
+            let buy_x = rng.gen_bool(0.5);
+            let input = if buy_x {
+                rng.gen_range(10.0..20_000.0)
+            } else {
+                rng.gen_range(0.1..200.0)
+            };
+            let quote = |pool: &mut BaselinePool, part: f64| match (part > MIN_TRADE_SIZE, buy_x) {
+                (false, _) => 0.0,
+                (true, true) => pool.amm.quote_buy_x(part),
+                (true, false) => pool.amm.quote_sell_x(part),
+            };
+
+            let allocation = OrderRouter::allocate_baselines(input, buy_x, &pools);
+            assert!((allocation.iter().sum::<f64>() - input).abs() <= input * 1e-12);
+            let routed: f64 = pools
+                .iter_mut()
+                .zip(&allocation)
+                .map(|(pool, &part)| quote(pool, part))
+                .sum();
+
+            let steps = 200;
+            let mut brute = 0.0_f64;
+            for i in 0..=steps {
+                for j in 0..=steps - i {
+                    let parts = [i, j, steps - i - j].map(|k| input * k as f64 / steps as f64);
+                    let out: f64 = pools
+                        .iter_mut()
+                        .zip(parts)
+                        .map(|(pool, part)| quote(pool, part))
+                        .sum();
+                    brute = brute.max(out);
+                }
+            }
+            assert!(
+                routed + brute * 1e-4 >= brute,
+                "case {case_idx}: allocation {allocation:?} gives {routed}, brute force {brute}"
+            );
+        }
+
+        let single = [cp_pool(30, (100.0, 10_000.0))];
+        assert_eq!(OrderRouter::allocate_baselines(5.0, false, &single), vec![5.0]);
+    }
+
+    static DEAD_QUOTES: AtomicU64 = AtomicU64::new(0);
+
+    fn dead_swap(_data: &[u8]) -> u64 {
+        DEAD_QUOTES.fetch_add(1, Ordering::Relaxed);
+        0
+    }
+
+    #[test]
+    fn down_submission_gets_no_flow_and_no_split_search() {
+        let router = OrderRouter::new();
+        let order = |is_buy| RetailOrder {
+            is_buy,
+            size: 50.0,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        };
+        let mut amm_sub = BpfAmm::new_native(dead_swap, None, 100.0, 10_000.0, "sub".to_string());
+        let amm_norm =
+            BpfAmm::new_native(normalizer_swap, None, 100.0, 10_000.0, "norm".to_string());
+        let mut baselines = [BaselinePool {
+            amm: amm_norm,
+            fee_bps: 30,
+            black_box: false,
+        }];
+
+        let trades = router
+            .route_order(&order(true), &mut amm_sub, &mut baselines, 100.0, false)
+            .unwrap()
+            .trades;
+        assert!(!trades.is_empty() && trades.iter().all(|t| !t.is_submission()));
+        // The all-submission quote, then the liveness probes; no split search.
+        assert_eq!(DEAD_QUOTES.load(Ordering::Relaxed), 7);
+        assert!(amm_sub.known_down());
+
+        // Later orders in the same step skip the submission without quoting it.
+        let trades = router
+            .route_order(&order(false), &mut amm_sub, &mut baselines, 100.0, false)
+            .unwrap()
+            .trades;
+        assert!(!trades.is_empty() && trades.iter().all(|t| !t.is_submission()));
+        assert_eq!(DEAD_QUOTES.load(Ordering::Relaxed), 7);
+
+        amm_sub.set_current_step(1);
+        assert!(!amm_sub.known_down());
+    }
+
+    #[test]
+    fn router_strategies_split_as_documented() {
+        let order = RetailOrder {
+            is_buy: true,
+            size: 2_000.0,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        };
+        let route = |router: OrderRouter| {
+            let mut amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 300.0, 30_000.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+            let trades = router
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+                .unwrap()
+                .trades;
+            let sub_y: f64 =
+                trades.iter().filter(|t| t.is_submission()).map(|t| t.amount_y).sum();
+            (sub_y, total_output_from_trades(&order, &trades))
+        };
+
+        let (golden_y, golden_out) = route(OrderRouter::new());
+        let (best_y, best_out) = route(OrderRouter::with_strategy(Box::new(BestPriceRoute)));
+        let (prop_y, prop_out) = route(OrderRouter::with_strategy(Box::new(ProportionalSplit)));
+
+        assert!(golden_y > 0.0 && golden_y < order.size);
+        // The deeper, cheaper submission wins the whole order.
+        assert_eq!(best_y, order.size);
+        // It holds three quarters of the Y reserves.
+        assert!((prop_y - 0.75 * order.size).abs() < 1e-9);
+        assert!(golden_out > best_out);
+        assert_close_to_optimal(golden_out, prop_out, DIVERSE_CURVE_TOLERANCE, "proportional");
+    }
+
+    thread_local! {
+        static PROGRAM_CALLS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    fn counted_starter_swap(data: &[u8]) -> u64 {
+        PROGRAM_CALLS.with(|calls| calls.set(calls.get() + 1));
+        starter_fee_swap(data)
+    }
+
+    fn counted_normalizer_swap(data: &[u8]) -> u64 {
+        PROGRAM_CALLS.with(|calls| calls.set(calls.get() + 1));
+        normalizer_swap(data)
+    }
+
+    #[test]
+    fn marginal_split_matches_golden_output_with_fewer_program_calls() {
+        let mut rng = Pcg64::seed_from_u64(23);
+        let (mut golden_calls, mut marginal_calls) = (0, 0);
+        for case_idx in 0..120 {
+            let is_buy = rng.gen_bool(0.5);
+            let sub_rx = rng.gen_range(20.0..400.0);
+            let sub_price = rng.gen_range(60.0..160.0);
+            let norm_rx = sub_rx * rng.gen_range(0.6..1.6);
+            let norm_price = sub_price * rng.gen_range(0.95..1.05);
+            let order = RetailOrder {
+                is_buy,
+                size: rng.gen_range(0.5..2_500.0),
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let route = |router: OrderRouter| {
+                let mut amm_sub = BpfAmm::new_native(
+                    counted_starter_swap,
+                    None,
+                    sub_rx,
+                    sub_rx * sub_price,
+                    "sub".to_string(),
+                );
+                let amm_norm = BpfAmm::new_native(
+                    counted_normalizer_swap,
+                    None,
+                    norm_rx,
+                    norm_rx * norm_price,
+                    "norm".to_string(),
+                );
+                let mut baselines = [BaselinePool {
+                    amm: amm_norm,
+                    fee_bps: 30,
+                    black_box: false,
+                }];
+                PROGRAM_CALLS.with(|calls| calls.set(0));
+                let trades = router
+                    .route_order(&order, &mut amm_sub, &mut baselines, sub_price, false)
+                    .unwrap()
+                    .trades;
+                (total_output_from_trades(&order, &trades), PROGRAM_CALLS.with(Cell::get))
+            };
+            let (golden, golden_count) = route(OrderRouter::new());
+            let (marginal, marginal_count) =
+                route(OrderRouter::with_strategy(Box::new(MarginalPriceSplit)));
+            golden_calls += golden_count;
+            marginal_calls += marginal_count;
+            // Golden section stops within ~1% of the optimum; the marginal solve lands on it.
+            assert_close_to_optimal(marginal, golden, 1e-5, &format!("marginal case {case_idx}"));
+        }
+        // The baselines' marginal is closed-form, so only the submission is probed.
+        assert!(
+            marginal_calls < golden_calls,
+            "marginal {marginal_calls} vs golden {golden_calls} program calls"
+        );
+    }
+
+    #[test]
+    fn black_box_baselines_route_close_to_bruteforce() {
+        let mut rng = Pcg64::seed_from_u64(29);
+        let curve_set: [SwapFn; 4] =
+            [normalizer_swap, low_fee_swap, starter_fee_swap, high_fee_swap];
+        for case_idx in 0..24 {
+            let price = rng.gen_range(60.0..160.0);
+            let order = RetailOrder {
+                is_buy: rng.gen_bool(0.5),
+                size: rng.gen_range(0.5..2_500.0),
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let specs: Vec<(SwapFn, f64, f64)> = (0..3)
+                .map(|_| {
+                    let rx = rng.gen_range(20.0..400.0);
+                    let ry = rx * price * rng.gen_range(0.95..1.05);
+                    (*curve_set.choose(&mut rng).unwrap(), rx, ry)
+                })
+                .collect();
+            let venue = |i: usize| {
+                let (swap, rx, ry) = specs[i];
+                BpfAmm::new_native(swap, None, rx, ry, "v".to_string())
+            };
+            // The baselines' curves need not match their fee, so they are routed as black boxes.
+            let baseline = |i: usize| BaselinePool {
+                amm: venue(i),
+                fee_bps: 30,
+                black_box: true,
+            };
+
+            let mut amm_sub = venue(0);
+            let mut baselines = [baseline(1), baseline(2)];
+            let trades = OrderRouter::new()
+                .route_order(&order, &mut amm_sub, &mut baselines, price, false)
+                .unwrap()
+                .trades;
+            assert!(trades.iter().all(|t| t.venue <= 2));
+            let total_input = if order.is_buy { order.size } else { order.size / price };
+            let quote = |amm: &mut BpfAmm, input: f64| {
+                if input <= MIN_TRADE_SIZE {
+                    0.0
+                } else if order.is_buy {
+                    amm.quote_buy_x(input)
+                } else {
+                    amm.quote_sell_x(input)
+                }
+            };
+            let mut brute: Vec<BpfAmm> = (0..3).map(venue).collect();
+            let mut best = 0.0_f64;
+            const GRID: usize = 80;
+            for a in 0..=GRID {
+                for b in 0..=GRID - a {
+                    let shares = [a, b, GRID - a - b].map(|n| total_input * n as f64 / GRID as f64);
+                    let output: f64 =
+                        brute.iter_mut().zip(shares).map(|(amm, input)| quote(amm, input)).sum();
+                    best = best.max(output);
+                }
+            }
+            assert_close_to_optimal(
+                total_output_from_trades(&order, &trades),
+                best,
+                DIVERSE_CURVE_TOLERANCE,
+                &format!("venue case {case_idx}"),
+            );
+        }
+    }
+
+    #[test]
+    fn black_box_baselines_match_the_closed_form_on_constant_product_pools() {
+        let order = RetailOrder {
+            is_buy: false,
+            size: 1_500.0,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: false,
+        };
+        let route = |black_box: bool| {
+            let mut amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (150.0, 15_000.0)), cp_pool(50, (80.0, 8_000.0))];
+            for pool in &mut baselines {
+                pool.black_box = black_box;
+            }
+            OrderRouter::new()
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+                .unwrap()
+                .trades
+        };
+        let closed_form = route(false);
+        let quoted = route(true);
+
+        for venue in 0..=2 {
+            assert!(quoted.iter().any(|t| t.venue == venue), "venue {venue} took no flow");
+        }
+        assert_close_to_optimal(
+            total_output_from_trades(&order, &quoted),
+            total_output_from_trades(&order, &closed_form),
+            DIVERSE_CURVE_TOLERANCE,
+            "black-box baselines",
+        );
+    }
+
+    #[test]
+    fn orders_missing_their_minimum_output_are_dropped_or_partially_filled() {
+        let route = |min_output: Option<f64>, partial_fill: bool| {
+            let order = RetailOrder {
+                is_buy: true,
+                size: 2_000.0,
+                informed: false,
+                max_slippage_bps: None,
+                min_output,
+                partial_fill,
+                exact_output: false,
+            };
+            let mut amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+            OrderRouter::new().route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+        };
+        let full = route(None, false).unwrap();
+        let full_output: f64 = full.trades.iter().map(|t| t.amount_x).sum();
+        assert_eq!(full.filled, 1.0);
+
+        // Asking 1% more than the whole order can get is only met by a smaller part of it.
+        let min_output = Some(1.01 * full_output);
+        assert!(route(min_output, false).is_none());
+        let partial = route(min_output, true).unwrap();
+        let output: f64 = partial.trades.iter().map(|t| t.amount_x).sum();
+        assert!(partial.filled > 0.5 && partial.filled < 1.0, "filled {}", partial.filled);
+        assert!(output >= 1.01 * full_output * partial.filled);
+        // Nothing meets a limit above the best price.
+        assert!(route(Some(2.0 * full_output), true).is_none());
+    }
+
+    #[test]
+    fn exact_sell_notional_pays_out_the_order_size() {
+        let route = |size: f64, exact: bool| {
+            let order = RetailOrder {
+                is_buy: false,
+                size,
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let mut amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+            let router = OrderRouter::new().with_exact_sell_notional(exact);
+            let routed =
+                router.route_order(&order, &mut amm_sub, &mut baselines, 100.0, false).unwrap();
+            let sold: f64 = routed.trades.iter().map(|t| t.amount_x).sum();
+            let paid: f64 = routed.trades.iter().map(|t| t.amount_y).sum();
+            (sold, paid)
+        };
+        for size in [10.0, 2_000.0, 8_000.0] {
+            let (sold, paid) = route(size, true);
+            assert!((paid - size).abs() <= 1e-4 * size, "size {size}: paid {paid}");
+            let (converted, short) = route(size, false);
+            assert!(short < paid && converted < sold, "size {size}");
+        }
+        // Beyond what the pools hold, a sell falls back to the converted size.
+        assert_eq!(route(50_000.0, true), route(50_000.0, false));
+    }
+
+    #[test]
+    fn exact_output_buys_receive_the_order_size_in_x() {
+        let route = |size: f64, exact_output: bool| {
+            let order = RetailOrder {
+                is_buy: true,
+                size,
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output,
+            };
+            let mut amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+            let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+            let routed = OrderRouter::new()
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+                .unwrap();
+            let bought: f64 = routed.trades.iter().map(|t| t.amount_x).sum();
+            let paid: f64 = routed.trades.iter().map(|t| t.amount_y).sum();
+            (bought, paid)
+        };
+        for size in [10.0, 2_000.0, 8_000.0] {
+            let target = size / 100.0;
+            let (bought, paid) = route(size, true);
+            assert!((bought - target).abs() <= 1e-4 * target, "size {size}: bought {bought}");
+            let (short, spent) = route(size, false);
+            assert!(short < bought && spent < paid, "size {size}");
+        }
+    }
+
+    #[test]
+    fn exact_output_fills_below_the_default_min_trade_size() {
+        // Configured dust is 1e-6, so an order needing about 9e-4 Y is a real trade.
+        let min_trade_size = 1e-6;
+        let order = RetailOrder {
+            is_buy: true,
+            size: 9e-4,
+            informed: false,
+            max_slippage_bps: None,
+            min_output: None,
+            partial_fill: false,
+            exact_output: true,
+        };
+        let mut amm_sub =
+            BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+        amm_sub.set_min_trade_size(min_trade_size);
+        let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+        baselines[0].amm.set_min_trade_size(min_trade_size);
+        let routed = OrderRouter::new()
+            .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+            .unwrap();
+        let bought: f64 = routed.trades.iter().map(|t| t.amount_x).sum();
+        let paid: f64 = routed.trades.iter().map(|t| t.amount_y).sum();
+        let target = order.size / 100.0;
+        assert!((bought - target).abs() <= 1e-3 * target, "bought {bought}");
+        assert!(paid < 1e-3, "paid {paid}");
+    }
+
+    #[test]
+    fn exact_out_quotes_find_the_smallest_paying_input() {
+        let mut amm = BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+        let unit = 1e-9;
+        for output in [1e-6, 0.5, 40.0] {
+            let input = amm.quote_buy_x_exact_out(output).unwrap();
+            assert!(amm.quote_buy_x(input) >= output - unit, "buy {output}");
+            assert!(amm.quote_buy_x(input - unit) < output, "buy {output}");
+            let input = amm.quote_sell_x_exact_out(100.0 * output).unwrap();
+            assert!(amm.quote_sell_x(input) >= 100.0 * output - unit, "sell {output}");
+            assert!(amm.quote_sell_x(input - unit) < 100.0 * output, "sell {output}");
+        }
+        assert_eq!(amm.quote_buy_x_exact_out(0.0), Some(0.0));
+        assert_eq!(amm.quote_buy_x_exact_out(100.0), None);
+    }
+
+    #[test]
+    fn execution_short_of_the_quote_reroutes_to_the_baselines() {
+        let mut amm_sub =
+            BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+        let mut baselines = [cp_pool(30, (100.0, 10_000.0))];
+        let in_sub = 500.0;
+        let out_sub = amm_sub.quote_buy_x(in_sub);
+        let point = |out_sub: f64| QuotePoint {
+            in_sub,
+            in_norm: 300.0,
+            out_sub,
+            out_norm: 1.0,
+            submission_down: false,
+        };
+
+        // A quote the submission still honors executes as searched.
+        let trades =
+            OrderRouter::execute_split(&point(out_sub), true, &mut amm_sub, &mut baselines);
+        assert!(trades[0].is_submission());
+        assert_eq!(trades[0].amount_x, out_sub);
+
+        // One it no longer does (here, a stale quote from before that trade) moves to the
+        // baselines whole.
+        let before = amm_sub.reserve_x;
+        let trades =
+            OrderRouter::execute_split(&point(out_sub), true, &mut amm_sub, &mut baselines);
+        assert_eq!(amm_sub.reserve_x, before);
+        assert!(trades.iter().all(|t| !t.is_submission()));
+        let input: f64 = trades.iter().map(|t| t.amount_y).sum();
+        assert!((input - 800.0).abs() < 1e-9, "input {input}");
+    }
+
+    #[test]
+    fn winner_take_all_fills_each_order_on_one_venue() {
+        let route = |sub_reserves: (f64, f64)| {
+            let order = RetailOrder {
+                is_buy: false,
+                size: 1_000.0,
+                informed: false,
+                max_slippage_bps: None,
+                min_output: None,
+                partial_fill: false,
+                exact_output: false,
+            };
+            let (rx, ry) = sub_reserves;
+            let mut amm_sub = BpfAmm::new_native(low_fee_swap, None, rx, ry, "sub".to_string());
+            let mut baselines = [cp_pool(30, (100.0, 10_000.0)), cp_pool(10, (150.0, 15_000.0))];
+            let router = OrderRouter::with_strategy(Box::new(WinnerTakeAll));
+            let trades = router
+                .route_order(&order, &mut amm_sub, &mut baselines, 100.0, false)
+                .unwrap()
+                .trades;
+            assert_eq!(trades.len(), 1);
+            assert_eq!(trades[0].amount_x, 10.0);
+            trades[0].venue
+        };
+        // The deeper, cheaper extra venue beats both the normalizer and a shallow submission.
+        assert_eq!(route((20.0, 2_000.0)), 2);
+        // A deep enough submission beats it.
+        assert_eq!(route((1_000.0, 100_000.0)), super::SUBMISSION_VENUE);
+    }
+
+    #[test]
+    fn adaptive_golden_scales_search_effort_with_order_size() {
+        // Depth is 30_000 Y across both pools, so these are 2e-5, 2e-4 and 5e-2 of it.
+        for (size, full) in [(0.6, false), (6.0, false), (1_500.0, true)] {
+            let search = |strategy: &dyn OrderRouterStrategy| {
+                let mut amm_sub =
+                    BpfAmm::new_native(starter_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+                let mut baselines = [cp_pool(30, (200.0, 20_000.0))];
+                strategy.split(size, true, &mut amm_sub, &mut baselines)
+            };
+            let adaptive = search(&AdaptiveGoldenSplit);
+            let golden = search(&GoldenSectionSplit);
+            if size < 1.0 {
+                // Only the two endpoints.
+                assert_eq!(adaptive.sampled.len(), 2);
+                assert!(golden.sampled.len() > 2);
+            }
+            let score = |point: &QuotePoint| point.out_sub + point.out_norm;
+            if full {
+                assert_eq!(adaptive.sampled.len(), golden.sampled.len());
+                assert_eq!(score(&adaptive.best), score(&golden.best));
+            } else {
+                // Golden section's own early stops may cut it as short.
+                assert!(adaptive.sampled.len() <= golden.sampled.len(), "size {size}");
+                assert_close_to_optimal(
+                    score(&adaptive.best),
+                    score(&golden.best),
+                    DIVERSE_CURVE_TOLERANCE,
+                    &format!("adaptive golden size {size}"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn warm_start_golden_needs_fewer_quotes_on_a_steady_stream() {
+        let warm = WarmStartGoldenSplit::default();
+        let (mut warm_quotes, mut golden_quotes) = (0, 0);
+        for i in 0..40 {
+            let size = 800.0 + 5.0 * i as f64;
+            // The submission's share drops sharply for the last orders.
+            let sub_rx = if i < 30 { 100.0 } else { 10.0 };
+            let pools = || {
+                let amm_sub = BpfAmm::new_native(
+                    starter_fee_swap,
+                    None,
+                    sub_rx,
+                    sub_rx * 100.0,
+                    "sub".to_string(),
+                );
+                (amm_sub, [cp_pool(30, (120.0, 12_000.0))])
+            };
+            let (mut amm_sub, mut baselines) = pools();
+            let warm_search = warm.split(size, true, &mut amm_sub, &mut baselines);
+            warm.execute(&warm_search.best, true, &mut amm_sub, &mut baselines);
+            let (mut amm_sub, mut baselines) = pools();
+            let golden_search = GoldenSectionSplit.split(size, true, &mut amm_sub, &mut baselines);
+            warm_quotes += warm_search.sampled.len();
+            golden_quotes += golden_search.sampled.len();
+            let score = |point: &QuotePoint| point.out_sub + point.out_norm;
+            assert_close_to_optimal(
+                score(&warm_search.best),
+                score(&golden_search.best),
+                DIVERSE_CURVE_TOLERANCE,
+                &format!("warm-started golden order {i}"),
+            );
+        }
+        assert!(
+            4 * warm_quotes < 3 * golden_quotes,
+            "warm {warm_quotes} vs golden {golden_quotes} quotes"
+        );
+    }
+
+    #[test]
+    fn warm_start_golden_follows_only_executed_splits() {
+        let pools = |sub_rx: f64| {
+            let amm_sub =
+                BpfAmm::new_native(starter_fee_swap, None, sub_rx, sub_rx * 100.0, "sub".into());
+            (amm_sub, [cp_pool(30, (120.0, 12_000.0))])
+        };
+        let (plain, probed) = (WarmStartGoldenSplit::default(), WarmStartGoldenSplit::default());
+        for i in 0..20 {
+            let size = 800.0 + 5.0 * i as f64;
+            // Searches that are never executed, on very different pools, in between.
+            let (mut amm_sub, mut baselines) = pools(10.0 + 20.0 * i as f64);
+            probed.split(0.5 * size, true, &mut amm_sub, &mut baselines);
+            probed.split(3.0 * size, true, &mut amm_sub, &mut baselines);
+
+            let route = |strategy: &WarmStartGoldenSplit| {
+                let (mut amm_sub, mut baselines) = pools(100.0);
+                let search = strategy.split(size, true, &mut amm_sub, &mut baselines);
+                strategy.execute(&search.best, true, &mut amm_sub, &mut baselines);
+                (search.best.in_sub, search.sampled.len())
+            };
+            assert_eq!(route(&plain), route(&probed), "order {i}");
+        }
+    }
+
+    #[test]
+    fn integer_golden_probes_whole_units_and_matches_golden() {
+        let decimals = TokenDecimals { x: 4, y: 2 };
+        let mut rng = Pcg64::seed_from_u64(41);
+        for case_idx in 0..80 {
+            let buy_x = rng.gen_bool(0.5);
+            let sub_rx = rng.gen_range(50.0..300.0);
+            let norm_rx = sub_rx * rng.gen_range(0.5..2.0);
+            let size = rng.gen_range(100.0..5_000.0);
+            let total_input = if buy_x { size } else { size / 100.0 };
+            let unit = if buy_x { 1e-2 } else { 1e-4 };
+            let search = |strategy: &dyn OrderRouterStrategy| {
+                let mut amm_sub = BpfAmm::new_native(
+                    starter_fee_swap,
+                    None,
+                    sub_rx,
+                    sub_rx * 100.0,
+                    "sub".to_string(),
+                );
+                amm_sub.set_token_decimals(decimals);
+                let mut baselines = [cp_pool(30, (norm_rx, norm_rx * 100.0))];
+                baselines[0].amm.set_token_decimals(decimals);
+                strategy.split(total_input, buy_x, &mut amm_sub, &mut baselines)
+            };
+
+            let integer = search(&IntegerGoldenSplit);
+            let whole = (total_input / unit).floor() * unit;
+            for point in &integer.sampled {
+                let units = point.in_sub / unit;
+                assert!((units - units.round()).abs() < 1e-6, "case {case_idx}: {units}");
+                let total = point.in_sub + point.in_norm;
+                assert!((total - whole).abs() < 1e-9 * whole, "case {case_idx}: {total}");
+            }
+            let score = |point: &QuotePoint| point.out_sub + point.out_norm;
+            let golden = search(&GoldenSectionSplit);
+            assert_close_to_optimal(
+                score(&integer.best),
+                score(&golden.best),
+                DIVERSE_CURVE_TOLERANCE,
+                &format!("integer golden case {case_idx}"),
+            );
+        }
+    }
+
+    #[test]
+    fn fixed_share_orders_are_quoted_as_the_strategy_executes_them() {
+        let pools = || {
+            let amm_sub =
+                BpfAmm::new_native(low_fee_swap, None, 100.0, 10_000.0, "sub".to_string());
+            (amm_sub, [cp_pool(30, (100.0, 10_000.0)), cp_pool(10, (100.0, 10_000.0))])
+        };
+        let (size, share) = (1_000.0, 0.3);
+        let (mut amm_sub, mut baselines) = pools();
+        let executes = WinnerTakeAll.quote(size, share, true, &mut amm_sub, &mut baselines);
+        let (mut amm_sub, mut baselines) = pools();
+        let spread = OrderRouter::quote_split(size, share, true, &mut amm_sub, &mut baselines);
+        let (executes, spread) =
+            (executes.out_sub + executes.out_norm, spread.out_sub + spread.out_norm);
+        // Splitting the baselines' part would pay out more than one baseline can.
+        assert!(spread > executes, "{spread} vs {executes}");
+
+        let router = OrderRouter::with_strategy(Box::new(WinnerTakeAll));
+        let route = |min_output: f64| {
+            let order = RetailOrder {
+                is_buy: true,
+                size,
+                informed: false,
+                max_slippage_bps: None,
+                min_output: Some(min_output),
+                partial_fill: false,
+                exact_output: false,
+            };
+            let (mut amm_sub, mut baselines) = pools();
+            router
+                .route_order_at_share(&order, share, &mut amm_sub, &mut baselines, 100.0)
+                .map(|routed| total_output_from_trades(&order, &routed.trades))
+        };
+        // A minimum only the spread quote meets is missed, not filled short of it.
+        assert!(route(0.5 * (spread + executes)).is_none());
+        let output = route(executes * (1.0 - 1e-9)).unwrap();
+        assert!((output - executes).abs() <= 1e-9 * executes, "{output} vs {executes}");
+    }
 }
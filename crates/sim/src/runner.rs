@@ -1,40 +1,119 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
 use rayon::prelude::*;
 
 use prop_amm_executor::{AfterSwapFn, BpfProgram, SwapFn};
 use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
-use prop_amm_shared::result::{BatchResult, SimResult};
+use prop_amm_shared::result::{BatchResult, PairedBatch, SimResult};
 
 use crate::engine;
+use crate::observer::{NoopObserver, SimObserver};
+use crate::price_process::PricePath;
 
-fn default_configs(
+pub fn default_configs(
     n_sims: u32,
     n_steps: u32,
     seed_start: u64,
     seed_stride: u64,
 ) -> Vec<SimulationConfig> {
-    let variance = HyperparameterVariance::default();
-    let mut base = SimulationConfig::default();
-    base.n_steps = n_steps;
+    configs_for_seeds(&seed_sequence(n_sims, seed_start, seed_stride), n_steps)
+}
 
+/// `seed_start + i * seed_stride` for `i` in `0..n_sims`, wrapping.
+pub fn seed_sequence(n_sims: u32, seed_start: u64, seed_stride: u64) -> Vec<u64> {
     (0..n_sims)
-        .map(|i| {
-            variance.apply(
-                &base,
-                seed_start.wrapping_add((i as u64).wrapping_mul(seed_stride)),
-            )
-        })
+        .map(|i| seed_start.wrapping_add((i as u64).wrapping_mul(seed_stride)))
         .collect()
 }
 
+/// Hyperparameter-varied configs for an explicit seed list, in the given order.
+pub fn configs_for_seeds(seeds: &[u64], n_steps: u32) -> Vec<SimulationConfig> {
+    configs_for_variance(&HyperparameterVariance::default(), seeds, n_steps)
+}
+
+/// Like [`configs_for_seeds`], sampling from a custom variance box.
+pub fn configs_for_variance(
+    variance: &HyperparameterVariance,
+    seeds: &[u64],
+    n_steps: u32,
+) -> Vec<SimulationConfig> {
+    let base = SimulationConfig {
+        n_steps,
+        ..SimulationConfig::default()
+    };
+
+    seeds.iter().map(|&seed| variance.apply(&base, seed)).collect()
+}
+
+/// Check every config before any simulation starts, so a bad one fails fast with its seed.
+pub fn validate_configs(configs: &[SimulationConfig]) -> anyhow::Result<()> {
+    for config in configs {
+        config
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Config for seed {}: {}", config.seed, e))?;
+    }
+    Ok(())
+}
+
+/// A simulation that returned an error or panicked (e.g. on a shape violation), tagged with
+/// its seed so callers can re-run just that simulation.
+#[derive(Debug, Clone)]
+pub struct SimulationFailed {
+    pub seed: u64,
+    pub message: String,
+}
+
+impl fmt::Display for SimulationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Simulation for seed {} failed: {}", self.seed, self.message)
+    }
+}
+
+impl std::error::Error for SimulationFailed {}
+
+fn run_guarded(
+    config: &SimulationConfig,
+    simulate: impl FnOnce() -> anyhow::Result<SimResult>,
+) -> anyhow::Result<SimResult> {
+    let message = match panic::catch_unwind(AssertUnwindSafe(simulate)) {
+        Ok(Ok(result)) => return Ok(result),
+        Ok(Err(err)) => format!("{err:#}"),
+        Err(payload) => panic_message(payload.as_ref()),
+    };
+    Err(SimulationFailed {
+        seed: config.seed,
+        message,
+    }
+    .into())
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panicked".to_string()
+    }
+}
+
 pub fn run_batch(
     submission_program: BpfProgram,
     normalizer_program: BpfProgram,
     configs: Vec<SimulationConfig>,
     n_workers: Option<usize>,
 ) -> anyhow::Result<BatchResult> {
+    validate_configs(&configs)?;
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
         .build()?;
+    tracing::debug!(
+        sims = configs.len(),
+        workers = pool.current_num_threads(),
+        "starting batch"
+    );
 
     let results: Result<Vec<SimResult>, _> = pool.install(|| {
         configs
@@ -42,7 +121,7 @@ pub fn run_batch(
             .map(|config| {
                 let sub = submission_program.clone();
                 let norm = normalizer_program.clone();
-                engine::run_simulation(sub, norm, config)
+                run_guarded(config, || engine::run_simulation(sub, norm, config))
             })
             .collect()
     });
@@ -58,21 +137,61 @@ pub fn run_batch_native(
     configs: Vec<SimulationConfig>,
     n_workers: Option<usize>,
 ) -> anyhow::Result<BatchResult> {
+    validate_configs(&configs)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
+        .build()?;
+    tracing::debug!(
+        sims = configs.len(),
+        workers = pool.current_num_threads(),
+        "starting batch"
+    );
+
+    let results: Result<Vec<SimResult>, _> = pool.install(|| {
+        configs
+            .par_iter()
+            .map(|config| {
+                run_guarded(config, || {
+                    engine::run_simulation_native(
+                        submission_fn,
+                        submission_after_swap,
+                        normalizer_fn,
+                        normalizer_after_swap,
+                        config,
+                    )
+                })
+            })
+            .collect()
+    });
+
+    Ok(BatchResult::from_results(results?))
+}
+
+pub fn run_batch_mixed(
+    submission_program: BpfProgram,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    configs: Vec<SimulationConfig>,
+    n_workers: Option<usize>,
+) -> anyhow::Result<BatchResult> {
+    validate_configs(&configs)?;
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
         .build()?;
+    tracing::debug!(
+        sims = configs.len(),
+        workers = pool.current_num_threads(),
+        "starting batch"
+    );
 
     let results: Result<Vec<SimResult>, _> = pool.install(|| {
         configs
             .par_iter()
             .map(|config| {
-                engine::run_simulation_native(
-                    submission_fn,
-                    submission_after_swap,
-                    normalizer_fn,
-                    normalizer_after_swap,
-                    config,
-                )
+                let sub = submission_program.clone();
+                run_guarded(config, || {
+                    engine::run_simulation_mixed(sub, normalizer_fn, normalizer_after_swap, config)
+                })
             })
             .collect()
     });
@@ -80,6 +199,192 @@ pub fn run_batch_native(
     Ok(BatchResult::from_results(results?))
 }
 
+/// [`run_batch_native`] with a fresh observer per simulation from `make_observer`. The
+/// observers are returned in config order, after each has seen its simulation finish.
+pub fn run_batch_native_observed<O: SimObserver + Send>(
+    submission_fn: SwapFn,
+    submission_after_swap: Option<AfterSwapFn>,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    configs: Vec<SimulationConfig>,
+    n_workers: Option<usize>,
+    make_observer: impl Fn(&SimulationConfig) -> O + Sync,
+) -> anyhow::Result<(BatchResult, Vec<O>)> {
+    run_observed(&configs, n_workers, make_observer, |config, observer| {
+        engine::run_simulation_native_observed(
+            submission_fn,
+            submission_after_swap,
+            normalizer_fn,
+            normalizer_after_swap,
+            config,
+            observer,
+        )
+    })
+}
+
+/// [`run_batch_mixed`] with a fresh observer per simulation; see
+/// [`run_batch_native_observed`].
+pub fn run_batch_mixed_observed<O: SimObserver + Send>(
+    submission_program: BpfProgram,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    configs: Vec<SimulationConfig>,
+    n_workers: Option<usize>,
+    make_observer: impl Fn(&SimulationConfig) -> O + Sync,
+) -> anyhow::Result<(BatchResult, Vec<O>)> {
+    run_observed(&configs, n_workers, make_observer, |config, observer| {
+        engine::run_simulation_mixed_observed(
+            submission_program.clone(),
+            normalizer_fn,
+            normalizer_after_swap,
+            config,
+            observer,
+        )
+    })
+}
+
+fn run_observed<O: SimObserver + Send>(
+    configs: &[SimulationConfig],
+    n_workers: Option<usize>,
+    make_observer: impl Fn(&SimulationConfig) -> O + Sync,
+    simulate: impl Fn(&SimulationConfig, &mut dyn SimObserver) -> anyhow::Result<SimResult> + Sync,
+) -> anyhow::Result<(BatchResult, Vec<O>)> {
+    validate_configs(configs)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
+        .build()?;
+    tracing::debug!(
+        sims = configs.len(),
+        workers = pool.current_num_threads(),
+        "starting observed batch"
+    );
+
+    let runs: anyhow::Result<Vec<(SimResult, O)>> = pool.install(|| {
+        configs
+            .par_iter()
+            .map(|config| {
+                let mut observer = make_observer(config);
+                let result = run_guarded(config, || simulate(config, &mut observer))?;
+                Ok((result, observer))
+            })
+            .collect()
+    });
+    let (results, observers) = runs?.into_iter().unzip();
+    Ok((BatchResult::from_results(results), observers))
+}
+
+/// A natively compiled submission: its quote function and optional `after_swap` hook.
+#[derive(Clone, Copy)]
+pub struct NativeSubmission {
+    pub swap_fn: SwapFn,
+    pub after_swap_fn: Option<AfterSwapFn>,
+}
+
+/// Run submissions `a` and `b` on every config, each against its own fresh normalizer.
+/// Both follow one price path drawn per config, and retail orders come from RNG streams
+/// keyed only by the seed (see [`engine::RngStreams`]), so both face identical markets and
+/// only the arbitrageur's response to their curves differs.
+pub fn run_paired_native(
+    a: NativeSubmission,
+    b: NativeSubmission,
+    normalizer_fn: SwapFn,
+    normalizer_after_swap: Option<AfterSwapFn>,
+    configs: Vec<SimulationConfig>,
+    n_workers: Option<usize>,
+) -> anyhow::Result<PairedBatch> {
+    validate_configs(&configs)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
+        .build()?;
+    tracing::debug!(
+        sims = configs.len(),
+        workers = pool.current_num_threads(),
+        "starting paired batch"
+    );
+
+    let run = |submission: NativeSubmission, config: &SimulationConfig, path: &PricePath| {
+        run_guarded(config, || {
+            engine::run_simulation_native_on_path(
+                submission.swap_fn,
+                submission.after_swap_fn,
+                normalizer_fn,
+                normalizer_after_swap,
+                config,
+                path,
+                &mut NoopObserver,
+            )
+        })
+    };
+    let pairs: anyhow::Result<Vec<(SimResult, SimResult)>> = pool.install(|| {
+        configs
+            .par_iter()
+            .map(|config| {
+                let path = PricePath::generate(config);
+                anyhow::Ok((run(a, config, &path)?, run(b, config, &path)?))
+            })
+            .collect()
+    });
+    let (a_results, b_results) = pairs?.into_iter().unzip();
+
+    Ok(PairedBatch {
+        a: BatchResult::from_results(a_results),
+        b: BatchResult::from_results(b_results),
+    })
+}
+
+/// Stop a batch once average edge is pinned down to within `half_width`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyStop {
+    /// Target half-width of the confidence interval on average edge.
+    pub half_width: f64,
+    /// Two-sided critical value of the interval.
+    pub z: f64,
+    /// Never stop before this many simulations, so the variance estimate is meaningful.
+    pub min_sims: usize,
+    /// Simulations run between checks. Stopping points depend only on this, not on the
+    /// worker count, so a given plan always uses the same seeds.
+    pub chunk: usize,
+}
+
+impl EarlyStop {
+    pub const Z_95: f64 = 1.96;
+
+    pub fn new(half_width: f64) -> Self {
+        Self {
+            half_width,
+            z: Self::Z_95,
+            min_sims: 32,
+            chunk: 32,
+        }
+    }
+}
+
+/// Run `configs` in order, `stop.chunk` at a time through `run_chunk` (e.g. a closure over
+/// [`run_batch_native`]), until the confidence interval on average edge is no wider than
+/// `stop.half_width` or the configs run out. `n_sims()` of the result is the number used.
+pub fn run_until_converged(
+    configs: Vec<SimulationConfig>,
+    stop: &EarlyStop,
+    mut run_chunk: impl FnMut(Vec<SimulationConfig>) -> anyhow::Result<BatchResult>,
+) -> anyhow::Result<BatchResult> {
+    if !(stop.half_width > 0.0 && stop.z > 0.0) || stop.chunk == 0 {
+        anyhow::bail!("Early stop needs a positive half-width, z, and chunk size");
+    }
+    let mut batch = BatchResult::from_results(Vec::with_capacity(configs.len()));
+    for chunk in configs.chunks(stop.chunk) {
+        let mut results = std::mem::take(&mut batch.results);
+        results.extend(run_chunk(chunk.to_vec())?.results);
+        // Re-sum from scratch so totals match a plain batch over the same seeds bit for bit.
+        batch = BatchResult::from_results(results);
+        let half_width = batch.edge_ci_half_width(stop.z);
+        tracing::debug!(sims = batch.n_sims(), half_width, "early stop check");
+        if batch.n_sims() >= stop.min_sims && half_width <= stop.half_width {
+            break;
+        }
+    }
+    Ok(batch)
+}
+
 pub fn run_default_batch(
     submission_program: BpfProgram,
     normalizer_program: BpfProgram,
@@ -113,24 +418,16 @@ pub fn run_default_batch_mixed(
     n_workers: Option<usize>,
 ) -> anyhow::Result<BatchResult> {
     let configs = default_configs(n_sims, n_steps, 0, 1);
-
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
-        .build()?;
-
-    let results: Result<Vec<SimResult>, _> = pool.install(|| {
-        configs
-            .par_iter()
-            .map(|config| {
-                let sub = submission_program.clone();
-                engine::run_simulation_mixed(sub, normalizer_fn, normalizer_after_swap, config)
-            })
-            .collect()
-    });
-
-    Ok(BatchResult::from_results(results?))
+    run_batch_mixed(
+        submission_program,
+        normalizer_fn,
+        normalizer_after_swap,
+        configs,
+        n_workers,
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_default_batch_mixed_seeded(
     submission_program: BpfProgram,
     normalizer_fn: SwapFn,
@@ -142,22 +439,13 @@ pub fn run_default_batch_mixed_seeded(
     seed_stride: u64,
 ) -> anyhow::Result<BatchResult> {
     let configs = default_configs(n_sims, n_steps, seed_start, seed_stride);
-
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(n_workers.unwrap_or_else(|| rayon::current_num_threads().min(8)))
-        .build()?;
-
-    let results: Result<Vec<SimResult>, _> = pool.install(|| {
-        configs
-            .par_iter()
-            .map(|config| {
-                let sub = submission_program.clone();
-                engine::run_simulation_mixed(sub, normalizer_fn, normalizer_after_swap, config)
-            })
-            .collect()
-    });
-
-    Ok(BatchResult::from_results(results?))
+    run_batch_mixed(
+        submission_program,
+        normalizer_fn,
+        normalizer_after_swap,
+        configs,
+        n_workers,
+    )
 }
 
 pub fn run_default_batch_native(
@@ -180,6 +468,7 @@ pub fn run_default_batch_native(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_default_batch_native_seeded(
     submission_fn: SwapFn,
     submission_after_swap: Option<AfterSwapFn>,
@@ -201,3 +490,38 @@ pub fn run_default_batch_native_seeded(
         n_workers,
     )
 }
+
+/// BPF submission vs the embedded BPF normalizer, with no local normalizer build required.
+#[cfg(feature = "prebuilt-programs")]
+pub fn run_reference_batch_bpf_seeded(
+    submission_program: BpfProgram,
+    n_sims: u32,
+    n_steps: u32,
+    n_workers: Option<usize>,
+    seed_start: u64,
+    seed_stride: u64,
+) -> anyhow::Result<BatchResult> {
+    let normalizer_program = crate::prebuilt::normalizer_program()?;
+    let configs = default_configs(n_sims, n_steps, seed_start, seed_stride);
+    run_batch(submission_program, normalizer_program, configs, n_workers)
+}
+
+/// Embedded BPF starter vs embedded BPF normalizer: the reference baseline for a seed range.
+#[cfg(feature = "prebuilt-programs")]
+pub fn run_starter_reference_batch_seeded(
+    n_sims: u32,
+    n_steps: u32,
+    n_workers: Option<usize>,
+    seed_start: u64,
+    seed_stride: u64,
+) -> anyhow::Result<BatchResult> {
+    let starter_program = crate::prebuilt::starter_program()?;
+    run_reference_batch_bpf_seeded(
+        starter_program,
+        n_sims,
+        n_steps,
+        n_workers,
+        seed_start,
+        seed_stride,
+    )
+}
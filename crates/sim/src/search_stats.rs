@@ -14,10 +14,30 @@ pub struct SearchStatsSnapshot {
     pub arb_golden_iters: u64,
     pub arb_golden_evals: u64,
     pub arb_early_stop_amount_tol: u64,
+    pub arb_cp_fit_hits: u64,
+    pub arb_cp_fit_misses: u64,
     pub router_calls: u64,
     pub router_golden_iters: u64,
     pub router_evals: u64,
     pub router_early_stop_rel_gap: u64,
+    /// Searches skipped because the venue was down (see `BpfAmm::is_down`).
+    pub arb_venue_down: u64,
+    pub router_venue_down: u64,
+    /// Marginal-price splits that found the marginals noisy and fell back to golden section.
+    pub router_marginal_fallbacks: u64,
+    /// Submission parts re-routed to the baselines because executing them would have paid
+    /// out materially less than their quote.
+    pub router_execution_fallbacks: u64,
+    /// Warm-started splits whose optimum landed on their bracket's edge and were searched
+    /// again over every share.
+    pub router_warm_start_misses: u64,
+    /// Routed retail orders by the split they executed: all on the submission, all on the
+    /// baselines (the normalizer and any extra venues), or divided between the two.
+    pub router_split_all_submission: u64,
+    pub router_split_all_baselines: u64,
+    pub router_split_interior: u64,
+    /// Quotes served from `BpfAmm`'s quote cache, on every pool.
+    pub quote_cache_hits: u64,
 }
 
 static ARB_BRACKET_CALLS: AtomicU64 = AtomicU64::new(0);
@@ -26,11 +46,22 @@ static ARB_GOLDEN_CALLS: AtomicU64 = AtomicU64::new(0);
 static ARB_GOLDEN_ITERS: AtomicU64 = AtomicU64::new(0);
 static ARB_GOLDEN_EVALS: AtomicU64 = AtomicU64::new(0);
 static ARB_EARLY_STOP_AMOUNT_TOL: AtomicU64 = AtomicU64::new(0);
+static ARB_CP_FIT_HITS: AtomicU64 = AtomicU64::new(0);
+static ARB_CP_FIT_MISSES: AtomicU64 = AtomicU64::new(0);
 
 static ROUTER_CALLS: AtomicU64 = AtomicU64::new(0);
 static ROUTER_GOLDEN_ITERS: AtomicU64 = AtomicU64::new(0);
 static ROUTER_EVALS: AtomicU64 = AtomicU64::new(0);
 static ROUTER_EARLY_STOP_REL_GAP: AtomicU64 = AtomicU64::new(0);
+static ARB_VENUE_DOWN: AtomicU64 = AtomicU64::new(0);
+static ROUTER_VENUE_DOWN: AtomicU64 = AtomicU64::new(0);
+static ROUTER_MARGINAL_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+static ROUTER_EXECUTION_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+static ROUTER_WARM_START_MISSES: AtomicU64 = AtomicU64::new(0);
+static ROUTER_SPLIT_ALL_SUBMISSION: AtomicU64 = AtomicU64::new(0);
+static ROUTER_SPLIT_ALL_BASELINES: AtomicU64 = AtomicU64::new(0);
+static ROUTER_SPLIT_INTERIOR: AtomicU64 = AtomicU64::new(0);
+static QUOTE_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
 
 pub fn reset() {
     ARB_BRACKET_CALLS.store(0, Ordering::Relaxed);
@@ -39,10 +70,21 @@ pub fn reset() {
     ARB_GOLDEN_ITERS.store(0, Ordering::Relaxed);
     ARB_GOLDEN_EVALS.store(0, Ordering::Relaxed);
     ARB_EARLY_STOP_AMOUNT_TOL.store(0, Ordering::Relaxed);
+    ARB_CP_FIT_HITS.store(0, Ordering::Relaxed);
+    ARB_CP_FIT_MISSES.store(0, Ordering::Relaxed);
     ROUTER_CALLS.store(0, Ordering::Relaxed);
     ROUTER_GOLDEN_ITERS.store(0, Ordering::Relaxed);
     ROUTER_EVALS.store(0, Ordering::Relaxed);
     ROUTER_EARLY_STOP_REL_GAP.store(0, Ordering::Relaxed);
+    ARB_VENUE_DOWN.store(0, Ordering::Relaxed);
+    ROUTER_VENUE_DOWN.store(0, Ordering::Relaxed);
+    ROUTER_MARGINAL_FALLBACKS.store(0, Ordering::Relaxed);
+    ROUTER_EXECUTION_FALLBACKS.store(0, Ordering::Relaxed);
+    ROUTER_WARM_START_MISSES.store(0, Ordering::Relaxed);
+    ROUTER_SPLIT_ALL_SUBMISSION.store(0, Ordering::Relaxed);
+    ROUTER_SPLIT_ALL_BASELINES.store(0, Ordering::Relaxed);
+    ROUTER_SPLIT_INTERIOR.store(0, Ordering::Relaxed);
+    QUOTE_CACHE_HITS.store(0, Ordering::Relaxed);
 }
 
 pub fn snapshot_if_enabled() -> Option<SearchStatsSnapshot> {
@@ -56,10 +98,21 @@ pub fn snapshot_if_enabled() -> Option<SearchStatsSnapshot> {
         arb_golden_iters: ARB_GOLDEN_ITERS.load(Ordering::Relaxed),
         arb_golden_evals: ARB_GOLDEN_EVALS.load(Ordering::Relaxed),
         arb_early_stop_amount_tol: ARB_EARLY_STOP_AMOUNT_TOL.load(Ordering::Relaxed),
+        arb_cp_fit_hits: ARB_CP_FIT_HITS.load(Ordering::Relaxed),
+        arb_cp_fit_misses: ARB_CP_FIT_MISSES.load(Ordering::Relaxed),
         router_calls: ROUTER_CALLS.load(Ordering::Relaxed),
         router_golden_iters: ROUTER_GOLDEN_ITERS.load(Ordering::Relaxed),
         router_evals: ROUTER_EVALS.load(Ordering::Relaxed),
         router_early_stop_rel_gap: ROUTER_EARLY_STOP_REL_GAP.load(Ordering::Relaxed),
+        arb_venue_down: ARB_VENUE_DOWN.load(Ordering::Relaxed),
+        router_venue_down: ROUTER_VENUE_DOWN.load(Ordering::Relaxed),
+        router_marginal_fallbacks: ROUTER_MARGINAL_FALLBACKS.load(Ordering::Relaxed),
+        router_execution_fallbacks: ROUTER_EXECUTION_FALLBACKS.load(Ordering::Relaxed),
+        router_warm_start_misses: ROUTER_WARM_START_MISSES.load(Ordering::Relaxed),
+        router_split_all_submission: ROUTER_SPLIT_ALL_SUBMISSION.load(Ordering::Relaxed),
+        router_split_all_baselines: ROUTER_SPLIT_ALL_BASELINES.load(Ordering::Relaxed),
+        router_split_interior: ROUTER_SPLIT_INTERIOR.load(Ordering::Relaxed),
+        quote_cache_hits: QUOTE_CACHE_HITS.load(Ordering::Relaxed),
     })
 }
 
@@ -105,6 +158,20 @@ pub(crate) fn inc_arb_early_stop_amount_tol() {
     }
 }
 
+#[inline]
+pub(crate) fn inc_arb_cp_fit_hit() {
+    if enabled() {
+        ARB_CP_FIT_HITS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn inc_arb_cp_fit_miss() {
+    if enabled() {
+        ARB_CP_FIT_MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 #[inline]
 pub(crate) fn inc_router_call() {
     if enabled() {
@@ -132,3 +199,58 @@ pub(crate) fn inc_router_early_stop_rel_gap() {
         ROUTER_EARLY_STOP_REL_GAP.fetch_add(1, Ordering::Relaxed);
     }
 }
+
+#[inline]
+pub(crate) fn inc_arb_venue_down() {
+    if enabled() {
+        ARB_VENUE_DOWN.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn inc_router_venue_down() {
+    if enabled() {
+        ROUTER_VENUE_DOWN.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn inc_router_marginal_fallback() {
+    if enabled() {
+        ROUTER_MARGINAL_FALLBACKS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn inc_router_execution_fallback() {
+    if enabled() {
+        ROUTER_EXECUTION_FALLBACKS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn inc_router_warm_start_miss() {
+    if enabled() {
+        ROUTER_WARM_START_MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Count a routed order's executed split by where its input went.
+#[inline]
+pub(crate) fn inc_router_split(to_submission: bool, to_baselines: bool) {
+    if enabled() {
+        let counter = match (to_submission, to_baselines) {
+            (true, true) => &ROUTER_SPLIT_INTERIOR,
+            (true, false) => &ROUTER_SPLIT_ALL_SUBMISSION,
+            (false, _) => &ROUTER_SPLIT_ALL_BASELINES,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline]
+pub(crate) fn add_quote_cache_hits(hits: u64) {
+    if enabled() && hits > 0 {
+        QUOTE_CACHE_HITS.fetch_add(hits, Ordering::Relaxed);
+    }
+}
@@ -1,10 +1,15 @@
 use prop_amm_executor::NativeExecutor;
-use prop_amm_shared::config::{HyperparameterVariance, SimulationConfig};
-use prop_amm_shared::instruction::STORAGE_SIZE;
+use prop_amm_shared::config::{
+    AfterSwapFailurePolicy, ArbInventory, ArrivalWindow, BaselineVenue, Clock, EdgeMode,
+    ExecutionMode, HyperparameterVariance, LiquidityEvent, MomentumCohort, PriceGap, RetailProfile,
+    RouterKind, ShapeViolationPolicy, SimulationConfig, StepSequencing, VolatilityRegimes,
+};
+use prop_amm_shared::instruction::{INSTRUCTION_SIZE, STORAGE_SIZE};
 use prop_amm_shared::nano::{f64_to_nano, nano_to_f64};
 use prop_amm_shared::normalizer::{
     after_swap as normalizer_after_swap, compute_swap as normalizer_swap,
 };
+use prop_amm_shared::result::{Counterparty, TapeTrade};
 
 const EMPTY_STORAGE: [u8; STORAGE_SIZE] = [0u8; STORAGE_SIZE];
 
@@ -28,12 +33,12 @@ fn starter_swap(data: &[u8]) -> u64 {
         0 => {
             let net_y = input_amount.saturating_mul(950) / 1000;
             let new_ry = reserve_y + net_y;
-            reserve_x.saturating_sub((k + new_ry - 1) / new_ry) as u64
+            reserve_x.saturating_sub(k.div_ceil(new_ry)) as u64
         }
         1 => {
             let net_x = input_amount.saturating_mul(950) / 1000;
             let new_rx = reserve_x + net_x;
-            reserve_y.saturating_sub((k + new_rx - 1) / new_rx) as u64
+            reserve_y.saturating_sub(k.div_ceil(new_rx)) as u64
         }
         _ => 0,
     }
@@ -48,8 +53,7 @@ fn linear_swap(data: &[u8]) -> u64 {
         return 0;
     }
 
-    let input_amount = u64::from_le_bytes(data[1..9].try_into().expect("linear input amount"));
-    input_amount
+    u64::from_le_bytes(data[1..9].try_into().expect("linear input amount"))
 }
 
 fn normalizer_exec() -> NativeExecutor {
@@ -330,6 +334,102 @@ fn test_batch_runner() {
     assert_eq!(result.n_sims(), 4);
 }
 
+#[cfg(feature = "prebuilt-programs")]
+#[test]
+fn test_prebuilt_programs_match_their_native_builds() {
+    let bpf =
+        prop_amm_sim::runner::run_starter_reference_batch_seeded(2, 500, Some(1), 0, 1).unwrap();
+    let native = prop_amm_sim::runner::run_default_batch_native_seeded(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        2,
+        500,
+        Some(1),
+        0,
+        1,
+    )
+    .unwrap();
+    assert!(
+        (bpf.total_edge - native.total_edge).abs() < 1e-6,
+        "bpf {} vs native {}",
+        bpf.total_edge,
+        native.total_edge
+    );
+}
+
+fn panicking_swap(_data: &[u8]) -> u64 {
+    panic!("quote exploded");
+}
+
+#[test]
+fn test_batch_reports_seed_of_failed_simulation() {
+    let configs = vec![SimulationConfig {
+        n_steps: 100,
+        seed: 7,
+        ..SimulationConfig::default()
+    }];
+    let err = prop_amm_sim::runner::run_batch_native(
+        panicking_swap,
+        None,
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        configs,
+        Some(1),
+    )
+    .unwrap_err();
+    let failed = err
+        .downcast_ref::<prop_amm_sim::runner::SimulationFailed>()
+        .expect("failure is tagged with its seed");
+    assert_eq!(failed.seed, 7);
+    assert!(failed.message.contains("quote exploded"), "{}", failed.message);
+}
+
+/// The starter curve, paying 5% more once the input passes 1% of its side's reserve: the
+/// slope jumps up there, so the curve is not concave.
+fn kinked_swap(data: &[u8]) -> u64 {
+    let output = starter_swap(data);
+    let input = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    let reserve_in = if data[0] == 0 { &data[17..25] } else { &data[9..17] };
+    if input > u64::from_le_bytes(reserve_in.try_into().unwrap()) / 100 {
+        output / 20 * 21
+    } else {
+        output
+    }
+}
+
+#[test]
+fn test_shape_violations_zero_edge_instead_of_failing() {
+    let config = SimulationConfig {
+        n_steps: 200,
+        seed: 3,
+        ..SimulationConfig::default()
+    };
+    let run = |config: &SimulationConfig| {
+        prop_amm_sim::runner::run_batch_native(
+            kinked_swap,
+            None,
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            vec![config.clone()],
+            Some(1),
+        )
+    };
+    let err = run(&config).unwrap_err().to_string();
+    assert!(err.contains("shape violation"), "{err}");
+
+    let result = run(&SimulationConfig {
+        shape_violation_policy: ShapeViolationPolicy::ZeroEdge,
+        ..config
+    })
+    .unwrap();
+    let sim = &result.results[0];
+    assert!(sim.shape.violations > 0);
+    assert_eq!(sim.submission_edge, 0.0);
+    assert_eq!(sim.edge_by_counterparty.total(), 0.0);
+}
+
 #[test]
 fn test_after_swap_noop() {
     let exec = starter_exec();
@@ -361,6 +461,299 @@ fn test_storage_persists_across_swaps() {
     assert!(result.submission_edge.is_finite(), "edge should be finite");
 }
 
+#[test]
+fn test_after_swap_failure_policy_leaves_native_runs_unchanged() {
+    // Native after_swap cannot return an error, so no policy changes a native run.
+    let run = |after_swap_failure_policy| {
+        let config = SimulationConfig {
+            n_steps: 300,
+            seed: 8,
+            after_swap_failure_policy,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(marking_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let ignored = run(AfterSwapFailurePolicy::Ignore);
+    for policy in [AfterSwapFailurePolicy::CountAndReport, AfterSwapFailurePolicy::FailSim] {
+        let result = run(policy);
+        assert_eq!(result.after_swap_failures, 0);
+        assert_eq!(result.checksum, ignored.checksum);
+    }
+}
+
+#[test]
+fn test_profiling_times_components_without_changing_results() {
+    let run = |profile_components| {
+        let config = SimulationConfig {
+            n_steps: 500,
+            seed: 13,
+            profile_components,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let plain = run(false);
+    let profiled = run(true);
+    assert!(plain.timing.is_empty());
+    assert_eq!(profiled.checksum, plain.checksum);
+    let timing = profiled.timing;
+    assert!(timing.router_ns > 0 && timing.arb_ns > 0);
+    assert!(timing.submission_program_ns > 0 && timing.baseline_program_ns > 0);
+}
+
+thread_local! {
+    static SWAP_CALLS: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
+
+fn counted_normalizer_swap(data: &[u8]) -> u64 {
+    SWAP_CALLS.with(|calls| calls.set(calls.get() + 1));
+    normalizer_swap(data)
+}
+
+#[test]
+fn test_quote_cache_skips_repeated_quotes_without_changing_results() {
+    let mut amm = prop_amm_sim::amm::BpfAmm::new_native(
+        counted_normalizer_swap,
+        Some(normalizer_after_swap),
+        100.0,
+        10_000.0,
+        "submission".to_string(),
+    );
+    amm.enable_quote_cache();
+    let calls = || SWAP_CALLS.with(|calls| calls.get());
+    let first = amm.quote_buy_x(50.0);
+    assert_eq!(amm.quote_buy_x(50.0), first);
+    assert_eq!((calls(), amm.quote_cache_hits()), (1, 1));
+    // Executing reuses the quote, then runs after_swap, which may change storage.
+    amm.execute_buy_x(50.0);
+    amm.quote_buy_x(50.0);
+    amm.quote_buy_x(50.0);
+    assert_eq!((calls(), amm.quote_cache_hits()), (2, 3));
+    amm.set_current_step(1);
+    amm.quote_buy_x(50.0);
+    assert_eq!((calls(), amm.quote_cache_hits()), (3, 3));
+
+    let run = |quote_cache| {
+        let config = SimulationConfig {
+            n_steps: 500,
+            seed: 17,
+            quote_cache,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let (plain, cached) = (run(false), run(true));
+    assert_eq!(cached.checksum, plain.checksum);
+    assert_eq!(cached.submission_edge, plain.submission_edge);
+}
+
+thread_local! {
+    static LAST_QUOTE: std::cell::Cell<(u64, u32)> = const { std::cell::Cell::new((0, 0)) };
+}
+
+/// How many times in a row before this call the same input was asked for.
+fn quote_repeats(data: &[u8]) -> u32 {
+    let input = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    LAST_QUOTE.with(|last| {
+        let (prev, repeats) = last.get();
+        let repeats = if prev == input { repeats + 1 } else { 0 };
+        last.set((input, repeats));
+        repeats
+    })
+}
+
+/// The normalizer, paying 0.1% less each time the same input is asked for again in a row,
+/// so executing a size right after quoting it delivers less than the quote.
+fn repeat_shading_swap(data: &[u8]) -> u64 {
+    let repeats = quote_repeats(data);
+    (normalizer_swap(data) as f64 * (1.0 - 1e-3 * repeats as f64)) as u64
+}
+
+/// Like [`repeat_shading_swap`], but paying 0.1% more on each repeat.
+fn repeat_boosting_swap(data: &[u8]) -> u64 {
+    let repeats = quote_repeats(data);
+    (normalizer_swap(data) as f64 * (1.0 + 1e-3 * repeats as f64)) as u64
+}
+
+#[test]
+fn test_arb_quote_divergence_flags_stateful_swaps() {
+    let run = |submission: fn(&[u8]) -> u64| {
+        let config = SimulationConfig {
+            n_steps: 300,
+            seed: 23,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            submission,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+        .arb_quote_divergence
+    };
+    let honest = run(normalizer_swap);
+    assert!(honest.checked > 0);
+    assert_eq!(honest.diverged, 0);
+
+    let shading = run(repeat_shading_swap);
+    assert!(shading.diverged > 0, "{shading:?}");
+    assert!(shading.net_y < 0.0 && shading.max_rel > 0.0, "{shading:?}");
+}
+
+#[test]
+fn test_retail_quote_divergence_checks_routed_fills() {
+    // Best-price routing quotes the whole order on the submission last, then executes it.
+    let run = |submission: fn(&[u8]) -> u64| {
+        let config = SimulationConfig {
+            n_steps: 300,
+            seed: 23,
+            router: RouterKind::BestPrice,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            submission,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+        .retail_quote_divergence
+    };
+    let honest = run(normalizer_swap);
+    assert!(honest.checked > 0);
+    assert_eq!(honest.diverged, 0);
+
+    let boosting = run(repeat_boosting_swap);
+    assert!(boosting.diverged > 0, "{boosting:?}");
+    assert!(boosting.net_y > 0.0 && boosting.max_rel > 0.0, "{boosting:?}");
+
+    // Fills short of their quote are re-routed rather than executed.
+    assert_eq!(run(repeat_shading_swap).diverged, 0);
+}
+
+thread_local! {
+    static SUBMISSION_STALLED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// The normalizer's after_swap, stalling the submission in alternate blocks of ten steps.
+fn stalling_after_swap(data: &[u8], storage: &mut [u8]) {
+    let step = u64::from_le_bytes(data[34..42].try_into().unwrap());
+    SUBMISSION_STALLED.with(|stalled| stalled.set((step / 10) % 2 == 1));
+    normalizer_after_swap(data, storage);
+}
+
+/// The normalizer, quoting nothing while stalled, as a program out of compute would.
+fn stalled_swap(data: &[u8]) -> u64 {
+    if SUBMISSION_STALLED.with(|stalled| stalled.get()) {
+        0
+    } else {
+        normalizer_swap(data)
+    }
+}
+
+#[test]
+fn test_down_submission_steps_are_counted_and_skipped() {
+    let run = |submission: fn(&[u8]) -> u64| {
+        // Short of the first liveness probe, which would end the run as depleted.
+        let config = SimulationConfig {
+            n_steps: 99,
+            seed: 37,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            submission,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(stalling_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let healthy = run(normalizer_swap);
+    assert_eq!(healthy.submission_down_steps, 0);
+
+    let stalled = run(stalled_swap);
+    assert!(stalled.depletion.is_none());
+    assert!(
+        0 < stalled.submission_down_steps && stalled.submission_down_steps < 99,
+        "{} down steps",
+        stalled.submission_down_steps
+    );
+    // Down in alternate blocks of ten steps, so it fills less of the flow.
+    assert!(stalled.volume_by_counterparty.retail_y() < healthy.volume_by_counterparty.retail_y());
+}
+
+static MAX_RESERVE_Y_SEEN: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// The normalizer, recording the largest Y reserve it was quoted at.
+fn reserve_recording_swap(data: &[u8]) -> u64 {
+    let reserve_y = u64::from_le_bytes(data[17..25].try_into().unwrap());
+    MAX_RESERVE_Y_SEEN.fetch_max(reserve_y, std::sync::atomic::Ordering::Relaxed);
+    normalizer_swap(data)
+}
+
+#[test]
+fn test_token_decimals_rescale_what_programs_see() {
+    use prop_amm_shared::nano::TokenDecimals;
+
+    let run = |token_decimals, submission: fn(&[u8]) -> u64| {
+        let config = SimulationConfig {
+            n_steps: 300,
+            seed: 19,
+            token_decimals,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            submission,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let usdc_quote = TokenDecimals { x: 9, y: 6 };
+    run(usdc_quote, reserve_recording_swap);
+    // The pool opens with 10,000 Y; a 6-decimal program sees about 1e10 units, not 1e13.
+    let seen = MAX_RESERVE_Y_SEEN.load(std::sync::atomic::Ordering::Relaxed) as f64;
+    assert!((5e9..2e10).contains(&seen), "largest Y reserve seen {seen}");
+
+    // A constant-product curve is scale-free, so only rounding changes.
+    let nano = run(TokenDecimals::default(), normalizer_swap);
+    let scaled = run(usdc_quote, normalizer_swap);
+    assert!(
+        (scaled.submission_edge - nano.submission_edge).abs()
+            < 1e-3 * nano.submission_edge.abs().max(1.0),
+        "edge {} at 9:6 vs {} at 9:9",
+        scaled.submission_edge,
+        nano.submission_edge
+    );
+}
+
 #[test]
 fn test_storage_reset_between_simulations() {
     // Run two simulations with the same config — they should produce identical results
@@ -492,3 +885,2337 @@ fn test_hyperparameter_variance_generates_varied_configs() {
     assert!(liq_max <= 2.0, "liq_max {} above range", liq_max);
     assert!(liq_max - liq_min > 0.5, "liq range too narrow: [{}, {}]", liq_min, liq_max);
 }
+
+#[test]
+fn test_trade_hash_is_reproducible_and_seed_sensitive() {
+    let run = |seed: u64| {
+        let config = SimulationConfig {
+            n_steps: 2_500,
+            seed,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let a = run(7);
+    let b = run(7);
+    assert_eq!(a.trade_hash, b.trade_hash);
+    assert_eq!(a.trade_hash_checkpoints.len(), 2);
+    assert_eq!(a.first_divergent_checkpoint(&b), None);
+
+    let c = run(8);
+    assert_ne!(a.trade_hash, c.trade_hash);
+    assert_eq!(a.first_divergent_checkpoint(&c), Some(0));
+}
+
+fn marking_after_swap(_data: &[u8], storage: &mut [u8]) {
+    storage[0] = 1;
+}
+
+fn counting_after_swap(_data: &[u8], storage: &mut [u8]) {
+    storage[0] = storage[0].wrapping_add(1);
+}
+
+#[test]
+fn test_checksum_covers_final_state_beyond_trades() {
+    let config = SimulationConfig {
+        n_steps: 1_000,
+        // Under the hashed RNG streams, seed 7's path never moves far enough to clear the
+        // starter's 5% fee, so nothing trades and no after_swap runs.
+        seed: 3,
+        ..SimulationConfig::default()
+    };
+    let run = |after_swap: fn(&[u8], &mut [u8])| {
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let a = run(starter_after_swap);
+    assert!(a.volume_by_counterparty.total_y() > 0.0);
+    assert_eq!(a.checksum, run(starter_after_swap).checksum);
+    assert_ne!(a.checksum, a.trade_hash);
+
+    // The starter's quotes ignore storage, so only the final storage differs.
+    let b = run(counting_after_swap);
+    assert_eq!(a.trade_hash, b.trade_hash);
+    assert_ne!(a.checksum, b.checksum);
+}
+
+#[test]
+fn test_edge_by_counterparty_sums_to_net_edge() {
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 42,
+        // Volatile enough that arbitrageurs clear the starter's 5% fee.
+        gbm_sigma: 0.005,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    let breakdown = result.edge_by_counterparty;
+    assert!(
+        (breakdown.total() - result.submission_edge).abs() < 1e-9,
+        "breakdown {} != net edge {}",
+        breakdown.total(),
+        result.submission_edge
+    );
+    // Retail pays the spread; arbitrageurs take value from stale quotes.
+    assert!(breakdown.get(Counterparty::Retail) > 0.0);
+    assert!(breakdown.get(Counterparty::Arbitrageur) < 0.0);
+    assert_eq!(result.arb_edge(), breakdown.get(Counterparty::Arbitrageur));
+    assert_eq!(result.retail_edge(), breakdown.get(Counterparty::Retail));
+    // Informed flow is off by default.
+    for counterparty in [Counterparty::Arbitrageur, Counterparty::Retail] {
+        assert!(breakdown.trades(counterparty) > 0);
+        assert!(result.normalizer_edge_by_counterparty.trades(counterparty) > 0);
+    }
+}
+
+#[test]
+fn test_retail_mix_composes_independent_traders() {
+    let run = |retail_mix: Vec<RetailProfile>| {
+        let config = SimulationConfig {
+            n_steps: 2_000,
+            seed: 7,
+            retail_mix,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    // A single unit profile is the default trader, seed included.
+    let single = run(Vec::new());
+    let unit = run(vec![RetailProfile {
+        arrival_scale: 1.0,
+        size_scale: 1.0,
+        size_sigma: SimulationConfig::default().retail_size_sigma,
+        buy_prob: None,
+    }]);
+    assert_eq!(single.trade_hash, unit.trade_hash);
+
+    let mixed = run(RetailProfile::two_tier());
+    assert_ne!(single.trade_hash, mixed.trade_hash);
+    assert!(mixed.edge_by_counterparty.get(Counterparty::Retail) > 0.0);
+}
+
+#[test]
+fn test_normalizer_edge_matches_identical_submission() {
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 11,
+        norm_liquidity_mult: 1.0,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    assert!(result.normalizer_edge().abs() > 1.0);
+    assert!(
+        result.relative_edge().abs() < 1e-3 * result.normalizer_edge().abs(),
+        "identical venues earned {} vs {}",
+        result.submission_edge,
+        result.normalizer_edge()
+    );
+    let share = result.edge_share().unwrap();
+    assert!((share - 0.5).abs() < 1e-3, "edge share {share}");
+}
+
+#[test]
+fn test_warmup_steps_trade_but_do_not_accrue_edge() {
+    let run = |warmup_steps: u32, n_steps: u32| {
+        let config = SimulationConfig {
+            warmup_steps,
+            n_steps,
+            seed: 5,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let full = run(0, 1_500);
+    let warmed = run(500, 1_000);
+    assert_eq!(full.trade_hash, warmed.trade_hash);
+    assert_ne!(full.submission_edge, warmed.submission_edge);
+
+    let warmup_only = run(1_500, 0);
+    assert_eq!(warmup_only.trade_hash, full.trade_hash);
+    assert_eq!(warmup_only.submission_edge, 0.0);
+    assert_eq!(warmup_only.normalizer_edge(), 0.0);
+}
+
+fn zero_fee_cpmm_swap(data: &[u8]) -> u64 {
+    if data.len() < 25 {
+        return 0;
+    }
+    let side = data[0];
+    let input = u64::from_le_bytes(data[1..9].try_into().expect("cpmm input")) as u128;
+    let rx = u64::from_le_bytes(data[9..17].try_into().expect("cpmm reserve x")) as u128;
+    let ry = u64::from_le_bytes(data[17..25].try_into().expect("cpmm reserve y")) as u128;
+    if rx == 0 || ry == 0 {
+        return 0;
+    }
+    let k = rx * ry;
+    match side {
+        0 => rx.saturating_sub(k.div_ceil(ry + input)) as u64,
+        1 => ry.saturating_sub(k.div_ceil(rx + input)) as u64,
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_lvr_matches_constant_product_closed_form() {
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 3,
+        gbm_sigma: 0.001,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        zero_fee_cpmm_swap,
+        None,
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    // For x * y = k, LVR accrues at sigma^2 / 8 of pool value per unit time, and pool value
+    // (initially 2 * 100 * 100) moves only with sqrt(P) over this horizon.
+    let pool_value = 2.0 * config.initial_x * config.initial_price;
+    let expected = config.gbm_sigma.powi(2) / 8.0 * pool_value * config.n_steps as f64;
+    assert!(
+        (result.lvr / expected - 1.0).abs() < 0.02,
+        "lvr {} vs closed form {}",
+        result.lvr,
+        expected
+    );
+}
+
+#[test]
+fn test_rng_stream_derivation_is_pinned() {
+    use prop_amm_shared::rng::{substream_seed, Component};
+    use prop_amm_sim::engine::RngStreams;
+
+    // Changing these breaks every recorded manifest; update deliberately or not at all.
+    let config = SimulationConfig {
+        seed: 41,
+        ..SimulationConfig::default()
+    };
+    let streams = RngStreams::for_config(&config);
+    assert_eq!(streams.config, 0xd14f_ff2f_83f0_060c);
+    assert_eq!(streams.price, 0xf04a_c638_8edf_502d);
+    assert_eq!(streams.retail, vec![0x0f45_8d41_99ce_9a4e]);
+    assert_eq!(streams.arb, 0x2e40_544a_a4bd_e46f);
+    assert_eq!(streams.informed, None);
+
+    let informed = SimulationConfig {
+        informed_prob: 0.2,
+        ..config.clone()
+    };
+    let with_informed = RngStreams::for_config(&informed);
+    assert_eq!(with_informed.informed, Some(0x5564_e30b_5832_dd88));
+    // Enabling a component leaves every other stream where it was.
+    assert_eq!(with_informed.price, streams.price);
+    assert_eq!(with_informed.retail, streams.retail);
+    assert_eq!(with_informed.arb, streams.arb);
+
+    let gapped = SimulationConfig {
+        random_gaps: 3,
+        random_gap_size: 0.05,
+        ..config.clone()
+    };
+    let with_gaps = RngStreams::for_config(&gapped);
+    assert_eq!(streams.gaps, None);
+    assert_eq!(with_gaps.gaps, Some(0x745f_aa14_6322_27a9));
+    assert_eq!(with_gaps.price, streams.price);
+    assert_eq!(with_gaps.retail, streams.retail);
+
+    let switching = SimulationConfig {
+        volatility_regimes: Some(VolatilityRegimes {
+            calm_sigma: 0.001,
+            volatile_sigma: 0.005,
+            calm_to_volatile: 0.01,
+            volatile_to_calm: 0.05,
+        }),
+        ..config.clone()
+    };
+    let with_regimes = RngStreams::for_config(&switching);
+    assert_eq!(streams.regimes, None);
+    assert_eq!(with_regimes.regimes, Some(0x935a_711d_6e11_71ca));
+    assert_eq!(with_regimes.price, streams.price);
+
+    let trending = SimulationConfig {
+        momentum_traders: Some(MomentumCohort {
+            arrival_scale: 0.5,
+            lookback_steps: 20,
+            weight: 0.5,
+        }),
+        ..config.clone()
+    };
+    let with_momentum = RngStreams::for_config(&trending);
+    assert_eq!(streams.momentum, None);
+    assert_eq!(with_momentum.momentum, Some(0xb255_3826_7900_bbeb));
+    assert_eq!(with_momentum.retail, streams.retail);
+
+    let occasional = SimulationConfig {
+        arb_arrival_prob: 0.25,
+        ..config.clone()
+    };
+    let with_arrivals = RngStreams::for_config(&occasional);
+    assert_eq!(streams.arb_arrival, None);
+    assert_eq!(with_arrivals.arb_arrival, Some(0xd979_c6e7_2c75_b504));
+    assert_eq!(with_arrivals.arb, streams.arb);
+
+    let noisy = SimulationConfig {
+        arb_size_noise: 0.2,
+        ..config.clone()
+    };
+    let with_noise = RngStreams::for_config(&noisy);
+    assert_eq!(streams.arb_noise, None);
+    assert_eq!(with_noise.arb_noise, Some(0xf874_8df0_3764_ff25));
+    assert_eq!(with_noise.arb, streams.arb);
+
+    let observed = SimulationConfig {
+        oracle_noise: 0.01,
+        ..config.clone()
+    };
+    let with_oracle = RngStreams::for_config(&observed);
+    assert_eq!(streams.oracle, None);
+    assert_eq!(with_oracle.oracle, Some(0x176f_54f9_4254_4946));
+    assert_eq!(with_oracle.price, streams.price);
+
+    let evented = SimulationConfig {
+        clock: Clock::EventDriven {
+            arb_check_rate: 1.0,
+        },
+        ..config.clone()
+    };
+    let with_clock = RngStreams::for_config(&evented);
+    assert_eq!(streams.clock, None);
+    assert_eq!(with_clock.clock, Some(0x366a_1c02_4d43_9367));
+    assert_eq!(with_clock.price, streams.price);
+    assert_eq!(with_clock.retail, streams.retail);
+
+    let interleaved = SimulationConfig {
+        step_sequencing: StepSequencing::Interleaved,
+        ..config.clone()
+    };
+    let with_sequencing = RngStreams::for_config(&interleaved);
+    assert_eq!(streams.sequencing, None);
+    assert_eq!(with_sequencing.sequencing, Some(0x5d8e_aac3_00b8_8c80));
+    assert_eq!(with_sequencing.arb, streams.arb);
+
+    let mixed = SimulationConfig {
+        retail_mix: RetailProfile::two_tier(),
+        ..config
+    };
+    assert_eq!(
+        RngStreams::for_config(&mixed).retail,
+        vec![0x0f45_8d41_99ce_9a4e, substream_seed(41, Component::Retail, 1)]
+    );
+}
+
+#[test]
+fn test_oracle_noise_only_moves_sell_sizing() {
+    let run = |retail_buy_prob, oracle_noise| {
+        let config = SimulationConfig {
+            n_steps: 500,
+            seed: 29,
+            retail_buy_prob,
+            oracle_noise,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    // Buy orders are sized in Y and need no price, so they fill exactly as before.
+    assert_eq!(run(1.0, 0.05).checksum, run(1.0, 0.0).checksum);
+    // Sell orders are converted to X at the observed price.
+    let exact = run(0.0, 0.0);
+    let noisy = run(0.0, 0.05);
+    assert_ne!(noisy.trade_hash, exact.trade_hash);
+    assert!(noisy.submission_edge.is_finite());
+}
+
+#[test]
+fn test_inventory_pnl_equals_edge_at_constant_price() {
+    // With a flat price, every trade is valued at the terminal price, so marking the
+    // inventory adds nothing beyond edge.
+    let config = SimulationConfig {
+        n_steps: 1_000,
+        warmup_steps: 100,
+        seed: 13,
+        gbm_mu: 0.0,
+        gbm_sigma: 0.0,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    assert!(result.submission_edge > 0.0);
+    assert!(
+        (result.inventory_pnl() - result.submission_edge).abs() < 1e-6,
+        "inventory PnL {} vs edge {}",
+        result.inventory_pnl(),
+        result.submission_edge
+    );
+    assert_ne!(result.inventory_start, result.inventory_end);
+}
+
+#[test]
+fn test_fee_revenue_is_zero_for_fee_free_curve() {
+    let config = SimulationConfig {
+        n_steps: 1_000,
+        seed: 17,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        zero_fee_cpmm_swap,
+        None,
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    // Only nano rounding separates the quotes from the exact constant-product output.
+    assert!(
+        result.fee_revenue.abs() < 1e-3,
+        "fee revenue {}",
+        result.fee_revenue
+    );
+    assert!(result.normalizer_fee_revenue > 1.0);
+}
+
+#[test]
+fn test_identical_venues_split_routed_volume_evenly() {
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 19,
+        norm_liquidity_mult: 1.0,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    for counterparty in [Counterparty::Arbitrageur, Counterparty::Retail] {
+        assert!(result.volume_by_counterparty.x(counterparty) > 0.0);
+        assert!(result.volume_by_counterparty.y(counterparty) > 0.0);
+    }
+    let share = result.retail_volume_share();
+    assert!((share - 0.5).abs() < 0.05, "retail volume share {share}");
+}
+
+#[test]
+fn test_trajectory_snapshots_every_stride_steps() {
+    let run = |trajectory_stride: Option<u32>| {
+        let config = SimulationConfig {
+            n_steps: 1_050,
+            seed: 29,
+            trajectory_stride,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let plain = run(None);
+    let recorded = run(Some(100));
+    assert!(plain.trajectory.is_empty());
+    assert_eq!(plain.trade_hash, recorded.trade_hash);
+
+    let steps: Vec<u32> = recorded.trajectory.iter().map(|s| s.step).collect();
+    let mut expected: Vec<u32> = (1..=10).map(|i| i * 100 - 1).collect();
+    expected.push(1_049);
+    assert_eq!(steps, expected);
+    let last = recorded.trajectory.last().unwrap();
+    assert_eq!(last.reserve_x, recorded.inventory_end.reserve_x);
+    assert_eq!(last.fair_price, recorded.inventory_end.fair_price);
+}
+
+#[test]
+fn test_max_drawdown_bounds_final_loss() {
+    for seed in 0..5 {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed,
+            ..SimulationConfig::default()
+        };
+        let result = prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap();
+        // Cumulative edge starts at zero, so ending below zero is itself a drawdown.
+        assert!(result.max_drawdown >= (-result.submission_edge).max(0.0));
+        assert!(result.max_drawdown.is_finite());
+    }
+}
+
+#[test]
+fn test_early_stop_uses_a_prefix_of_the_seeds() {
+    let configs = prop_amm_sim::runner::default_configs(40, 300, 0, 1);
+    let run_chunk = |configs| {
+        prop_amm_sim::runner::run_batch_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            configs,
+            Some(2),
+        )
+    };
+    let stop = prop_amm_sim::runner::EarlyStop {
+        min_sims: 16,
+        chunk: 8,
+        ..prop_amm_sim::runner::EarlyStop::new(1e9)
+    };
+
+    let loose =
+        prop_amm_sim::runner::run_until_converged(configs.clone(), &stop, run_chunk).unwrap();
+    assert_eq!(loose.n_sims(), 16);
+    let plain = run_chunk(configs[..16].to_vec()).unwrap();
+    assert_eq!(loose.total_edge.to_bits(), plain.total_edge.to_bits());
+    assert_eq!(loose.trade_hash(), plain.trade_hash());
+
+    let tight = prop_amm_sim::runner::EarlyStop {
+        half_width: 1e-12,
+        ..stop
+    };
+    let all = prop_amm_sim::runner::run_until_converged(configs, &tight, run_chunk).unwrap();
+    assert_eq!(all.n_sims(), 40);
+}
+
+#[test]
+fn test_pre_drawn_price_path_replays_the_simulation() {
+    use prop_amm_sim::price_process::PricePath;
+
+    let config = SimulationConfig {
+        n_steps: 400,
+        warmup_steps: 50,
+        sessions: 2,
+        seed: 12,
+        ..SimulationConfig::default()
+    };
+    let run = |path: Option<&PricePath>| {
+        let mut log = RegimeLog::default();
+        let result = match path {
+            Some(path) => prop_amm_sim::engine::run_simulation_native_on_path(
+                starter_swap,
+                Some(starter_after_swap),
+                normalizer_swap,
+                Some(normalizer_after_swap),
+                &config,
+                path,
+                &mut log,
+            ),
+            None => prop_amm_sim::engine::run_simulation_native_observed(
+                starter_swap,
+                Some(starter_after_swap),
+                normalizer_swap,
+                Some(normalizer_after_swap),
+                &config,
+                &mut log,
+            ),
+        };
+        (result, log)
+    };
+    let path = PricePath::generate(&config);
+    assert_eq!(path.steps(), 850);
+    let (drawn, drawn_log) = run(None);
+    let (replayed, replayed_log) = run(Some(&path));
+    let (drawn, replayed) = (drawn.unwrap(), replayed.unwrap());
+    assert_eq!(replayed.checksum, drawn.checksum);
+    assert_eq!(replayed_log.fair_prices, drawn_log.fair_prices);
+    let prices: Vec<f64> = path.prices().take(850).collect();
+    assert_eq!(prices, drawn_log.fair_prices);
+
+    let other_seed = PricePath::generate(&SimulationConfig {
+        seed: 13,
+        ..config.clone()
+    });
+    assert!(run(Some(&other_seed)).0.is_err());
+}
+
+#[test]
+fn test_paired_runs_share_price_paths() {
+    use prop_amm_sim::runner::NativeSubmission;
+
+    let configs: Vec<SimulationConfig> = (0..4)
+        .map(|seed| SimulationConfig {
+            n_steps: 500,
+            seed,
+            trajectory_stride: Some(50),
+            ..SimulationConfig::default()
+        })
+        .collect();
+    let starter = NativeSubmission {
+        swap_fn: starter_swap,
+        after_swap_fn: Some(starter_after_swap),
+    };
+    let zero_fee = NativeSubmission {
+        swap_fn: zero_fee_cpmm_swap,
+        after_swap_fn: None,
+    };
+
+    let same = prop_amm_sim::runner::run_paired_native(
+        starter,
+        starter,
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        configs.clone(),
+        Some(2),
+    )
+    .unwrap();
+    assert!(same.edge_differences().iter().all(|&(_, d)| d == 0.0));
+    assert_eq!(same.edge_difference_std_error(), 0.0);
+
+    let paired = prop_amm_sim::runner::run_paired_native(
+        starter,
+        zero_fee,
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        configs,
+        Some(2),
+    )
+    .unwrap();
+    for (a, b) in paired.a.results.iter().zip(&paired.b.results) {
+        assert_eq!(a.seed, b.seed);
+        let prices = |r: &prop_amm_shared::result::SimResult| {
+            r.trajectory.iter().map(|s| s.fair_price).collect::<Vec<_>>()
+        };
+        assert_eq!(prices(a), prices(b));
+    }
+    assert!(paired.avg_edge_difference() < 0.0, "a fee-free curve gives edge away");
+    let expected = paired.b.avg_edge() - paired.a.avg_edge();
+    assert!((paired.avg_edge_difference() - expected).abs() < 1e-9);
+}
+
+#[derive(Default)]
+struct EdgeTally {
+    steps: u32,
+    arbs: u32,
+    retail_fills: u32,
+    scored_edge: f64,
+    last_step_edge: f64,
+    finished_seed: Option<u64>,
+}
+
+impl prop_amm_sim::observer::SimObserver for EdgeTally {
+    fn on_step(&mut self, event: &prop_amm_sim::observer::StepEvent) {
+        assert_eq!(event.step, self.steps);
+        self.steps += 1;
+        self.last_step_edge = event.submission_edge;
+    }
+
+    fn on_arb(&mut self, trade: &prop_amm_sim::observer::TradeEvent) {
+        self.arbs += 1;
+        if trade.scored && trade.is_submission {
+            self.scored_edge += trade.edge;
+        }
+    }
+
+    fn on_retail_trade(&mut self, trade: &prop_amm_sim::observer::TradeEvent) {
+        self.retail_fills += 1;
+        if trade.scored && trade.is_submission {
+            self.scored_edge += trade.edge;
+        }
+    }
+
+    fn on_finish(&mut self, result: &prop_amm_shared::result::SimResult) {
+        assert!(self.finished_seed.is_none());
+        self.finished_seed = Some(result.seed);
+    }
+}
+
+#[test]
+fn test_observer_sees_every_step_and_trade() {
+    let configs: Vec<SimulationConfig> = (0..3)
+        .map(|seed| SimulationConfig {
+            n_steps: 400,
+            warmup_steps: 50,
+            seed,
+            ..SimulationConfig::default()
+        })
+        .collect();
+    let (observed, tallies) = prop_amm_sim::runner::run_batch_native_observed(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        configs.clone(),
+        Some(2),
+        |_| EdgeTally::default(),
+    )
+    .unwrap();
+    let plain = prop_amm_sim::runner::run_batch_native(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        configs,
+        Some(2),
+    )
+    .unwrap();
+    assert_eq!(observed.trade_hash(), plain.trade_hash());
+
+    for (result, tally) in observed.results.iter().zip(&tallies) {
+        assert_eq!(tally.finished_seed, Some(result.seed));
+        assert_eq!(tally.steps, 450);
+        assert!(tally.arbs > 0 && tally.retail_fills > 0);
+        assert!((tally.scored_edge - result.submission_edge).abs() < 1e-9);
+        assert_eq!(tally.last_step_edge, result.submission_edge);
+    }
+}
+
+#[test]
+fn test_extra_venues_compete_for_retail_flow() {
+    let base = SimulationConfig {
+        n_steps: 2000,
+        seed: 5,
+        ..SimulationConfig::default()
+    };
+    let run = |config: &SimulationConfig| {
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            config,
+        )
+        .unwrap()
+    };
+    let two_venues = run(&base);
+    assert!(two_venues.extra_venue_edge_by_counterparty.is_empty());
+
+    let crowded = SimulationConfig {
+        extra_venues: vec![
+            BaselineVenue { fee_bps: 10, liquidity_mult: 2.0 },
+            BaselineVenue { fee_bps: 50, liquidity_mult: 0.5 },
+        ],
+        ..base.clone()
+    };
+    let four_venues = run(&crowded);
+    assert_eq!(four_venues.extra_venue_edge_by_counterparty.len(), 2);
+    assert_eq!(four_venues.extra_venue_volume_by_counterparty.len(), 2);
+    let cheap_venue = &four_venues.extra_venue_volume_by_counterparty[0];
+    assert!(cheap_venue.y(Counterparty::Retail) > 0.0);
+    assert!(
+        four_venues.retail_volume_share() < two_venues.retail_volume_share(),
+        "a cheap deep venue should take retail share: {} vs {}",
+        four_venues.retail_volume_share(),
+        two_venues.retail_volume_share()
+    );
+
+    // Splitting the baselines' part by their quotes alone lands close to the closed form.
+    let quoted = run(&SimulationConfig {
+        black_box_baselines: true,
+        ..crowded
+    });
+    let cheap_share = |result: &prop_amm_shared::result::SimResult| {
+        result.extra_venue_volume_by_counterparty[0].y(Counterparty::Retail)
+    };
+    let (share, expected) = (quoted.retail_volume_share(), four_venues.retail_volume_share());
+    assert!((share - expected).abs() < 0.05 * expected, "{share} vs {expected}");
+    let (cheap, expected) = (cheap_share(&quoted), cheap_share(&four_venues));
+    assert!((cheap - expected).abs() < 0.05 * expected, "{cheap} vs {expected}");
+}
+
+static LP_EVENT_CALLS: [std::sync::atomic::AtomicU32; 2] =
+    [std::sync::atomic::AtomicU32::new(0), std::sync::atomic::AtomicU32::new(0)];
+
+fn lp_event_recording_after_swap(data: &[u8], _storage: &mut [u8]) {
+    use prop_amm_shared::instruction::{LIQUIDITY_EVENT_TAG, SIDE_DEPOSIT, SIDE_WITHDRAW};
+    if data[0] != LIQUIDITY_EVENT_TAG {
+        return;
+    }
+    let slot = match data[1] {
+        SIDE_DEPOSIT => 0,
+        SIDE_WITHDRAW => 1,
+        _ => return,
+    };
+    LP_EVENT_CALLS[slot].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[test]
+fn test_liquidity_events_rescale_pools_and_notify_after_swap() {
+    let config = SimulationConfig {
+        n_steps: 1_000,
+        warmup_steps: 50,
+        seed: 17,
+        gbm_sigma: 0.0,
+        liquidity_events: vec![
+            LiquidityEvent { step: 200, scale: 3.0 },
+            LiquidityEvent { step: 700, scale: 0.5 },
+        ],
+        liquidity_event_callbacks: true,
+        ..SimulationConfig::default()
+    };
+    let mut depth = ReserveLog::default();
+    let result = prop_amm_sim::engine::run_simulation_native_observed(
+        starter_swap,
+        Some(lp_event_recording_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+        &mut depth,
+    )
+    .unwrap();
+
+    let calls = |slot: usize| LP_EVENT_CALLS[slot].load(std::sync::atomic::Ordering::Relaxed);
+    assert_eq!((calls(0), calls(1)), (1, 1));
+    // Without the opt-in, programs see no LP event calls.
+    let silent = SimulationConfig {
+        liquidity_event_callbacks: false,
+        ..config.clone()
+    };
+    prop_amm_sim::runner::run_batch_native(
+        starter_swap,
+        Some(lp_event_recording_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        vec![silent],
+        Some(1),
+    )
+    .unwrap();
+    assert_eq!((calls(0), calls(1)), (1, 1));
+    // Steps in observer events count warm-up too.
+    let ratio = |log: &[(f64, f64)], step: usize| log[step].0 / log[step - 1].0;
+    for log in [&depth.submission, &depth.normalizer] {
+        assert!((2.5..3.5).contains(&ratio(log, 250)), "deposit ratio {}", ratio(log, 250));
+        assert!((0.4..0.6).contains(&ratio(log, 750)), "withdrawal ratio {}", ratio(log, 750));
+    }
+    // Deposits are not PnL: at a flat price, marked inventory still tracks edge exactly.
+    assert!(result.liquidity_added > 0.0);
+    assert!(
+        (result.inventory_pnl() - result.submission_edge).abs() < 1e-6,
+        "inventory PnL {} vs edge {}",
+        result.inventory_pnl(),
+        result.submission_edge
+    );
+}
+
+#[derive(Default)]
+struct ReserveLog {
+    submission: Vec<(f64, f64)>,
+    normalizer: Vec<(f64, f64)>,
+    fair_prices: Vec<f64>,
+}
+
+impl prop_amm_sim::observer::SimObserver for ReserveLog {
+    fn on_step(&mut self, event: &prop_amm_sim::observer::StepEvent) {
+        self.submission.push(event.submission_reserves);
+        self.normalizer.push(event.normalizer_reserves);
+        self.fair_prices.push(event.fair_price);
+    }
+}
+
+#[test]
+fn test_informed_flow_is_adversely_selected() {
+    let run = |informed_prob: f64| {
+        let config = SimulationConfig {
+            n_steps: 3_000,
+            seed: 23,
+            gbm_sigma: 0.003,
+            informed_prob,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        let mut log = ReserveLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+            &mut log,
+        )
+        .unwrap();
+        (result, log.fair_prices)
+    };
+    let (uninformed, uninformed_prices) = run(0.0);
+    let (informed, prices) = run(0.3);
+    assert_eq!(uninformed.edge_by_counterparty.trades(Counterparty::Informed), 0);
+    // Informed tagging draws from its own stream, so the price path is unchanged.
+    assert_eq!(uninformed_prices, prices);
+
+    let informed_trades: Vec<_> = informed
+        .tape
+        .iter()
+        .filter(|trade| trade.counterparty == Counterparty::Informed)
+        .collect();
+    assert!(!informed_trades.is_empty());
+    for trade in informed_trades {
+        let Some(&next) = prices.get(trade.step as usize + 1) else {
+            continue;
+        };
+        if next != trade.fair_price {
+            // The pool sells X ahead of a rise and buys it ahead of a fall.
+            assert_eq!(trade.amm_buys_x, next < trade.fair_price, "step {}", trade.step);
+        }
+    }
+
+    // So marked to the next step's price, the pools earn less on each informed trade than
+    // on each uninformed one. Edge at the trade's own fair price cannot show this: the
+    // informed trader's advantage only arrives with the move.
+    let per_trade_markout = |counterparty| {
+        let (total, trades) = informed
+            .tape
+            .iter()
+            .filter(|trade| trade.counterparty == counterparty)
+            .filter_map(|trade| {
+                let next = *prices.get(trade.step as usize + 1)?;
+                Some(if trade.amm_buys_x {
+                    trade.amount_x * next - trade.amount_y
+                } else {
+                    trade.amount_y - trade.amount_x * next
+                })
+            })
+            .fold((0.0, 0), |(total, trades), edge| (total + edge, trades + 1));
+        total / f64::from(trades.max(1))
+    };
+    let (adverse, uninformed_markout) = (
+        per_trade_markout(Counterparty::Informed),
+        per_trade_markout(Counterparty::Retail),
+    );
+    assert!(
+        adverse < uninformed_markout,
+        "informed {adverse} vs uninformed {uninformed_markout} markout per trade"
+    );
+    let classes = informed.retail_edge() + informed.informed_edge() + informed.arb_edge();
+    assert!((classes - informed.submission_edge).abs() < 1e-6);
+}
+
+#[test]
+fn test_correlated_flow_follows_the_price_move() {
+    // Share of retail fills that buy X after an up move or sell it after a down move.
+    let share_with_move = |flow_price_correlation: f64| {
+        let config = SimulationConfig {
+            n_steps: 3_000,
+            seed: 31,
+            flow_price_correlation,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        let mut log = ReserveLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+            &mut log,
+        )
+        .unwrap();
+        let fills: Vec<bool> = result
+            .tape
+            .iter()
+            .filter(|trade| trade.counterparty == Counterparty::Retail)
+            .map(|trade| {
+                let step = trade.step as usize;
+                let previous =
+                    step.checked_sub(1).map_or(config.initial_price, |s| log.fair_prices[s]);
+                // Retail buying X means the pool sells it.
+                (trade.fair_price > previous) != trade.amm_buys_x
+            })
+            .collect();
+        fills.iter().filter(|&&with| with).count() as f64 / fills.len() as f64
+    };
+    assert_eq!(share_with_move(1.0), 1.0);
+    assert_eq!(share_with_move(-1.0), 0.0);
+    let uncorrelated = share_with_move(0.0);
+    assert!((0.45..0.55).contains(&uncorrelated), "{uncorrelated}");
+    let half = share_with_move(0.5);
+    assert!((0.7..0.8).contains(&half), "{half}");
+}
+
+#[test]
+fn test_arrival_windows_shape_retail_activity() {
+    let config = SimulationConfig {
+        n_steps: 3_000,
+        seed: 5,
+        retail_arrival_rate: 0.8,
+        arrival_windows: vec![
+            ArrivalWindow {
+                start_step: 1_000,
+                end_step: 1_500,
+                multiplier: 4.0,
+            },
+            ArrivalWindow {
+                start_step: 2_000,
+                end_step: 2_500,
+                multiplier: 0.0,
+            },
+        ],
+        record_tape: true,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+    // Retail fills on the submission per step in `steps`; each order fills there at most once.
+    let rate = |steps: std::ops::Range<u32>| {
+        let fills = result
+            .tape
+            .iter()
+            .filter(|t| t.venue == 0 && t.counterparty == Counterparty::Retail)
+            .filter(|t| steps.contains(&t.step))
+            .count();
+        fills as f64 / steps.len() as f64
+    };
+    let (quiet, burst, lull) = (rate(0..1_000), rate(1_000..1_500), rate(2_000..2_500));
+    assert!(quiet > 0.5, "{quiet} fills per step");
+    assert!(burst > 3.0 * quiet, "burst {burst} vs {quiet}");
+    assert_eq!(lull, 0.0, "a zero multiplier lets no orders arrive");
+}
+
+#[test]
+fn test_zero_arrival_window_produces_no_orders() {
+    let config = SimulationConfig {
+        n_steps: 1_000,
+        seed: 9,
+        arrival_windows: vec![ArrivalWindow {
+            start_step: 0,
+            end_step: 1_000,
+            multiplier: 0.0,
+        }],
+        record_tape: true,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+    let retail = result.tape.iter().filter(|t| t.counterparty == Counterparty::Retail).count();
+    assert_eq!(retail, 0);
+}
+
+#[test]
+fn test_retail_classes_keep_their_own_buy_prob() {
+    // Small orders only ever buy and large ones only ever sell.
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 17,
+        retail_mix: vec![
+            RetailProfile {
+                buy_prob: Some(1.0),
+                ..RetailProfile::SMALL_FREQUENT
+            },
+            RetailProfile {
+                buy_prob: Some(0.0),
+                ..RetailProfile::LARGE_INFREQUENT
+            },
+        ],
+        flow_price_correlation: 0.5,
+        record_tape: true,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+    // Mean Y notional of the retail fills on the submission where the trader buys X.
+    let mean_fill = |trader_buys: bool| {
+        let fills: Vec<f64> = result
+            .tape
+            .iter()
+            .filter(|t| t.venue == 0 && t.counterparty == Counterparty::Retail)
+            .filter(|t| t.amm_buys_x != trader_buys)
+            .map(|t| t.amount_y)
+            .collect();
+        assert!(!fills.is_empty());
+        fills.iter().sum::<f64>() / fills.len() as f64
+    };
+    // The price correlation tilts both classes, but buys still come mostly from the small.
+    let (buys, sells) = (mean_fill(true), mean_fill(false));
+    assert!(sells > 2.0 * buys, "sells {sells} vs buys {buys}");
+}
+
+#[test]
+fn test_flow_persistence_makes_retail_direction_come_in_runs() {
+    // Share of consecutive retail fills on the submission that trade the same way. Each
+    // order fills there at most once, so fills follow the order sequence.
+    let repeat_share = |flow_persistence: f64| {
+        let config = SimulationConfig {
+            n_steps: 3_000,
+            seed: 19,
+            flow_persistence,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        let result = prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap();
+        let sides: Vec<bool> = result
+            .tape
+            .iter()
+            .filter(|t| t.venue == 0 && t.counterparty == Counterparty::Retail)
+            .map(|t| t.amm_buys_x)
+            .collect();
+        let repeats = sides.windows(2).filter(|pair| pair[0] == pair[1]).count();
+        repeats as f64 / (sides.len() - 1) as f64
+    };
+    let independent = repeat_share(0.0);
+    let persistent = repeat_share(0.8);
+    assert!((independent - 0.5).abs() < 0.1, "{independent}");
+    assert!(persistent > 0.85, "{persistent}");
+}
+
+#[test]
+fn test_momentum_traders_follow_the_recent_trend() {
+    const LOOKBACK: usize = 30;
+    // Retail fills on the submission, and the share of them that trade with the trend
+    // over the lookback.
+    let run = |momentum_traders: Option<MomentumCohort>| {
+        let config = SimulationConfig {
+            n_steps: 3_000,
+            seed: 13,
+            momentum_traders,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        let mut log = ReserveLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+            &mut log,
+        )
+        .unwrap();
+        let with_trend: Vec<bool> = result
+            .tape
+            .iter()
+            .filter(|t| t.venue == 0 && t.counterparty == Counterparty::Retail)
+            .filter(|t| t.step as usize >= LOOKBACK)
+            .map(|t| {
+                let start = log.fair_prices[t.step as usize - LOOKBACK];
+                (t.fair_price > start) != t.amm_buys_x
+            })
+            .collect();
+        let share = with_trend.iter().filter(|&&w| w).count() as f64 / with_trend.len() as f64;
+        (with_trend.len(), share)
+    };
+    let cohort = MomentumCohort {
+        arrival_scale: 1.0,
+        lookback_steps: LOOKBACK as u32,
+        weight: 1.0,
+    };
+    let (noise_fills, noise_share) = run(None);
+    let (mixed_fills, mixed_share) = run(Some(cohort));
+    let (_, untilted_share) = run(Some(MomentumCohort {
+        weight: 0.0,
+        ..cohort
+    }));
+    // As many momentum orders as noise orders, all of them with the trend.
+    let ratio = mixed_fills as f64 / noise_fills as f64;
+    assert!((1.7..2.3).contains(&ratio), "fill ratio {ratio}");
+    assert!((0.45..0.55).contains(&noise_share), "{noise_share}");
+    assert!((0.45..0.55).contains(&untilted_share), "{untilted_share}");
+    assert!((0.7..0.8).contains(&mixed_share), "{mixed_share}");
+}
+
+#[test]
+fn test_mispriced_launch_is_arbitraged_on_the_first_step() {
+    let mut config = SimulationConfig {
+        n_steps: 200,
+        seed: 5,
+        gbm_sigma: 0.0,
+        record_tape: true,
+        ..SimulationConfig::default()
+    };
+    config.set_initial_pool(40_000.0, 0.1);
+    assert_eq!(config.initial_y, 20_000.0);
+    let mut log = ReserveLog::default();
+    let result = prop_amm_sim::engine::run_simulation_native_observed(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+        &mut log,
+    )
+    .unwrap();
+
+    // Both pools open 10% rich, so the arbitrageur sells X into each before anything else.
+    for venue in [0, 1] {
+        let first = result.tape.iter().find(|t| t.venue == venue).unwrap();
+        assert_eq!((first.step, first.counterparty), (0, Counterparty::Arbitrageur));
+        assert!(first.amm_buys_x);
+    }
+    for (x, y) in [log.submission[0], log.normalizer[0]] {
+        let spot = y / x;
+        assert!((99.0..101.0).contains(&spot), "spot after the first step {spot}");
+        // Twice the default depth: k stays near the opening 20,000 * 20,000 / 110.
+        let k0 = 20_000.0 * 20_000.0 / 110.0;
+        assert!((k0..k0 * 1.01).contains(&(x * y)), "k {} vs {}", x * y, k0);
+    }
+}
+
+#[test]
+fn test_external_spread_damps_arbitrage() {
+    let run = |external_spread_bps: f64| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 23,
+            external_spread_bps,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let frictionless = run(0.0);
+    let spread = run(50.0);
+    let arb_volume = |result: &prop_amm_shared::result::SimResult| {
+        result.volume_by_counterparty.y(Counterparty::Arbitrageur)
+    };
+    assert!(
+        arb_volume(&spread) < 0.8 * arb_volume(&frictionless),
+        "arb volume {} vs {}",
+        arb_volume(&spread),
+        arb_volume(&frictionless)
+    );
+    // Edge is still valued at mid: pools keep losing to the arbitrageur.
+    assert!(spread.edge_by_counterparty.get(Counterparty::Arbitrageur) < 0.0);
+}
+
+#[test]
+fn test_cross_venue_arbitrage_keeps_the_pools_together() {
+    let run = |arb_cross_venue| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 23,
+            // Noisy sizing over- and undershoots, pushing the pools past each other.
+            arb_size_noise: 0.5,
+            arb_cross_venue,
+            ..SimulationConfig::default()
+        };
+        let mut log = ReserveLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+            &mut log,
+        )
+        .unwrap();
+        // Mean absolute log gap between the two pools' spot prices.
+        let gap = log
+            .submission
+            .iter()
+            .zip(&log.normalizer)
+            .map(|(s, n)| ((s.1 / s.0) / (n.1 / n.0)).ln().abs())
+            .sum::<f64>()
+            / log.submission.len() as f64;
+        (result, gap)
+    };
+    let (apart, apart_gap) = run(false);
+    let (together, together_gap) = run(true);
+    assert!(together_gap < 0.9 * apart_gap, "gap {together_gap} vs {apart_gap}");
+    let arb_volume = |result: &prop_amm_shared::result::SimResult| {
+        result.normalizer_volume_by_counterparty.y(Counterparty::Arbitrageur)
+    };
+    assert!(arb_volume(&together) > arb_volume(&apart));
+}
+
+#[test]
+fn test_price_gaps_move_the_fair_price_and_draw_arbitrage() {
+    let run = |config: &SimulationConfig| {
+        let mut log = ReserveLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            config,
+            &mut log,
+        )
+        .unwrap();
+        (result, log.fair_prices)
+    };
+    let config = SimulationConfig {
+        n_steps: 500,
+        warmup_steps: 20,
+        seed: 9,
+        gbm_sigma: 0.0,
+        record_tape: true,
+        price_gaps: vec![PriceGap { step: 100, size: -0.05 }],
+        ..SimulationConfig::default()
+    };
+    let (result, prices) = run(&config);
+    // Gap steps are scored steps; observer steps count warm-up too.
+    assert_eq!(prices[119], 100.0);
+    assert!((prices[120] - 95.0).abs() < 1e-9, "gapped price {}", prices[120]);
+    assert!(prices[120..].iter().all(|&p| p == prices[120]));
+    // The drop leaves every pool rich, so the arbitrageur buys X back from each at once.
+    for venue in [0, 1] {
+        let hit = result
+            .tape
+            .iter()
+            .find(|t| {
+                t.venue == venue && t.step == 120 && t.counterparty == Counterparty::Arbitrageur
+            })
+            .unwrap();
+        assert!(hit.amm_buys_x);
+    }
+
+    let random = SimulationConfig {
+        gbm_sigma: 0.001,
+        price_gaps: Vec::new(),
+        random_gaps: 4,
+        random_gap_size: 0.1,
+        ..config.clone()
+    };
+    let (first, gapped) = run(&random);
+    let (second, again) = run(&random);
+    assert_eq!(gapped, again);
+    assert_eq!(first.trade_hash, second.trade_hash);
+    let big_moves = gapped.windows(2).filter(|w| (w[1] / w[0] - 1.0).abs() > 0.08).count();
+    assert!((1..=4).contains(&big_moves), "{big_moves} gaps seen");
+}
+
+#[derive(Default)]
+struct ScenarioLog {
+    fair_prices: Vec<f64>,
+    retail_steps: Vec<u32>,
+}
+
+impl prop_amm_sim::observer::SimObserver for ScenarioLog {
+    fn on_step(&mut self, event: &prop_amm_sim::observer::StepEvent) {
+        self.fair_prices.push(event.fair_price);
+    }
+
+    fn on_retail_trade(&mut self, trade: &prop_amm_sim::observer::TradeEvent) {
+        self.retail_steps.push(trade.step);
+    }
+}
+
+#[test]
+fn test_scenario_scripts_prices_and_orders() {
+    use prop_amm_shared::config::{Scenario, ScriptedOrder, ScriptedPrice};
+
+    let point = |step, price| ScriptedPrice { step, price };
+    let scenario = Scenario {
+        orders: vec![ScriptedOrder {
+            step: 250,
+            is_buy: false,
+            size: 500.0,
+            max_slippage_bps: None,
+            min_output: None,
+        }],
+        // A 30% crash over steps 200..300.
+        prices: vec![point(200, 100.0), point(300, 70.0)],
+        background_retail: false,
+    };
+    let config = SimulationConfig {
+        n_steps: 400,
+        seed: 4,
+        scenario: Some(scenario.clone()),
+        ..SimulationConfig::default()
+    };
+    let mut log = ScenarioLog::default();
+    let result = prop_amm_sim::engine::run_simulation_native_observed(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+        &mut log,
+    )
+    .unwrap();
+    for (step, &price) in log.fair_prices.iter().enumerate() {
+        let scripted = scenario.price_at(step as u32).unwrap();
+        assert!((price - scripted).abs() < 1e-9, "step {step}: {price} vs {scripted}");
+    }
+    // The whale is the only retail flow; the router may split it across venues.
+    assert!(!log.retail_steps.is_empty());
+    assert!(log.retail_steps.iter().all(|&step| step == 250));
+    let retail = result.volume_by_counterparty.retail_y()
+        + result.normalizer_volume_by_counterparty.retail_y();
+    assert!((retail - 500.0).abs() < 50.0, "whale filled {retail} Y");
+
+    // Keeping background flow adds the generated orders back.
+    let mut background = ScenarioLog::default();
+    prop_amm_sim::engine::run_simulation_native_observed(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &SimulationConfig {
+            scenario: Some(Scenario {
+                background_retail: true,
+                ..scenario
+            }),
+            ..config
+        },
+        &mut background,
+    )
+    .unwrap();
+    assert!(background.retail_steps.iter().any(|&step| step != 250));
+}
+
+#[derive(Default)]
+struct RegimeLog {
+    fair_prices: Vec<f64>,
+    volatile: Vec<bool>,
+}
+
+impl prop_amm_sim::observer::SimObserver for RegimeLog {
+    fn on_step(&mut self, event: &prop_amm_sim::observer::StepEvent) {
+        self.fair_prices.push(event.fair_price);
+        self.volatile.push(event.volatile);
+    }
+}
+
+#[test]
+fn test_student_t_shocks_fatten_tails_at_the_same_volatility() {
+    use prop_amm_sim::price_process::{GBMPriceProcess, PriceProcess};
+
+    // (variance, kurtosis) of the log moves.
+    let moments = |process: GBMPriceProcess| {
+        let mut process = process;
+        let mut price = process.current_price();
+        let moves: Vec<f64> = (0..40_000)
+            .map(|_| {
+                let next = process.step();
+                let ret = (next / price).ln();
+                price = next;
+                ret
+            })
+            .collect();
+        let n = moves.len() as f64;
+        let mean = moves.iter().sum::<f64>() / n;
+        let var = moves.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        let fourth = moves.iter().map(|r| (r - mean).powi(4)).sum::<f64>() / n;
+        (var, fourth / (var * var))
+    };
+    let gbm = || GBMPriceProcess::new(100.0, 0.0, 0.001, 1.0, 5);
+    let (normal_var, normal_kurt) = moments(gbm());
+    let (t_var, t_kurt) = moments(gbm().with_student_t(5.0));
+    assert!((normal_kurt - 3.0).abs() < 0.2, "gaussian kurtosis {normal_kurt}");
+    // A unit-variance t(5) has kurtosis 9.
+    assert!(t_kurt > 5.0, "student-t kurtosis {t_kurt}");
+    assert!((t_var / normal_var - 1.0).abs() < 0.1, "variance {t_var} vs {normal_var}");
+}
+
+#[test]
+fn test_finer_steps_keep_retail_flow_per_unit_of_time() {
+    let run = |n_steps, gbm_dt| {
+        let config = SimulationConfig {
+            n_steps,
+            gbm_dt,
+            seed: 31,
+            ..SimulationConfig::default()
+        };
+        let result = prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap();
+        let normalizer = result.normalizer_volume_by_counterparty.retail_y();
+        result.volume_by_counterparty.retail_y() + normalizer
+    };
+    // The same 2,000 units of time, in whole steps and in quarter steps.
+    let coarse = run(2_000, 1.0);
+    let fine = run(8_000, 0.25);
+    assert!((fine / coarse - 1.0).abs() < 0.1, "retail volume {fine} vs {coarse}");
+}
+
+#[test]
+fn test_volatility_regimes_switch_the_diffusion_rate() {
+    let run = |config: &SimulationConfig| {
+        let mut log = RegimeLog::default();
+        let result = prop_amm_sim::engine::run_simulation_native_observed(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            config,
+            &mut log,
+        )
+        .unwrap();
+        (result, log)
+    };
+    let config = SimulationConfig {
+        n_steps: 4000,
+        seed: 17,
+        ..SimulationConfig::default()
+    };
+    let (plain, plain_log) = run(&config);
+    assert!(plain_log.volatile.iter().all(|&v| !v));
+
+    // Two regimes with the same sigma: switching draws from its own stream, so the price
+    // path and every trade are unchanged.
+    let same_sigma = VolatilityRegimes {
+        calm_sigma: config.gbm_sigma,
+        volatile_sigma: config.gbm_sigma,
+        calm_to_volatile: 0.01,
+        volatile_to_calm: 0.02,
+    };
+    let (same, same_log) = run(&SimulationConfig {
+        volatility_regimes: Some(same_sigma),
+        ..config.clone()
+    });
+    assert_eq!(same_log.fair_prices, plain_log.fair_prices);
+    assert_eq!(same.trade_hash, plain.trade_hash);
+    assert_eq!(same.submission_edge, plain.submission_edge);
+    assert!(same_log.volatile.iter().any(|&v| v));
+
+    let regimes = VolatilityRegimes {
+        calm_sigma: 0.0005,
+        volatile_sigma: 0.005,
+        ..same_sigma
+    };
+    let (switched, log) = run(&SimulationConfig {
+        volatility_regimes: Some(regimes),
+        ..config.clone()
+    });
+    // The chain spends about p_up / (p_up + p_down) = 1/3 of its steps volatile.
+    let volatile_steps = log.volatile.iter().filter(|&&v| v).count();
+    assert!((400..2400).contains(&volatile_steps), "{volatile_steps} volatile steps");
+    let rms_move = |volatile: bool| {
+        let moves: Vec<f64> = log
+            .fair_prices
+            .windows(2)
+            .zip(&log.volatile[1..])
+            .filter(|(_, &v)| v == volatile)
+            .map(|(w, _)| (w[1] / w[0]).ln().powi(2))
+            .collect();
+        (moves.iter().sum::<f64>() / moves.len() as f64).sqrt()
+    };
+    let (calm, volatile) = (rms_move(false), rms_move(true));
+    assert!((0.0004..0.0006).contains(&calm), "calm rms move {calm}");
+    assert!((0.004..0.006).contains(&volatile), "volatile rms move {volatile}");
+    // LVR accrues at the regime's variance, so the volatile stretches dominate it.
+    let (calm_only, _) = run(&SimulationConfig {
+        volatility_regimes: Some(VolatilityRegimes {
+            calm_to_volatile: 0.0,
+            ..regimes
+        }),
+        ..config
+    });
+    assert!(switched.lvr > 5.0 * calm_only.lvr, "lvr {} vs calm {}", switched.lvr, calm_only.lvr);
+}
+
+/// The normalizer's curve until `marking_after_swap` has marked storage, then the starter's
+/// 5% fee, which draws no flow at a flat price.
+fn one_trade_swap(data: &[u8]) -> u64 {
+    if data.get(INSTRUCTION_SIZE) == Some(&1) {
+        return starter_swap(data);
+    }
+    normalizer_swap(data)
+}
+
+#[test]
+fn test_sessions_reopen_pools_but_keep_storage() {
+    let config = SimulationConfig {
+        n_steps: 400,
+        sessions: 3,
+        seed: 12,
+        gbm_sigma: 0.0,
+        record_tape: true,
+        ..SimulationConfig::default()
+    };
+    let mut log = ReserveLog::default();
+    let result = prop_amm_sim::engine::run_simulation_native_observed(
+        one_trade_swap,
+        Some(marking_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+        &mut log,
+    )
+    .unwrap();
+
+    assert_eq!(log.fair_prices.len(), 1_200);
+    // Storage outlives the first session, so the submission never matches the normalizer
+    // again and its flow stops there.
+    let submission: Vec<_> = result.tape.iter().filter(|t| t.venue == 0).collect();
+    assert!(!submission.is_empty());
+    assert!(submission.iter().all(|t| t.step < 400));
+    // The normalizer trades in every session.
+    for session in 0..3 {
+        let steps = session * 400..(session + 1) * 400;
+        assert!(result.tape.iter().any(|t| t.venue == 1 && steps.contains(&t.step)));
+    }
+    // Resets are not PnL: at a flat price, marked inventory still tracks edge exactly.
+    assert!(
+        (result.inventory_pnl() - result.submission_edge).abs() < 1e-6,
+        "inventory PnL {} vs edge {}",
+        result.inventory_pnl(),
+        result.submission_edge
+    );
+
+    let volatile = SimulationConfig {
+        gbm_sigma: 0.003,
+        ..config
+    };
+    let mut log = ReserveLog::default();
+    prop_amm_sim::engine::run_simulation_native_observed(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &volatile,
+        &mut log,
+    )
+    .unwrap();
+    // Each session opens one diffusion step away from the initial price.
+    for start in [0, 400, 800] {
+        assert!((99.0..101.0).contains(&log.fair_prices[start]), "{}", log.fair_prices[start]);
+    }
+}
+
+#[test]
+fn test_batch_auction_routes_one_net_order_per_step() {
+    let run = |execution_mode: ExecutionMode| {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed: 4,
+            retail_arrival_rate: 3.0,
+            execution_mode,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let retail_fills = |result: &prop_amm_shared::result::SimResult| {
+        let mut fills = std::collections::BTreeMap::<(u32, u8), Vec<bool>>::new();
+        for trade in result.tape.iter().filter(|t| t.counterparty == Counterparty::Retail) {
+            fills.entry((trade.step, trade.venue)).or_default().push(trade.amm_buys_x);
+        }
+        fills
+    };
+    let sequential = run(ExecutionMode::Sequential);
+    let batched = run(ExecutionMode::BatchAuction);
+
+    assert!(retail_fills(&sequential).values().any(|fills| fills.len() > 1));
+    let fills = retail_fills(&batched);
+    assert!(!fills.is_empty());
+    assert!(fills.values().all(|fills| fills.len() == 1));
+    // Both venues fill the same net order, so they trade the same way within a step.
+    for (&(step, venue), directions) in &fills {
+        if let Some(other) = fills.get(&(step, 1 - venue)) {
+            assert_eq!(directions, other, "step {step}");
+        }
+    }
+    // Offsetting orders cancel, so less volume reaches the pools.
+    assert!(
+        batched.volume_by_counterparty.retail_y() < sequential.volume_by_counterparty.retail_y()
+    );
+}
+
+#[test]
+fn test_batch_auction_keeps_scripted_order_limits() {
+    use prop_amm_shared::config::{Scenario, ScriptedOrder, ScriptedPrice};
+
+    let order = |is_buy, size, min_output| ScriptedOrder {
+        step: 250,
+        is_buy,
+        size,
+        max_slippage_bps: None,
+        min_output,
+    };
+    let config = SimulationConfig {
+        n_steps: 400,
+        seed: 4,
+        execution_mode: ExecutionMode::BatchAuction,
+        scenario: Some(Scenario {
+            // The unlimited orders net to an 800 Y buy; the sell's own limit is out of reach.
+            orders: vec![
+                order(true, 1_000.0, None),
+                order(false, 200.0, None),
+                order(false, 500.0, Some(1e9)),
+            ],
+            prices: vec![ScriptedPrice { step: 0, price: 100.0 }],
+            background_retail: false,
+        }),
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+    // Netting the limited sell in too would have sent a 300 Y buy.
+    let retail = result.volume_by_counterparty.retail_y()
+        + result.normalizer_volume_by_counterparty.retail_y();
+    assert!((retail - 800.0).abs() < 50.0, "retail filled {retail} Y");
+}
+
+static BATCH_QUOTES: [std::sync::atomic::AtomicU32; 2] =
+    [std::sync::atomic::AtomicU32::new(0), std::sync::atomic::AtomicU32::new(0)];
+
+/// The normalizer, counting its quotes by execution mode (0 sequential, 1 batch-routed).
+fn sequential_counting_swap(data: &[u8]) -> u64 {
+    BATCH_QUOTES[0].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    normalizer_swap(data)
+}
+
+fn batch_counting_swap(data: &[u8]) -> u64 {
+    BATCH_QUOTES[1].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    normalizer_swap(data)
+}
+
+#[test]
+fn test_batch_routing_shares_one_split_search_per_direction() {
+    let run = |execution_mode: ExecutionMode,
+               retail_max_slippage_bps: Option<f64>,
+               submission: fn(&[u8]) -> u64| {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed: 4,
+            retail_arrival_rate: 10.0,
+            retail_max_slippage_bps,
+            execution_mode,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            submission,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let sequential = run(ExecutionMode::Sequential, None, sequential_counting_swap);
+    let batched = run(ExecutionMode::BatchRouted, None, batch_counting_swap);
+
+    // Later orders in a step reuse the split, so the submission is quoted less; arbitrage
+    // quotes it as often either way.
+    let quotes = |mode: usize| BATCH_QUOTES[mode].load(std::sync::atomic::Ordering::Relaxed);
+    assert!(10 * quotes(1) < 9 * quotes(0), "{} vs {} quotes", quotes(1), quotes(0));
+    // Nothing is netted: the pools see about the volume of routing order by order.
+    let (volume, expected) = (
+        batched.volume_by_counterparty.retail_y(),
+        sequential.volume_by_counterparty.retail_y(),
+    );
+    assert!((volume - expected).abs() < 0.02 * expected, "{volume} vs {expected}");
+
+    // Each order keeps its own slippage limit.
+    let limited = run(ExecutionMode::BatchRouted, Some(40.0), normalizer_swap);
+    let limited_sequential = run(ExecutionMode::Sequential, Some(40.0), normalizer_swap);
+    assert!(limited.abandoned_orders > 0);
+    let (abandoned, expected) = (
+        limited.abandoned_orders as f64,
+        limited_sequential.abandoned_orders as f64,
+    );
+    assert!((abandoned - expected).abs() < 0.1 * expected, "{abandoned} vs {expected}");
+}
+
+#[test]
+fn test_dust_orders_are_dropped_and_counted() {
+    let run = |min_trade_size: f64| {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed: 5,
+            retail_mean_size: 1.0,
+            min_trade_size,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let default = run(SimulationConfig::default().min_trade_size);
+    assert!(default.dust_orders > 0 && default.dust_notional > 0.0);
+
+    // Lowering the threshold routes the orders it used to drop.
+    let fine = run(1e-4);
+    assert!(fine.dust_orders < default.dust_orders);
+    let volume = |result: &prop_amm_shared::result::SimResult| {
+        result.volume_by_counterparty.retail_y()
+            + result.normalizer_volume_by_counterparty.retail_y()
+    };
+    assert!(volume(&fine) > volume(&default));
+    let dropped = default.dust_notional - fine.dust_notional;
+    assert!((volume(&fine) - volume(&default) - dropped).abs() < 0.05 * dropped);
+}
+
+#[test]
+fn test_price_guard_leaves_orders_unfilled_and_counts_them() {
+    let run = |price_guard_bps: Option<f64>| {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed: 7,
+            price_guard_bps,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let retail_volume = |result: &prop_amm_shared::result::SimResult| {
+        result.volume_by_counterparty.retail_y()
+            + result.normalizer_volume_by_counterparty.retail_y()
+    };
+    let open = run(None);
+    assert_eq!(open.guarded_orders, 0);
+
+    // Both venues charge 30 bps, so a 10 bps guard only fills orders on the side a stale
+    // pool price favors.
+    let tight = run(Some(10.0));
+    assert!(tight.guarded_orders > 0);
+    assert!(retail_volume(&tight) < 0.1 * retail_volume(&open));
+
+    // A guard no order comes near changes nothing.
+    let loose = run(Some(5_000.0));
+    assert_eq!(loose.guarded_orders, 0);
+    assert_eq!(loose.trade_hash, open.trade_hash);
+}
+
+#[test]
+fn test_step_sequencing_places_arbitrage_among_retail() {
+    let run = |step_sequencing: StepSequencing| {
+        let config = SimulationConfig {
+            n_steps: 1_000,
+            seed: 6,
+            retail_arrival_rate: 2.0,
+            step_sequencing,
+            record_tape: true,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    // Per step with both kinds of fill: whether an arbitrage fill preceded a retail one, and
+    // whether one followed it.
+    let placements = |result: &prop_amm_shared::result::SimResult| {
+        let (mut before, mut after) = (0u32, 0u32);
+        for step in result.tape.chunk_by(|a, b| a.step == b.step) {
+            let first_retail = step.iter().position(|t| t.counterparty == Counterparty::Retail);
+            let last_retail = step.iter().rposition(|t| t.counterparty == Counterparty::Retail);
+            let arb = |t: &TapeTrade| t.counterparty == Counterparty::Arbitrageur;
+            if let (Some(first), Some(last)) = (first_retail, last_retail) {
+                before += step[..first].iter().any(arb) as u32;
+                after += step[last..].iter().any(arb) as u32;
+            }
+        }
+        (before, after)
+    };
+
+    let (before, after) = placements(&run(StepSequencing::ArbFirst));
+    assert!(before > 0);
+    assert_eq!(after, 0);
+    let (before, after) = placements(&run(StepSequencing::ArbLast));
+    assert_eq!(before, 0);
+    assert!(after > 0);
+    let (before, after) = placements(&run(StepSequencing::Interleaved));
+    assert!(before > 0 && after > 0, "{before} {after}");
+}
+
+#[test]
+fn test_execution_stats_cover_every_retail_fill() {
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 3,
+        informed_prob: 0.2,
+        ..SimulationConfig::default()
+    };
+    let result = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+
+    let venues = [&result.edge_by_counterparty, &result.normalizer_edge_by_counterparty];
+    assert_eq!(result.execution_by_venue.len(), venues.len());
+    for (stats, edges) in result.execution_by_venue.iter().zip(venues) {
+        let fills =
+            edges.trades(Counterparty::Retail) + edges.trades(Counterparty::Informed);
+        assert!(fills > 0);
+        assert_eq!(stats.trades(), fills);
+        assert_eq!(stats.impact.count, fills);
+        // A 30 bps constant-product fill always costs more than the fee over spot.
+        let below_fee: u64 = stats.impact.buckets[..6].iter().sum();
+        assert_eq!(below_fee, 0, "fills cheaper than 20 bps over spot");
+        assert!(stats.impact.mean() > 30.0);
+        assert!(stats.impact.percentile(0.95) >= stats.impact.percentile(0.5));
+        assert!(stats.slippage.max >= stats.slippage.mean());
+    }
+}
+
+#[test]
+fn test_inventory_adjusted_edge_equals_inventory_pnl() {
+    let run = |edge_mode: EdgeMode| {
+        let config = SimulationConfig {
+            n_steps: 2_000,
+            warmup_steps: 100,
+            seed: 12,
+            gbm_sigma: 0.003,
+            edge_mode,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let flow = run(EdgeMode::Flow);
+    let adjusted = run(EdgeMode::InventoryAdjusted);
+    assert_eq!(flow.trade_hash, adjusted.trade_hash);
+    assert_eq!(flow.inventory_revaluation, 0.0);
+    assert_eq!(flow.normalizer_inventory_revaluation, 0.0);
+    assert!(adjusted.inventory_revaluation != 0.0);
+
+    assert!((adjusted.submission_edge - flow.inventory_pnl()).abs() < 1e-6);
+    let adjusted_flow = adjusted.submission_edge - adjusted.inventory_revaluation;
+    assert!((adjusted_flow - flow.submission_edge).abs() < 1e-9);
+    let normalizer_flow = adjusted.normalizer_edge() - adjusted.normalizer_inventory_revaluation;
+    assert!((normalizer_flow - flow.normalizer_edge()).abs() < 1e-9);
+    // The breakdown still sums to flow edge.
+    assert_eq!(adjusted.edge_by_counterparty, flow.edge_by_counterparty);
+}
+
+fn dead_swap(_data: &[u8]) -> u64 {
+    0
+}
+
+fn giveaway_swap(data: &[u8]) -> u64 {
+    // Pays out 99.99% of the output reserve for any input.
+    let side = data[0];
+    let rx = u64::from_le_bytes(data[9..17].try_into().unwrap());
+    let ry = u64::from_le_bytes(data[17..25].try_into().unwrap());
+    let reserve = if side == 0 { rx } else { ry };
+    reserve - reserve / 10_000
+}
+
+#[test]
+fn test_depleted_venue_ends_the_simulation() {
+    use prop_amm_shared::result::{Depletion, DepletionCause};
+
+    let config = SimulationConfig {
+        n_steps: 2_000,
+        seed: 3,
+        trajectory_stride: Some(1),
+        ..SimulationConfig::default()
+    };
+    let run = |swap: fn(&[u8]) -> u64| {
+        prop_amm_sim::engine::run_simulation_native(
+            swap,
+            None,
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let dead = run(dead_swap);
+    assert_eq!(
+        dead.depletion,
+        Some(Depletion {
+            step: 99,
+            venue: 0,
+            cause: DepletionCause::NoQuotes,
+        })
+    );
+    assert_eq!(dead.trajectory.len(), 100);
+
+    let drained = run(giveaway_swap);
+    let depletion = drained.depletion.expect("giveaway pool should be drained");
+    assert_eq!((depletion.venue, depletion.cause), (0, DepletionCause::Reserves));
+    assert_eq!(drained.trajectory.len() as u32, depletion.step + 1);
+
+    assert_eq!(run(starter_swap).depletion, None);
+}
+
+#[test]
+fn test_trade_tape_replays_submission_edge() {
+    let run = |record_tape: bool| {
+        let config = SimulationConfig {
+            n_steps: 1_500,
+            seed: 31,
+            informed_prob: 0.2,
+            record_tape,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            starter_swap,
+            Some(starter_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let plain = run(false);
+    let taped = run(true);
+    assert!(plain.tape.is_empty());
+    assert_eq!(plain.trade_hash, taped.trade_hash);
+
+    let submission: Vec<_> = taped.tape.iter().filter(|t| t.venue == 0).collect();
+    let edge: f64 = submission
+        .iter()
+        .map(|t| {
+            if t.amm_buys_x {
+                t.amount_x * t.fair_price - t.amount_y
+            } else {
+                t.amount_y - t.amount_x * t.fair_price
+            }
+        })
+        .sum();
+    assert!((edge - taped.submission_edge).abs() < 1e-6);
+    for counterparty in Counterparty::ALL {
+        let trades = submission.iter().filter(|t| t.counterparty == counterparty).count();
+        assert_eq!(trades as u64, taped.edge_by_counterparty.trades(counterparty));
+    }
+    assert!(taped.tape.windows(2).all(|w| w[0].step <= w[1].step));
+}
+
+#[test]
+fn test_retail_slippage_limit_abandons_expensive_orders() {
+    let run = |retail_max_slippage_bps: Option<f64>| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 29,
+            retail_max_slippage_bps,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let unlimited = run(None);
+    assert_eq!(unlimited.abandoned_orders, 0);
+    // Every fill pays the 30 bps fee, so a limit well above it abandons nothing.
+    let loose = run(Some(5_000.0));
+    assert_eq!(loose.abandoned_orders, 0);
+    assert_eq!(loose.submission_edge, unlimited.submission_edge);
+    let retail_volume = |result: &prop_amm_shared::result::SimResult| {
+        result.volume_by_counterparty.y(Counterparty::Retail)
+    };
+    // Tighter limits abandon more orders; below the fee only fills against a pool priced
+    // through fair survive.
+    let tight = run(Some(40.0));
+    let strict = run(Some(10.0));
+    assert!(0 < tight.abandoned_orders && tight.abandoned_orders < strict.abandoned_orders);
+    assert!(retail_volume(&tight) < 0.5 * retail_volume(&unlimited));
+    assert!(retail_volume(&strict) < retail_volume(&tight));
+
+    // Partial fills take the part of each order that meets the limit instead.
+    let config = SimulationConfig {
+        n_steps: 2000,
+        seed: 29,
+        retail_max_slippage_bps: Some(40.0),
+        retail_partial_fills: true,
+        ..SimulationConfig::default()
+    };
+    let partial = prop_amm_sim::engine::run_simulation_native(
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+    )
+    .unwrap();
+    assert!(partial.partial_fills > 0 && partial.abandoned_orders < tight.abandoned_orders);
+    assert!(retail_volume(&partial) > retail_volume(&tight));
+}
+
+#[test]
+fn test_arb_inventory_limits_arbitrage_until_restocked() {
+    let run = |arb_inventory: Option<ArbInventory>| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 31,
+            arb_inventory,
+            ..SimulationConfig::default()
+        };
+        let result = prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap();
+        result.volume_by_counterparty.y(Counterparty::Arbitrageur)
+    };
+    let unlimited = run(None);
+    let inventory = |rebalance_steps: u32| ArbInventory {
+        x: 0.5,
+        y: 50.0,
+        rebalance_steps,
+    };
+    let never_restocked = run(Some(inventory(0)));
+    let restocked = run(Some(inventory(10)));
+    assert!(never_restocked < restocked, "{never_restocked} vs {restocked}");
+    assert!(restocked < unlimited, "{restocked} vs {unlimited}");
+    // Plenty of capital never binds.
+    let deep = run(Some(ArbInventory {
+        x: 1e9,
+        y: 1e11,
+        rebalance_steps: 0,
+    }));
+    assert_eq!(deep, unlimited);
+}
+
+#[test]
+fn test_occasional_arbitrageur_leaves_mispricings_to_retail() {
+    let run = |arb_arrival_prob: f64| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 37,
+            arb_arrival_prob,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let always = run(1.0);
+    let occasional = run(0.2);
+    let arb_trades = |result: &prop_amm_shared::result::SimResult| {
+        result.edge_by_counterparty.trades(Counterparty::Arbitrageur)
+    };
+    assert!(
+        arb_trades(&occasional) < arb_trades(&always) / 2,
+        "{} vs {} arb trades",
+        arb_trades(&occasional),
+        arb_trades(&always)
+    );
+    // Each visit finds a larger mispricing to correct.
+    let arb_volume = |result: &prop_amm_shared::result::SimResult| {
+        result.volume_by_counterparty.y(Counterparty::Arbitrageur) / arb_trades(result) as f64
+    };
+    assert!(arb_volume(&occasional) > arb_volume(&always));
+    assert_eq!(run(0.0).edge_by_counterparty.trades(Counterparty::Arbitrageur), 0);
+}
+
+#[test]
+fn test_cp_fast_path_tracks_the_searched_arbitrage() {
+    let run = |arb_cp_fast_path: bool| {
+        let config = SimulationConfig {
+            n_steps: 2000,
+            seed: 43,
+            arb_cp_fast_path,
+            ..SimulationConfig::default()
+        };
+        prop_amm_sim::engine::run_simulation_native(
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            normalizer_swap,
+            Some(normalizer_after_swap),
+            &config,
+        )
+        .unwrap()
+    };
+    let searched = run(false);
+    let fitted = run(true);
+    assert_ne!(searched.trade_hash, fitted.trade_hash);
+    let arb_edge = |result: &prop_amm_shared::result::SimResult| {
+        result.edge_by_counterparty.get(Counterparty::Arbitrageur)
+    };
+    // Closed-form sizing is at least as sharp as the search, within its tolerance.
+    assert!(
+        arb_edge(&fitted) <= 0.98 * arb_edge(&searched),
+        "{} vs {}",
+        arb_edge(&fitted),
+        arb_edge(&searched)
+    );
+    let relative = (fitted.submission_edge - searched.submission_edge).abs();
+    assert!(relative < 0.05 * searched.submission_edge.abs(), "{relative}");
+}
+
+#[test]
+fn test_event_clock_moves_the_price_to_each_arrival() {
+    use prop_amm_sim::clock::{EventKind, EventSchedule};
+    use prop_amm_sim::price_process::PricePath;
+
+    let config = SimulationConfig {
+        n_steps: 4000,
+        gbm_dt: 0.5,
+        seed: 33,
+        clock: Clock::EventDriven {
+            arb_check_rate: 0.4,
+        },
+        ..SimulationConfig::default()
+    };
+    assert_eq!(EventSchedule::for_config(&SimulationConfig::default()), None);
+    let schedule = EventSchedule::for_config(&config).unwrap();
+    let events = schedule.events();
+    // 2000 units of time at 0.8 retail orders and 0.4 arbitrage checks per unit.
+    assert!((events.len() as f64 - 2400.0).abs() < 200.0, "{} events", events.len());
+    let arb_checks = events.iter().filter(|e| e.kind == EventKind::ArbCheck).count();
+    let arb_share = arb_checks as f64 / events.len() as f64;
+    assert!((arb_share - 1.0 / 3.0).abs() < 0.05, "arb share {arb_share}");
+    for pair in events.windows(2) {
+        assert!(pair[1].time > pair[0].time);
+        assert!((pair[1].elapsed - (pair[1].time - pair[0].time)).abs() < 1e-9);
+    }
+    for event in events {
+        assert_eq!(event.step, (event.time / config.gbm_dt) as u32);
+    }
+
+    // Every move's variance is sigma^2 times the time it covers, however short.
+    let path = PricePath::generate(&config);
+    assert_eq!(path.steps() as usize, events.len());
+    let prices: Vec<f64> = std::iter::once(config.initial_price).chain(path.prices()).collect();
+    let elapsed = events.iter().map(|e| e.elapsed).chain([schedule.remaining()]);
+    let moves: Vec<f64> = prices
+        .windows(2)
+        .zip(elapsed)
+        .map(|(pair, dt)| (pair[1] / pair[0]).ln() / (config.gbm_sigma * dt.sqrt()))
+        .collect();
+    let variance = moves.iter().map(|z| z * z).sum::<f64>() / moves.len() as f64;
+    assert!((0.9..1.1).contains(&variance), "standardized variance {variance}");
+
+    // The engine walks the same events: one price per event, retail fills only at orders.
+    let mut log = ScenarioLog::default();
+    prop_amm_sim::engine::run_simulation_native_observed(
+        starter_swap,
+        Some(starter_after_swap),
+        normalizer_swap,
+        Some(normalizer_after_swap),
+        &config,
+        &mut log,
+    )
+    .unwrap();
+    let event_prices: Vec<f64> = path.prices().take(events.len()).collect();
+    assert_eq!(log.fair_prices, event_prices);
+    assert!(!log.retail_steps.is_empty());
+    for step in log.retail_steps {
+        assert!(matches!(events[step as usize].kind, EventKind::Retail(0)));
+    }
+}